@@ -1,7 +1,24 @@
-use std::{io, ops::Range, path::PathBuf};
+use std::{
+    io,
+    ops::Range,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
-use crate::engine::{Edit, EngineResult};
-use crate::BufferAction;
+use crate::diagnostic::Diagnostics;
+use crate::disk::{self, Check, DiskState};
+use crate::encoding::{self, Format};
+use crate::engine::{read_file, Change, Delete, Edit, EngineError, EngineResult, Insert, Replace};
+use crate::fold::Folds;
+use crate::history::{History, Step};
+use crate::motion::{self, TextObject};
+use crate::options::gen_options;
+use crate::snippet::Active;
+use crate::swap::Swap;
+use crate::syntax::{Highlighter, Span};
+#[cfg(feature = "tree-sitter")]
+use crate::tree::SyntaxTree;
+use crate::{BufferAction, Move};
 
 use ropey::Rope;
 
@@ -13,11 +30,75 @@ pub struct Buffer {
     pub loc: Location,
     pub path: PathBuf,
     pub text: Rope,
+    /// the file as it was last loaded or saved, none if it didn't exist
+    pub disk: Option<DiskState>,
+    /// how the text is stored on disk
+    pub format: Format,
+    pub local: Local,
+    /// whether there are changes that haven't been written
+    pub modified: bool,
+    /// the journal of unsaved changes, none if swap files are disabled
+    pub swap: Option<Swap>,
+    /// a swap file left behind by an earlier session, waiting to be recovered
+    pub recovery: Option<PathBuf>,
+    pub history: History,
+    /// bumped on every change to the text, for caches built from it
+    pub revision: u64,
+    /// the file is over the `largefile` size, expensive features are off
+    pub large: bool,
+    /// what the text is highlighted with, see [`Buffer::highlight`]
+    pub syntax: Highlighter,
+    /// the syntax tree, none until it's first needed or when there's no
+    /// grammar for the `filetype`
+    #[cfg(feature = "tree-sitter")]
+    pub tree: Option<SyntaxTree>,
+    pub folds: Folds,
+    /// the selections that selecting syntax nodes grew from, the last one
+    /// most recent, for shrinking back to
+    pub grown: Vec<Range<usize>>,
+    /// the changes since a language server was last told of them, none when
+    /// no server follows the buffer
+    pub edits: Option<Vec<Edited>>,
+    /// whether the file was written since a language server was last told
+    pub written: bool,
+    /// what language servers and linters found wrong with the text
+    pub diagnostics: Diagnostics,
+    /// the snippet being filled in, see [`snippet`](crate::snippet)
+    pub snippet: Option<Active>,
+    /// the [`History::current`] revision when the file was last saved
+    pub(crate) saved: usize,
+    /// when [`Buffer::check_disk`] last looked at a large file
+    pub(crate) checked: Option<Instant>,
 }
 
-#[derive(Debug, Default)]
+/// how often large files are looked at, reloading one being slow
+const LARGE_CHECK: Duration = Duration::from_secs(10);
+
+gen_options! {
+    /// Options local to a buffer, besides its [`Format`]
+    pub struct Local {
+        /// the language the text is in, see [`filetype::detect`]
+        ///
+        /// [`filetype::detect`]: crate::filetype::detect
+        filetype | ft: String = String::new(),
+    }
+}
+
+/// A change to the text, bytes `range` of the text before it becoming `text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edited {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct Location {
     pub char: usize,
+    /// the column up and down motions aim for, see [`motion::apply`]
+    pub want: Option<usize>,
+    /// where the selection started, the other end from the cursor, while in
+    /// visual mode
+    pub anchor: Option<usize>,
 }
 
 impl Buffer {
@@ -29,46 +110,478 @@ impl Buffer {
     }
 
     pub fn apply(&mut self, action: BufferAction) -> EngineResult<()> {
+        if !matches!(action, BufferAction::Move(_)) {
+            self.loc.want = None;
+        }
+        let placeholder = self
+            .snippet
+            .as_mut()
+            .and_then(|snippet| snippet.take_placeholder(self.loc.char));
         match action {
             BufferAction::Append(text) => {
-                self.text
-                    .try_insert(self.fix_index(self.loc.char)?, &text)?;
+                if let Some(range) = placeholder {
+                    self.splice(range, "")?;
+                }
+                self.insert(self.fix_index(self.loc.char)?, &text)?;
                 self.loc.char += text.len();
+                self.mirror()
+            }
+            BufferAction::Undo => match self.history.undo() {
+                Some(steps) => self.travel(steps),
+                None => Ok(()),
+            },
+            BufferAction::Redo => match self.history.redo() {
+                Some(steps) => self.travel(steps),
+                None => Ok(()),
+            },
+            BufferAction::Move(mv) => {
+                let from = self.loc.char;
+                self.loc.char = motion::apply(&self.text, from, &mv, &mut self.loc.want);
+                // a closed fold is passed over like a single line
+                let line = self.cursor_line();
+                match self.folds.at(line) {
+                    Some(fold) if line == fold.start => {}
+                    Some(fold) if matches!(mv, Move::Up) => self.goto_line(fold.start),
+                    Some(fold) if fold.end < self.text.len_lines() => self.goto_line(fold.end),
+                    Some(_) => self.loc.char = from,
+                    None => {}
+                }
                 Ok(())
             }
         }
     }
 
+    /// moves the cursor to another line, keeping its column where it can
+    pub fn goto_line(&mut self, line: usize) {
+        let (current, col) = motion::line_col(&self.text, self.loc.char);
+        let content = motion::line_text(&self.text, current);
+        let want = *self
+            .loc
+            .want
+            .get_or_insert_with(|| motion::column(&content, col));
+        self.loc.char = motion::to_line(&self.text, line, Some(want));
+    }
+
+    /// the line the cursor is on
+    pub fn cursor_line(&self) -> usize {
+        motion::line_col(&self.text, self.loc.char).0
+    }
+
+    /// moves the cursor off the end of its line, see [`motion::clamp`]
+    pub fn clamp_cursor(&mut self) {
+        self.loc.char = motion::clamp(&self.text, self.loc.char);
+    }
+
+    /// moves the cursor back inside the text and onto a character, for a
+    /// cursor kept aside while the text changed
+    pub fn fit_cursor(&mut self) {
+        let at = self.loc.char.min(self.text.len_bytes());
+        self.loc.char = self.text.char_to_byte(self.text.byte_to_char(at));
+    }
+
+    /// selects `range` in visual mode, with the cursor on its last character
+    pub fn select(&mut self, range: Range<usize>) {
+        let last = match motion::line_col(&self.text, range.end) {
+            // a range ending with a line ending has that as its last character
+            (_, 0) if range.end > range.start => range.end - 1,
+            _ => motion::prev_char(&self.text, range.end),
+        };
+        self.loc = Location {
+            char: last.max(range.start),
+            want: None,
+            anchor: Some(range.start),
+        };
+    }
+
+    /// what's between the anchor and the cursor, as a byte range into the
+    /// text, none when there's no anchor
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.loc.anchor?.min(self.text.len_bytes());
+        let (start, end) = (anchor.min(self.loc.char), anchor.max(self.loc.char));
+        Some(start..motion::next_char(&self.text, end))
+    }
+
+    /// ends the current undo step, the next change starts a new one
+    pub fn commit(&mut self) {
+        self.history.commit();
+    }
+
+    /// replaces byte ranges of the text all at once, as a single undo step,
+    /// for edits worked out elsewhere like a language server's
+    pub fn replace(&mut self, edits: &[Replace]) -> EngineResult<()> {
+        let mut edits = edits.to_vec();
+        // from the end back, so earlier ranges stay put, and inserts at the
+        // same place end up in the order given
+        edits.reverse();
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+        self.commit();
+        for edit in edits {
+            let start = self.text.try_byte_to_char(edit.range.start)?;
+            let end = self.text.try_byte_to_char(edit.range.end)?;
+            if start < end {
+                self.remove(start..end)?;
+            }
+            if !edit.new_text.is_empty() {
+                self.insert(start, edit.new_text)?;
+            }
+        }
+        self.commit();
+        self.fit_cursor();
+        Ok(())
+    }
+
+    /// replaces a byte range of the text as part of the current undo step,
+    /// leaving the cursor after the new text, like an accepted completion
+    pub fn splice(&mut self, range: Range<usize>, text: &str) -> EngineResult<()> {
+        let range = self.fix_range(range)?;
+        if !range.is_empty() {
+            self.remove(range.clone())?;
+        }
+        if !text.is_empty() {
+            self.insert(range.start, text)?;
+        }
+        self.loc.char = self.text.try_char_to_byte(range.start)? + text.len();
+        self.loc.want = None;
+        Ok(())
+    }
+
+    /// copies the current tabstop of the snippet being filled in, where the
+    /// cursor is, over to its mirrors
+    pub fn mirror(&mut self) -> EngineResult<()> {
+        let Some(stop) = self.snippet.as_ref().and_then(Active::current) else {
+            return Ok(());
+        };
+        let at = self.loc.char;
+        let ranges = stop.ranges.len();
+        let Some(typed) = stop
+            .ranges
+            .iter()
+            .position(|range| range.contains(&at) || range.end == at)
+        else {
+            return Ok(());
+        };
+        let text = self.text.byte_slice(stop.ranges[typed].clone()).to_string();
+        for i in (0..ranges).filter(|&i| i != typed) {
+            // earlier mirrors changing move the rest along
+            let range = match self.snippet.as_ref().and_then(Active::current) {
+                Some(stop) => stop.ranges[i].clone(),
+                None => break,
+            };
+            if self.text.byte_slice(range.clone()) == text.as_str() {
+                continue;
+            }
+            let chars = self.fix_range(range.clone())?;
+            self.remove(chars.clone())?;
+            self.insert(chars.start, &text)?;
+            if range.end <= self.loc.char {
+                self.loc.char = self.loc.char + text.len() - range.len();
+            }
+        }
+        Ok(())
+    }
+
+    /// applies steps coming out of the history, for undo and redo
+    fn travel(&mut self, steps: Vec<Step>) -> EngineResult<()> {
+        for step in &steps {
+            self.step(step)?;
+        }
+        if let Some(step) = steps.last() {
+            self.loc.char = self.text.try_char_to_byte(step.cursor())?;
+        }
+        self.modified = self.history.current() != self.saved;
+        Ok(())
+    }
+
+    /// applies a change from a swap file
+    pub fn replay(&mut self, change: Change) -> EngineResult<()> {
+        match change {
+            Change::Insert(Insert { index, text }) => self.insert(index, &text),
+            Change::Delete(Delete { range }) => self.remove(range),
+        }
+    }
+
+    /// changes the text without touching the history
+    fn step(&mut self, step: &Step) -> EngineResult<()> {
+        let start = self.text.try_char_to_byte(step.cursor())?;
+        let before = self.text.clone();
+        let (change, old_end, new_end) = match step {
+            Step::Insert { at, text } => {
+                self.text.try_insert(*at, text)?;
+                let change = Change::Insert(Insert {
+                    index: *at,
+                    text: text.clone().into(),
+                });
+                (change, start, start + text.len())
+            }
+            Step::Remove { at, text } => {
+                let range = *at..at + text.chars().count();
+                if self.text.get_slice(range.clone()) != Some(text.as_str().into()) {
+                    return Err(EngineError::History);
+                }
+                self.text.try_remove(range.clone())?;
+                (Change::Delete(Delete { range }), start + text.len(), start)
+            }
+        };
+        self.edited(&before, start, old_end, new_end);
+        self.modified = true;
+        self.revision += 1;
+        if let Some(swap) = &mut self.swap {
+            swap.record(change);
+        }
+        Ok(())
+    }
+
+    /// moves what was worked out from the text along with a change to it,
+    /// bytes `start..old_end` of `before` becoming `start..new_end`
+    fn edited(&mut self, before: &Rope, start: usize, old_end: usize, new_end: usize) {
+        let first = motion::line_col(before, start).0;
+        let last = motion::line_col(before, old_end).0;
+        let now = motion::line_col(&self.text, new_end).0;
+        self.syntax.edit(first, last, now);
+        self.folds.edit(first, last, now);
+        self.diagnostics.edit(start, old_end, new_end);
+        if let Some(snippet) = &mut self.snippet {
+            snippet.edit(start, old_end, new_end);
+        }
+        if let Some(edits) = &mut self.edits {
+            edits.push(Edited {
+                range: start..old_end,
+                text: self.text.byte_slice(start..new_end).to_string(),
+            });
+        }
+        #[cfg(feature = "tree-sitter")]
+        if let Some(tree) = &mut self.tree {
+            let point = |text: &Rope, at| {
+                let (row, column) = motion::line_col(text, at);
+                tree_sitter::Point { row, column }
+            };
+            tree.edit(&tree_sitter::InputEdit {
+                start_byte: start,
+                old_end_byte: old_end,
+                new_end_byte: new_end,
+                start_position: point(before, start),
+                old_end_position: point(before, old_end),
+                new_end_position: point(&self.text, new_end),
+            });
+        }
+    }
+
+    fn insert(&mut self, index: usize, text: &str) -> EngineResult<()> {
+        let step = Step::Insert {
+            at: index,
+            text: text.to_owned(),
+        };
+        self.step(&step)?;
+        self.history.record(step);
+        Ok(())
+    }
+
     fn fix_index(&self, i: usize) -> ropey::Result<usize> {
         self.text.try_byte_to_char(i)
     }
 
-    #[allow(unused)]
     fn fix_range(&self, r: Range<usize>) -> ropey::Result<Range<usize>> {
         Ok(self.fix_index(r.start)?..self.fix_index(r.end)?)
     }
 
-    #[allow(unused)]
-    fn remove(&mut self, r: Range<usize>) -> ropey::Result<()> {
-        self.text.try_remove(r.start..r.end)?;
+    fn remove(&mut self, r: Range<usize>) -> EngineResult<()> {
+        let step = Step::Remove {
+            at: r.start,
+            text: self
+                .text
+                .get_slice(r)
+                .ok_or(EngineError::History)?
+                .to_string(),
+        };
+        self.step(&step)?;
+        self.history.record(step);
         Ok(())
     }
 
-    pub async fn write_async(&self) -> io::Result<()> {
+    /// the highlighted pieces of a line, none in large files or when there's
+    /// no language for the `filetype`
+    pub fn highlight(&mut self, line: usize) -> &[Span] {
+        if self.large {
+            return &[];
+        }
+        if self.syntax.filetype() != self.local.filetype {
+            self.syntax = Highlighter::new(&self.local.filetype);
+        }
+        self.syntax.line(&self.text, line)
+    }
+
+    /// the syntax tree and the text it's for, none in large files or when
+    /// there's no grammar for the `filetype`
+    #[cfg(feature = "tree-sitter")]
+    fn syntax_tree(&mut self) -> Option<(&mut SyntaxTree, &Rope)> {
+        if self.large {
+            return None;
+        }
+        if self
+            .tree
+            .as_ref()
+            .is_none_or(|tree| tree.filetype() != self.local.filetype)
+        {
+            self.tree = SyntaxTree::new(&self.local.filetype);
+        }
+        Some((self.tree.as_mut()?, &self.text))
+    }
+
+    /// the bytes of the `object` around the cursor, see
+    /// [`SyntaxTree::text_object`]
+    pub fn text_object(&mut self, object: TextObject, inner: bool) -> Option<Range<usize>> {
+        #[cfg(feature = "tree-sitter")]
+        {
+            let at = self.loc.char;
+            let (tree, text) = self.syntax_tree()?;
+            tree.text_object(text, at, object, inner)
+        }
+        #[cfg(not(feature = "tree-sitter"))]
+        {
+            let _ = (object, inner);
+            None
+        }
+    }
+
+    /// the syntax node around `range` that is bigger than it, see
+    /// [`SyntaxTree::expand`]
+    pub fn expand(&mut self, range: Range<usize>) -> Option<Range<usize>> {
+        #[cfg(feature = "tree-sitter")]
+        {
+            let (tree, text) = self.syntax_tree()?;
+            tree.expand(text, range)
+        }
+        #[cfg(not(feature = "tree-sitter"))]
+        {
+            let _ = range;
+            None
+        }
+    }
+
+    /// the lines of every fold the syntax tree has, outer ones first
+    pub fn fold_ranges(&mut self) -> Vec<Range<usize>> {
+        #[cfg(feature = "tree-sitter")]
+        if let Some((tree, text)) = self.syntax_tree() {
+            return tree.folds(text);
+        }
+        Vec::new()
+    }
+
+    /// rereads the file, dropping any changes
+    pub fn reload(&mut self) -> EngineResult<()> {
+        let large = self.large;
+        let (text, disk, format) = match read_file(&self.path, |_| large)? {
+            Some((text, disk, format)) => (text, Some(disk), format),
+            None => (Rope::new(), None, self.format.clone()),
+        };
+        self.loc.char = self.loc.char.min(text.len_bytes());
+        if let Some(edits) = &mut self.edits {
+            edits.push(Edited {
+                range: 0..self.text.len_bytes(),
+                text: text.to_string(),
+            });
+        }
+        self.text = text;
+        self.disk = disk;
+        self.format = format;
+        self.modified = false;
+        self.revision += 1;
+        self.history = History::default();
+        self.saved = 0;
+        self.syntax = Highlighter::new(&self.local.filetype);
+        #[cfg(feature = "tree-sitter")]
+        {
+            self.tree = None;
+        }
+        self.folds.open_all();
+        // they were about the text that's gone
+        self.diagnostics.clear();
+        self.snippet = None;
+        self.reset_swap()?;
+        Ok(())
+    }
+
+    /// takes over a history read from an undo file, see [`History::load`]
+    pub fn restore(&mut self, history: History) {
+        self.history = history;
+        self.saved = self.history.current();
+    }
+
+    /// the hash of the contents the swap journal applies to
+    pub fn base_hash(&self) -> u64 {
+        self.disk.map_or(0, |disk| disk.hash)
+    }
+
+    fn reset_swap(&mut self) -> io::Result<()> {
+        let base = self.base_hash();
+        match &mut self.swap {
+            Some(swap) => swap.reset(base),
+            None => Ok(()),
+        }
+    }
+
+    /// compares the file on disk with what was last loaded or saved
+    ///
+    /// large files are only compared by their metadata, and at most every
+    /// [`LARGE_CHECK`].
+    pub fn check_disk(&mut self) -> io::Result<Check> {
+        if self.large {
+            if self.checked.is_some_and(|at| at.elapsed() < LARGE_CHECK) {
+                return Ok(Check::Same);
+            }
+            self.checked = Some(Instant::now());
+            return match self.disk {
+                Some(disk) => disk.stat(&self.path),
+                None => Ok(match disk::handle_nf(std::fs::metadata(&self.path))? {
+                    Some(meta) => Check::Changed(DiskState {
+                        mtime: meta.modified().ok(),
+                        len: meta.len(),
+                        hash: 0,
+                    }),
+                    None => Check::Same,
+                }),
+            };
+        }
+        let Some(disk) = self.disk else {
+            return Ok(match DiskState::read(&self.path)? {
+                Some(now) => Check::Changed(now),
+                None => Check::Same,
+            });
+        };
+        let check = disk.check(&self.path)?;
+        if let Check::Touched(now) = check {
+            self.disk = Some(now);
+        }
+        Ok(check)
+    }
+
+    pub async fn write_async(&mut self) -> io::Result<()> {
         use tokio::{
             fs,
             io::{self, AsyncWriteExt},
         };
-        let mut writer = io::BufWriter::new(fs::File::open(&self.path).await?);
-        for chunk in self.text.chunks() {
-            writer.write(chunk.as_bytes()).await?;
-        }
-        Ok(())
+        let bytes = encoding::encode(&self.text, &self.format)?;
+        let mut writer = io::BufWriter::new(fs::File::create(&self.path).await?);
+        writer.write_all(&bytes).await?;
+        writer.flush().await?;
+        let meta = writer.into_inner().metadata().await?;
+        self.saved(&meta, &bytes)
+    }
+
+    pub fn write(&mut self) -> io::Result<()> {
+        use std::{fs, io::Write};
+        let bytes = encoding::encode(&self.text, &self.format)?;
+        let mut file = fs::File::create(&self.path)?;
+        file.write_all(&bytes)?;
+        self.saved(&file.metadata()?, &bytes)
     }
 
-    pub fn write(&self) -> io::Result<()> {
-        use std::{fs, io};
-        self.text
-            .write_to(io::BufWriter::new(fs::File::open(&self.path)?))
+    fn saved(&mut self, meta: &std::fs::Metadata, bytes: &[u8]) -> io::Result<()> {
+        self.disk = Some(DiskState::new(meta, bytes));
+        self.modified = false;
+        self.written = true;
+        self.history.commit();
+        self.saved = self.history.current();
+        self.reset_swap()
     }
 }
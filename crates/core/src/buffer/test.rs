@@ -65,3 +65,39 @@
 //         Ok(())
 //     );
 // }
+
+#[test]
+fn replace_is_one_undo_step() {
+    use ropey::Rope;
+
+    use crate::buffer::{Buffer, Edited};
+    use crate::engine::Replace;
+    use crate::BufferAction;
+
+    let mut buf = Buffer {
+        text: Rope::from_str("let a = 1;\nlet b = a;\n"),
+        edits: Some(Vec::new()),
+        ..Buffer::default()
+    };
+    let edit = |range, new_text| Replace { range, new_text };
+    buf.replace(&[
+        edit(4..5, "x"),
+        edit(19..20, "x"),
+        edit(0..0, "// "),
+        edit(0..0, "hi\n"),
+    ])
+    .unwrap();
+    assert_eq!(buf.text, "// hi\nlet x = 1;\nlet b = x;\n");
+    // the edits are told from the end back, each against the text before it,
+    // a replacement as a removal and an insert
+    let edits = buf.edits.take().unwrap();
+    assert_eq!(edits.len(), 6);
+    let edited = |range, text: &str| Edited {
+        range,
+        text: text.into(),
+    };
+    assert_eq!(edits[..2], [edited(19..20, ""), edited(19..19, "x")]);
+
+    buf.apply(BufferAction::Undo).unwrap();
+    assert_eq!(buf.text, "let a = 1;\nlet b = a;\n");
+}
@@ -0,0 +1,369 @@
+//! Ex commands, as typed on the command line
+
+use std::{ops::Range, str::FromStr};
+
+use regex::Regex;
+use ropey::Rope;
+use thiserror::Error;
+
+use crate::options::OptionError;
+use crate::window::Split;
+
+#[cfg(test)]
+mod test;
+
+/// A parsed ex command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// `:e[dit][!]`, rereads the current file from disk
+    Edit { force: bool },
+    /// `:w[rite]`
+    Write,
+    /// `:wq[!]` and `:x[it][!]`, writes then quits, `:xit` only writing when
+    /// there are changes
+    WriteQuit { force: bool, modified_only: bool },
+    /// `:se[t] {option}...`
+    Set(Vec<String>),
+    /// `:sp[lit] [file]` and `:vs[plit] [file]`
+    Split { split: Split, file: Option<String> },
+    /// `:clo[se]`
+    Close,
+    /// `:on[ly]`
+    Only,
+    /// `:q[uit][!]`, closes the window or leaves evie from the last one
+    Quit { force: bool },
+    /// `:tabnew [file]`, without a file the new tab page shows the current
+    /// buffer
+    TabNew { file: Option<String> },
+    /// `:tabc[lose]`
+    TabClose,
+    /// `:tabm[ove] [N|+N|-N]`
+    TabMove(TabMove),
+    /// `:ren[ame] {name}`, renames the symbol under the cursor through the
+    /// language server
+    Rename(String),
+    /// `:form[at]`, formats the buffer through the language server
+    Format,
+    /// `:diag[nostics]`, shows or hides the list of every buffer's
+    /// diagnostics
+    Diagnostics,
+    /// `:colo[rscheme] [name]`, without a name tells the one in use
+    Colorscheme(Option<String>),
+    /// `:[range]s[ubstitute]/{pattern}/{string}/[g]`
+    Substitute(Substitute),
+}
+
+/// What `:substitute` replaces
+///
+/// The pattern is a Rust regex rather than a vim one. In the replacement `&`
+/// and `\0` stand for the whole match and `\1` to `\9` for its groups.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Substitute {
+    pub lines: Lines,
+    pub pattern: String,
+    pub replacement: String,
+    /// the `g` flag, every match on a line rather than the first
+    pub global: bool,
+}
+
+/// The lines an ex command runs on, `%` for all of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lines {
+    pub start: Address,
+    pub end: Address,
+}
+
+/// A line typed in a range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Address {
+    /// `.`, the cursor line
+    Current,
+    /// `$`
+    Last,
+    /// counting from 1
+    Line(usize),
+}
+
+/// Where `:tabmove` puts the current tab page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabMove {
+    /// after the tab page with this number, counted before the move, 0 makes
+    /// it the first
+    After(usize),
+    /// this many places along
+    By(isize),
+    Last,
+}
+
+#[derive(Debug, Error)]
+pub enum CommandError {
+    #[error("Not an editor command: {0}")]
+    Unknown(String),
+    #[error("Trailing characters: {0}")]
+    Trailing(String),
+    #[error("No write since last change (add ! to override)")]
+    Modified,
+    #[error("No buffer to run {0} on")]
+    NoBuffer(String),
+    #[error("Cannot close last window")]
+    LastWindow,
+    #[error("Cannot close last tab page")]
+    LastTab,
+    #[error("Invalid argument: {0}")]
+    Argument(String),
+    #[error("No range allowed")]
+    NoRange,
+    #[error("Invalid pattern: {0}")]
+    Pattern(String),
+    #[error("Pattern not found: {0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Option(#[from] OptionError),
+}
+
+impl FromStr for Command {
+    type Err = CommandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_start_matches(|c: char| c == ':' || c.is_whitespace());
+        let (range, s) = lines(s)?;
+        let name_len = s
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(s.len());
+        let (name, rest) = s.split_at(name_len);
+        // the delimiter can be anything, `!` included
+        if !name.is_empty() && abbrev(name, "substitute", 1) {
+            return substitute(range.unwrap_or(Lines::CURRENT), rest);
+        }
+        if range.is_some() {
+            return Err(CommandError::NoRange);
+        }
+        let (force, rest) = match rest.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        let arg = rest.trim();
+
+        let cmd = if abbrev(name, "edit", 1) {
+            Self::Edit { force }
+        } else if abbrev(name, "write", 1) {
+            Self::Write
+        } else if name == "wq" || abbrev(name, "xit", 1) {
+            Self::WriteQuit {
+                force,
+                modified_only: name.starts_with('x'),
+            }
+        } else if abbrev(name, "set", 2) {
+            return Ok(Self::Set(
+                arg.split_whitespace().map(str::to_owned).collect(),
+            ));
+        } else if abbrev(name, "split", 2) || abbrev(name, "vsplit", 2) {
+            let split = match name.starts_with('v') {
+                true => Split::Vertical,
+                false => Split::Horizontal,
+            };
+            let file = (!arg.is_empty()).then(|| arg.to_owned());
+            return Ok(Self::Split { split, file });
+        } else if abbrev(name, "close", 3) {
+            Self::Close
+        } else if abbrev(name, "only", 2) {
+            Self::Only
+        } else if abbrev(name, "quit", 1) {
+            Self::Quit { force }
+        } else if name == "tabnew" {
+            let file = (!arg.is_empty()).then(|| arg.to_owned());
+            return Ok(Self::TabNew { file });
+        } else if abbrev(name, "tabclose", 4) {
+            Self::TabClose
+        } else if abbrev(name, "tabmove", 4) {
+            return tab_move(arg).map(Self::TabMove);
+        } else if abbrev(name, "rename", 3) {
+            if arg.is_empty() {
+                return Err(CommandError::Argument(s.to_owned()));
+            }
+            return Ok(Self::Rename(arg.to_owned()));
+        } else if abbrev(name, "format", 4) {
+            Self::Format
+        } else if abbrev(name, "diagnostics", 4) {
+            Self::Diagnostics
+        } else if abbrev(name, "colorscheme", 4) {
+            let name = (!arg.is_empty()).then(|| arg.to_owned());
+            return Ok(Self::Colorscheme(name));
+        } else {
+            return Err(CommandError::Unknown(s.to_owned()));
+        };
+        if !arg.is_empty() {
+            return Err(CommandError::Trailing(arg.to_owned()));
+        }
+        Ok(cmd)
+    }
+}
+
+impl Lines {
+    /// no range typed, just the cursor line
+    pub const CURRENT: Self = Self {
+        start: Address::Current,
+        end: Address::Current,
+    };
+    /// `%`
+    pub const ALL: Self = Self {
+        start: Address::Line(1),
+        end: Address::Last,
+    };
+
+    /// the lines, counting from 0, given the cursor line and how many lines
+    /// the buffer has
+    pub fn resolve(self, current: usize, len: usize) -> Range<usize> {
+        let last = len.saturating_sub(1);
+        let line = |address| match address {
+            Address::Current => current,
+            Address::Last => last,
+            Address::Line(n) => n.saturating_sub(1).min(last),
+        };
+        let (start, end) = (line(self.start), line(self.end));
+        start.min(end)..start.max(end) + 1
+    }
+}
+
+impl Substitute {
+    /// the replacement for each line of `lines` with a match, as the byte
+    /// range of its text and what goes there
+    pub fn changes(
+        &self,
+        text: &Rope,
+        lines: Range<usize>,
+    ) -> Result<Vec<(Range<usize>, String)>, CommandError> {
+        let regex =
+            Regex::new(&self.pattern).map_err(|_| CommandError::Pattern(self.pattern.clone()))?;
+        let replacement = self.expander();
+        let mut changes = Vec::new();
+        for line in lines.filter(|&line| line < text.len_lines()) {
+            let content = text.line(line).to_string();
+            let content = content.trim_end_matches(['\n', '\r']);
+            if !regex.is_match(content) {
+                continue;
+            }
+            let new = match self.global {
+                true => regex.replace_all(content, replacement.as_str()),
+                false => regex.replace(content, replacement.as_str()),
+            };
+            let start = text.line_to_byte(line);
+            changes.push((start..start + content.len(), new.into_owned()));
+        }
+        if changes.is_empty() {
+            return Err(CommandError::NotFound(self.pattern.clone()));
+        }
+        Ok(changes)
+    }
+
+    /// the replacement written the way [`Regex::replace`] expands it
+    fn expander(&self) -> String {
+        let mut out = String::new();
+        let mut chars = self.replacement.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '&' => out.push_str("${0}"),
+                '$' => out.push_str("$$"),
+                '\\' => match chars.next() {
+                    Some(n @ '0'..='9') => out.push_str(&format!("${{{n}}}")),
+                    Some('n') => out.push('\n'),
+                    Some('t') => out.push('\t'),
+                    Some('$') => out.push_str("$$"),
+                    Some(c) => out.push(c),
+                    None => out.push('\\'),
+                },
+                c => out.push(c),
+            }
+        }
+        out
+    }
+}
+
+/// the range at the start of a command, if there is one, and the rest
+fn lines(s: &str) -> Result<(Option<Lines>, &str), CommandError> {
+    if let Some(rest) = s.strip_prefix('%') {
+        return Ok((Some(Lines::ALL), rest));
+    }
+    let Some((start, rest)) = address(s) else {
+        return Ok((None, s));
+    };
+    let Some(rest) = rest.strip_prefix(',') else {
+        return Ok((Some(Lines { start, end: start }), rest));
+    };
+    let (end, rest) = address(rest).ok_or_else(|| CommandError::Argument(s.to_owned()))?;
+    Ok((Some(Lines { start, end }), rest))
+}
+
+fn address(s: &str) -> Option<(Address, &str)> {
+    if let Some(rest) = s.strip_prefix('.') {
+        return Some((Address::Current, rest));
+    }
+    if let Some(rest) = s.strip_prefix('$') {
+        return Some((Address::Last, rest));
+    }
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let n = s[..digits].parse().ok()?;
+    Some((Address::Line(n), &s[digits..]))
+}
+
+/// `/{pattern}/{string}/[flags]`, with any delimiter that isn't a letter,
+/// digit, space or backslash
+fn substitute(lines: Lines, s: &str) -> Result<Command, CommandError> {
+    let invalid = || CommandError::Argument(s.to_owned());
+    let delimiter = s
+        .chars()
+        .next()
+        .filter(|c| !c.is_alphanumeric() && !c.is_whitespace() && *c != '\\' && *c != '"')
+        .ok_or_else(invalid)?;
+    let mut parts = vec![String::new()];
+    let mut chars = s[delimiter.len_utf8()..].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // an escaped delimiter is the delimiter itself, other escapes are
+            // left for the pattern or the replacement
+            '\\' => match chars.next() {
+                Some(c) if c == delimiter => parts.last_mut().ok_or_else(invalid)?.push(c),
+                Some(c) => parts.last_mut().ok_or_else(invalid)?.extend(['\\', c]),
+                None => parts.last_mut().ok_or_else(invalid)?.push('\\'),
+            },
+            c if c == delimiter && parts.len() < 3 => parts.push(String::new()),
+            c => parts.last_mut().ok_or_else(invalid)?.push(c),
+        }
+    }
+    let mut parts = parts.into_iter();
+    let pattern = parts.next().filter(|p| !p.is_empty()).ok_or_else(invalid)?;
+    let replacement = parts.next().unwrap_or_default();
+    let flags = parts.next().unwrap_or_default();
+    let global = match flags.trim() {
+        "" => false,
+        "g" => true,
+        flags => return Err(CommandError::Trailing(flags.to_owned())),
+    };
+    Ok(Command::Substitute(Substitute {
+        lines,
+        pattern,
+        replacement,
+        global,
+    }))
+}
+
+fn tab_move(arg: &str) -> Result<TabMove, CommandError> {
+    let invalid = || CommandError::Argument(arg.to_owned());
+    let by = |sign: isize, n: &str| match n {
+        "" => Ok(TabMove::By(sign)),
+        n => Ok(TabMove::By(
+            sign * n.parse::<isize>().map_err(|_| invalid())?,
+        )),
+    };
+    match arg {
+        "" | "$" => Ok(TabMove::Last),
+        _ if arg.starts_with('+') => by(1, &arg[1..]),
+        _ if arg.starts_with('-') => by(-1, &arg[1..]),
+        _ => arg.parse().map(TabMove::After).map_err(|_| invalid()),
+    }
+}
+
+/// whether `name` is `full` cut down to at least `min` characters
+fn abbrev(name: &str, full: &str, min: usize) -> bool {
+    name.len() >= min && full.starts_with(name)
+}
@@ -0,0 +1,77 @@
+use ropey::Rope;
+
+use crate::command::{Address, Command, CommandError, Lines, Substitute};
+
+fn substitute(cmd: &str) -> Substitute {
+    match cmd.parse() {
+        Ok(Command::Substitute(sub)) => sub,
+        other => panic!("not a substitute: {other:?}"),
+    }
+}
+
+#[test]
+fn ranges_and_delimiters() {
+    let sub = substitute(":%s/a/b/g");
+    assert_eq!(sub.lines, Lines::ALL);
+    assert_eq!((sub.pattern.as_str(), sub.replacement.as_str()), ("a", "b"));
+    assert!(sub.global);
+
+    let sub = substitute("2,$s#/usr#\\#opt#");
+    assert_eq!(
+        sub.lines,
+        Lines {
+            start: Address::Line(2),
+            end: Address::Last
+        }
+    );
+    assert_eq!(
+        (sub.pattern.as_str(), sub.replacement.as_str()),
+        ("/usr", "#opt")
+    );
+    assert!(!sub.global);
+    assert_eq!(substitute("s/x").lines, Lines::CURRENT);
+
+    assert!(matches!(
+        "%w".parse::<Command>(),
+        Err(CommandError::NoRange)
+    ));
+    assert!(matches!(
+        "s/a/b/q".parse::<Command>(),
+        Err(CommandError::Trailing(_))
+    ));
+    assert_eq!(
+        "wq!".parse::<Command>().unwrap(),
+        Command::WriteQuit {
+            force: true,
+            modified_only: false
+        }
+    );
+    assert_eq!(
+        "x".parse::<Command>().unwrap(),
+        Command::WriteQuit {
+            force: false,
+            modified_only: true
+        }
+    );
+}
+
+#[test]
+fn substitute_lines() {
+    let text = Rope::from_str("a cat\nno\ncat, cat\n");
+    let lines = Lines::ALL.resolve(0, text.len_lines());
+    assert_eq!(lines, 0..4);
+
+    let changes = substitute("%s/cat/[&]/").changes(&text, lines.clone());
+    assert_eq!(
+        changes.unwrap(),
+        [(0..5, "a [cat]".into()), (9..17, "[cat], cat".into())]
+    );
+    let changes = substitute("%s/(c)(a)t/\\2\\1$/g").changes(&text, lines.clone());
+    assert_eq!(changes.unwrap()[1].1, "ac$, ac$");
+    // only the lines in the range
+    let lines = Lines::CURRENT.resolve(1, text.len_lines());
+    assert!(matches!(
+        substitute("s/cat/dog/").changes(&text, lines),
+        Err(CommandError::NotFound(_))
+    ));
+}
@@ -0,0 +1,378 @@
+//! Completing the word before the cursor in insert mode
+//!
+//! `Ctrl-n` or `Ctrl-p` opens a [`Completion`] for the word being typed,
+//! asking every [`Source`] for candidates there and then. Sources that take a
+//! while, like language servers, answer later through
+//! [`Evie::complete_with`] instead, so typing never waits on them. As typing
+//! carries on the candidates are ranked again against what's been typed of
+//! the word, see [`fuzzy`].
+//!
+//! [`Evie::complete_with`]: crate::Evie::complete_with
+
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use ropey::Rope;
+
+use crate::disk;
+use crate::motion::line_col;
+
+#[cfg(test)]
+mod test;
+
+/// A candidate for the word being completed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    /// what replaces the word
+    pub word: String,
+    /// shown next to it, like what kind of symbol it is
+    pub menu: String,
+    /// the [`Source::name`] it came from
+    pub source: String,
+    /// a snippet body expanded in place of the word, see [`snippet`]
+    ///
+    /// [`snippet`]: crate::snippet
+    pub snippet: Option<String>,
+}
+
+impl Item {
+    pub fn new(word: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            word: word.into(),
+            menu: String::new(),
+            source: source.into(),
+            snippet: None,
+        }
+    }
+
+    pub fn with_menu(self, menu: impl Into<String>) -> Self {
+        Self {
+            menu: menu.into(),
+            ..self
+        }
+    }
+
+    pub fn with_snippet(self, body: impl Into<String>) -> Self {
+        Self {
+            snippet: Some(body.into()),
+            ..self
+        }
+    }
+}
+
+/// What a [`Source`] completes from
+#[derive(Debug, Clone, Copy)]
+pub struct Context<'a> {
+    /// the text of the buffer being typed in
+    pub text: &'a Rope,
+    pub path: &'a Path,
+    pub filetype: &'a str,
+    /// where the word being completed starts, see [`start`]
+    pub start: usize,
+    /// the cursor, where it ends
+    pub at: usize,
+    /// the text of every open buffer, this one included, but those in large
+    /// file mode
+    pub buffers: &'a [Rope],
+}
+
+impl Context<'_> {
+    /// what's been typed of the word
+    pub fn typed(&self) -> String {
+        self.text.byte_slice(self.start..self.at).to_string()
+    }
+}
+
+/// Somewhere candidates come from, asked each time a completion opens
+pub trait Source: Debug {
+    fn name(&self) -> &str;
+
+    fn complete(&self, cx: &Context) -> Vec<Item>;
+}
+
+/// The words in every open buffer
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Words;
+
+impl Source for Words {
+    fn name(&self) -> &str {
+        "words"
+    }
+
+    fn complete(&self, cx: &Context) -> Vec<Item> {
+        let typed = cx.typed();
+        let mut seen = HashSet::new();
+        let mut items = Vec::new();
+        for text in cx.buffers {
+            for line in text.lines() {
+                let line = line.to_string();
+                for word in line.split(|c: char| !is_word(c)) {
+                    let first = word.chars().next();
+                    if word.len() < 2 || word == typed || first.is_some_and(|c| c.is_numeric()) {
+                        continue;
+                    }
+                    if seen.insert(word.to_owned()) {
+                        items.push(Item::new(word, self.name()));
+                    }
+                }
+            }
+        }
+        items
+    }
+}
+
+/// The files in the directory typed before the word, relative to the
+/// buffer's own directory. Directories end with `/`, to carry on into them
+///
+/// a directory is only listed again once it changes, slow ones like network
+/// mounts costing a `stat` after the first time.
+#[derive(Debug, Default, Clone)]
+pub struct Paths {
+    /// the entries of each directory listed, with its mtime then
+    listed: RefCell<HashMap<PathBuf, (SystemTime, Vec<Item>)>>,
+}
+
+impl Source for Paths {
+    fn name(&self) -> &str {
+        "paths"
+    }
+
+    fn complete(&self, cx: &Context) -> Vec<Item> {
+        let Some(dir) = typed_dir(cx.text, cx.start) else {
+            return Vec::new();
+        };
+        let dir = match dir.as_str() {
+            dir if dir.starts_with('~') => disk::expand_home(dir),
+            dir => cx.path.parent().unwrap_or(Path::new("")).join(dir),
+        };
+        let Ok(mtime) = std::fs::metadata(&dir).and_then(|meta| meta.modified()) else {
+            return Vec::new();
+        };
+        if let Some((listed, items)) = self.listed.borrow().get(&dir) {
+            if *listed == mtime {
+                return items.clone();
+            }
+        }
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return Vec::new();
+        };
+        let mut items: Vec<_> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                Some(match entry.file_type().ok()?.is_dir() {
+                    true => Item::new(format!("{name}/"), self.name()).with_menu("dir"),
+                    false => Item::new(name, self.name()).with_menu("file"),
+                })
+            })
+            .collect();
+        items.sort_by(|a, b| a.word.cmp(&b.word));
+        self.listed.borrow_mut().insert(dir, (mtime, items.clone()));
+        items
+    }
+}
+
+/// the directory typed right before byte `start`, ending with `/`
+fn typed_dir(text: &Rope, start: usize) -> Option<String> {
+    let (line, col) = line_col(text, start);
+    let before = text.line(line).to_string();
+    let before = &before[..col];
+    let dir = before
+        .rsplit(|c: char| c.is_whitespace() || "\"'`(=".contains(c))
+        .next()?;
+    dir.ends_with('/').then(|| dir.to_owned())
+}
+
+/// whether `c` is part of a word, for completing words
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// where the word before byte `at` starts. after a `/` only the last part
+/// of the path is completed
+pub fn start(text: &Rope, at: usize) -> usize {
+    let (line, col) = line_col(text, at);
+    let content = text.line(line).to_string();
+    let before = &content[..col];
+    let path = before.rsplit(char::is_whitespace).next().and_then(|run| {
+        run.rfind('/')
+            .map(|slash| before.len() - run.len() + slash + 1)
+    });
+    let word = before
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_word(c))
+        .last()
+        .map_or(col, |(i, _)| i);
+    text.line_to_byte(line) + path.unwrap_or(word)
+}
+
+/// how well `typed` matches `candidate`, none when its characters aren't all
+/// in it in order. matches at the start, at the start of words and runs of
+/// matching characters score higher, skipped characters lower
+pub fn fuzzy(typed: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut last = None;
+    for c in typed.chars() {
+        let found =
+            (next..candidate.len()).find(|&i| candidate[i].to_lowercase().eq(c.to_lowercase()))?;
+        let before = found.checked_sub(1).map(|i| candidate[i]);
+        score += match before {
+            None => 8,
+            _ if last.is_some_and(|last| last + 1 == found) => 5,
+            Some(before) if !is_word(before) => 6,
+            Some(before) if before.is_lowercase() && candidate[found].is_uppercase() => 6,
+            _ => 1,
+        };
+        if candidate[found] == c {
+            score += 1;
+        }
+        score -= (found - next) as i64;
+        last = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+/// A completion open in a buffer, see the module docs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub path: PathBuf,
+    /// where the word being completed starts
+    pub start: usize,
+    items: Vec<Item>,
+    /// the items that match what's been typed, best first
+    matches: Vec<usize>,
+    /// what the matches were ranked against
+    typed: String,
+    /// whether what's typed filters and ranks the candidates, which it
+    /// doesn't for a snippet's choices
+    ranked: bool,
+    /// which match is selected, none before `Ctrl-n` or `Ctrl-p`. it can be
+    /// past the end while there are no matches, until some come in
+    selected: Option<usize>,
+}
+
+impl Completion {
+    pub fn new(path: PathBuf, start: usize) -> Self {
+        Self {
+            path,
+            start,
+            items: Vec::new(),
+            matches: Vec::new(),
+            typed: String::new(),
+            ranked: true,
+            selected: None,
+        }
+    }
+
+    /// the choices of a snippet's tabstop, in the order given
+    pub fn choices(path: PathBuf, start: usize, items: Vec<Item>) -> Self {
+        let mut completion = Self {
+            ranked: false,
+            ..Self::new(path, start)
+        };
+        completion.add(items);
+        completion
+    }
+
+    /// takes in more candidates, leaving out words there already are
+    pub fn add(&mut self, items: Vec<Item>) {
+        let mut seen: HashSet<_> = self.items.iter().map(|item| item.word.clone()).collect();
+        let items = items
+            .into_iter()
+            .filter(|item| seen.insert(item.word.clone()));
+        self.items.extend(items);
+        let typed = std::mem::take(&mut self.typed);
+        self.rank(&typed, true);
+    }
+
+    /// ranks the candidates against what's been typed of the word, keeping
+    /// the selection on the best match if there is one
+    pub fn filter(&mut self, typed: &str) {
+        if self.ranked && typed != self.typed {
+            self.rank(typed, false);
+        }
+    }
+
+    fn rank(&mut self, typed: &str, keep: bool) {
+        let selected = self.selected().cloned();
+        let mut scored: Vec<_> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| match self.ranked {
+                true => Some((i, fuzzy(typed, &item.word)?)),
+                false => Some((i, 0)),
+            })
+            .collect();
+        if self.ranked {
+            scored.sort_by_key(|&(i, score)| (Reverse(score), self.items[i].word.len(), i));
+        }
+        self.matches = scored.into_iter().map(|(i, _)| i).collect();
+        self.typed = typed.to_owned();
+        // more candidates coming in shouldn't move the selection
+        let kept = selected
+            .filter(|_| keep)
+            .and_then(|selected| self.matches.iter().position(|&i| self.items[i] == selected));
+        self.selected = self.selected.map(|_| kept.unwrap_or(0));
+    }
+
+    /// the candidates that match, best first
+    pub fn matches(&self) -> Vec<&Item> {
+        self.matches.iter().map(|&i| &self.items[i]).collect()
+    }
+
+    pub fn selected(&self) -> Option<&Item> {
+        Some(&self.items[*self.matches.get(self.selected?)?])
+    }
+
+    /// where the selection is among the matches
+    pub fn selected_index(&self) -> Option<usize> {
+        self.selected
+            .filter(|&selected| selected < self.matches.len())
+    }
+
+    /// moves the selection `by` matches, wrapping around, starting from the
+    /// first going down or the last going up
+    pub fn select(&mut self, by: isize) {
+        let len = self.matches.len() as isize;
+        if len == 0 {
+            self.selected = Some(0);
+            return;
+        }
+        let to = match self.selected {
+            Some(selected) => selected as isize + by,
+            None if by > 0 => by - 1,
+            None => len + by,
+        };
+        self.selected = Some(to.rem_euclid(len) as usize);
+    }
+}
+
+/// What the frontend shows of an open completion, see
+/// [`BufferView::completion`](crate::BufferView::completion)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Menu {
+    /// the matches, best first
+    pub items: Vec<Item>,
+    pub selected: Option<usize>,
+    /// where the word being completed starts, which the menu lines up with
+    pub start: usize,
+}
+
+impl From<&Completion> for Menu {
+    fn from(completion: &Completion) -> Self {
+        Self {
+            items: completion.matches().into_iter().cloned().collect(),
+            selected: completion.selected_index(),
+            start: completion.start,
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use ropey::Rope;
+
+use crate::completion::{fuzzy, start, Completion, Context, Item, Paths, Source, Words};
+
+fn words(completion: &Completion) -> Vec<&str> {
+    completion
+        .matches()
+        .iter()
+        .map(|item| item.word.as_str())
+        .collect()
+}
+
+#[test]
+fn fuzzy_ranking() {
+    assert!(fuzzy("fb", "foo_bar").is_some());
+    assert!(fuzzy("bf", "foo_bar").is_none());
+    // the start of the word and of its parts beat the middle
+    assert!(fuzzy("fb", "foo_bar") > fuzzy("fb", "xfooxbar"));
+    assert!(fuzzy("gv", "getValue") > fuzzy("gv", "gravel"));
+    // and a run beats a gap
+    assert!(fuzzy("buf", "buffer") > fuzzy("buf", "bound_file"));
+
+    let mut completion = Completion::new("a.rs".into(), 0);
+    let items = ["bound_file", "buffer", "Buf", "other"].map(|word| Item::new(word, "test"));
+    completion.add(items.to_vec());
+    completion.filter("buf");
+    // matching the case typed counts for a little
+    assert_eq!(words(&completion), ["buffer", "Buf", "bound_file"]);
+    // the same word from another source isn't added twice
+    completion.add(vec![Item::new("buffer", "lsp"), Item::new("bufs", "lsp")]);
+    assert_eq!(words(&completion), ["bufs", "buffer", "Buf", "bound_file"]);
+}
+
+#[test]
+fn selection_wraps_and_survives_new_items() {
+    let mut completion = Completion::new("a.rs".into(), 0);
+    assert!(completion.selected().is_none());
+    completion.select(1);
+    completion.add(vec![Item::new("one", "test"), Item::new("two", "test")]);
+    assert_eq!(completion.selected().unwrap().word, "one");
+    completion.select(-1);
+    assert_eq!(completion.selected().unwrap().word, "two");
+    completion.select(1);
+    assert_eq!(completion.selected_index(), Some(0));
+    // candidates coming in later leave the selection on the same one
+    completion.select(1);
+    completion.add(vec![Item::new("on", "test")]);
+    assert_eq!(completion.selected().unwrap().word, "two");
+    completion.filter("zz");
+    assert!(completion.selected().is_none());
+}
+
+#[test]
+fn words_and_start() {
+    let text = Rope::from_str("let buffer = buf_len(x2);\nlet b");
+    let other = Rope::from_str("buffered 42 buffer");
+    let at = text.len_bytes();
+    assert_eq!(start(&text, at), at - 1);
+    assert_eq!(start(&text, 4), 4);
+    let path = Rope::from_str("open(\"src/ma");
+    assert_eq!(start(&path, 12), 10);
+
+    let cx = Context {
+        text: &text,
+        path: Path::new("a.rs"),
+        filetype: "rust",
+        start: at - 1,
+        at,
+        buffers: &[text.clone(), other],
+    };
+    let found: Vec<_> = Words
+        .complete(&cx)
+        .into_iter()
+        .map(|item| item.word)
+        .collect();
+    assert_eq!(found, ["let", "buffer", "buf_len", "x2", "buffered"]);
+}
+
+#[test]
+fn paths_from_the_buffer_directory() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    std::fs::create_dir_all(dir.join("src/bin")).unwrap();
+    std::fs::write(dir.join("src/main.rs"), "").unwrap();
+
+    let text = Rope::from_str("mod \"src/m");
+    let at = text.len_bytes();
+    let cx = Context {
+        text: &text,
+        path: &dir.join("notes.txt"),
+        filetype: "",
+        start: start(&text, at),
+        at,
+        buffers: &[],
+    };
+    let paths = Paths::default();
+    let found: Vec<_> = paths
+        .complete(&cx)
+        .into_iter()
+        .map(|item| (item.word, item.menu))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("bin/".into(), "dir".into()),
+            ("main.rs".into(), "file".into())
+        ]
+    );
+    // listed again once the directory changes
+    std::fs::write(dir.join("src/lib.rs"), "").unwrap();
+    assert_eq!(paths.complete(&cx).len(), 3);
+
+    // nothing without a directory before the word
+    let text = Rope::from_str("src");
+    let cx = Context {
+        text: &text,
+        start: 0,
+        at: 3,
+        ..cx
+    };
+    assert!(paths.complete(&cx).is_empty());
+}
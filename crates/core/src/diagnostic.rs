@@ -0,0 +1,182 @@
+//! Errors and warnings about the text, from language servers, linters and
+//! the like
+//!
+//! Each provider sets all of its diagnostics for a buffer at once, replacing
+//! the ones it set before, so several can report on the same buffer without
+//! knowing about each other. Until a provider looks at the text again its
+//! diagnostics move along with the edits made to it.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use ropey::Rope;
+
+use crate::theme::UiGroup;
+
+#[cfg(test)]
+mod test;
+
+/// How bad a diagnostic is, the worst first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Info => "info",
+            Self::Hint => "hint",
+        }
+    }
+
+    /// what's shown in the gutter next to the line
+    pub fn sign(self) -> &'static str {
+        match self {
+            Self::Error => "E",
+            Self::Warning => "W",
+            Self::Info => "I",
+            Self::Hint => "H",
+        }
+    }
+
+    /// the group it's drawn in
+    pub fn group(self) -> UiGroup {
+        match self {
+            Self::Error => UiGroup::DiagnosticError,
+            Self::Warning => UiGroup::DiagnosticWarn,
+            Self::Info => UiGroup::DiagnosticInfo,
+            Self::Hint => UiGroup::DiagnosticHint,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// the bytes of the text it's about
+    pub range: Range<usize>,
+    pub severity: Severity,
+    pub message: String,
+    /// what found it, like the name of a compiler
+    pub source: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(range: Range<usize>, severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            range,
+            severity,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn with_source(self, source: impl Into<String>) -> Self {
+        Self {
+            source: Some(source.into()),
+            ..self
+        }
+    }
+}
+
+/// The diagnostics of a buffer, by provider
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Diagnostics {
+    providers: BTreeMap<String, Vec<Diagnostic>>,
+}
+
+impl Diagnostics {
+    /// replaces what `provider` reported before, an empty list clears it
+    pub fn set(&mut self, provider: &str, diagnostics: Vec<Diagnostic>) {
+        if diagnostics.is_empty() {
+            self.providers.remove(provider);
+        } else {
+            self.providers.insert(provider.to_owned(), diagnostics);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.providers.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+
+    /// every provider's diagnostics, in the order they are in the text
+    pub fn all(&self) -> Vec<&Diagnostic> {
+        let mut all: Vec<_> = self.providers.values().flatten().collect();
+        all.sort_by_key(|diagnostic| (diagnostic.range.start, diagnostic.severity));
+        all
+    }
+
+    /// the worst severity on each line with a diagnostic starting on it
+    pub fn signs(&self, text: &Rope) -> BTreeMap<usize, Severity> {
+        let mut signs = BTreeMap::new();
+        for diagnostic in self.providers.values().flatten() {
+            let line = text.byte_to_line(diagnostic.range.start.min(text.len_bytes()));
+            signs
+                .entry(line)
+                .and_modify(|worst: &mut Severity| *worst = diagnostic.severity.min(*worst))
+                .or_insert(diagnostic.severity);
+        }
+        signs
+    }
+
+    /// the diagnostic starting after byte `at`, or before it going back,
+    /// wrapping around the ends of the text
+    pub fn next(&self, at: usize, forward: bool) -> Option<&Diagnostic> {
+        let all = self.all();
+        let found = match forward {
+            true => all.iter().find(|diagnostic| diagnostic.range.start > at),
+            false => all
+                .iter()
+                .rev()
+                .find(|diagnostic| diagnostic.range.start < at),
+        };
+        let wrapped = match forward {
+            true => all.first(),
+            false => all.last(),
+        };
+        found.or(wrapped).copied()
+    }
+
+    /// notes that bytes `start..old_end` were replaced with `start..new_end`.
+    /// text added where a diagnostic starts goes before it, where one ends
+    /// after it
+    pub fn edit(&mut self, start: usize, old_end: usize, new_end: usize) {
+        let moved = |at: usize| at + new_end - old_end;
+        for diagnostic in self.providers.values_mut().flatten() {
+            let range = &mut diagnostic.range;
+            let from = match range.start {
+                at if at < start => at,
+                at if at >= old_end => moved(at),
+                _ => new_end,
+            };
+            let to = match range.end {
+                at if at <= start => at,
+                at if at >= old_end => moved(at),
+                _ => start,
+            };
+            *range = from..to.max(from);
+        }
+    }
+}
+
+/// A diagnostic in the list of every buffer's, see [`Evie::diagnostics`]
+///
+/// [`Evie::diagnostics`]: crate::Evie::diagnostics
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listed {
+    pub path: PathBuf,
+    /// where it starts, counting from 0, the column on screen
+    pub line: usize,
+    pub col: usize,
+    pub diagnostic: Diagnostic,
+}
@@ -0,0 +1,76 @@
+use ropey::Rope;
+
+use crate::diagnostic::{Diagnostic, Diagnostics, Severity};
+
+#[test]
+fn providers_and_signs() {
+    let text = Rope::from_str("let a = 1;\nlet b = a +;\n");
+    let mut diagnostics = Diagnostics::default();
+    diagnostics.set(
+        "compiler",
+        vec![
+            Diagnostic::new(22..23, Severity::Error, "expected expression"),
+            Diagnostic::new(4..5, Severity::Hint, "unused").with_source("rustc"),
+        ],
+    );
+    diagnostics.set(
+        "linter",
+        vec![Diagnostic::new(15..16, Severity::Warning, "short name")],
+    );
+    let starts: Vec<_> = diagnostics.all().iter().map(|d| d.range.start).collect();
+    assert_eq!(starts, [4, 15, 22]);
+    let signs: Vec<_> = diagnostics.signs(&text).into_iter().collect();
+    assert_eq!(signs, [(0, Severity::Hint), (1, Severity::Error)]);
+
+    // each provider only replaces its own
+    diagnostics.set("compiler", Vec::new());
+    assert_eq!(diagnostics.all().len(), 1);
+    diagnostics.set("linter", Vec::new());
+    assert!(diagnostics.is_empty());
+}
+
+#[test]
+fn next_wraps_around() {
+    let mut diagnostics = Diagnostics::default();
+    diagnostics.set(
+        "test",
+        vec![
+            Diagnostic::new(10..12, Severity::Error, "b"),
+            Diagnostic::new(2..3, Severity::Error, "a"),
+        ],
+    );
+    let next = |at, forward| diagnostics.next(at, forward).unwrap().message.clone();
+    assert_eq!(next(0, true), "a");
+    assert_eq!(next(2, true), "b");
+    assert_eq!(next(10, true), "a");
+    assert_eq!(next(10, false), "a");
+    assert_eq!(next(2, false), "b");
+    assert!(Diagnostics::default().next(0, true).is_none());
+}
+
+#[test]
+fn edits_move_ranges() {
+    let mut diagnostics = Diagnostics::default();
+    diagnostics.set(
+        "test",
+        vec![
+            Diagnostic::new(4..8, Severity::Error, "a"),
+            Diagnostic::new(20..22, Severity::Error, "b"),
+        ],
+    );
+    let ranges = |diagnostics: &Diagnostics| -> Vec<_> {
+        diagnostics.all().iter().map(|d| d.range.clone()).collect()
+    };
+    // typed at the start of the first, inside it, and at its end
+    diagnostics.edit(4, 4, 6);
+    assert_eq!(ranges(&diagnostics), [6..10, 22..24]);
+    diagnostics.edit(8, 8, 9);
+    diagnostics.edit(11, 11, 12);
+    assert_eq!(ranges(&diagnostics), [6..11, 24..26]);
+    // a deletion over the start of the second
+    diagnostics.edit(20, 25, 20);
+    assert_eq!(ranges(&diagnostics), [6..11, 20..21]);
+    // and one taking all of the first
+    diagnostics.edit(5, 15, 7);
+    assert_eq!(ranges(&diagnostics), [7..7, 12..13]);
+}
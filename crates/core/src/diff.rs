@@ -0,0 +1,138 @@
+//! A small line based diff, used to show what changed on disk
+
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line {
+    Same(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// diffs two sequences of lines using myers' algorithm
+pub fn lines<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Line> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    let mut trace = Vec::new();
+
+    'outer: for d in 0..=max as isize {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                break 'outer;
+            }
+        }
+    }
+
+    let (mut x, mut y) = (n, m);
+    let mut out = Vec::new();
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let i = (k + offset) as usize;
+        let prev_k = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[(prev_k + offset) as usize];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            out.push(Line::Same(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                out.push(Line::Insert(prev_y as usize));
+            } else {
+                out.push(Line::Delete(prev_x as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    out.reverse();
+    out
+}
+
+/// renders a unified diff with three lines of context
+pub fn unified(old_name: &str, old: &str, new_name: &str, new: &str) -> String {
+    const CONTEXT: usize = 3;
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let script = lines(&a, &b);
+
+    let mut out = String::new();
+    let changes: Vec<usize> = script
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| !matches!(l, Line::Same(..)))
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return out;
+    }
+    let _ = writeln!(out, "--- {old_name}\n+++ {new_name}");
+
+    let mut start = 0;
+    while start < changes.len() {
+        let mut end = start;
+        while end + 1 < changes.len() && changes[end + 1] - changes[end] <= 2 * CONTEXT {
+            end += 1;
+        }
+        let lo = changes[start].saturating_sub(CONTEXT);
+        let hi = (changes[end] + CONTEXT + 1).min(script.len());
+        let hunk = &script[lo..hi];
+
+        let (mut old_start, mut new_start) = (None, None);
+        let (mut old_len, mut new_len) = (0, 0);
+        for line in hunk {
+            match *line {
+                Line::Same(x, y) => {
+                    old_start.get_or_insert(x);
+                    new_start.get_or_insert(y);
+                    old_len += 1;
+                    new_len += 1;
+                }
+                Line::Delete(x) => {
+                    old_start.get_or_insert(x);
+                    old_len += 1;
+                }
+                Line::Insert(y) => {
+                    new_start.get_or_insert(y);
+                    new_len += 1;
+                }
+            }
+        }
+        let _ = writeln!(
+            out,
+            "@@ -{},{old_len} +{},{new_len} @@",
+            old_start.map_or(0, |s| s + 1),
+            new_start.map_or(0, |s| s + 1),
+        );
+        for line in hunk {
+            let _ = match *line {
+                Line::Same(x, _) => writeln!(out, " {}", a[x]),
+                Line::Delete(x) => writeln!(out, "-{}", a[x]),
+                Line::Insert(y) => writeln!(out, "+{}", b[y]),
+            };
+        }
+        start = end + 1;
+    }
+    out
+}
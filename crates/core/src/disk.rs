@@ -0,0 +1,209 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+#[cfg(test)]
+mod test;
+
+/// What a file looked like on disk when it was last loaded or saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskState {
+    pub mtime: Option<SystemTime>,
+    pub len: u64,
+    pub hash: u64,
+}
+
+/// A change to an open buffer's file made by something other than evie
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiskEvent {
+    /// the buffer had no local changes, so it was reloaded
+    Reloaded(PathBuf),
+    /// the buffer has local changes, the user has to pick a side
+    Conflict(PathBuf),
+    /// the file is gone
+    Deleted(PathBuf),
+}
+
+impl DiskState {
+    /// builds the state for the given contents, which were just read or written
+    pub fn new(meta: &fs::Metadata, bytes: &[u8]) -> Self {
+        Self {
+            mtime: meta.modified().ok(),
+            len: meta.len(),
+            hash: hash(bytes),
+        }
+    }
+
+    /// reads the current state of a file, returns none if it doesn't exist
+    pub fn read(path: &Path) -> io::Result<Option<Self>> {
+        let Some(meta) = handle_nf(fs::metadata(path))? else {
+            return Ok(None);
+        };
+        let Some(bytes) = handle_nf(fs::read(path))? else {
+            return Ok(None);
+        };
+        Ok(Some(Self::new(&meta, &bytes)))
+    }
+
+    /// checks whether the file still matches this state
+    ///
+    /// the contents are only hashed when the metadata differs, so a `touch`
+    /// is not reported as a change.
+    pub fn check(&self, path: &Path) -> io::Result<Check> {
+        let Some(meta) = handle_nf(fs::metadata(path))? else {
+            return Ok(Check::Deleted);
+        };
+        if meta.modified().ok() == self.mtime && meta.len() == self.len {
+            return Ok(Check::Same);
+        }
+        let Some(bytes) = handle_nf(fs::read(path))? else {
+            return Ok(Check::Deleted);
+        };
+        let now = Self::new(&meta, &bytes);
+        Ok(if now.hash == self.hash {
+            Check::Touched(now)
+        } else {
+            Check::Changed(now)
+        })
+    }
+
+    /// like [`check`](Self::check) but from the metadata alone, for large
+    /// files that are too slow to read again
+    ///
+    /// a `touch` counts as a change, and the state keeps this hash.
+    pub fn stat(&self, path: &Path) -> io::Result<Check> {
+        let Some(meta) = handle_nf(fs::metadata(path))? else {
+            return Ok(Check::Deleted);
+        };
+        let mtime = meta.modified().ok();
+        Ok(if mtime == self.mtime && meta.len() == self.len {
+            Check::Same
+        } else {
+            Check::Changed(Self {
+                mtime,
+                len: meta.len(),
+                hash: self.hash,
+            })
+        })
+    }
+}
+
+/// The outcome of [`DiskState::check`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    Same,
+    /// the metadata changed but the contents did not
+    Touched(DiskState),
+    Changed(DiskState),
+    Deleted,
+}
+
+/// 64 bit FNV-1a, stable across runs and platforms
+pub fn hash(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// An incremental 64 bit FNV-1a hasher
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl std::hash::Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        Fnv::write(self, bytes)
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// maps not found errors to none
+pub(crate) fn handle_nf<T>(res: Result<T, io::Error>) -> Result<Option<T>, io::Error> {
+    match res {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if matches!(e.kind(), io::ErrorKind::NotFound) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// evie's directory for state like swap and undo files, following the xdg
+/// base directory spec
+pub fn state_dir() -> Option<PathBuf> {
+    let state = xdg("XDG_STATE_HOME")
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| home().map(|h| h.join(".local/state")))?;
+    Some(state.join("evie"))
+}
+
+/// evie's directory for sockets and the like, which only live as long as
+/// the session, the state directory where there's no runtime directory
+pub fn runtime_dir() -> Option<PathBuf> {
+    match xdg("XDG_RUNTIME_DIR") {
+        Some(runtime) => Some(runtime.join("evie")),
+        None => state_dir(),
+    }
+}
+
+/// evie's directory for configuration
+pub fn config_dir() -> Option<PathBuf> {
+    let config = xdg("XDG_CONFIG_HOME")
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| home().map(|h| h.join(".config")))?;
+    Some(config.join("evie"))
+}
+
+fn xdg(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+}
+
+fn home() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// expands a leading `~` to the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), home()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            home.join(rest.trim_start_matches(['/', '\\']))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// turns a path into a single file name, for files kept about other files
+pub(crate) fn flat_name(path: &Path) -> String {
+    path.to_string_lossy()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' => '%',
+            c => c,
+        })
+        .collect()
+}
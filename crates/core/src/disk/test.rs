@@ -0,0 +1,111 @@
+use std::fs;
+
+use tempfile::tempdir;
+
+use crate::{diff, disk::DiskEvent, engine::Engine, BufferAction};
+
+#[test]
+fn unchanged() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("unchanged.txt");
+    fs::write(&path, "one\n").unwrap();
+    let engine = Engine::default();
+    engine.add_buffer(&path, false).unwrap();
+    assert_eq!(engine.check_disk().unwrap(), []);
+    fs::write(&path, "one\n").unwrap();
+    assert_eq!(engine.check_disk().unwrap(), []);
+}
+
+#[test]
+fn reload_unmodified() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("reload.txt");
+    fs::write(&path, "one\n").unwrap();
+    let engine = Engine::default();
+    let buf = engine.add_buffer(&path, false).unwrap();
+    fs::write(&path, "one\ntwo\n").unwrap();
+    assert_eq!(
+        engine.check_disk().unwrap(),
+        [DiskEvent::Reloaded(path.clone())]
+    );
+    assert_eq!(buf.borrow().text, "one\ntwo\n");
+}
+
+#[test]
+fn conflict_modified() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("conflict.txt");
+    fs::write(&path, "one\n").unwrap();
+    let engine = Engine::default();
+    let buf = engine.add_buffer(&path, false).unwrap();
+    buf.borrow_mut()
+        .apply(BufferAction::Append("zero\n".into()))
+        .unwrap();
+    fs::write(&path, "one\ntwo\n").unwrap();
+    assert_eq!(
+        engine.check_disk().unwrap(),
+        [DiskEvent::Conflict(path.clone())]
+    );
+    // only reported once per change
+    assert_eq!(engine.check_disk().unwrap(), []);
+    assert_eq!(buf.borrow().text, "zero\none\n");
+
+    fs::remove_file(&path).unwrap();
+    assert_eq!(engine.check_disk().unwrap(), [DiskEvent::Deleted(path)]);
+}
+
+#[test]
+fn unified_diff() {
+    assert_eq!(diff::unified("a", "one\ntwo\n", "b", "one\ntwo\n"), "");
+    assert_eq!(
+        diff::unified("a", "one\ntwo\nthree\n", "b", "one\n2\nthree\nfour\n"),
+        "--- a\n+++ b\n@@ -1,3 +1,4 @@\n one\n-two\n+2\n three\n+four\n"
+    );
+}
+
+#[test]
+fn large_by_metadata() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("large.log");
+    let line = "x".repeat(1023) + "\n";
+    fs::write(&path, line.repeat(1025)).unwrap();
+    let engine = Engine::default();
+    engine.options.borrow_mut().largefile = 1;
+    let buf = engine.add_buffer(&path, false).unwrap();
+    assert!(buf.borrow().large);
+    let disk = buf.borrow().disk.unwrap();
+    buf.borrow_mut()
+        .apply(BufferAction::Append("zero\n".into()))
+        .unwrap();
+    fs::write(&path, line.repeat(1026)).unwrap();
+    assert_eq!(
+        engine.check_disk().unwrap(),
+        [DiskEvent::Conflict(path.clone())]
+    );
+    // not read again, so the swap journal's base stays
+    assert_eq!(buf.borrow().disk.unwrap().hash, disk.hash);
+    assert_ne!(buf.borrow().disk.unwrap().len, disk.len);
+}
+
+#[test]
+fn large_files_stream_in() {
+    let tmp = tempdir().unwrap();
+    let engine = Engine::default();
+    engine.options.borrow_mut().largefile = 1;
+    let line = "x".repeat(1023) + "\n";
+
+    let plain = tmp.path().join("plain.log");
+    fs::write(&plain, line.repeat(1025)).unwrap();
+    let buf = engine.add_buffer(&plain, false).unwrap();
+    assert_eq!(buf.borrow().text, line.repeat(1025));
+    // not hashed, nothing needing it in large files
+    assert_eq!(buf.borrow().disk.unwrap().hash, 0);
+
+    // what isn't plain utf-8 is still read whole and saved as it was
+    let broken = tmp.path().join("broken.log");
+    let bytes = [line.repeat(1025).as_bytes(), b"\xff\n"].concat();
+    fs::write(&broken, &bytes).unwrap();
+    engine.add_buffer(&broken, false).unwrap();
+    engine.write(&broken, false).unwrap();
+    assert_eq!(fs::read(&broken).unwrap(), bytes);
+}
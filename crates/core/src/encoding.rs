@@ -0,0 +1,271 @@
+//! Line endings and character encodings, detected on load and kept on save
+//!
+//! Text is always held with `\n` line endings as a utf-8 [`Rope`], the
+//! [`Format`] remembers how to turn it back into the bytes it came from.
+
+use std::{borrow::Cow, io};
+
+use ropey::Rope;
+
+use crate::options::{gen_options, Value};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    /// `\n`
+    #[default]
+    Unix,
+    /// `\r\n`
+    Dos,
+    /// `\r`
+    Mac,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+gen_options! {
+    /// How a buffer's text is stored on disk, these options are local to a buffer
+    pub struct Format {
+        /// the line ending
+        fileformat | ff: FileFormat = FileFormat::Unix,
+        /// the character encoding
+        fileencoding | fenc: Encoding = Encoding::Utf8,
+        /// whether the file starts with a byte order mark
+        bomb: bool = false,
+    }
+}
+
+impl FileFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Unix => "unix",
+            Self::Dos => "dos",
+            Self::Mac => "mac",
+        }
+    }
+
+    fn ending(self) -> &'static str {
+        match self {
+            Self::Unix => "\n",
+            Self::Dos => "\r\n",
+            Self::Mac => "\r",
+        }
+    }
+
+    /// picks the format most lines use, like vim a file is only dos if every
+    /// line ends in `\r\n`
+    fn detect(text: &str) -> Self {
+        let (mut crlf, mut cr, mut lf) = (0, 0, 0);
+        let mut chars = text.bytes().peekable();
+        while let Some(b) = chars.next() {
+            match b {
+                b'\r' if chars.peek() == Some(&b'\n') => {
+                    chars.next();
+                    crlf += 1;
+                }
+                b'\r' => cr += 1,
+                b'\n' => lf += 1,
+                _ => {}
+            }
+        }
+        if crlf > 0 && lf == 0 {
+            Self::Dos
+        } else if cr > 0 && lf == 0 && crlf == 0 {
+            Self::Mac
+        } else {
+            Self::Unix
+        }
+    }
+}
+
+impl Value for FileFormat {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "unix" => Some(Self::Unix),
+            "dos" => Some(Self::Dos),
+            "mac" => Some(Self::Mac),
+            _ => None,
+        }
+    }
+}
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16Le => "utf-16le",
+            Self::Utf16Be => "utf-16be",
+            Self::Latin1 => "latin1",
+        }
+    }
+
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Self::Utf8 => b"\xef\xbb\xbf",
+            Self::Utf16Le => b"\xff\xfe",
+            Self::Utf16Be => b"\xfe\xff",
+            Self::Latin1 => b"",
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> Option<Cow<'_, str>> {
+        match self {
+            Self::Utf8 => std::str::from_utf8(bytes).ok().map(Cow::Borrowed),
+            Self::Utf16Le | Self::Utf16Be => {
+                if !bytes.len().is_multiple_of(2) {
+                    return None;
+                }
+                let units = bytes.chunks_exact(2).map(|c| match self {
+                    Self::Utf16Le => u16::from_le_bytes([c[0], c[1]]),
+                    _ => u16::from_be_bytes([c[0], c[1]]),
+                });
+                char::decode_utf16(units)
+                    .collect::<Result<String, _>>()
+                    .ok()
+                    .map(Cow::Owned)
+            }
+            Self::Latin1 => Some(Cow::Owned(bytes.iter().map(|&b| b as char).collect())),
+        }
+    }
+
+    fn encode(self, text: &str, out: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            Self::Utf8 => out.extend_from_slice(text.as_bytes()),
+            Self::Utf16Le => text
+                .encode_utf16()
+                .for_each(|u| out.extend_from_slice(&u.to_le_bytes())),
+            Self::Utf16Be => text
+                .encode_utf16()
+                .for_each(|u| out.extend_from_slice(&u.to_be_bytes())),
+            Self::Latin1 => {
+                for c in text.chars() {
+                    let b = u8::try_from(c as u32).map_err(|_| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("can't write {c:?} as latin1"),
+                        )
+                    })?;
+                    out.push(b);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// guesses utf-16 without a bom from how the zero bytes of ascii text
+    /// line up
+    fn sniff_utf16(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+            return None;
+        }
+        let pairs = bytes.len() / 2;
+        let (mut even, mut odd) = (0, 0);
+        for c in bytes.chunks_exact(2) {
+            even += (c[0] == 0) as usize;
+            odd += (c[1] == 0) as usize;
+        }
+        if odd * 2 > pairs && even == 0 {
+            Some(Self::Utf16Le)
+        } else if even * 2 > pairs && odd == 0 {
+            Some(Self::Utf16Be)
+        } else {
+            None
+        }
+    }
+}
+
+impl Value for Encoding {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Self::Utf8),
+            "utf-16le" | "utf16le" | "ucs-2le" => Some(Self::Utf16Le),
+            "utf-16" | "utf-16be" | "utf16" | "utf16be" | "ucs-2" => Some(Self::Utf16Be),
+            "latin1" | "iso-8859-1" => Some(Self::Latin1),
+            _ => None,
+        }
+    }
+}
+
+/// turns the bytes of a file into text, detecting how it was stored
+pub fn decode(bytes: &[u8]) -> (Rope, Format) {
+    let (encoding, bom, body) = [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be]
+        .into_iter()
+        .find_map(|enc| Some((enc, true, bytes.strip_prefix(enc.bom())?)))
+        .or_else(|| Some((Encoding::sniff_utf16(bytes)?, false, bytes)))
+        .unwrap_or((Encoding::Utf8, false, bytes));
+
+    // utf-8 text with unix line endings, by far the most common, is never copied
+    // before going into the rope
+    let (text, encoding, bom) = match encoding.decode(body) {
+        Some(text) => (text, encoding, bom),
+        // every byte is a latin1 character, so whatever the file is, like
+        // binary that happens to start with a bom, it's saved as it was
+        None => (
+            Encoding::Latin1.decode(bytes).unwrap_or_default(),
+            Encoding::Latin1,
+            false,
+        ),
+    };
+
+    let fileformat = FileFormat::detect(&text);
+    let text = match fileformat {
+        FileFormat::Unix => text,
+        FileFormat::Dos => Cow::Owned(text.replace("\r\n", "\n")),
+        FileFormat::Mac => Cow::Owned(text.replace('\r', "\n")),
+    };
+    let format = Format {
+        fileformat,
+        fileencoding: encoding,
+        bomb: bom,
+    };
+    (Rope::from_str(&text), format)
+}
+
+/// reads plain utf-8 into a rope a chunk at a time, for files too large to
+/// hold twice, none for anything that has to be decoded whole
+///
+/// only the start is looked at for a bom, utf-16 or dos line endings.
+pub fn stream(mut reader: impl io::BufRead) -> io::Result<Option<Rope>> {
+    let head = reader.fill_buf()?;
+    let bom = [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be]
+        .into_iter()
+        .any(|enc| head.starts_with(enc.bom()));
+    let utf16 = Encoding::sniff_utf16(&head[..head.len() & !1]).is_some();
+    if bom || utf16 || head.contains(&b'\r') {
+        return Ok(None);
+    }
+    match Rope::from_reader(reader) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// turns text back into bytes the way `format` says
+pub fn encode(text: &Rope, format: &Format) -> io::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len_bytes());
+    if format.bomb {
+        out.extend_from_slice(format.fileencoding.bom());
+    }
+    let ending = format.fileformat.ending();
+    for chunk in text.chunks() {
+        let mut lines = chunk.split('\n');
+        if let Some(first) = lines.next() {
+            format.fileencoding.encode(first, &mut out)?;
+        }
+        for line in lines {
+            format.fileencoding.encode(ending, &mut out)?;
+            format.fileencoding.encode(line, &mut out)?;
+        }
+    }
+    Ok(out)
+}
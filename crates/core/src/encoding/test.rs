@@ -0,0 +1,97 @@
+use crate::encoding::{decode, encode, Encoding, FileFormat, Format};
+
+fn round_trip(bytes: &[u8], text: &str, format: Format) {
+    let (rope, detected) = decode(bytes);
+    assert_eq!(rope, text);
+    assert_eq!(detected, format);
+    assert_eq!(encode(&rope, &detected).unwrap(), bytes);
+}
+
+#[test]
+fn line_endings() {
+    round_trip(b"one\ntwo\n", "one\ntwo\n", Format::default());
+    round_trip(
+        b"one\r\ntwo\r\n",
+        "one\ntwo\n",
+        Format {
+            fileformat: FileFormat::Dos,
+            ..Default::default()
+        },
+    );
+    round_trip(
+        b"one\rtwo\r",
+        "one\ntwo\n",
+        Format {
+            fileformat: FileFormat::Mac,
+            ..Default::default()
+        },
+    );
+    // mixed endings stay unix, the stray \r is kept as text
+    round_trip(b"one\r\ntwo\n", "one\r\ntwo\n", Format::default());
+}
+
+#[test]
+fn encodings() {
+    round_trip(
+        b"\xef\xbb\xbfh\xc3\xa9\r\n",
+        "hé\n",
+        Format {
+            fileformat: FileFormat::Dos,
+            fileencoding: Encoding::Utf8,
+            bomb: true,
+        },
+    );
+    round_trip(
+        b"\xff\xfeh\0\xe9\0\n\0",
+        "hé\n",
+        Format {
+            fileencoding: Encoding::Utf16Le,
+            bomb: true,
+            ..Default::default()
+        },
+    );
+    round_trip(
+        b"\0h\0i\0\n",
+        "hi\n",
+        Format {
+            fileencoding: Encoding::Utf16Be,
+            ..Default::default()
+        },
+    );
+    round_trip(
+        b"caf\xe9\n",
+        "café\n",
+        Format {
+            fileencoding: Encoding::Latin1,
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn broken_unicode_is_kept_byte_for_byte() {
+    let latin1 = Format {
+        fileencoding: Encoding::Latin1,
+        ..Default::default()
+    };
+    // a bom, then an odd number of bytes
+    round_trip(b"\xff\xfeh\0i", "\u{ff}\u{fe}h\0i", latin1.clone());
+    // a lone surrogate
+    round_trip(b"\xff\xfe\0\xd8", "\u{ff}\u{fe}\0\u{d8}", latin1.clone());
+    // a utf-8 bom, then bytes that aren't utf-8
+    round_trip(b"\xef\xbb\xbfh\xe9", "\u{ef}\u{bb}\u{bf}h\u{e9}", latin1);
+}
+
+#[test]
+fn convert() {
+    let (rope, mut format) = decode(b"one\ntwo\n");
+    format.fileformat = FileFormat::Dos;
+    format.fileencoding = Encoding::Utf16Le;
+    assert_eq!(
+        encode(&rope, &format).unwrap(),
+        b"o\0n\0e\0\r\0\n\0t\0w\0o\0\r\0\n\0"
+    );
+    let (rope, mut format) = decode("ĉ\n".as_bytes());
+    format.fileencoding = Encoding::Latin1;
+    assert!(encode(&rope, &format).is_err());
+}
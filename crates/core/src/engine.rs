@@ -1,8 +1,7 @@
 use std::{
     borrow::Cow,
     cell::RefCell,
-    fs::File,
-    io::{self, BufReader},
+    fs, io,
     ops::Range,
     path::{Path, PathBuf},
     sync::Arc,
@@ -12,7 +11,13 @@ use dashmap::DashMap;
 use ropey::Rope;
 use thiserror::Error;
 
-use crate::buffer::Buffer;
+use crate::buffer::{Buffer, Local};
+use crate::disk::{flat_name, handle_nf, Check, DiskEvent, DiskState};
+use crate::encoding::{self, Format};
+use crate::filetype;
+use crate::history::History;
+use crate::options::Options;
+use crate::swap::{self, Swap};
 
 pub type BufferPointer = Arc<RefCell<Buffer>>;
 
@@ -21,6 +26,9 @@ pub type BufferPointer = Arc<RefCell<Buffer>>;
 pub struct Engine {
     base: PathBuf,
     file: DashMap<PathBuf, BufferPointer>,
+    /// where swap files are kept, none disables them
+    swap_dir: Option<PathBuf>,
+    pub options: RefCell<Options>,
 }
 
 #[derive(Error, Debug)]
@@ -31,6 +39,12 @@ pub enum EngineError {
     Rope(#[from] ropey::Error),
     #[error("Missing path: {0}")]
     MissingPath(PathBuf),
+    #[error("No swap file found for {0}")]
+    NoSwap(PathBuf),
+    #[error("Swap file {0} was made for different file contents")]
+    SwapMismatch(PathBuf),
+    #[error("Undo history doesn't match the text")]
+    History,
 }
 
 pub type EngineResult<T> = Result<T, EngineError>;
@@ -44,6 +58,18 @@ impl Engine {
         })
     }
 
+    /// the directory relative paths start from
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+
+    pub fn with_swap_dir(self, swap_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            swap_dir: Some(swap_dir.into()),
+            ..self
+        }
+    }
+
     pub fn get_buffer(
         &self,
         path: impl AsRef<Path>,
@@ -62,20 +88,205 @@ impl Engine {
         relative: bool,
     ) -> EngineResult<BufferPointer> {
         let path = self.norm_path(path, relative)?;
+        let (text, disk, format) =
+            match read_file(&path, |len| self.options.borrow().is_large(len))? {
+                Some((text, disk, format)) => (text, Some(disk), format),
+                None => Default::default(),
+            };
+        let base = disk.map_or(0, |disk| disk.hash);
+        let large = disk.is_some_and(|disk| self.options.borrow().is_large(disk.len));
+        let (swap, recovery) = match &self.swap_dir {
+            Some(dir) if !large => (
+                swap::fresh(dir, &path).map(|swap| Swap::new(swap, path.clone(), base)),
+                swap::find(dir, &path),
+            ),
+            _ => (None, None),
+        };
+        let local = Local {
+            filetype: filetype::detect(&path, &text).to_owned(),
+        };
+        let mut buf = Buffer {
+            path: path.clone(),
+            text,
+            disk,
+            format,
+            local,
+            swap,
+            recovery,
+            large,
+            ..Default::default()
+        };
+        if let (Some(undo_file), Some(disk), false) = (self.undo_file(&path), disk, large) {
+            if let Some(history) = History::load(&undo_file, &path, disk.hash) {
+                buf.restore(history);
+            }
+        }
+        #[allow(clippy::arc_with_non_send_sync)]
+        let buf = Arc::new(RefCell::new(buf));
+        self.file.insert(path, buf.clone());
+        Ok(buf)
+    }
+
+    /// adds an empty buffer for `path` without reading it, for when
+    /// [`Engine::add_buffer`] fails but there still has to be something to
+    /// edit
+    pub fn scratch(&self, path: impl AsRef<Path>, relative: bool) -> EngineResult<BufferPointer> {
+        let path = self.norm_path(path, relative)?;
+        #[allow(clippy::arc_with_non_send_sync)]
         let buf = Arc::new(RefCell::new(Buffer {
             path: path.clone(),
-            text: maybe_read(&path)?.unwrap_or_default(),
-            loc: Default::default(),
+            ..Default::default()
         }));
         self.file.insert(path, buf.clone());
         Ok(buf)
     }
 
-    pub(crate) fn norm_path(
-        &self,
-        path: impl AsRef<Path>,
-        relative: bool,
-    ) -> EngineResult<PathBuf> {
+    /// writes a buffer to its file, along with its undo file
+    pub fn write(&self, path: impl AsRef<Path>, relative: bool) -> EngineResult<()> {
+        let buf = self.get_buffer(path, relative)?;
+        let mut buf = buf.try_borrow_mut().map_err(|_| busy())?;
+        buf.write()?;
+        if let (Some(undo_file), Some(disk), false) =
+            (self.undo_file(&buf.path), buf.disk, buf.large)
+        {
+            if !buf.history.is_empty() {
+                buf.history.save(&undo_file, &buf.path, disk.hash)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// where the undo history of `path` is kept, none if undo files are off
+    ///
+    /// it's named after the full path, for each project's `README.md` to
+    /// have its own.
+    fn undo_file(&self, path: &Path) -> Option<PathBuf> {
+        let options = self.options.borrow();
+        if !options.undofile {
+            return None;
+        }
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_owned());
+        Some(options.undo_dir()?.join(flat_name(&path)))
+    }
+
+    /// looks for open files that were changed by something else
+    ///
+    /// unmodified buffers are reloaded, modified ones are left alone and
+    /// reported as a [`DiskEvent::Conflict`] once per change.
+    pub fn check_disk(&self) -> EngineResult<Vec<DiskEvent>> {
+        let mut events = Vec::new();
+        for entry in self.file.iter() {
+            let Ok(mut buf) = entry.value().try_borrow_mut() else {
+                continue;
+            };
+            let path = entry.key().clone();
+            match buf.check_disk()? {
+                Check::Same | Check::Touched(_) => {}
+                Check::Deleted => {
+                    buf.disk = None;
+                    events.push(DiskEvent::Deleted(path));
+                }
+                Check::Changed(_) if !buf.modified => {
+                    buf.reload()?;
+                    events.push(DiskEvent::Reloaded(path));
+                }
+                Check::Changed(now) => {
+                    buf.disk = Some(now);
+                    events.push(DiskEvent::Conflict(path));
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    /// replays the swap file found when the buffer was opened
+    ///
+    /// returns the number of changes recovered. the swap file is removed
+    /// afterwards, the recovered changes are journaled again.
+    pub fn recover(&self, path: impl AsRef<Path>, relative: bool) -> EngineResult<usize> {
+        let buf = self.get_buffer(path, relative)?;
+        let mut buf = buf.try_borrow_mut().map_err(|_| busy())?;
+        let Some(found) = buf.recovery.clone() else {
+            return Err(EngineError::NoSwap(buf.path.clone()));
+        };
+        let recovery = swap::read(&found)?;
+        if recovery.base != buf.base_hash() {
+            return Err(EngineError::SwapMismatch(found));
+        }
+        // every change has to apply before any is, for a journal that
+        // doesn't fit the text to leave the buffer as it was
+        let mut text = buf.text.clone();
+        for change in &recovery.changes {
+            match change {
+                Change::Insert(Insert { index, text: t }) => text.try_insert(*index, t)?,
+                Change::Delete(Delete { range }) => text.try_remove(range.clone())?,
+            }
+        }
+        let count = recovery.changes.len();
+        for change in recovery.changes {
+            buf.replay(change)?;
+        }
+        buf.recovery = None;
+        handle_nf(fs::remove_file(found))?;
+        Ok(count)
+    }
+
+    /// deletes the swap file found when the buffer was opened
+    pub fn discard_swap(&self, path: impl AsRef<Path>, relative: bool) -> EngineResult<()> {
+        let buf = self.get_buffer(path, relative)?;
+        let mut buf = buf.try_borrow_mut().map_err(|_| busy())?;
+        if let Some(found) = buf.recovery.take() {
+            handle_nf(fs::remove_file(found))?;
+        }
+        Ok(())
+    }
+
+    /// writes pending changes to the swap files
+    pub fn sync_swaps(&self) -> EngineResult<()> {
+        for entry in self.file.iter() {
+            let Ok(mut buf) = entry.value().try_borrow_mut() else {
+                continue;
+            };
+            if let Some(swap) = &mut buf.swap {
+                swap.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// every open buffer
+    pub fn buffers(&self) -> Vec<BufferPointer> {
+        self.file
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// the buffers with changes that haven't been written
+    pub fn modified(&self) -> Vec<PathBuf> {
+        self.file
+            .iter()
+            .filter(|entry| entry.value().try_borrow().is_ok_and(|buf| buf.modified))
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// removes every swap file, on a clean exit
+    pub fn close(&self) -> EngineResult<()> {
+        for entry in self.file.iter() {
+            let Ok(mut buf) = entry.value().try_borrow_mut() else {
+                continue;
+            };
+            if let Some(swap) = buf.swap.take() {
+                swap.remove()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// the path a buffer is known by, `path` joined to the base directory
+    /// when `relative`
+    pub fn norm_path(&self, path: impl AsRef<Path>, relative: bool) -> EngineResult<PathBuf> {
         if relative {
             Ok(self.base.join(path))
         } else {
@@ -84,38 +295,66 @@ impl Engine {
     }
 }
 
-/// tries to read a file, returns none if it doesn't exist
-fn maybe_read(path: &Path) -> EngineResult<Option<Rope>> {
-    Ok(handle_nf(
-        File::open(&path).and_then(|file| Rope::from_reader(BufReader::new(file))),
-    )?)
+fn busy() -> io::Error {
+    io::Error::new(io::ErrorKind::WouldBlock, "buffer is in use")
 }
 
-fn handle_nf<T>(res: Result<T, io::Error>) -> Result<Option<T>, io::Error> {
-    match res {
-        Ok(value) => Ok(Some(value)),
-        Err(e) if matches!(e.kind(), io::ErrorKind::NotFound) => Ok(None),
-        Err(e) => Err(e.into()),
+/// tries to read a file, returns none if it doesn't exist
+///
+/// a file `large` says is too big for it is streamed into the rope when it's
+/// plain utf-8, and not hashed, swap and undo files being off for it anyway.
+pub(crate) fn read_file(
+    path: &Path,
+    large: impl FnOnce(u64) -> bool,
+) -> EngineResult<Option<(Rope, DiskState, Format)>> {
+    let Some(meta) = handle_nf(fs::metadata(path))? else {
+        return Ok(None);
+    };
+    if large(meta.len()) {
+        let Some(file) = handle_nf(fs::File::open(path))? else {
+            return Ok(None);
+        };
+        if let Some(text) = encoding::stream(io::BufReader::new(file))? {
+            let disk = DiskState {
+                mtime: meta.modified().ok(),
+                len: meta.len(),
+                hash: 0,
+            };
+            return Ok(Some((text, disk, Format::default())));
+        }
     }
+    let Some(bytes) = handle_nf(fs::read(path))? else {
+        return Ok(None);
+    };
+    let disk = DiskState::new(&meta, &bytes);
+    let (text, format) = encoding::decode(&bytes);
+    Ok(Some((text, disk, format)))
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Replace<'a> {
     pub range: Range<usize>,
     pub new_text: &'a str,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Delete {
     pub range: Range<usize>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Insert<'a> {
     pub index: usize,
     pub text: Cow<'a, str>,
 }
 
+/// A primitive change to a buffer's text, indexed by char
+#[derive(Debug, Clone)]
+pub enum Change {
+    Insert(Insert<'static>),
+    Delete(Delete),
+}
+
 #[derive(Debug)]
 pub enum Edit<T> {
     One(T),
@@ -0,0 +1,48 @@
+//! Working out what language a file is written in
+
+use std::path::Path;
+
+use ropey::Rope;
+
+#[cfg(test)]
+mod test;
+
+/// guesses a file's type from its name, then from a `#!` line
+///
+/// returns an empty string when nothing matches, like vim's `filetype`.
+pub fn detect(path: &Path, text: &Rope) -> &'static str {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    let by_name = match (name, ext) {
+        ("Cargo.lock", _) => "toml",
+        (".bashrc" | ".bash_profile" | ".profile" | ".zshrc", _) => "sh",
+        ("evierc" | ".evierc", _) => "evie",
+        (_, "rs") => "rust",
+        (_, "toml") => "toml",
+        (_, "md" | "markdown") => "markdown",
+        (_, "sh" | "bash" | "zsh") => "sh",
+        _ => "",
+    };
+    if !by_name.is_empty() || text.len_lines() == 0 {
+        return by_name;
+    }
+
+    let first = text.line(0);
+    let shebang = first.chars().take(128).collect::<String>();
+    let Some(command) = shebang.strip_prefix("#!") else {
+        return "";
+    };
+    // `#!/usr/bin/env bash` names the interpreter after env
+    let mut words = command.split_whitespace();
+    let program = match words.next() {
+        Some(env) if env.ends_with("/env") => words.find(|word| !word.starts_with('-')),
+        program => program,
+    };
+    match program.and_then(|program| program.rsplit('/').next()) {
+        Some("sh" | "bash" | "dash" | "zsh" | "ksh") => "sh",
+        _ => "",
+    }
+}
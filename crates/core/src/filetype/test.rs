@@ -0,0 +1,20 @@
+use std::path::Path;
+
+use ropey::Rope;
+
+use crate::filetype::detect;
+
+#[test]
+fn by_name_and_shebang() {
+    let empty = Rope::new();
+    assert_eq!(detect(Path::new("src/main.rs"), &empty), "rust");
+    assert_eq!(detect(Path::new("Cargo.lock"), &empty), "toml");
+    assert_eq!(detect(Path::new("README.md"), &empty), "markdown");
+    assert_eq!(detect(Path::new("notes.txt"), &empty), "");
+
+    let script = |text| detect(Path::new("build"), &Rope::from_str(text));
+    assert_eq!(script("#!/bin/sh\necho hi\n"), "sh");
+    assert_eq!(script("#!/usr/bin/env -S bash -e\n"), "sh");
+    assert_eq!(script("#!/usr/bin/env python3\n"), "");
+    assert_eq!(script("echo hi\n"), "");
+}
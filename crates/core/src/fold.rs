@@ -0,0 +1,88 @@
+//! Closed folds
+//!
+//! A fold hides lines behind the first of them. Which lines can fold comes
+//! from the syntax tree, see [`SyntaxTree::folds`], this only keeps track of
+//! the ones that are closed, moving them along as lines are added and
+//! removed above them.
+//!
+//! [`SyntaxTree::folds`]: crate::tree::SyntaxTree::folds
+
+use std::cmp::Reverse;
+use std::ops::Range;
+
+#[cfg(test)]
+mod test;
+
+/// The closed folds of a buffer, as ranges of lines
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Folds {
+    /// sorted by where they start, and never overlapping without one being
+    /// inside the other
+    closed: Vec<Range<usize>>,
+}
+
+impl Folds {
+    pub fn close(&mut self, lines: Range<usize>) {
+        if lines.len() < 2 || self.closed.contains(&lines) {
+            return;
+        }
+        // outer folds go before the ones inside them
+        let key = |fold: &Range<usize>| (fold.start, Reverse(fold.end));
+        let at = self.closed.partition_point(|fold| key(fold) < key(&lines));
+        self.closed.insert(at, lines);
+    }
+
+    /// opens the fold hiding `line`, returning whether there was one. folds
+    /// closed inside it stay closed
+    pub fn open(&mut self, line: usize) -> bool {
+        let Some(fold) = self.at(line) else {
+            return false;
+        };
+        self.closed.retain(|closed| *closed != fold);
+        true
+    }
+
+    /// whether `lines` is closed, or inside a closed fold
+    pub fn hidden(&self, lines: &Range<usize>) -> bool {
+        self.closed
+            .iter()
+            .any(|fold| fold.start <= lines.start && lines.end <= fold.end)
+    }
+
+    pub fn open_all(&mut self) {
+        self.closed.clear();
+    }
+
+    /// the closed folds that aren't inside another, which are what hides
+    /// lines
+    pub fn outer(&self) -> Vec<Range<usize>> {
+        let mut outer: Vec<Range<usize>> = Vec::new();
+        for fold in &self.closed {
+            if outer.last().is_none_or(|last| fold.start >= last.end) {
+                outer.push(fold.clone());
+            }
+        }
+        outer
+    }
+
+    /// the lines hidden with `line`, none if it isn't in a closed fold
+    pub fn at(&self, line: usize) -> Option<Range<usize>> {
+        self.outer().into_iter().find(|fold| fold.contains(&line))
+    }
+
+    /// notes that lines `first..=last` were replaced with `first..=now`,
+    /// opening the folds the change is in
+    pub fn edit(&mut self, first: usize, last: usize, now: usize) {
+        self.closed.retain_mut(|fold| {
+            if fold.end <= first {
+                return true;
+            }
+            if fold.start > last {
+                fold.start = fold.start + now - last;
+                fold.end = fold.end + now - last;
+                return true;
+            }
+            false
+        });
+    }
+}
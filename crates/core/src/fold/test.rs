@@ -0,0 +1,31 @@
+use crate::fold::Folds;
+
+#[test]
+fn nested() {
+    let mut folds = Folds::default();
+    folds.close(4..6);
+    folds.close(2..8);
+    folds.close(2..3);
+    assert_eq!(folds.outer(), vec![2..8]);
+    assert_eq!(folds.at(5), Some(2..8));
+    assert!(folds.hidden(&(4..6)));
+    // opening the outer fold shows the one closed inside it
+    assert!(folds.open(5));
+    assert_eq!(folds.outer(), vec![4..6]);
+    assert!(!folds.open(2));
+    folds.open_all();
+    assert_eq!(folds.at(5), None);
+}
+
+#[test]
+fn edits_move_folds() {
+    let mut folds = Folds::default();
+    folds.close(2..4);
+    folds.close(6..9);
+    // two lines added above both
+    folds.edit(0, 0, 2);
+    assert_eq!(folds.outer(), vec![4..6, 8..11]);
+    // a line joined inside the first opens it
+    folds.edit(5, 6, 5);
+    assert_eq!(folds.outer(), vec![7..10]);
+}
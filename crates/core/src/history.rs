@@ -0,0 +1,232 @@
+//! Undo history, and the undo files that keep it across sessions
+//!
+//! An undo file starts with a header naming the file and the hash of the
+//! contents the history ends at, followed by the undo and redo stacks:
+//!
+//! ```text
+//! evie undo 1
+//! /path/to/file
+//! 0123456789abcdef
+//! undo 1
+//! rev 2
+//! i 0 5
+//! hello
+//! d 5 1
+//! !
+//! redo 0
+//! ```
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Read, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+#[cfg(test)]
+mod test;
+
+const MAGIC: &str = "evie undo 1";
+
+/// A single change, with enough information to reverse it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Insert { at: usize, text: String },
+    Remove { at: usize, text: String },
+}
+
+impl Step {
+    pub fn inverse(&self) -> Self {
+        match self {
+            Self::Insert { at, text } => Self::Remove {
+                at: *at,
+                text: text.clone(),
+            },
+            Self::Remove { at, text } => Self::Insert {
+                at: *at,
+                text: text.clone(),
+            },
+        }
+    }
+
+    /// the char the cursor goes to after this step
+    pub fn cursor(&self) -> usize {
+        match self {
+            Self::Insert { at, .. } | Self::Remove { at, .. } => *at,
+        }
+    }
+}
+
+/// A group of steps undone together
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Revision {
+    pub steps: Vec<Step>,
+    pub seq: usize,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct History {
+    undo: Vec<Revision>,
+    redo: Vec<Revision>,
+    /// the revision steps are currently being added to
+    open: Vec<Step>,
+    seq: usize,
+}
+
+impl History {
+    pub fn record(&mut self, step: Step) {
+        self.open.push(step);
+    }
+
+    /// closes the open revision, so the next step starts a new one
+    pub fn commit(&mut self) {
+        if self.open.is_empty() {
+            return;
+        }
+        self.seq += 1;
+        self.undo.push(Revision {
+            steps: std::mem::take(&mut self.open),
+            seq: self.seq,
+        });
+        self.redo.clear();
+    }
+
+    /// the steps that undo the last revision, in the order to apply them
+    pub fn undo(&mut self) -> Option<Vec<Step>> {
+        self.commit();
+        let rev = self.undo.pop()?;
+        let steps = rev.steps.iter().rev().map(Step::inverse).collect();
+        self.redo.push(rev);
+        Some(steps)
+    }
+
+    /// the steps that redo the last undone revision
+    pub fn redo(&mut self) -> Option<Vec<Step>> {
+        self.commit();
+        let rev = self.redo.pop()?;
+        let steps = rev.steps.clone();
+        self.undo.push(rev);
+        Some(steps)
+    }
+
+    /// identifies the current state of the text, see [`Buffer::modified`]
+    ///
+    /// [`Buffer::modified`]: crate::buffer::Buffer::modified
+    pub fn current(&self) -> usize {
+        if self.open.is_empty() {
+            self.undo.last().map_or(0, |rev| rev.seq)
+        } else {
+            usize::MAX
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.undo.is_empty() && self.redo.is_empty() && self.open.is_empty()
+    }
+
+    /// writes the history for contents hashing to `hash`
+    pub fn save(&self, undo_file: &Path, original: &Path, hash: u64) -> io::Result<()> {
+        let mut out = Vec::new();
+        let _ = writeln!(out, "{MAGIC}\n{}\n{hash:016x}", original.display());
+        for (name, stack) in [("undo", &self.undo), ("redo", &self.redo)] {
+            let _ = writeln!(out, "{name} {}", stack.len());
+            for rev in stack {
+                let _ = writeln!(out, "rev {}", rev.seq);
+                for step in &rev.steps {
+                    let (kind, at, text) = match step {
+                        Step::Insert { at, text } => ('i', at, text),
+                        Step::Remove { at, text } => ('d', at, text),
+                    };
+                    let _ = writeln!(out, "{kind} {at} {}", text.len());
+                    out.extend_from_slice(text.as_bytes());
+                    out.push(b'\n');
+                }
+                out.extend_from_slice(b"!\n");
+            }
+        }
+        if let Some(parent) = undo_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // write then rename, so a crash never leaves half an undo file
+        let mut tmp = undo_file.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, out)?;
+        fs::rename(tmp, undo_file)
+    }
+
+    /// reads a history saved for `original` with contents hashing to `hash`
+    ///
+    /// returns none for a missing, broken or mismatched undo file.
+    pub fn load(undo_file: &Path, original: &Path, hash: u64) -> Option<Self> {
+        let file = fs::File::open(undo_file).ok()?;
+        let size = file.metadata().ok()?.len();
+        let mut reader = BufReader::new(file);
+        if read_line(&mut reader)? != MAGIC
+            || PathBuf::from(read_line(&mut reader)?) != original
+            || u64::from_str_radix(&read_line(&mut reader)?, 16).ok()? != hash
+        {
+            return None;
+        }
+
+        let mut history = Self::default();
+        for name in ["undo", "redo"] {
+            let count: usize = read_line(&mut reader)?
+                .strip_prefix(name)?
+                .trim()
+                .parse()
+                .ok()?;
+            let mut stack = Vec::with_capacity(count.min(1024));
+            for _ in 0..count {
+                stack.push(read_revision(&mut reader, size)?);
+            }
+            match name {
+                "undo" => history.undo = stack,
+                _ => history.redo = stack,
+            }
+        }
+        history.seq = history
+            .undo
+            .iter()
+            .chain(&history.redo)
+            .map(|rev| rev.seq)
+            .max()
+            .unwrap_or(0);
+        Some(history)
+    }
+}
+
+fn read_line(reader: &mut impl BufRead) -> Option<String> {
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    Some(line.strip_suffix('\n')?.to_owned())
+}
+
+/// reads a revision from a file `size` bytes long
+fn read_revision(reader: &mut (impl BufRead + Seek), size: u64) -> Option<Revision> {
+    let seq = read_line(reader)?.strip_prefix("rev ")?.parse().ok()?;
+    let mut steps = Vec::new();
+    loop {
+        let line = read_line(reader)?;
+        if line == "!" {
+            return Some(Revision { steps, seq });
+        }
+        let mut parts = line.split(' ');
+        let (kind, at, len) = (parts.next()?, parts.next()?, parts.next()?);
+        let (at, len): (usize, usize) = (at.parse().ok()?, len.parse().ok()?);
+        // the text and its newline, which have to fit in what's left
+        let len = u64::try_from(len).ok()?.checked_add(1)?;
+        if len > size.checked_sub(reader.stream_position().ok()?)? {
+            return None;
+        }
+        let mut text = Vec::new();
+        reader.take(len).read_to_end(&mut text).ok()?;
+        if text.len() as u64 != len || text.pop() != Some(b'\n') {
+            return None;
+        }
+        let text = String::from_utf8(text).ok()?;
+        steps.push(match kind {
+            "i" => Step::Insert { at, text },
+            "d" => Step::Remove { at, text },
+            _ => return None,
+        });
+    }
+}
@@ -0,0 +1,126 @@
+use std::{fs, path::Path};
+
+use tempfile::tempdir;
+
+use crate::{engine::Engine, history::History, options::Set, BufferAction};
+
+fn engine(undo_dir: &Path) -> Engine {
+    let engine = Engine::default();
+    engine
+        .options
+        .borrow_mut()
+        .set(&format!("undodir={}", undo_dir.display()))
+        .unwrap();
+    engine
+}
+
+fn act(engine: &Engine, path: &Path, action: BufferAction) {
+    let buf = engine.get_buffer(path, false).unwrap();
+    let mut buf = buf.borrow_mut();
+    buf.apply(action).unwrap();
+    buf.commit();
+}
+
+#[test]
+fn undo_redo() {
+    let tmp = tempdir().unwrap();
+    let (path, dir) = (tmp.path().join("undo.txt"), tmp.path().join("undo"));
+    let engine = engine(&dir);
+    let buf = engine.add_buffer(&path, false).unwrap();
+    act(&engine, &path, BufferAction::Append("one ".into()));
+    act(&engine, &path, BufferAction::Append("two".into()));
+    assert_eq!(buf.borrow().text, "one two");
+
+    act(&engine, &path, BufferAction::Undo);
+    assert_eq!(buf.borrow().text, "one ");
+    act(&engine, &path, BufferAction::Undo);
+    assert_eq!(buf.borrow().text, "");
+    assert!(!buf.borrow().modified);
+    act(&engine, &path, BufferAction::Redo);
+    assert_eq!(buf.borrow().text, "one ");
+    assert!(buf.borrow().modified);
+}
+
+#[test]
+fn persist_across_sessions() {
+    let tmp = tempdir().unwrap();
+    let (path, dir) = (tmp.path().join("persist.txt"), tmp.path().join("undo"));
+    fs::write(&path, "zero\n").unwrap();
+    let engine = engine(&dir);
+    engine.add_buffer(&path, false).unwrap();
+    act(&engine, &path, BufferAction::Append("one ".into()));
+    engine.write(&path, false).unwrap();
+    drop(engine);
+
+    let engine = self::engine(&dir);
+    let buf = engine.add_buffer(&path, false).unwrap();
+    assert_eq!(buf.borrow().text, "one zero\n");
+    assert!(!buf.borrow().modified);
+    act(&engine, &path, BufferAction::Undo);
+    assert_eq!(buf.borrow().text, "zero\n");
+    assert!(buf.borrow().modified);
+}
+
+#[test]
+fn ignore_changed_file() {
+    let tmp = tempdir().unwrap();
+    let (path, dir) = (tmp.path().join("changed.txt"), tmp.path().join("undo"));
+    let engine = engine(&dir);
+    engine.add_buffer(&path, false).unwrap();
+    act(&engine, &path, BufferAction::Append("one".into()));
+    engine.write(&path, false).unwrap();
+    drop(engine);
+
+    fs::write(&path, "two").unwrap();
+    let engine = self::engine(&dir);
+    let buf = engine.add_buffer(&path, false).unwrap();
+    assert!(buf.borrow().history.is_empty());
+}
+
+#[test]
+fn ignore_broken_file() {
+    let tmp = tempdir().unwrap();
+    let (path, dir) = (tmp.path().join("broken.txt"), tmp.path().join("undo"));
+    fs::create_dir_all(&dir).unwrap();
+    let undo = dir.join("broken");
+    for junk in [
+        "",
+        "evie undo 1\n",
+        "evie undo 1\n/elsewhere\n0000000000000000\nundo 0\nredo 0\n",
+        "evie undo 1\n{path}\n0000000000000000\nundo 1\nrev 1\ni 0 99999999\nab\n!\n",
+        "evie undo 1\n{path}\n0000000000000000\nundo 1\nrev 1\ni 0 18446744073709551615\nab\n!\n",
+        "evie undo 1\n{path}\n0000000000000000\nundo 1\nrev 1\nx 0 1\na\n!\nredo 0\n",
+    ] {
+        fs::write(&undo, junk.replace("{path}", &path.display().to_string())).unwrap();
+        assert_eq!(History::load(&undo, &path, 0), None);
+    }
+    fs::write(
+        &undo,
+        format!(
+            "evie undo 1\n{}\n0000000000000000\nundo 1\nrev 1\ni 0 2\nab\n!\nredo 0\n",
+            path.display()
+        ),
+    )
+    .unwrap();
+    assert!(History::load(&undo, &path, 0).is_some());
+}
+
+#[test]
+fn one_undo_file_per_full_path() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("undo");
+    let engine = engine(&dir);
+    let paths = [tmp.path().join("a.rs"), tmp.path().join("a.txt")];
+    for path in &paths {
+        engine.add_buffer(path, false).unwrap();
+        act(&engine, path, BufferAction::Append("one".into()));
+        engine.write(path, false).unwrap();
+    }
+    let mut names = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    names.sort();
+    let flat = |path: &Path| std::ffi::OsString::from(crate::disk::flat_name(path));
+    assert_eq!(names, [flat(&paths[0]), flat(&paths[1])]);
+}
@@ -1,5 +1,6 @@
 use std::{
-    cell::{BorrowError, BorrowMutError, Cell},
+    cell::{BorrowError, BorrowMutError, Cell, Ref, RefCell, RefMut},
+    ops::Range,
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -9,12 +10,44 @@ use arc_swap::ArcSwap;
 use engine::Engine;
 use thiserror::Error;
 
+use buffer::{Buffer, Location};
+use command::{Command, CommandError, TabMove};
+use completion::{Completion, Item, Menu, Paths, Source, Words};
+use diagnostic::Listed;
+use disk::DiskEvent;
 use engine::{BufferPointer, EngineError};
+use motion::{DisplayLines, DisplayMove, Logical, TextObject};
+use options::{OptionError, Set};
+use snippet::{Library, Snippet, Variables};
+use status::Status;
+use theme::{Background, Theme, ThemeError};
 use trigger::{Modes, Trigger, TriggerMap};
+use view::{Scroll, Viewport};
+use window::{Split, TabPage, Tabs, Window, WindowId, Windows};
 
 pub mod buffer;
+pub mod command;
+pub mod completion;
+pub mod diagnostic;
+pub mod diff;
+pub mod disk;
+pub mod encoding;
 pub mod engine;
+pub mod filetype;
+pub mod fold;
+pub mod history;
+pub mod motion;
+pub mod options;
+pub mod snippet;
+pub mod status;
+pub mod swap;
+pub mod syntax;
+pub mod theme;
+#[cfg(feature = "tree-sitter")]
+pub mod tree;
 pub mod trigger;
+pub mod view;
+pub mod window;
 
 #[derive(Debug, Error)]
 pub enum EvieError {
@@ -24,8 +57,27 @@ pub enum EvieError {
     BorrowMut(#[from] BorrowMutError),
     #[error("engine error: {0}")]
     Engine(#[from] EngineError),
+    #[error("{0}")]
+    Command(#[from] CommandError),
+    #[error("{0}")]
+    Theme(#[from] ThemeError),
+    #[error("no such window")]
+    NoWindow,
+    /// from a language server, as told by the frontend talking to it
+    #[error("{0}")]
+    Lsp(String),
 }
 
+/// Something to tell the user, shown in the message area
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Note {
+    Info(String),
+    Error(String),
+}
+
+/// how many notes are kept, the oldest are dropped first
+const NOTES: usize = 100;
+
 /// The central interface
 #[derive(Debug, Default)]
 pub struct Evie<K: Key> {
@@ -33,6 +85,32 @@ pub struct Evie<K: Key> {
     pub engine: Engine,
     pub mdata: Modes<K>,
     pub trig: ArcSwap<TriggerMap<K>>,
+    /// what has been typed on the command line so far
+    pub cmdline: RefCell<String>,
+    /// the tab pages, each with its windows, and which is on screen
+    pub tabs: RefCell<Tabs>,
+    /// set once the last window is closed, for the frontend to exit
+    pub quit: Cell<bool>,
+    /// the colorscheme, see `:colorscheme`
+    pub theme: RefCell<Theme>,
+    /// whether the system prefers dark or light, for the frontend to keep up
+    /// to date, see the `background` option
+    pub system: Cell<Background>,
+    /// what's been asked of the language servers, for the frontend to send
+    pub requests: RefCell<Vec<LspRequest>>,
+    /// how many pixels text is zoomed in by, see [`CoreAction::Zoom`]
+    pub zoom: Cell<isize>,
+    /// whether the frontend shows the list of diagnostics, see
+    /// [`Evie::diagnostics`]
+    pub diagnostics_list: Cell<bool>,
+    /// the completion open in insert mode, see [`CoreAction::Complete`]
+    pub completion: RefCell<Option<Completion>>,
+    /// where completions come from, besides language servers and snippets
+    sources: Vec<Box<dyn Source>>,
+    /// the snippets `Tab` expands, see [`CoreAction::Snippet`]
+    pub snippets: Library,
+    /// errors and messages for the user, oldest first
+    notes: RefCell<Vec<Note>>,
 }
 
 pub type EvieCentral<K> = Arc<Evie<K>>;
@@ -47,17 +125,117 @@ impl<K: Key> Evie<K> {
             engine: Default::default(),
             trig: mdata.normal.clone().into(),
             mdata,
+            cmdline: Default::default(),
+            tabs: Default::default(),
+            quit: Default::default(),
+            theme: Default::default(),
+            system: Default::default(),
+            requests: Default::default(),
+            zoom: Default::default(),
+            diagnostics_list: Default::default(),
+            completion: Default::default(),
+            sources: vec![Box::new(Words), Box::new(Paths::default())],
+            snippets: Library::from_config(),
+            notes: Default::default(),
         }
     }
 
-    pub fn view_buffer(
-        self: &Arc<Self>,
-        path: impl AsRef<Path>,
-        relative: bool,
-    ) -> Result<BufferView<K>, EngineError> {
+    pub fn with_engine(self, engine: Engine) -> Self {
+        Self { engine, ..self }
+    }
+
+    /// completes from `source` too
+    pub fn with_source(mut self, source: impl Source + 'static) -> Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    pub fn with_snippets(self, snippets: Library) -> Self {
+        Self { snippets, ..self }
+    }
+
+    pub fn into_central(self) -> EvieCentral<K> {
+        Arc::new(self)
+    }
+
+    /// tells the user about an error instead of failing, so that a frontend
+    /// can carry on
+    pub fn report(&self, e: impl Into<EvieError>) {
+        self.note(Note::Error(e.into().to_string()));
+    }
+
+    pub fn inform(&self, info: impl Into<String>) {
+        self.note(Note::Info(info.into()));
+    }
+
+    fn note(&self, note: Note) {
+        let mut notes = self.notes.borrow_mut();
+        if notes.len() >= NOTES {
+            notes.remove(0);
+        }
+        notes.push(note);
+    }
+
+    /// the background the theme is drawn for, from the `background` option
+    /// or else what the system prefers
+    pub fn background(&self) -> Background {
+        match self.engine.options.borrow().background.as_str() {
+            "dark" => Background::Dark,
+            "light" => Background::Light,
+            _ => self.system.get(),
+        }
+    }
+
+    /// the size text is drawn at, see the `fontsize` option
+    pub fn font_size(&self) -> usize {
+        self.engine.options.borrow().font_size(self.zoom.get())
+    }
+
+    /// the newest note, what the message area shows
+    pub fn last_note(&self) -> Option<Note> {
+        self.notes.borrow().last().cloned()
+    }
+
+    pub fn notes(&self) -> Vec<Note> {
+        self.notes.borrow().clone()
+    }
+
+    /// the windows of the tab page on screen
+    pub fn windows(&self) -> Result<Ref<'_, Windows>, EvieError> {
+        Ok(Ref::map(self.tabs.try_borrow()?, Tabs::current))
+    }
+
+    fn windows_mut(&self) -> Result<RefMut<'_, Windows>, EvieError> {
+        Ok(RefMut::map(self.tabs.try_borrow_mut()?, Tabs::current_mut))
+    }
+
+    pub fn view_window(self: &Arc<Self>, window: WindowId) -> Result<BufferView<K>, EvieError> {
+        let windows = self.windows()?;
         Ok(BufferView {
             evie: self.clone(),
-            buffer: self.engine.norm_path(path, relative)?,
+            window,
+            buffer: windows
+                .get(window)
+                .ok_or(EvieError::NoWindow)?
+                .buffer
+                .clone(),
+        })
+    }
+
+    /// a view through the window with focus, where keys go
+    pub fn focused(self: &Arc<Self>) -> Result<BufferView<K>, EvieError> {
+        let window = self.windows()?.focus();
+        self.view_window(window)
+    }
+
+    /// shows a buffer in the window with focus
+    pub fn show(&self, path: impl AsRef<Path>, relative: bool) -> Result<(), EvieError> {
+        let buffer = self.engine.norm_path(path, relative)?;
+        self.in_windows(|windows| {
+            *windows.focused() = Window {
+                buffer,
+                ..Default::default()
+            }
         })
     }
 
@@ -69,17 +247,778 @@ impl<K: Key> Evie<K> {
         self.engine.add_buffer(path, relative)
     }
 
-    fn apply(&self, action: CoreAction) -> Result<(), EvieError> {
+    /// puts the cursor on byte `at` of a file, showing it in the window with
+    /// focus and opening it if it isn't yet
+    pub fn goto(&self, path: &Path, at: usize) -> Result<(), EvieError> {
+        let buf = match self.engine.get_buffer(path, false) {
+            Ok(buf) => buf,
+            Err(_) => self.engine.add_buffer(path, false)?,
+        };
+        let windows = self.windows()?;
+        let showing = windows
+            .get(windows.focus())
+            .is_some_and(|window| window.buffer == path);
+        drop(windows);
+        if !showing {
+            self.show(path, false)?;
+        }
+        let mut buf = buf.try_borrow_mut()?;
+        buf.loc = Location {
+            char: at,
+            ..Default::default()
+        };
+        buf.fit_cursor();
+        if self.mode.get().on_char() {
+            buf.clamp_cursor();
+        }
+        Ok(())
+    }
+
+    /// asks the language server of a buffer for something about the text at
+    /// the cursor, see [`Evie::requests`]
+    fn request(&self, buffer: &Path, action: LspAction) -> Result<(), EvieError> {
+        let buf = self.engine.get_buffer(buffer, false)?;
+        let at = buf.try_borrow()?.loc.char;
+        self.requests.try_borrow_mut()?.push(LspRequest {
+            path: buffer.to_owned(),
+            at,
+            action,
+        });
+        Ok(())
+    }
+
+    /// takes in candidates that came later, like a language server's, for
+    /// the completion open in a buffer if it still is
+    pub fn complete_with(&self, path: &Path, items: Vec<Item>) -> Result<(), EvieError> {
+        let mut completion = self.completion.try_borrow_mut()?;
+        if let Some(open) = completion.as_mut().filter(|open| open.path == path) {
+            open.add(items);
+        }
+        Ok(())
+    }
+
+    /// opens a completion for the word before the cursor, asking the
+    /// language server too
+    fn open_completion(&self, buffer: &Path) -> Result<Completion, EvieError> {
+        let buffers: Vec<_> = self
+            .engine
+            .buffers()
+            .iter()
+            .filter_map(|buf| Some(buf.try_borrow().ok()?).filter(|buf| !buf.large))
+            .map(|buf| buf.text.clone())
+            .collect();
+        let buf = self.engine.get_buffer(buffer, false)?;
+        let buf = buf.try_borrow()?;
+        let at = buf.loc.char;
+        let cx = completion::Context {
+            text: &buf.text,
+            path: &buf.path,
+            filetype: &buf.local.filetype,
+            start: completion::start(&buf.text, at),
+            at,
+            buffers: &buffers,
+        };
+        let mut open = Completion::new(buffer.to_owned(), cx.start);
+        // snippets first, over words of the same name
+        open.add(self.snippets.complete(&cx));
+        for source in &self.sources {
+            open.add(source.complete(&cx));
+        }
+        open.filter(&cx.typed());
+        drop(buf);
+        self.request(buffer, LspAction::Complete)?;
+        Ok(open)
+    }
+
+    fn complete(&self, action: CompleteAction, buffer: &Path) -> Result<(), EvieError> {
+        let mut completion = self.completion.try_borrow_mut()?;
+        match action {
+            CompleteAction::Next | CompleteAction::Prev => {
+                let open = match completion.take() {
+                    Some(open) => open,
+                    None => self.open_completion(buffer)?,
+                };
+                let open = completion.insert(open);
+                open.select(match action {
+                    CompleteAction::Next => 1,
+                    _ => -1,
+                });
+            }
+            CompleteAction::Accept => {
+                let Some(open) = completion.take() else {
+                    return Ok(());
+                };
+                // expanding a snippet can open another, for its choices
+                drop(completion);
+                let Some(item) = open.selected() else {
+                    return Ok(());
+                };
+                let buf = self.engine.get_buffer(&open.path, false)?;
+                let mut buf = buf.try_borrow_mut()?;
+                let range = open.start..buf.loc.char.max(open.start);
+                match &item.snippet {
+                    Some(body) => self.expand(&mut buf, range, body)?,
+                    None => {
+                        buf.splice(range, &item.word)?;
+                        if let Some(snippet) = &mut buf.snippet {
+                            snippet.fresh = false;
+                        }
+                        buf.mirror()?;
+                    }
+                }
+                return Ok(());
+            }
+            CompleteAction::Cancel => *completion = None,
+        }
+        Ok(())
+    }
+
+    /// closes the completion once the cursor leaves the word it's for, or
+    /// ranks it again against what's been typed of it
+    fn follow_completion(&self) -> Result<(), EvieError> {
+        let mut completion = self.completion.try_borrow_mut()?;
+        let Some(open) = completion.as_mut() else {
+            return Ok(());
+        };
+        let buf = self.engine.get_buffer(&open.path, false)?;
+        let buf = buf.try_borrow()?;
+        let at = buf.loc.char;
+        let typed = (self.mode.get() == Mode::Insert && at >= open.start)
+            .then(|| buf.text.get_byte_slice(open.start..at))
+            .flatten()
+            .map(|typed| typed.to_string())
+            .filter(|typed| !typed.contains(char::is_whitespace));
+        match typed {
+            Some(typed) => open.filter(&typed),
+            None => *completion = None,
+        }
+        Ok(())
+    }
+
+    /// `Tab` and `Shift-Tab` in insert mode, see [`CoreAction::Snippet`]
+    fn tab(&self, buffer: &Path, forward: bool) -> Result<(), EvieError> {
+        let buf = self.engine.get_buffer(buffer, false)?;
+        let mut buf = buf.try_borrow_mut()?;
+        if forward {
+            let at = buf.loc.char;
+            let start = completion::start(&buf.text, at);
+            let prefix = buf.text.byte_slice(start..at).to_string();
+            let found = (!prefix.is_empty())
+                .then(|| self.snippets.find(&buf.local.filetype, &prefix))
+                .flatten();
+            if let Some(definition) = found {
+                return self.expand(&mut buf, start..at, &definition.body);
+            }
+            if buf.snippet.is_none() {
+                buf.apply(BufferAction::Append("\t".into()))?;
+                return Ok(());
+            }
+        }
+        self.jump(&mut buf, if forward { 1 } else { -1 })
+    }
+
+    /// puts a snippet in place of bytes `range` of a buffer, indented like
+    /// the line it's on, and goes to its first tabstop
+    fn expand(&self, buf: &mut Buffer, range: Range<usize>, body: &str) -> Result<(), EvieError> {
+        let line = motion::line_col(&buf.text, range.start).0;
+        let current_line = motion::line_text(&buf.text, line);
+        let indent: String = current_line
+            .chars()
+            .take_while(|&c| c == ' ' || c == '\t')
+            .collect();
+        let variables = Variables {
+            path: &buf.path,
+            line,
+            current_line: &current_line,
+        };
+        let expansion = Snippet::parse(body).expand(&indent, &variables);
+        // the old one's tabstops shouldn't move with the new one going in
+        buf.snippet = None;
+        buf.splice(range.clone(), &expansion.text)?;
+        buf.snippet = Some(snippet::Active::new(expansion.stops, range.start));
+        self.jump(buf, 1)
+    }
+
+    /// goes to another tabstop of the snippet being filled in, which ends at
+    /// its last, offering the choices it has
+    fn jump(&self, buf: &mut Buffer, by: isize) -> Result<(), EvieError> {
+        let Some(snippet) = &mut buf.snippet else {
+            return Ok(());
+        };
+        let Some(stop) = snippet.jump(by).cloned() else {
+            buf.snippet = None;
+            return Ok(());
+        };
+        let range = stop.ranges[0].clone();
+        buf.loc.char = range.end;
+        buf.loc.want = None;
+        if stop.index == 0 {
+            buf.snippet = None;
+        }
+        if !stop.choices.is_empty() {
+            let items = stop
+                .choices
+                .iter()
+                .map(|choice| Item::new(choice, "choices"));
+            let choices = Completion::choices(buf.path.clone(), range.start, items.collect());
+            *self.completion.try_borrow_mut()? = Some(choices);
+        }
+        Ok(())
+    }
+
+    /// looks for open files that were changed outside of evie
+    pub fn check_disk(&self) -> Result<Vec<DiskEvent>, EvieError> {
+        Ok(self.engine.check_disk()?)
+    }
+
+    /// replays the swap file left behind for a buffer, see [`Engine::recover`]
+    pub fn recover(&self, path: impl AsRef<Path>, relative: bool) -> Result<usize, EvieError> {
+        Ok(self.engine.recover(path, relative)?)
+    }
+
+    /// writes pending changes to the swap files
+    pub fn sync_swaps(&self) -> Result<(), EvieError> {
+        Ok(self.engine.sync_swaps()?)
+    }
+
+    /// diffs a buffer against its file on disk
+    pub fn disk_diff(&self, path: impl AsRef<Path>, relative: bool) -> Result<String, EvieError> {
+        let buf = self.engine.get_buffer(path, relative)?;
+        let buf = buf.try_borrow()?;
+        let disk = engine::read_file(&buf.path, |_| buf.large)?
+            .map(|(text, ..)| text.to_string())
+            .unwrap_or_default();
+        let name = buf.path.display();
+        Ok(diff::unified(
+            &format!("{name} (disk)"),
+            &disk,
+            &format!("{name} (buffer)"),
+            &buf.text.to_string(),
+        ))
+    }
+
+    /// runs an ex command, against the given buffer if there is one
+    pub fn execute(&self, cmd: &str, buffer: Option<&Path>) -> Result<(), EvieError> {
+        let buf = || match buffer {
+            Some(buffer) => Ok(self.engine.get_buffer(buffer, false)?),
+            None => Err(EvieError::from(CommandError::NoBuffer(cmd.to_owned()))),
+        };
+        match cmd.parse::<Command>()? {
+            Command::Edit { force } => {
+                let buf = buf()?;
+                let mut buf = buf.try_borrow_mut()?;
+                if buf.modified && !force {
+                    return Err(CommandError::Modified.into());
+                }
+                buf.reload()?;
+            }
+            Command::Write => {
+                let buf = buf()?;
+                let path = buf.try_borrow()?.path.clone();
+                self.engine.write(path, false)?;
+            }
+            Command::WriteQuit {
+                force,
+                modified_only,
+            } => {
+                let buf = buf()?;
+                let (path, modified) = {
+                    let buf = buf.try_borrow()?;
+                    (buf.path.clone(), buf.modified)
+                };
+                if modified || !modified_only {
+                    self.engine.write(path, false)?;
+                }
+                self.quit(force)?;
+            }
+            Command::Substitute(sub) => {
+                let buf = buf()?;
+                let mut buf = buf.try_borrow_mut()?;
+                let lines = sub.lines.resolve(buf.cursor_line(), buf.text.len_lines());
+                let changes = sub.changes(&buf.text, lines)?;
+                // like vim, the cursor ends up on the last line changed
+                let last = buf.text.byte_to_line(changes[changes.len() - 1].0.start);
+                let edits: Vec<_> = changes
+                    .iter()
+                    .map(|(range, new_text)| engine::Replace {
+                        range: range.clone(),
+                        new_text,
+                    })
+                    .collect();
+                buf.replace(&edits)?;
+                buf.loc = Location {
+                    char: buf.text.line_to_byte(last),
+                    ..Default::default()
+                };
+                if self.mode.get().on_char() {
+                    buf.clamp_cursor();
+                }
+                if changes.len() > 1 {
+                    self.inform(format!("{} lines changed", changes.len()));
+                }
+            }
+            Command::Split { split, file } => {
+                let buffer = file.map(|file| self.load(&file)).transpose()?;
+                self.in_windows(|windows| {
+                    windows.split(split, buffer);
+                })?;
+            }
+            Command::Close => self.window(WindowAction::Close)?,
+            Command::Only => self.window(WindowAction::Only)?,
+            Command::Quit { force } => self.quit(force)?,
+            Command::TabNew { file } => {
+                let buffer = match file {
+                    Some(file) => self.load(&file)?,
+                    None => {
+                        let windows = self.windows()?;
+                        let window = windows.get(windows.focus()).ok_or(EvieError::NoWindow)?;
+                        window.buffer.clone()
+                    }
+                };
+                self.in_tabs(|tabs| tabs.open(Windows::new(buffer)))?;
+            }
+            Command::TabClose => self.close_tab()?,
+            Command::TabMove(to) => {
+                let mut tabs = self.tabs.try_borrow_mut()?;
+                let current = tabs.index();
+                tabs.shift(match to {
+                    TabMove::After(n) if n > current => n - 1,
+                    TabMove::After(n) => n,
+                    TabMove::By(by) => current.saturating_add_signed(by),
+                    TabMove::Last => usize::MAX,
+                });
+            }
+            Command::Rename(name) => {
+                let path = buf()?.try_borrow()?.path.clone();
+                self.request(&path, LspAction::Rename(name))?;
+            }
+            Command::Format => {
+                let path = buf()?.try_borrow()?.path.clone();
+                self.request(&path, LspAction::Format)?;
+            }
+            Command::Diagnostics => self.diagnostics_list.set(!self.diagnostics_list.get()),
+            Command::Colorscheme(None) => {
+                let name = self.theme.try_borrow()?.name.clone();
+                self.inform(name);
+            }
+            Command::Colorscheme(Some(name)) => {
+                *self.theme.try_borrow_mut()? = Theme::load(&name)?;
+            }
+            Command::Set(args) => {
+                let buf = buffer.and_then(|_| buf().ok());
+                let mut options = self.engine.options.try_borrow_mut()?;
+                for arg in args {
+                    // buffer local options first, then global ones
+                    let local = match &buf {
+                        Some(buf) => {
+                            let mut buf = buf.try_borrow_mut()?;
+                            // changing the format changes what gets written
+                            match buf.format.set(&arg) {
+                                Err(OptionError::Unknown(_)) => buf.local.set(&arg),
+                                res => {
+                                    buf.modified |= res.is_ok();
+                                    res
+                                }
+                            }
+                        }
+                        None => Err(OptionError::Unknown(arg.clone())),
+                    };
+                    match local {
+                        Err(OptionError::Unknown(_)) => options.set(&arg),
+                        res => res,
+                    }
+                    .map_err(CommandError::from)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// runs every line of a file as an ex command, skipping blank lines and
+    /// `"` comments
+    pub fn source(&self, path: impl AsRef<Path>) -> Result<(), EvieError> {
+        let script = std::fs::read_to_string(path).map_err(EngineError::from)?;
+        script
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('"'))
+            .try_for_each(|line| self.execute(line, None))
+    }
+
+    /// the path of the buffer for `file`, opening it if it isn't yet
+    fn load(&self, file: &str) -> Result<PathBuf, EvieError> {
+        let buf = match self.engine.get_buffer(file, true) {
+            Ok(buf) => buf,
+            Err(_) => self.engine.add_buffer(file, true)?,
+        };
+        let path = buf.try_borrow()?.path.clone();
+        Ok(path)
+    }
+
+    /// splits, closes and moves between windows
+    pub fn window(&self, action: WindowAction) -> Result<(), EvieError> {
+        match action {
+            WindowAction::Split(split) => self.in_windows(|windows| {
+                windows.split(split, None);
+            })?,
+            // like vim, closing the last window of a tab page closes the page
+            WindowAction::Close
+                if self.windows()?.len() == 1 && self.tabs.try_borrow()?.len() > 1 =>
+            {
+                self.close_tab()?
+            }
+            WindowAction::Close => {
+                if !self.in_windows(|windows| windows.close(windows.focus()))? {
+                    return Err(CommandError::LastWindow.into());
+                }
+            }
+            WindowAction::Quit => self.quit(false)?,
+            WindowAction::Only => self.in_windows(Windows::only)?,
+            WindowAction::Focus(towards) => self.in_windows(|windows| {
+                if let Some(id) = windows.neighbor(towards) {
+                    windows.set_focus(id);
+                }
+            })?,
+            WindowAction::Cycle(by) => self.in_windows(|windows| {
+                windows.set_focus(windows.cycle(by));
+            })?,
+            WindowAction::Resize(split, by) => self.windows_mut()?.resize(split, by),
+            WindowAction::Equalize => self.windows_mut()?.equalize(),
+            WindowAction::Rotate(forward) => self.windows_mut()?.rotate(forward),
+        }
+        Ok(())
+    }
+
+    /// goes to the tab page `by` places along, wrapping around
+    pub fn next_tab(&self, by: isize) -> Result<(), EvieError> {
+        self.in_tabs(|tabs| tabs.cycle(by))
+    }
+
+    /// goes to a tab page, counting from 0
+    pub fn select_tab(&self, index: usize) -> Result<(), EvieError> {
+        self.in_tabs(|tabs| tabs.select(index))
+    }
+
+    fn close_tab(&self) -> Result<(), EvieError> {
+        if !self.in_tabs(Tabs::close)? {
+            return Err(CommandError::LastTab.into());
+        }
+        Ok(())
+    }
+
+    /// what the tab bar shows
+    pub fn tab_pages(&self) -> Result<Vec<TabPage>, EvieError> {
+        let tabs = self.tabs.try_borrow()?;
+        tabs.pages()
+            .iter()
+            .enumerate()
+            .map(|(i, windows)| {
+                let window = windows.get(windows.focus()).ok_or(EvieError::NoWindow)?;
+                let modified = match self.engine.get_buffer(&window.buffer, false) {
+                    Ok(buf) => buf.try_borrow()?.modified,
+                    Err(_) => false,
+                };
+                Ok(TabPage {
+                    name: window
+                        .buffer
+                        .file_name()
+                        .map_or("[No Name]".into(), |name| name.to_string_lossy().into()),
+                    modified,
+                    current: i == tabs.index(),
+                })
+            })
+            .collect()
+    }
+
+    /// every buffer's diagnostics, by file and then where they are in it
+    pub fn diagnostics(&self) -> Result<Vec<Listed>, EvieError> {
+        let mut listed = Vec::new();
+        for buf in self.engine.buffers() {
+            let buf = buf.try_borrow()?;
+            for diagnostic in buf.diagnostics.all() {
+                let (line, col) = motion::line_col(&buf.text, diagnostic.range.start);
+                listed.push(Listed {
+                    path: buf.path.clone(),
+                    line,
+                    col: motion::column(&motion::line_text(&buf.text, line), col),
+                    diagnostic: diagnostic.clone(),
+                });
+            }
+        }
+        listed.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(listed)
+    }
+
+    /// closes the focused window, or the whole editor from the last one
+    fn quit(&self, force: bool) -> Result<(), EvieError> {
+        if self.windows()?.len() > 1 || self.tabs.try_borrow()?.len() > 1 {
+            return self.window(WindowAction::Close);
+        }
+        if !force && !self.engine.modified().is_empty() {
+            return Err(CommandError::Modified.into());
+        }
+        self.quit.set(true);
+        Ok(())
+    }
+
+    /// changes the windows of the tab page on screen, see [`Self::in_tabs`]
+    fn in_windows<T>(&self, f: impl FnOnce(&mut Windows) -> T) -> Result<T, EvieError> {
+        self.in_tabs(|tabs| f(tabs.current_mut()))
+    }
+
+    /// changes the tab pages, taking the cursor out of the focused window's
+    /// buffer first and putting the cursor of whichever has focus after back
+    fn in_tabs<T>(&self, f: impl FnOnce(&mut Tabs) -> T) -> Result<T, EvieError> {
+        let mut tabs = self.tabs.try_borrow_mut()?;
+        let window = tabs.current_mut().focused();
+        if let Ok(buf) = self.engine.get_buffer(&window.buffer, false) {
+            window.loc = buf.try_borrow()?.loc;
+        }
+        let out = f(&mut tabs);
+        let window = tabs.current_mut().focused();
+        if let Ok(buf) = self.engine.get_buffer(&window.buffer, false) {
+            let mut buf = buf.try_borrow_mut()?;
+            buf.loc = window.loc;
+            buf.fit_cursor();
+            if self.mode.get().on_char() {
+                buf.clamp_cursor();
+            }
+        }
+        Ok(out)
+    }
+
+    /// runs `f` on a window and its buffer, with the buffer's cursor where
+    /// that window has it
+    fn in_window<T>(
+        &self,
+        window: WindowId,
+        f: impl FnOnce(&mut Buffer, &mut Window) -> Result<T, EvieError>,
+    ) -> Result<T, EvieError> {
+        let mut windows = self.windows_mut()?;
+        let focused = windows.focus() == window;
+        let window = windows.get_mut(window).ok_or(EvieError::NoWindow)?;
+        let buf = self.engine.get_buffer(&window.buffer, false)?;
+        let mut buf = buf.try_borrow_mut()?;
+        if focused {
+            return f(&mut buf, window);
+        }
+        std::mem::swap(&mut buf.loc, &mut window.loc);
+        buf.fit_cursor();
+        buf.clamp_cursor();
+        let out = f(&mut buf, window);
+        std::mem::swap(&mut buf.loc, &mut window.loc);
+        out
+    }
+
+    fn apply(&self, action: CoreAction, window: WindowId, buffer: &Path) -> Result<(), EvieError> {
         match action {
-            CoreAction::SetMode(mode) => self.change_mode(mode),
+            CoreAction::SetMode(mode) => {
+                let buf = self.engine.get_buffer(buffer, false)?;
+                let mut buf = buf.try_borrow_mut()?;
+                buf.commit();
+                // like vim, leaving insert mode steps back onto the last
+                // character typed
+                if let (Mode::Insert | Mode::Replace, Mode::Normal) = (self.mode.get(), mode) {
+                    buf.apply(BufferAction::Move(Move::Left))?;
+                }
+                self.change_mode(mode);
+                if self.mode.get().on_char() {
+                    buf.clamp_cursor();
+                }
+                buf.loc.anchor = (mode == Mode::Visual).then_some(buf.loc.char);
+                buf.grown.clear();
+                if mode != Mode::Insert {
+                    buf.snippet = None;
+                }
+            }
+            CoreAction::Select(select) => {
+                let buf = self.engine.get_buffer(buffer, false)?;
+                self.select(&mut *buf.try_borrow_mut()?, select);
+            }
+            CoreAction::Fold(fold) => {
+                let buf = self.engine.get_buffer(buffer, false)?;
+                let mut buf = buf.try_borrow_mut()?;
+                self.fold(&mut buf, fold);
+                if self.mode.get().on_char() {
+                    buf.clamp_cursor();
+                }
+            }
+            CoreAction::Scroll(scroll) => {
+                let so = self.engine.options.try_borrow()?.scrolloff;
+                let on_char = self.mode.get().on_char();
+                self.in_window(window, |buf, window| {
+                    let line = buf.cursor_line();
+                    let to = window.view.scroll(scroll, line, buf.text.len_lines(), so);
+                    if to != line {
+                        buf.goto_line(to);
+                        if on_char {
+                            buf.clamp_cursor();
+                        }
+                    }
+                    Ok(())
+                })?;
+            }
+            CoreAction::CmdlinePush(c) => self.cmdline.borrow_mut().push(c),
+            CoreAction::CmdlinePop => {
+                if self.cmdline.borrow_mut().pop().is_none() {
+                    self.change_mode(Mode::Normal);
+                }
+            }
+            CoreAction::CmdlineExecute => {
+                let cmd = self.cmdline.take();
+                self.change_mode(Mode::Normal);
+                self.execute(&cmd, Some(buffer))?;
+            }
+            CoreAction::Window(action) => self.window(action)?,
+            CoreAction::NextTab(by) => self.next_tab(by)?,
+            CoreAction::Lsp(action) => self.request(buffer, action)?,
+            CoreAction::Complete(action) => self.complete(action, buffer)?,
+            CoreAction::Snippet(forward) => self.tab(buffer, forward)?,
+            CoreAction::NextDiagnostic(forward) => {
+                let buf = self.engine.get_buffer(buffer, false)?;
+                let mut buf = buf.try_borrow_mut()?;
+                let Some(diagnostic) = buf.diagnostics.next(buf.loc.char, forward).cloned() else {
+                    self.inform("No diagnostics");
+                    return Ok(());
+                };
+                buf.loc.char = diagnostic.range.start;
+                buf.fit_cursor();
+                if self.mode.get().on_char() {
+                    buf.clamp_cursor();
+                }
+                self.inform(format!(
+                    "{}: {}",
+                    diagnostic.severity.name(),
+                    diagnostic.message
+                ));
+            }
+            CoreAction::Zoom(by) => {
+                let options = self.engine.options.try_borrow()?;
+                let zoom = match by {
+                    0 => 0,
+                    // past the smallest size zooming back in should take
+                    // effect straight away
+                    by => {
+                        options.font_size(self.zoom.get() + by) as isize - options.fontsize as isize
+                    }
+                };
+                self.zoom.set(zoom);
+            }
         }
         Ok(())
     }
+
+    /// selects a piece of code found through the syntax tree, doing nothing
+    /// when there's none
+    fn select(&self, buf: &mut Buffer, select: Select) {
+        let selection = buf.selection();
+        let range = match select {
+            Select::Object(object, inner) => buf.text_object(object, inner),
+            Select::Node => buf.expand(buf.loc.char..buf.loc.char),
+            Select::Expand => buf.expand(selection.clone().unwrap_or_default()),
+            Select::Shrink => buf.grown.pop(),
+        };
+        let Some(range) = range else {
+            return;
+        };
+        match (select, selection) {
+            (Select::Expand, Some(selection)) => buf.grown.push(selection),
+            (Select::Shrink, _) => {}
+            _ => buf.grown.clear(),
+        }
+        self.change_mode(Mode::Visual);
+        buf.select(range);
+    }
+
+    /// opens or closes the folds the syntax tree has
+    fn fold(&self, buf: &mut Buffer, fold: FoldAction) {
+        let line = buf.cursor_line();
+        // the innermost fold around the cursor that isn't closed yet, so
+        // closing again closes the one around that
+        let close = |buf: &mut Buffer| {
+            let mut around: Vec<_> = buf
+                .fold_ranges()
+                .into_iter()
+                .filter(|lines| lines.contains(&line))
+                .collect();
+            around.sort_by_key(|lines| lines.len());
+            let lines = around.into_iter().find(|lines| !buf.folds.hidden(lines));
+            if let Some(lines) = lines {
+                buf.folds.close(lines);
+            }
+        };
+        match fold {
+            FoldAction::Close => close(buf),
+            FoldAction::Open => {
+                buf.folds.open(line);
+            }
+            FoldAction::Toggle => {
+                if !buf.folds.open(line) {
+                    close(buf);
+                }
+            }
+            FoldAction::CloseAll => {
+                for lines in buf.fold_ranges() {
+                    buf.folds.close(lines);
+                }
+            }
+            FoldAction::OpenAll => buf.folds.open_all(),
+        }
+        // the cursor goes to the line a fold is shown on
+        if let Some(fold) = buf.folds.at(line) {
+            if fold.start != line {
+                buf.goto_line(fold.start);
+            }
+        }
+    }
+
+    /// what the status line shows for a window
+    pub fn status(&self, window: WindowId) -> Result<Status, EvieError> {
+        self.in_window(window, |buf, _| Ok(self.buffer_status(buf)))
+    }
+
+    fn buffer_status(&self, buf: &Buffer) -> Status {
+        let (line, col) = motion::line_col(&buf.text, buf.loc.char);
+        let content = motion::line_text(&buf.text, line);
+        // a trailing newline ends the last line rather than starting another
+        let trailing = buf.text.len_chars() > 0 && buf.text.char(buf.text.len_chars() - 1) == '\n';
+        Status {
+            mode: self.mode.get(),
+            path: buf
+                .path
+                .strip_prefix(self.engine.base())
+                .unwrap_or(&buf.path)
+                .to_owned(),
+            full_path: buf.path.clone(),
+            modified: buf.modified,
+            line,
+            col: motion::column(&content, col),
+            lines: (buf.text.len_lines() - trailing as usize).max(1),
+            filetype: buf.local.filetype.clone(),
+            encoding: buf.format.fileencoding.name(),
+            fileformat: buf.format.fileformat.name(),
+        }
+    }
+
+    /// scrolls a window to keep its cursor on screen
+    fn follow(&self, window: WindowId) -> Result<(), EvieError> {
+        let options = self.engine.options.try_borrow()?;
+        self.in_window(window, |buf, window| {
+            let (line, col) = motion::line_col(&buf.text, buf.loc.char);
+            let col = motion::column(&motion::line_text(&buf.text, line), col);
+            window
+                .view
+                .follow(line, col, options.scrolloff, options.sidescrolloff);
+            Ok(())
+        })
+    }
 }
 
+/// A buffer as seen through one window
 #[derive(Debug)]
 pub struct BufferView<K: Key> {
     evie: EvieCentral<K>,
+    window: WindowId,
     buffer: PathBuf,
 }
 
@@ -89,25 +1028,237 @@ impl<K: Key> BufferView<K> {
             .evie
             .engine
             .get_buffer(&self.buffer, true)?
-            .borrow()
+            .try_borrow()?
             .text
             .clone())
     }
+    /// see [`Buffer::revision`](buffer::Buffer::revision)
+    pub fn revision(&self) -> Result<u64, EvieError> {
+        Ok(self
+            .evie
+            .engine
+            .get_buffer(&self.buffer, true)?
+            .try_borrow()?
+            .revision)
+    }
+
+    /// see [`Buffer::highlight`]
+    pub fn highlight(&self, line: usize) -> Result<Vec<syntax::Span>, EvieError> {
+        let buf = self.evie.engine.get_buffer(&self.buffer, true)?;
+        let mut buf = buf.try_borrow_mut()?;
+        Ok(buf.highlight(line).to_vec())
+    }
+
     pub fn on_key(&self, key: K) -> Result<bool, EvieError> {
+        self.on_key_with(key, &Logical)
+    }
+
+    /// runs a key, with `display` working out motions over wrapped lines
+    pub fn on_key_with(&self, key: K, display: &dyn DisplayLines) -> Result<bool, EvieError> {
         let Some(action) = self.evie.trigger(key) else {
             return Ok(false);
         };
         match action {
-            Action::Core(ca) => self.evie.apply(ca)?,
-            Action::Buffer(ba) => self
-                .evie
-                .engine
-                .get_buffer(&self.buffer, true)?
-                .try_borrow_mut()?
-                .apply(ba)?,
+            Action::Core(ca) => self.evie.apply(ca, self.window, &self.buffer)?,
+            Action::Buffer(ba) => {
+                let buf = self.evie.engine.get_buffer(&self.buffer, true)?;
+                let mut buf = buf.try_borrow_mut()?;
+                buf.apply(ba)?;
+                if self.evie.mode.get().on_char() {
+                    buf.clamp_cursor();
+                }
+            }
+            Action::Display(mv) => {
+                let buf = self.evie.engine.get_buffer(&self.buffer, true)?;
+                let mut buf = buf.try_borrow_mut()?;
+                let Location { char, want, .. } = buf.loc;
+                let (char, want) = display
+                    .resolve(&buf.text, char, want, mv)
+                    .or_else(|| Logical.resolve(&buf.text, char, want, mv))
+                    .unwrap_or((char, want));
+                buf.loc = Location {
+                    char,
+                    want,
+                    ..buf.loc
+                };
+                buf.fit_cursor();
+                if self.evie.mode.get().on_char() {
+                    buf.clamp_cursor();
+                }
+            }
         }
+        self.evie.follow_completion()?;
+        // the key may have moved focus to another window
+        let focus = self.evie.windows()?.focus();
+        self.evie.follow(focus)?;
         Ok(true)
     }
+
+    pub fn window(&self) -> WindowId {
+        self.window
+    }
+
+    /// handles the mouse in this window, giving it focus, returns whether
+    /// the `mouse` option let it do anything
+    pub fn pointer(&self, pointer: Pointer) -> Result<bool, EvieError> {
+        let mode = self.evie.mode.get();
+        if mode == Mode::Command || !self.evie.engine.options.try_borrow()?.mouse_in(mode) {
+            return Ok(false);
+        }
+        self.evie
+            .in_windows(|windows| windows.set_focus(self.window))?;
+        let buf = self.evie.engine.get_buffer(&self.buffer, true)?;
+        let mut buf = buf.try_borrow_mut()?;
+        let visual = |buf: &mut Buffer, range: Range<usize>| {
+            self.evie.change_mode(Mode::Visual);
+            buf.select(range);
+        };
+        match pointer {
+            Pointer::Place(at) => {
+                if mode == Mode::Visual {
+                    self.evie.change_mode(Mode::Normal);
+                }
+                buf.loc = Location {
+                    char: at,
+                    ..Default::default()
+                };
+            }
+            // a press that doesn't move off its character is still a click
+            Pointer::Drag(at) if mode != Mode::Visual && at == buf.loc.char => return Ok(false),
+            Pointer::Drag(at) => {
+                let anchor = buf.loc.anchor.unwrap_or(buf.loc.char);
+                self.evie.change_mode(Mode::Visual);
+                buf.loc = Location {
+                    char: at,
+                    want: None,
+                    anchor: Some(anchor),
+                };
+            }
+            Pointer::Word(at) => {
+                let word = motion::word(&buf.text, at);
+                visual(&mut buf, word)
+            }
+            Pointer::Line(at) => {
+                let line = motion::line(&buf.text, at);
+                visual(&mut buf, line)
+            }
+            Pointer::Paste(at, text) => {
+                if mode == Mode::Visual {
+                    self.evie.change_mode(Mode::Normal);
+                }
+                buf.commit();
+                buf.loc = Location {
+                    char: at,
+                    ..Default::default()
+                };
+                buf.fit_cursor();
+                buf.apply(BufferAction::Append(text))?;
+                buf.commit();
+            }
+        }
+        buf.fit_cursor();
+        if self.evie.mode.get().on_char() {
+            buf.clamp_cursor();
+        }
+        drop(buf);
+        self.evie.follow(self.window)?;
+        Ok(true)
+    }
+
+    /// what is selected in visual mode, as a byte range into the text, none
+    /// outside of it or when another window has focus
+    pub fn selection(&self) -> Result<Option<Range<usize>>, EvieError> {
+        if self.evie.mode.get() != Mode::Visual || self.evie.windows()?.focus() != self.window {
+            return Ok(None);
+        }
+        let buf = self.evie.engine.get_buffer(&self.buffer, true)?;
+        let selection = buf.try_borrow()?.selection();
+        Ok(selection)
+    }
+
+    /// the closed folds, as the lines each hides behind its first, see
+    /// [`Folds::outer`](fold::Folds::outer)
+    pub fn folds(&self) -> Result<Vec<Range<usize>>, EvieError> {
+        let buf = self.evie.engine.get_buffer(&self.buffer, true)?;
+        let folds = buf.try_borrow()?.folds.outer();
+        Ok(folds)
+    }
+
+    /// see [`Buffer::diagnostics`](buffer::Buffer::diagnostics)
+    pub fn diagnostics(&self) -> Result<diagnostic::Diagnostics, EvieError> {
+        let buf = self.evie.engine.get_buffer(&self.buffer, true)?;
+        let diagnostics = buf.try_borrow()?.diagnostics.clone();
+        Ok(diagnostics)
+    }
+
+    /// the placeholder of a snippet that typing replaces, if this window has
+    /// focus
+    pub fn placeholder(&self) -> Result<Option<Range<usize>>, EvieError> {
+        if self.evie.mode.get() != Mode::Insert || self.evie.windows()?.focus() != self.window {
+            return Ok(None);
+        }
+        let buf = self.evie.engine.get_buffer(&self.buffer, true)?;
+        let placeholder = buf
+            .try_borrow()?
+            .snippet
+            .as_ref()
+            .and_then(|s| s.placeholder());
+        Ok(placeholder)
+    }
+
+    /// the completion open in this window's buffer, if it has focus
+    pub fn completion(&self) -> Result<Option<Menu>, EvieError> {
+        if self.evie.windows()?.focus() != self.window {
+            return Ok(None);
+        }
+        let completion = self.evie.completion.try_borrow()?;
+        let open = completion.as_ref().filter(|open| open.path == self.buffer);
+        Ok(open.map(Menu::from))
+    }
+
+    /// see [`Evie::status`]
+    pub fn status(&self) -> Result<Status, EvieError> {
+        self.evie.status(self.window)
+    }
+
+    /// scrolls without a key, like with the mouse wheel
+    pub fn scroll(&self, scroll: Scroll) -> Result<(), EvieError> {
+        self.evie
+            .apply(CoreAction::Scroll(scroll), self.window, &self.buffer)?;
+        self.evie.follow(self.window)
+    }
+
+    pub fn viewport(&self) -> Result<Viewport, EvieError> {
+        let windows = self.evie.windows()?;
+        Ok(windows.get(self.window).ok_or(EvieError::NoWindow)?.view)
+    }
+
+    /// tells the view how many lines and columns fit on screen, scrolling to
+    /// keep the cursor on it
+    pub fn resize(&self, height: usize, width: usize) -> Result<(), EvieError> {
+        {
+            let mut windows = self.evie.windows_mut()?;
+            let view = &mut windows
+                .get_mut(self.window)
+                .ok_or(EvieError::NoWindow)?
+                .view;
+            if (view.height, view.width) == (height, width) {
+                return Ok(());
+            }
+            view.height = height;
+            view.width = width;
+        }
+        self.evie.follow(self.window)
+    }
+
+    /// where the window's cursor is, as a byte offset into the text
+    pub fn cursor(&self) -> Result<usize, EvieError> {
+        self.evie.in_window(self.window, |buf, _| Ok(buf.loc.char))
+    }
+
+    pub fn evie(&self) -> &EvieCentral<K> {
+        &self.evie
+    }
 }
 
 impl<K: Key> Evie<K> {
@@ -121,7 +1272,7 @@ impl<K: Key> Evie<K> {
         match trig {
             Some(Trigger::End(a)) => {
                 self.trig.store(self.root());
-                return Some(Some(a));
+                Some(Some(a))
             }
             Some(Trigger::Map(tm)) => {
                 self.trig.store(tm);
@@ -135,10 +1286,21 @@ impl<K: Key> Evie<K> {
     }
 
     pub fn change_mode(&self, mode: Mode) {
+        if let Mode::Command = mode {
+            self.cmdline.borrow_mut().clear();
+        }
+        if mode != Mode::Insert {
+            self.completion.borrow_mut().take();
+        }
         self.mode.set(mode);
         self.trig.store(self.root());
     }
 
+    /// whether part of a key sequence has been typed, like the `d` of `dw`
+    pub fn pending(&self) -> bool {
+        !Arc::ptr_eq(&self.trig.load(), &self.root())
+    }
+
     fn root(&self) -> Arc<TriggerMap<K>> {
         match self.mode.get() {
             Mode::Normal => &self.mdata.normal,
@@ -156,16 +1318,140 @@ impl<K: Key> Evie<K> {
 pub enum Action {
     Core(CoreAction),
     Buffer(BufferAction),
+    /// see [`BufferView::on_key_with`]
+    Display(DisplayMove),
 }
 
 #[derive(Debug, Clone)]
 pub enum CoreAction {
     SetMode(Mode),
+    CmdlinePush(char),
+    /// deletes the last character, leaving command mode when there is none
+    CmdlinePop,
+    CmdlineExecute,
+    Scroll(Scroll),
+    Window(WindowAction),
+    /// goes to the tab page this many places along, wrapping around
+    NextTab(isize),
+    /// selects through the syntax tree, see [`Select`]
+    Select(Select),
+    Fold(FoldAction),
+    /// asks the buffer's language server, see [`Evie::requests`]
+    Lsp(LspAction),
+    /// `]d` and `[d`, goes to the next diagnostic in the buffer, or the one
+    /// before with false, wrapping around
+    NextDiagnostic(bool),
+    /// works the completion menu in insert mode, see [`completion`]
+    Complete(CompleteAction),
+    /// `Tab` in insert mode expands the snippet named by the word before the
+    /// cursor, goes on to the next tabstop of the one being filled in, or
+    /// inserts a tab. `Shift-Tab`, with false, goes back a tabstop
+    Snippet(bool),
+    /// makes text this many pixels bigger in every window, 0 goes back to
+    /// `fontsize`
+    Zoom(isize),
+}
+
+/// What's selected from the syntax tree, entering visual mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Select {
+    /// a [`TextObject`] around the cursor, or just its inside
+    Object(TextObject, bool),
+    /// the smallest syntax node at the cursor
+    Node,
+    /// grows the selection to the node around it
+    Expand,
+    /// goes back to what the selection grew from
+    Shrink,
+}
+
+/// What `Ctrl-n`, `Ctrl-p`, `Ctrl-y` and `Ctrl-e` do in insert mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompleteAction {
+    /// selects the next candidate, opening the menu if it isn't
+    Next,
+    Prev,
+    /// replaces the word with the selected candidate, closing the menu
+    Accept,
+    /// closes the menu, leaving the word as typed
+    Cancel,
+}
+
+/// What a language server is asked about the text at the cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LspAction {
+    Hover,
+    Definition,
+    References,
+    /// renames the symbol everywhere it's used
+    Rename(String),
+    /// formats the whole buffer
+    Format,
+    /// candidates for the word before the cursor, see
+    /// [`Evie::complete_with`]
+    Complete,
+}
+
+/// A request for a language server, from the cursor at byte `at` of a buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LspRequest {
+    pub path: PathBuf,
+    pub at: usize,
+    pub action: LspAction,
+}
+
+/// What `z` does with folds, like vim's `zc`, `zo`, `za`, `zM` and `zR`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldAction {
+    Close,
+    Open,
+    Toggle,
+    CloseAll,
+    OpenAll,
+}
+
+/// What `Ctrl-w` does, see [`Evie::window`]
+#[derive(Debug, Clone)]
+pub enum WindowAction {
+    Split(Split),
+    /// closes the focused window, unless it's the last
+    Close,
+    /// like `:quit`
+    Quit,
+    Only,
+    /// moves focus to the window next to this one
+    Focus(Move),
+    /// moves focus through the windows in order, wrapping around
+    Cycle(isize),
+    /// grows or shrinks the focused window, see [`Windows::resize`]
+    Resize(Split, i32),
+    Equalize,
+    /// see [`Windows::rotate`]
+    Rotate(bool),
+}
+
+/// What the mouse does in a window, at a byte offset into the text, see
+/// [`BufferView::pointer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pointer {
+    /// a click, which moves the cursor
+    Place(usize),
+    /// moving with the button held, which selects from where it was pressed
+    Drag(usize),
+    /// a double click, which selects a word
+    Word(usize),
+    /// a triple click, which selects a line
+    Line(usize),
+    /// a middle click, which pastes
+    Paste(usize, String),
 }
 
 #[derive(Debug, Clone)]
 pub enum BufferAction {
     Append(String),
+    Undo,
+    Redo,
+    Move(Move),
 }
 
 impl From<CoreAction> for Action {
@@ -174,13 +1460,19 @@ impl From<CoreAction> for Action {
     }
 }
 
+impl From<DisplayMove> for Action {
+    fn from(value: DisplayMove) -> Self {
+        Self::Display(value)
+    }
+}
+
 impl From<BufferAction> for Action {
     fn from(value: BufferAction) -> Self {
         Self::Buffer(value)
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub enum Move {
     Left,
     Right,
@@ -189,7 +1481,7 @@ pub enum Move {
 }
 
 // TODO: consider making this a trait instead.
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
     /// The default mode
     #[default]
@@ -206,6 +1498,26 @@ pub enum Mode {
     Terminal,
 }
 
+impl Mode {
+    /// what the status line calls it
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Normal => "NORMAL",
+            Self::Insert => "INSERT",
+            Self::Visual => "VISUAL",
+            Self::Command => "COMMAND",
+            Self::Replace => "REPLACE",
+            Self::Terminal => "TERMINAL",
+        }
+    }
+
+    /// whether the cursor sits on a character rather than between two, so it
+    /// can't go past the end of a line
+    pub fn on_char(self) -> bool {
+        matches!(self, Self::Normal | Self::Visual)
+    }
+}
+
 pub trait Key: std::hash::Hash + std::cmp::Eq + std::fmt::Debug + Clone {}
 // impl<K: std::hash::Hash + std::cmp::Eq> Key for K {}
 
@@ -0,0 +1,201 @@
+//! Cursor motions
+//!
+//! Positions are byte offsets into the text, like [`Location::char`]. The
+//! cursor always moves by whole grapheme clusters, so it never lands inside a
+//! wide character or between a letter and its combining marks.
+//!
+//! [`Location::char`]: crate::buffer::Location::char
+
+use std::ops::Range;
+
+use ropey::Rope;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::Move;
+
+#[cfg(test)]
+mod test;
+
+/// the line `at` is on, and how many bytes into the line it is
+pub fn line_col(text: &Rope, at: usize) -> (usize, usize) {
+    let at = at.min(text.len_bytes());
+    let line = text.byte_to_line(at);
+    (line, at - text.line_to_byte(line))
+}
+
+/// a line's text without its line ending
+pub fn line_text(text: &Rope, line: usize) -> String {
+    let mut content = text.line(line).to_string();
+    if content.ends_with('\n') {
+        content.pop();
+    }
+    content
+}
+
+/// where `mv` takes the cursor from `at`
+///
+/// `want` is the column up and down motions aim for, kept between them so
+/// passing through a short line doesn't lose it.
+pub fn apply(text: &Rope, at: usize, mv: &Move, want: &mut Option<usize>) -> usize {
+    let (line, col) = line_col(text, at);
+    let start = text.line_to_byte(line);
+    let content = line_text(text, line);
+    match mv {
+        Move::Left => {
+            *want = None;
+            start + prev_boundary(&content, col)
+        }
+        Move::Right => {
+            *want = None;
+            start + next_boundary(&content, col)
+        }
+        Move::Up | Move::Down => {
+            let target = match mv {
+                Move::Up if line > 0 => line - 1,
+                Move::Down if line + 1 < text.len_lines() => line + 1,
+                _ => return at,
+            };
+            want.get_or_insert_with(|| column(&content, col));
+            to_line(text, target, *want)
+        }
+    }
+}
+
+/// A motion over lines as they're shown, which wrapping can split into
+/// several rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayMove {
+    /// down this many rows, or up when negative, like `gj` and `gk`
+    Rows(isize),
+    /// the start of the row, like `g0`
+    Start,
+    /// the end of the row, like `g$`
+    End,
+}
+
+/// Lines as a frontend lays them out on screen
+pub trait DisplayLines {
+    /// where `mv` takes the cursor from `at`, with the column to aim for
+    /// next, none when the rows involved aren't laid out
+    fn resolve(
+        &self,
+        text: &Rope,
+        at: usize,
+        want: Option<usize>,
+        mv: DisplayMove,
+    ) -> Option<(usize, Option<usize>)>;
+}
+
+/// Lines as they are in the text, for frontends that don't wrap
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Logical;
+
+impl DisplayLines for Logical {
+    fn resolve(
+        &self,
+        text: &Rope,
+        at: usize,
+        mut want: Option<usize>,
+        mv: DisplayMove,
+    ) -> Option<(usize, Option<usize>)> {
+        let (line, _) = line_col(text, at);
+        let start = text.line_to_byte(line);
+        let at = match mv {
+            DisplayMove::Rows(by) => {
+                let step = if by < 0 { Move::Up } else { Move::Down };
+                (0..by.unsigned_abs()).fold(at, |at, _| apply(text, at, &step, &mut want))
+            }
+            DisplayMove::Start => return Some((start, None)),
+            DisplayMove::End => return Some((start + line_text(text, line).len(), None)),
+        };
+        Some((at, want))
+    }
+}
+
+/// A piece of code found through its syntax tree, like `af` selects a
+/// function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextObject {
+    Function,
+    /// a struct, enum, impl or the like, or a section in markdown
+    Class,
+    /// a parameter or argument
+    Parameter,
+    Comment,
+}
+
+/// where the cursor lands on `line`, aiming for grapheme column `want`
+pub fn to_line(text: &Rope, line: usize, want: Option<usize>) -> usize {
+    let content = line_text(text, line);
+    let offset = content
+        .grapheme_indices(true)
+        .nth(want.unwrap_or(0))
+        .map_or(content.len(), |(i, _)| i);
+    text.line_to_byte(line) + offset
+}
+
+/// how many graphemes come before byte `col` of a line
+pub fn column(content: &str, col: usize) -> usize {
+    content[..col].graphemes(true).count()
+}
+
+/// keeps the cursor on a character, as in normal mode where it can't sit past
+/// the end of a line
+pub fn clamp(text: &Rope, at: usize) -> usize {
+    let (line, col) = line_col(text, at);
+    let content = line_text(text, line);
+    if col < content.len() {
+        return text.line_to_byte(line) + col;
+    }
+    text.line_to_byte(line) + prev_boundary(&content, content.len())
+}
+
+/// where the grapheme at `at` ends, staying on its line
+pub fn next_char(text: &Rope, at: usize) -> usize {
+    let (line, col) = line_col(text, at);
+    text.line_to_byte(line) + next_boundary(&line_text(text, line), col)
+}
+
+/// where the grapheme before `at` starts, staying on its line
+pub fn prev_char(text: &Rope, at: usize) -> usize {
+    let (line, col) = line_col(text, at);
+    text.line_to_byte(line) + prev_boundary(&line_text(text, line), col)
+}
+
+/// the word, run of blanks or punctuation `at` is in, as a double click
+/// selects it
+pub fn word(text: &Rope, at: usize) -> Range<usize> {
+    let (line, col) = line_col(text, at);
+    let start = text.line_to_byte(line);
+    let content = line_text(text, line);
+    content
+        .split_word_bound_indices()
+        .find(|(i, word)| col < i + word.len())
+        .map_or(start + col..start + col, |(i, word)| {
+            start + i..start + i + word.len()
+        })
+}
+
+/// the line `at` is on, without its line ending
+pub fn line(text: &Rope, at: usize) -> Range<usize> {
+    let (line, _) = line_col(text, at);
+    let start = text.line_to_byte(line);
+    start..start + line_text(text, line).len()
+}
+
+fn prev_boundary(content: &str, col: usize) -> usize {
+    content
+        .grapheme_indices(true)
+        .map(|(i, _)| i)
+        .take_while(|&i| i < col)
+        .last()
+        .unwrap_or(0)
+}
+
+fn next_boundary(content: &str, col: usize) -> usize {
+    content
+        .grapheme_indices(true)
+        .map(|(i, g)| i + g.len())
+        .find(|&end| end > col)
+        .unwrap_or(content.len())
+}
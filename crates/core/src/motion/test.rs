@@ -0,0 +1,65 @@
+use ropey::Rope;
+
+use crate::motion::{apply, clamp, DisplayLines, DisplayMove, Logical};
+use crate::Move;
+
+fn walk(text: &Rope, mut at: usize, moves: &[Move]) -> Vec<usize> {
+    let mut want = None;
+    moves
+        .iter()
+        .map(|mv| {
+            at = apply(text, at, mv, &mut want);
+            at
+        })
+        .collect()
+}
+
+#[test]
+fn graphemes() {
+    // a wide character, then an e with a combining acute accent
+    let text = Rope::from_str("a漢e\u{301}b\n");
+    assert_eq!(
+        walk(
+            &text,
+            0,
+            &[Move::Right, Move::Right, Move::Right, Move::Right]
+        ),
+        [1, 4, 7, 8]
+    );
+    assert_eq!(
+        walk(&text, 7, &[Move::Left, Move::Left, Move::Left]),
+        [4, 1, 0]
+    );
+}
+
+#[test]
+fn sticky_column() {
+    let text = Rope::from_str("long line\nab\nlong line\n");
+    assert_eq!(walk(&text, 6, &[Move::Down, Move::Down]), [12, 19]);
+    assert_eq!(walk(&text, 6, &[Move::Up, Move::Left]), [6, 5]);
+    // the last line is empty
+    assert_eq!(clamp(&text, 9), 8);
+    assert_eq!(clamp(&text, 23), 23);
+}
+
+#[test]
+fn logical_display_motions() {
+    let text = Rope::from_str("one\nlonger line\nx\n");
+    assert_eq!(
+        Logical.resolve(&text, 6, None, DisplayMove::Start),
+        Some((4, None))
+    );
+    assert_eq!(
+        Logical.resolve(&text, 6, None, DisplayMove::End),
+        Some((15, None))
+    );
+    // rows are whole lines, keeping the column like j and k
+    assert_eq!(
+        Logical.resolve(&text, 2, None, DisplayMove::Rows(1)),
+        Some((6, Some(2)))
+    );
+    assert_eq!(
+        Logical.resolve(&text, 6, Some(2), DisplayMove::Rows(-1)),
+        Some((2, Some(2)))
+    );
+}
@@ -0,0 +1,253 @@
+//! Options, as changed with `:set`
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum OptionError {
+    #[error("Unknown option: {0}")]
+    Unknown(String),
+    #[error("Invalid argument: {0}")]
+    Invalid(String),
+}
+
+/// the smallest text can be zoomed out to
+pub const MIN_FONT_SIZE: usize = 4;
+
+/// How a flag is changed, `name`, `noname` or `invname`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    On,
+    Off,
+    Toggle,
+}
+
+/// A type an option can hold
+pub trait Value: Sized {
+    /// parses `name=value`
+    fn parse(value: &str) -> Option<Self>;
+
+    /// handles `name`, `noname` and `invname`, only flags support it
+    fn flag(&self, _flag: Flag) -> Option<Self> {
+        None
+    }
+}
+
+impl Value for bool {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "1" | "true" | "on" => Some(true),
+            "0" | "false" | "off" => Some(false),
+            _ => None,
+        }
+    }
+
+    fn flag(&self, flag: Flag) -> Option<Self> {
+        Some(match flag {
+            Flag::On => true,
+            Flag::Off => false,
+            Flag::Toggle => !self,
+        })
+    }
+}
+
+impl Value for usize {
+    fn parse(value: &str) -> Option<Self> {
+        value.parse().ok()
+    }
+}
+
+impl Value for String {
+    fn parse(value: &str) -> Option<Self> {
+        Some(value.to_owned())
+    }
+}
+
+/// One argument of `:set`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op<'a> {
+    Assign(&'a str),
+    Flag(Flag),
+    Reset,
+}
+
+pub(crate) fn update<T: Value>(old: &T, op: Op, default: T) -> Option<T> {
+    match op {
+        Op::Assign(value) => T::parse(value),
+        Op::Flag(flag) => old.flag(flag),
+        Op::Reset => Some(default),
+    }
+}
+
+/// A set of options that `:set` can change
+pub trait Set {
+    /// applies `op` to the option called `name`, none if there is no such option
+    fn update(&mut self, name: &str, op: Op) -> Option<Result<(), ()>>;
+
+    /// applies a single argument of `:set`, like `undofile`, `noundofile` or
+    /// `undodir=/tmp/undo`
+    fn set(&mut self, arg: &str) -> Result<(), OptionError> {
+        let (name, op) = match arg.find(['=', ':']) {
+            Some(i) => (&arg[..i], Op::Assign(&arg[i + 1..])),
+            None => match arg.strip_suffix('!') {
+                Some(name) => (name, Op::Flag(Flag::Toggle)),
+                None => match arg.strip_suffix('&') {
+                    Some(name) => (name, Op::Reset),
+                    None => (arg, Op::Flag(Flag::On)),
+                },
+            },
+        };
+        let found = match (op, name.strip_prefix("no"), name.strip_prefix("inv")) {
+            (Op::Flag(Flag::On), Some(flag), _) => self
+                .update(name, op)
+                .or_else(|| self.update(flag, Op::Flag(Flag::Off))),
+            (Op::Flag(Flag::On), _, Some(flag)) => self
+                .update(name, op)
+                .or_else(|| self.update(flag, Op::Flag(Flag::Toggle))),
+            _ => self.update(name, op),
+        };
+        match found {
+            Some(Ok(())) => Ok(()),
+            Some(Err(())) => Err(OptionError::Invalid(arg.to_owned())),
+            None => Err(OptionError::Unknown(name.to_owned())),
+        }
+    }
+}
+
+macro_rules! gen_options {
+    (
+        $(#[doc = $sdoc:literal])*
+        pub struct $struct:ident {$(
+            $(#[doc = $doc:literal])*
+            $name:ident $(| $short:ident)?: $ty:ty = $default:expr,
+        )*}
+    ) => {
+        $(#[doc = $sdoc])*
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct $struct {
+            $($(#[doc = $doc])* pub $name: $ty,)*
+        }
+
+        impl Default for $struct {
+            fn default() -> Self {
+                Self { $($name: $default,)* }
+            }
+        }
+
+        impl $crate::options::Set for $struct {
+            fn update(
+                &mut self,
+                name: &str,
+                op: $crate::options::Op,
+            ) -> Option<Result<(), ()>> {
+                match name {
+                    $(stringify!($name) $(| stringify!($short))? => Some(
+                        $crate::options::update(&self.$name, op, $default)
+                            .map(|value| self.$name = value)
+                            .ok_or(()),
+                    ),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+pub(crate) use gen_options;
+
+gen_options! {
+    /// Global options
+    pub struct Options {
+        /// keep undo history in an undo file across sessions
+        undofile | udf: bool = true,
+        /// where undo files are kept, empty for the state directory
+        undodir | udir: String = String::new(),
+        /// files over this many MiB open in large file mode, streamed in
+        /// rather than read whole, with swap files, undo files and other
+        /// expensive features off. 0 turns it off
+        largefile | lf: usize = 64,
+        /// how many milliseconds the cursor stays on and then off for when
+        /// blinking, 0 keeps it on
+        cursorblink | cbl: usize = 530,
+        /// lines kept above and below the cursor when scrolling
+        scrolloff | so: usize = 0,
+        /// columns kept left and right of the cursor when lines don't wrap
+        sidescrolloff | siso: usize = 0,
+        /// show line numbers
+        number | nu: bool = false,
+        /// show line numbers relative to the cursor
+        relativenumber | rnu: bool = false,
+        /// the least number of columns line numbers take, with the space after
+        numberwidth | nuw: usize = 4,
+        /// what the status line shows, see [`status`](crate::status)
+        statusline | stl: String = " %{mode}  %f%m%=%y  %{fenc}  %{ff}  %l:%c  %p%% ".to_owned(),
+        /// wrap long lines onto the rows below instead of scrolling sideways
+        wrap: bool = true,
+        /// wrap between words rather than anywhere in them
+        linebreak | lbr: bool = false,
+        /// indent wrapped rows as far as the line they continue
+        breakindent | bri: bool = false,
+        /// shown at the start of wrapped rows
+        showbreak | sbr: String = String::new(),
+        /// when to show the tab bar, 0 never, 1 with more than one tab page and 2
+        /// always
+        showtabline | stal: usize = 1,
+        /// the modes the mouse works in, any of `n`ormal, `v`isual, `i`nsert
+        /// and `c`ommand, or `a` for all of them. empty turns it off
+        mouse: String = "a".to_owned(),
+        /// whether the background is `dark` or `light`, for picking the
+        /// theme's colors. empty follows the system
+        background | bg: String = String::new(),
+        /// the font family text is drawn in, empty for the system's monospace
+        /// font
+        guifont | gfn: String = "Fira Code".to_owned(),
+        /// the font family for wide characters like CJK and emoji, empty
+        /// leaves them to the system's fallback fonts
+        guifontwide | gfw: String = String::new(),
+        /// the size of text in pixels, before zooming in or out
+        fontsize | fsz: usize = 16,
+        /// the height of a row as a percentage of `fontsize`
+        lineheight | lh: usize = 130,
+        /// join characters like `->` and `!=` into ligatures, for fonts with
+        /// them
+        ligatures | lig: bool = true,
+        /// start language servers for the filetypes that have one
+        lsp: bool = true,
+        /// show each line's worst diagnostic after its end
+        virtualtext | vt: bool = true,
+        /// the most completion candidates shown at once
+        pumheight | ph: usize = 10,
+    }
+}
+
+impl Options {
+    /// whether a file of `len` bytes should open in large file mode
+    pub fn is_large(&self, len: u64) -> bool {
+        self.largefile != 0 && len > (self.largefile as u64) << 20
+    }
+
+    /// whether the mouse works in `mode`, see `mouse`
+    pub fn mouse_in(&self, mode: crate::Mode) -> bool {
+        use crate::Mode::*;
+        let flag = match mode {
+            Normal => 'n',
+            Visual => 'v',
+            Insert | Replace | Terminal => 'i',
+            Command => 'c',
+        };
+        self.mouse.contains(['a', flag])
+    }
+
+    /// the size of text in pixels, `fontsize` zoomed in by `zoom`
+    pub fn font_size(&self, zoom: isize) -> usize {
+        self.fontsize.saturating_add_signed(zoom).max(MIN_FONT_SIZE)
+    }
+
+    /// where undo files go, none if there is nowhere to put them
+    pub fn undo_dir(&self) -> Option<PathBuf> {
+        match self.undodir.as_str() {
+            "" => crate::disk::state_dir().map(|dir| dir.join("undo")),
+            dir => Some(crate::disk::expand_home(dir)),
+        }
+    }
+}
@@ -0,0 +1,565 @@
+//! Snippets, templates typed out from a short prefix
+//!
+//! Bodies use the syntax of language servers and VS Code: `$1` or `${1}` is
+//! a tabstop, `${1:default}` one with a placeholder, `${1|one,two|}` one with
+//! choices and `$0` where the cursor ends up. A tabstop used more than once
+//! is mirrored, what's typed in one goes in the others too. `$TM_FILENAME`
+//! or `${TM_FILENAME:default}` is a variable, see [`Variables`]. Anything
+//! that doesn't parse goes in as it's written.
+//!
+//! Snippets for a filetype are read from `snippets/{filetype}.snippets` in
+//! the config directory, and those for all filetypes from
+//! `snippets/all.snippets`, in the format of snipMate, a `snippet` line
+//! with the prefix and a description followed by the body, each line of it
+//! indented by a tab that's left out of it:
+//!
+//! ```text
+//! # a comment
+//! snippet fn a function
+//!     fn ${1:name}(${2}) {
+//!         $0
+//!     }
+//! ```
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::completion::{Context, Item, Source};
+use crate::disk;
+
+#[cfg(test)]
+mod test;
+
+/// A piece of a snippet's body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Part {
+    Text(String),
+    /// a tabstop and its placeholder, which can have tabstops of its own
+    Tabstop(usize, Vec<Part>),
+    /// a tabstop with choices, the first of which goes in
+    Choice(usize, Vec<String>),
+    /// a variable and what goes in when it isn't known
+    Variable(String, Vec<Part>),
+}
+
+/// A parsed snippet body, see the module docs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub parts: Vec<Part>,
+}
+
+/// A tabstop in an expanded snippet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stop {
+    pub index: usize,
+    /// the bytes of each place it's in, the first one's where the cursor
+    /// goes and the rest mirror it
+    pub ranges: Vec<Range<usize>>,
+    /// what can be picked for it, if anything
+    pub choices: Vec<String>,
+}
+
+/// The text of a snippet and where its tabstops are in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expansion {
+    pub text: String,
+    /// in the order they're gone through, `$0` last
+    pub stops: Vec<Stop>,
+}
+
+impl Snippet {
+    pub fn parse(body: &str) -> Self {
+        let mut parser = Parser {
+            chars: body.chars().collect(),
+            at: 0,
+        };
+        Self {
+            parts: parser.parts(false),
+        }
+    }
+
+    /// the text of the snippet with every line after the first indented by
+    /// `indent`, and `$0` at its end when it has none
+    pub fn expand(&self, indent: &str, variables: &Variables) -> Expansion {
+        let mut placeholders = HashMap::new();
+        placeholders_of(&self.parts, &mut placeholders);
+        let mut render = Render {
+            text: String::new(),
+            stops: BTreeMap::new(),
+            indent,
+            variables,
+            placeholders: &placeholders,
+        };
+        render.parts(&self.parts, true);
+        let Render {
+            text, mut stops, ..
+        } = render;
+        let end = stops.remove(&0).unwrap_or(Stop {
+            index: 0,
+            ranges: std::iter::once(text.len()..text.len()).collect(),
+            choices: Vec::new(),
+        });
+        let stops = stops.into_values().chain([end]).collect();
+        Expansion { text, stops }
+    }
+}
+
+/// the first placeholder given for each tabstop, which all of its mirrors
+/// show
+fn placeholders_of<'a>(parts: &'a [Part], placeholders: &mut HashMap<usize, &'a Part>) {
+    for part in parts {
+        match part {
+            Part::Tabstop(index, placeholder) if !placeholder.is_empty() => {
+                placeholders.entry(*index).or_insert(part);
+                placeholders_of(placeholder, placeholders);
+            }
+            Part::Choice(index, _) => {
+                placeholders.entry(*index).or_insert(part);
+            }
+            Part::Variable(_, default) => placeholders_of(default, placeholders),
+            _ => {}
+        }
+    }
+}
+
+struct Render<'a> {
+    text: String,
+    stops: BTreeMap<usize, Stop>,
+    indent: &'a str,
+    variables: &'a Variables<'a>,
+    placeholders: &'a HashMap<usize, &'a Part>,
+}
+
+impl Render<'_> {
+    /// adds `parts` to the text, noting where their tabstops are if
+    /// `record`, which mirrors don't
+    fn parts(&mut self, parts: &[Part], record: bool) {
+        for part in parts {
+            let start = self.text.len();
+            let index = match part {
+                Part::Text(text) => {
+                    self.push(text);
+                    continue;
+                }
+                Part::Variable(name, default) => {
+                    match self.variables.get(name) {
+                        Some(value) => self.push(&value),
+                        None if default.is_empty() => self.push(name),
+                        None => self.parts(default, record),
+                    }
+                    continue;
+                }
+                Part::Tabstop(index, placeholder) if !placeholder.is_empty() => {
+                    // only the first placeholder is the tabstop's, others
+                    // are mirrors like the bare ones
+                    let own = std::ptr::eq(self.placeholders[index], part);
+                    self.parts(placeholder, record && own);
+                    index
+                }
+                Part::Tabstop(index, _) | Part::Choice(index, _) => {
+                    match self.placeholders.get(index) {
+                        Some(Part::Tabstop(_, placeholder)) => self.parts(placeholder, false),
+                        Some(Part::Choice(_, choices)) => self.push(&choices[0]),
+                        _ => {}
+                    }
+                    index
+                }
+            };
+            if !record {
+                continue;
+            }
+            let stop = self.stops.entry(*index).or_insert_with(|| Stop {
+                index: *index,
+                ranges: Vec::new(),
+                choices: Vec::new(),
+            });
+            stop.ranges.push(start..self.text.len());
+            if let Part::Choice(_, choices) = part {
+                stop.choices.clone_from(choices);
+            }
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.text.push('\n');
+                self.text.push_str(self.indent);
+            }
+            self.text.push_str(line);
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl Parser {
+    /// up to the end, or a `}` closing a placeholder when `nested`
+    fn parts(&mut self, nested: bool) -> Vec<Part> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        while let Some(&c) = self.chars.get(self.at) {
+            match c {
+                '}' if nested => break,
+                '\\' if matches!(self.chars.get(self.at + 1), Some('$' | '}' | '\\')) => {
+                    text.push(self.chars[self.at + 1]);
+                    self.at += 2;
+                }
+                '$' => {
+                    let from = self.at;
+                    self.at += 1;
+                    match self.dollar() {
+                        Some(part) => {
+                            if !text.is_empty() {
+                                parts.push(Part::Text(std::mem::take(&mut text)));
+                            }
+                            parts.push(part);
+                        }
+                        None => {
+                            self.at = from + 1;
+                            text.push('$');
+                        }
+                    }
+                }
+                c => {
+                    text.push(c);
+                    self.at += 1;
+                }
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        parts
+    }
+
+    /// what follows a `$`, none if it isn't a tabstop or variable
+    fn dollar(&mut self) -> Option<Part> {
+        if let Some(index) = self.number() {
+            return Some(Part::Tabstop(index, Vec::new()));
+        }
+        if let Some(name) = self.name() {
+            return Some(Part::Variable(name, Vec::new()));
+        }
+        self.eat('{')?;
+        if let Some(index) = self.number() {
+            if self.eat('}').is_some() {
+                return Some(Part::Tabstop(index, Vec::new()));
+            }
+            if self.eat(':').is_some() {
+                let placeholder = self.parts(true);
+                self.eat('}')?;
+                return Some(Part::Tabstop(index, placeholder));
+            }
+            self.eat('|')?;
+            return Some(Part::Choice(index, self.choices()?));
+        }
+        let name = self.name()?;
+        if self.eat('}').is_some() {
+            return Some(Part::Variable(name, Vec::new()));
+        }
+        if self.eat(':').is_some() {
+            let default = self.parts(true);
+            self.eat('}')?;
+            return Some(Part::Variable(name, default));
+        }
+        // transforms aren't supported, the variable goes in unchanged
+        self.eat('/')?;
+        loop {
+            match self.chars.get(self.at)? {
+                '\\' => self.at += 2,
+                '}' => break,
+                _ => self.at += 1,
+            }
+        }
+        self.at += 1;
+        Some(Part::Variable(name, Vec::new()))
+    }
+
+    /// the choices after `${1|`, through the closing `|}`
+    fn choices(&mut self) -> Option<Vec<String>> {
+        let mut choices = vec![String::new()];
+        loop {
+            let c = *self.chars.get(self.at)?;
+            self.at += 1;
+            match c {
+                '\\' => {
+                    let escaped = *self.chars.get(self.at)?;
+                    choices.last_mut()?.push(escaped);
+                    self.at += 1;
+                }
+                ',' => choices.push(String::new()),
+                '|' => {
+                    self.eat('}')?;
+                    return Some(choices);
+                }
+                c => choices.last_mut()?.push(c),
+            }
+        }
+    }
+
+    fn eat(&mut self, c: char) -> Option<()> {
+        (self.chars.get(self.at) == Some(&c)).then(|| self.at += 1)
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let digits = self.take_while(|c, _| c.is_ascii_digit());
+        digits.parse().ok()
+    }
+
+    fn name(&mut self) -> Option<String> {
+        let name = self.take_while(|c, first| {
+            c == '_' || c.is_ascii_alphabetic() || (!first && c.is_ascii_digit())
+        });
+        (!name.is_empty()).then_some(name)
+    }
+
+    fn take_while(&mut self, f: impl Fn(char, bool) -> bool) -> String {
+        let from = self.at;
+        while self
+            .chars
+            .get(self.at)
+            .is_some_and(|&c| f(c, self.at == from))
+        {
+            self.at += 1;
+        }
+        self.chars[from..self.at].iter().collect()
+    }
+}
+
+/// What the variables in a snippet stand for, where it's expanded
+///
+/// Those known are `TM_FILENAME`, `TM_FILENAME_BASE`, `TM_DIRECTORY`,
+/// `TM_FILEPATH`, `TM_LINE_INDEX`, `TM_LINE_NUMBER`, `TM_CURRENT_LINE` and
+/// `TM_SELECTED_TEXT`, which is always empty. Others go in as their default,
+/// or their name without one.
+#[derive(Debug, Clone, Copy)]
+pub struct Variables<'a> {
+    pub path: &'a Path,
+    /// the line expanded on, counting from 0, and its text
+    pub line: usize,
+    pub current_line: &'a str,
+}
+
+impl Variables<'_> {
+    pub fn get(&self, name: &str) -> Option<String> {
+        let file_name = || self.path.file_name().map(|name| name.to_string_lossy());
+        Some(match name {
+            "TM_FILENAME" => file_name()?.into_owned(),
+            "TM_FILENAME_BASE" => {
+                let name = file_name()?;
+                match name.split_once('.') {
+                    Some((base, _)) if !base.is_empty() => base.to_owned(),
+                    _ => name.into_owned(),
+                }
+            }
+            "TM_DIRECTORY" => self.path.parent()?.to_string_lossy().into_owned(),
+            "TM_FILEPATH" => self.path.to_string_lossy().into_owned(),
+            "TM_LINE_INDEX" => self.line.to_string(),
+            "TM_LINE_NUMBER" => (self.line + 1).to_string(),
+            "TM_CURRENT_LINE" => self.current_line.to_owned(),
+            "TM_SELECTED_TEXT" => String::new(),
+            _ => return None,
+        })
+    }
+}
+
+/// A snippet as defined in a snippets file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Definition {
+    /// what's typed to expand it
+    pub prefix: String,
+    pub description: String,
+    pub body: String,
+}
+
+/// reads a snippets file, see the module docs
+pub fn parse(text: &str) -> Vec<Definition> {
+    let mut found: Vec<(Definition, Vec<&str>)> = Vec::new();
+    // body lines only count right after a `snippet` line or another body line
+    let mut open = false;
+    for line in text.lines() {
+        let body = line.strip_prefix('\t').filter(|_| open);
+        if let (Some(body), Some((_, lines))) = (body, found.last_mut()) {
+            lines.push(body);
+            continue;
+        }
+        open = false;
+        let Some(header) = line.strip_prefix("snippet ") else {
+            continue;
+        };
+        let header = header.trim();
+        let (prefix, description) = header.split_once(' ').unwrap_or((header, ""));
+        let definition = Definition {
+            prefix: prefix.to_owned(),
+            description: description.trim().to_owned(),
+            body: String::new(),
+        };
+        found.push((definition, Vec::new()));
+        open = true;
+    }
+    let definitions = found.into_iter().map(|(definition, lines)| Definition {
+        body: lines.join("\n"),
+        ..definition
+    });
+    definitions.collect()
+}
+
+/// The snippets of each filetype, read from the snippets directories the
+/// first time they're needed
+#[derive(Debug, Default)]
+pub struct Library {
+    dirs: Vec<PathBuf>,
+    loaded: RefCell<HashMap<String, Rc<Vec<Definition>>>>,
+}
+
+impl Library {
+    /// snippets from `dirs`, the ones in later directories coming first
+    pub fn new(dirs: impl IntoIterator<Item = PathBuf>) -> Self {
+        Self {
+            dirs: dirs.into_iter().collect(),
+            loaded: Default::default(),
+        }
+    }
+
+    /// snippets from the `snippets` directory in the config directory
+    pub fn from_config() -> Self {
+        Self::new(disk::config_dir().map(|dir| dir.join("snippets")))
+    }
+
+    /// the snippets of `filetype` and those for all filetypes
+    pub fn definitions(&self, filetype: &str) -> Rc<Vec<Definition>> {
+        let mut loaded = self.loaded.borrow_mut();
+        let definitions = loaded.entry(filetype.to_owned()).or_insert_with(|| {
+            let mut files = vec!["all"];
+            files.extend(Some(filetype).filter(|filetype| !filetype.is_empty()));
+            let definitions = self.dirs.iter().rev().flat_map(|dir| {
+                files.iter().rev().flat_map(move |file| {
+                    let path = dir.join(format!("{file}.snippets"));
+                    parse(&std::fs::read_to_string(path).unwrap_or_default())
+                })
+            });
+            Rc::new(definitions.collect())
+        });
+        definitions.clone()
+    }
+
+    /// the snippet `prefix` expands
+    pub fn find(&self, filetype: &str, prefix: &str) -> Option<Definition> {
+        let definitions = self.definitions(filetype);
+        definitions.iter().find(|d| d.prefix == prefix).cloned()
+    }
+}
+
+impl Source for Library {
+    fn name(&self) -> &str {
+        "snippets"
+    }
+
+    fn complete(&self, cx: &Context) -> Vec<Item> {
+        let definitions = self.definitions(cx.filetype);
+        let items = definitions.iter().map(|definition| {
+            let menu = match definition.description.as_str() {
+                "" => "snippet",
+                description => description,
+            };
+            Item::new(&definition.prefix, self.name())
+                .with_menu(menu)
+                .with_snippet(&definition.body)
+        });
+        items.collect()
+    }
+}
+
+/// A snippet being filled in, its tabstops moving along with the edits made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Active {
+    stops: Vec<Stop>,
+    /// which of the stops the cursor is in, none before the first jump
+    current: Option<usize>,
+    /// whether the cursor is on a placeholder no one has typed over yet,
+    /// which typing replaces
+    pub fresh: bool,
+}
+
+impl Active {
+    /// the stops of an expansion, which went in at byte `at`
+    pub fn new(stops: Vec<Stop>, at: usize) -> Self {
+        let stops = stops.into_iter().map(|stop| Stop {
+            ranges: stop
+                .ranges
+                .into_iter()
+                .map(|range| range.start + at..range.end + at)
+                .collect(),
+            ..stop
+        });
+        Self {
+            stops: stops.collect(),
+            current: None,
+            fresh: false,
+        }
+    }
+
+    pub fn current(&self) -> Option<&Stop> {
+        self.stops.get(self.current?)
+    }
+
+    /// goes to the stop `by` along, staying on the first going back, none
+    /// going on past the last
+    pub fn jump(&mut self, by: isize) -> Option<&Stop> {
+        let to = self.current.map_or(by - 1, |current| current as isize + by);
+        let to = to.max(0) as usize;
+        let stop = self.stops.get(to)?;
+        self.current = Some(to);
+        self.fresh = stop.ranges.first().is_some_and(|range| !range.is_empty());
+        Some(stop)
+    }
+
+    /// the placeholder typing at byte `at` replaces, which it only does once
+    pub fn take_placeholder(&mut self, at: usize) -> Option<Range<usize>> {
+        let range = self.current()?.ranges.first()?.clone();
+        std::mem::take(&mut self.fresh)
+            .then_some(range)
+            .filter(|range| range.end == at)
+    }
+
+    /// the placeholder the cursor is on, while typing would replace it
+    pub fn placeholder(&self) -> Option<Range<usize>> {
+        let range = self.current()?.ranges.first()?;
+        self.fresh.then(|| range.clone())
+    }
+
+    /// notes that bytes `start..old_end` were replaced with `start..new_end`.
+    /// what's typed in or at the edges of the current stop's ranges grows
+    /// them, elsewhere text added where a range starts goes before it and
+    /// where one ends after it
+    pub fn edit(&mut self, start: usize, old_end: usize, new_end: usize) {
+        let moved = |at: usize| at + new_end - old_end;
+        for (i, stop) in self.stops.iter_mut().enumerate() {
+            let current = self.current == Some(i);
+            for range in &mut stop.ranges {
+                if current && range.start <= start && old_end <= range.end {
+                    range.end = moved(range.end);
+                    continue;
+                }
+                let from = match range.start {
+                    at if at < start => at,
+                    at if at >= old_end => moved(at),
+                    _ => new_end,
+                };
+                let to = match range.end {
+                    at if at <= start => at,
+                    at if at >= old_end => moved(at),
+                    _ => start,
+                };
+                *range = from..to.max(from);
+            }
+        }
+    }
+}
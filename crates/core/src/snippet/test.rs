@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use crate::snippet::{parse, Active, Part, Snippet, Stop, Variables};
+
+/// the ranges of a stop as pairs, which compare more readably
+fn spans(stop: &Stop) -> Vec<(usize, usize)> {
+    stop.ranges
+        .iter()
+        .map(|range| (range.start, range.end))
+        .collect()
+}
+
+fn variables() -> Variables<'static> {
+    Variables {
+        path: Path::new("/src/main.rs"),
+        line: 4,
+        current_line: "fn",
+    }
+}
+
+#[test]
+fn parses_the_syntax() {
+    let snippet = Snippet::parse(r"a $1 ${2:b ${3}} ${4|x,y\,z|} $NAME ${N:d} \$5 $ ${6");
+    use Part::*;
+    let text = |text: &str| Text(text.into());
+    assert_eq!(
+        snippet.parts,
+        [
+            text("a "),
+            Tabstop(1, vec![]),
+            text(" "),
+            Tabstop(2, vec![text("b "), Tabstop(3, vec![])]),
+            text(" "),
+            Choice(4, vec!["x".into(), "y,z".into()]),
+            text(" "),
+            Variable("NAME".into(), vec![]),
+            text(" "),
+            Variable("N".into(), vec![text("d")]),
+            // what doesn't parse goes in as written
+            text(" $5 $ ${6"),
+        ]
+    );
+}
+
+#[test]
+fn expands_stops_mirrors_and_variables() {
+    let snippet = Snippet::parse("fn ${1:name}() {\n\t$1 ${2|a,b|} $TM_FILENAME_BASE ${X:none}\n}");
+    let expansion = snippet.expand("    ", &variables());
+    assert_eq!(expansion.text, "fn name() {\n    \tname a main none\n    }");
+    let stops: Vec<_> = expansion
+        .stops
+        .iter()
+        .map(|stop| (stop.index, spans(stop)))
+        .collect();
+    // $0 goes at the end when there's none
+    assert_eq!(
+        stops,
+        [
+            (1, vec![(3, 7), (17, 21)]),
+            (2, vec![(22, 23)]),
+            (0, vec![(39, 39)])
+        ]
+    );
+    assert_eq!(expansion.stops[1].choices, ["a", "b"]);
+
+    // a mirror before the placeholder shows it too, without its tabstops
+    let expansion = Snippet::parse("$1 ${1:x${2:y}} $0.").expand("", &variables());
+    assert_eq!(expansion.text, "xy xy .");
+    let ranges: Vec<_> = expansion.stops.iter().map(spans).collect();
+    assert_eq!(ranges, [vec![(0, 2), (3, 5)], vec![(4, 5)], vec![(6, 6)]]);
+}
+
+#[test]
+fn reads_snippets_files() {
+    let text = "# comment\nsnippet fn a function\n\tfn $1() {\n\t\t$0\n\t}\n\nsnippet if\n\tif $1\nnot body\n\tnot body either\n";
+    let definitions = parse(text);
+    assert_eq!(definitions.len(), 2);
+    assert_eq!(definitions[0].prefix, "fn");
+    assert_eq!(definitions[0].description, "a function");
+    assert_eq!(definitions[0].body, "fn $1() {\n\t$0\n}");
+    assert_eq!(definitions[1].body, "if $1");
+}
+
+#[test]
+fn stops_follow_edits() {
+    let expansion = Snippet::parse("${1:ab} $1 $0").expand("", &variables());
+    let mut active = Active::new(expansion.stops, 10);
+    let ranges = |active: &Active| spans(active.current().unwrap());
+    assert_eq!(active.jump(1).unwrap().index, 1);
+    assert_eq!(active.placeholder(), Some(10..12));
+    // typing replaces the placeholder, once
+    assert_eq!(active.take_placeholder(12), Some(10..12));
+    assert_eq!(active.take_placeholder(12), None);
+    active.edit(10, 12, 10);
+    active.edit(10, 10, 13);
+    assert_eq!(ranges(&active), [(10, 13), (14, 16)]);
+    // text added before the snippet moves it along
+    active.edit(0, 0, 2);
+    assert_eq!(ranges(&active), [(12, 15), (16, 18)]);
+    assert_eq!(spans(active.jump(1).unwrap()), [(19, 19)]);
+    assert!(active.jump(1).is_none());
+    assert_eq!(active.jump(-5).unwrap().index, 1);
+}
@@ -0,0 +1,92 @@
+//! The status line, filled in from a format string like vim's `statusline`
+//!
+//! | item      | shows                                   |
+//! |-----------|-----------------------------------------|
+//! | `%f`      | the path, relative where it can be      |
+//! | `%F`      | the full path                           |
+//! | `%t`      | the file name                           |
+//! | `%m`      | `[+]` if there are unsaved changes      |
+//! | `%l` `%c` | the cursor's line and column            |
+//! | `%L`      | the number of lines                     |
+//! | `%p`      | how far through the file the cursor is  |
+//! | `%y`      | the filetype, like `[rust]`             |
+//! | `%{name}` | `mode`, `ft`, `fenc` or `ff`            |
+//! | `%=`      | the rest goes on the right              |
+//! | `%%`      | a `%`                                   |
+
+use std::{fmt::Write, path::PathBuf};
+
+use crate::Mode;
+
+#[cfg(test)]
+mod test;
+
+/// Everything the status line can show about a buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Status {
+    pub mode: Mode,
+    /// the path relative to the working directory, when it's under it
+    pub path: PathBuf,
+    pub full_path: PathBuf,
+    pub modified: bool,
+    /// the cursor's line and column, counting from 0
+    pub line: usize,
+    pub col: usize,
+    pub lines: usize,
+    pub filetype: String,
+    pub encoding: &'static str,
+    pub fileformat: &'static str,
+}
+
+/// fills in `fmt`, returning the parts before and after `%=`
+pub fn format(fmt: &str, status: &Status) -> (String, String) {
+    let (mut left, mut right) = (String::new(), String::new());
+    let mut out = &mut left;
+    let mut chars = fmt.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let _ = match chars.next() {
+            Some('f') => write!(out, "{}", status.path.display()),
+            Some('F') => write!(out, "{}", status.full_path.display()),
+            Some('t') => write!(
+                out,
+                "{}",
+                status
+                    .full_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+            ),
+            Some('m') if status.modified => write!(out, "[+]"),
+            Some('m') => Ok(()),
+            Some('l') => write!(out, "{}", status.line + 1),
+            Some('c') => write!(out, "{}", status.col + 1),
+            Some('L') => write!(out, "{}", status.lines),
+            Some('p') => write!(out, "{}", (status.line + 1) * 100 / status.lines.max(1)),
+            Some('y') if status.filetype.is_empty() => Ok(()),
+            Some('y') => write!(out, "[{}]", status.filetype),
+            Some('=') => {
+                out = &mut right;
+                Ok(())
+            }
+            Some('%') => write!(out, "%"),
+            Some('{') => {
+                let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                match name.as_str() {
+                    "mode" => write!(out, "{}", status.mode.name()),
+                    "ft" => write!(out, "{}", status.filetype),
+                    "fenc" => write!(out, "{}", status.encoding),
+                    "ff" => write!(out, "{}", status.fileformat),
+                    _ => write!(out, "%{{{name}}}"),
+                }
+            }
+            // anything else is kept as it was typed
+            Some(other) => write!(out, "%{other}"),
+            None => write!(out, "%"),
+        };
+    }
+    (left, right)
+}
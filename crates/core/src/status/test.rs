@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+use crate::status::{format, Status};
+use crate::Mode;
+
+fn status() -> Status {
+    Status {
+        mode: Mode::Insert,
+        path: PathBuf::from("src/main.rs"),
+        full_path: PathBuf::from("/home/evie/src/main.rs"),
+        modified: true,
+        line: 9,
+        col: 4,
+        lines: 40,
+        filetype: "rust".to_owned(),
+        encoding: "utf-8",
+        fileformat: "unix",
+    }
+}
+
+#[test]
+fn items() {
+    let (left, right) = format("%{mode} %f%m%=%y %{fenc} %l:%c %p%%", &status());
+    assert_eq!(left, "INSERT src/main.rs[+]");
+    assert_eq!(right, "[rust] utf-8 10:5 25%");
+
+    let (left, right) = format("%t %{ff} %L %q %{nope}", &status());
+    assert_eq!(left, "main.rs unix 40 %q %{nope}");
+    assert_eq!(right, "");
+}
@@ -0,0 +1,272 @@
+//! Swap files, a journal of unsaved changes used to recover from crashes
+//!
+//! A swap file starts with a header naming the file, the hash of the
+//! contents the journal applies to and the process writing it, followed by
+//! one record per change:
+//!
+//! ```text
+//! evie swap 2
+//! /path/to/file
+//! 0123456789abcdef
+//! 4242
+//! i 12 5
+//! hello
+//! d 3 7
+//! ```
+
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    disk::{flat_name, handle_nf, state_dir},
+    engine::{Change, Delete, Insert},
+};
+
+#[cfg(test)]
+mod test;
+
+const MAGIC: &str = "evie swap 2";
+/// the extensions tried in order, like vim
+const EXTENSIONS: [&str; 3] = ["swp", "swo", "swn"];
+
+/// The journal of a single buffer
+#[derive(Debug)]
+pub struct Swap {
+    path: PathBuf,
+    original: PathBuf,
+    /// the hash of the contents the journal starts from
+    base: u64,
+    pending: Vec<Change>,
+    file: Option<File>,
+}
+
+/// The contents of a swap file left behind by a crash
+#[derive(Debug)]
+pub struct Recovery {
+    pub swap: PathBuf,
+    pub original: PathBuf,
+    pub base: u64,
+    /// the process that wrote it
+    pub pid: u32,
+    pub changes: Vec<Change>,
+}
+
+impl Swap {
+    pub fn new(path: PathBuf, original: PathBuf, base: u64) -> Self {
+        Self {
+            path,
+            original,
+            base,
+            pending: Vec::new(),
+            file: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&mut self, change: Change) {
+        self.pending.push(change);
+    }
+
+    /// appends pending changes, creating the swap file if needed
+    pub fn flush(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                if let Some(parent) = self.path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let mut file = File::create(&self.path)?;
+                writeln!(
+                    file,
+                    "{MAGIC}\n{}\n{:016x}\n{}",
+                    self.original.display(),
+                    self.base,
+                    std::process::id()
+                )?;
+                self.file.insert(file)
+            }
+        };
+        let mut out = Vec::new();
+        for change in self.pending.drain(..) {
+            encode(&mut out, &change);
+        }
+        file.write_all(&out)?;
+        file.sync_data()
+    }
+
+    /// starts over from new contents, after a save or reload
+    pub fn reset(&mut self, base: u64) -> io::Result<()> {
+        self.base = base;
+        self.pending.clear();
+        if self.file.take().is_some() {
+            handle_nf(fs::remove_file(&self.path))?;
+        }
+        Ok(())
+    }
+
+    /// deletes the swap file, on a clean exit
+    pub fn remove(mut self) -> io::Result<()> {
+        self.reset(0)
+    }
+}
+
+fn encode(out: &mut Vec<u8>, change: &Change) {
+    match change {
+        Change::Insert(Insert { index, text }) => {
+            let _ = writeln!(out, "i {index} {}", text.len());
+            out.extend_from_slice(text.as_bytes());
+            out.push(b'\n');
+        }
+        Change::Delete(Delete { range }) => {
+            let _ = writeln!(out, "d {} {}", range.start, range.end);
+        }
+    }
+}
+
+/// the swap files that could belong to `file`, in order of preference
+///
+/// they're named after its full path, or the `src/main.rs` of two projects
+/// would share them.
+pub fn candidates<'a>(dir: &'a Path, file: &Path) -> impl Iterator<Item = PathBuf> + 'a {
+    let name = flat_name(&std::path::absolute(file).unwrap_or_else(|_| file.to_owned()));
+    EXTENSIONS
+        .iter()
+        .map(move |ext| dir.join(format!("{name}.{ext}")))
+}
+
+/// finds a swap file left behind for `file`, passing over those of another
+/// evie that's still running and editing it
+pub fn find(dir: &Path, file: &Path) -> Option<PathBuf> {
+    candidates(dir, file).find(|p| p.exists() && !owner(p).is_some_and(running))
+}
+
+/// the process that wrote a swap file
+fn owner(swap: &Path) -> Option<u32> {
+    let mut reader = BufReader::new(File::open(swap).ok()?);
+    Some(header(&mut reader).ok()?.2)
+}
+
+/// whether `pid` is another process that's still alive
+#[cfg(unix)]
+fn running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    if pid == std::process::id() as libc::pid_t {
+        return false;
+    }
+    // signal 0 only checks the process is there, one of another user's can't
+    // be signalled but is there all the same
+    // SAFETY: kill with signal 0 sends nothing
+    let found = unsafe { libc::kill(pid, 0) } == 0;
+    found || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// there's no cheap way to ask, so every swap file counts as left behind
+#[cfg(not(unix))]
+fn running(_pid: u32) -> bool {
+    false
+}
+
+/// picks a name for a new swap file, one that doesn't exist yet
+pub fn fresh(dir: &Path, file: &Path) -> Option<PathBuf> {
+    candidates(dir, file).find(|p| !p.exists())
+}
+
+/// reads a swap file
+///
+/// a record cut short by the crash is dropped along with everything after it.
+pub fn read(swap: &Path) -> io::Result<Recovery> {
+    let file = File::open(swap)?;
+    let size = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    let (original, base, pid) = header(&mut reader)?;
+
+    let mut changes = Vec::new();
+    while let Some(record) = next_line(&mut reader)? {
+        let mut parts = record.split(' ');
+        let (kind, a, b) = (parts.next(), parts.next(), parts.next());
+        let (Some(a), Some(b)) = (
+            a.and_then(|a| a.parse::<usize>().ok()),
+            b.and_then(|b| b.parse::<usize>().ok()),
+        ) else {
+            break;
+        };
+        match kind {
+            Some("i") => {
+                // the text and its newline, which have to fit in what's left
+                let len = u64::try_from(b).ok().and_then(|b| b.checked_add(1));
+                let left = size.checked_sub(reader.stream_position()?);
+                let (Some(len), Some(left)) = (len, left) else {
+                    break;
+                };
+                let mut text = Vec::new();
+                if len > left
+                    || (&mut reader).take(len).read_to_end(&mut text).is_err()
+                    || text.len() as u64 != len
+                    || text.pop() != Some(b'\n')
+                {
+                    break;
+                }
+                let Ok(text) = String::from_utf8(text) else {
+                    break;
+                };
+                changes.push(Change::Insert(Insert {
+                    index: a,
+                    text: text.into(),
+                }));
+            }
+            Some("d") => changes.push(Change::Delete(Delete { range: a..b })),
+            _ => break,
+        }
+    }
+    Ok(Recovery {
+        swap: swap.to_owned(),
+        original,
+        base,
+        pid,
+        changes,
+    })
+}
+
+/// reads the file name, base hash and process id a swap file starts with
+fn header(reader: &mut impl BufRead) -> io::Result<(PathBuf, u64, u32)> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_owned());
+    if next_line(reader)?.as_deref() != Some(MAGIC) {
+        return Err(invalid("not an evie swap file"));
+    }
+    let original = next_line(reader)?
+        .map(PathBuf::from)
+        .ok_or_else(|| invalid("missing file name"))?;
+    let base = next_line(reader)?
+        .and_then(|h| u64::from_str_radix(&h, 16).ok())
+        .ok_or_else(|| invalid("missing hash"))?;
+    let pid = next_line(reader)?
+        .and_then(|pid| pid.parse().ok())
+        .ok_or_else(|| invalid("missing process id"))?;
+    Ok((original, base, pid))
+}
+
+/// the next whole line, none at the end or for one cut short
+fn next_line(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 || !line.ends_with('\n') {
+        return Ok(None);
+    }
+    line.pop();
+    Ok(Some(line))
+}
+
+/// where swap files go by default
+pub fn default_dir() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("swap"))
+}
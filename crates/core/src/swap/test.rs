@@ -0,0 +1,171 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tempfile::tempdir;
+
+use crate::{engine::Engine, swap, BufferAction};
+
+fn append(engine: &Engine, path: &PathBuf, text: &str) {
+    engine
+        .get_buffer(path, false)
+        .unwrap()
+        .borrow_mut()
+        .apply(BufferAction::Append(text.into()))
+        .unwrap();
+}
+
+#[test]
+fn recover_after_crash() {
+    let tmp = tempdir().unwrap();
+    let (path, dir) = (tmp.path().join("crash.txt"), tmp.path().join("swap"));
+    fs::write(&path, "world\n").unwrap();
+
+    let engine = Engine::default().with_swap_dir(&dir);
+    engine.add_buffer(&path, false).unwrap();
+    append(&engine, &path, "hello ");
+    engine.sync_swaps().unwrap();
+    append(&engine, &path, "there ");
+    engine.sync_swaps().unwrap();
+    // crash, the engine goes away without closing
+    drop(engine);
+
+    let engine = Engine::default().with_swap_dir(&dir);
+    let buf = engine.add_buffer(&path, false).unwrap();
+    let found = buf.borrow().recovery.clone().unwrap();
+    assert_eq!(engine.recover(&path, false).unwrap(), 2);
+    assert_eq!(buf.borrow().text, "hello there world\n");
+    assert!(buf.borrow().modified);
+    assert!(!found.exists());
+
+    engine.close().unwrap();
+    assert_eq!(swap::find(&dir, &path), None);
+}
+
+#[test]
+fn save_resets_journal() {
+    let tmp = tempdir().unwrap();
+    let (path, dir) = (tmp.path().join("save.txt"), tmp.path().join("swap"));
+    let engine = Engine::default().with_swap_dir(&dir);
+    let buf = engine.add_buffer(&path, false).unwrap();
+    append(&engine, &path, "one");
+    engine.sync_swaps().unwrap();
+    assert!(swap::find(&dir, &path).is_some());
+    buf.borrow_mut().write().unwrap();
+    assert_eq!(swap::find(&dir, &path), None);
+}
+
+#[test]
+fn mismatched_contents() {
+    let tmp = tempdir().unwrap();
+    let (path, dir) = (tmp.path().join("mismatch.txt"), tmp.path().join("swap"));
+    fs::write(&path, "one\n").unwrap();
+    let engine = Engine::default().with_swap_dir(&dir);
+    engine.add_buffer(&path, false).unwrap();
+    append(&engine, &path, "zero\n");
+    engine.sync_swaps().unwrap();
+    drop(engine);
+
+    fs::write(&path, "two\n").unwrap();
+    let engine = Engine::default().with_swap_dir(&dir);
+    engine.add_buffer(&path, false).unwrap();
+    assert!(engine.recover(&path, false).is_err());
+}
+
+#[test]
+fn recover_all_or_nothing() {
+    let tmp = tempdir().unwrap();
+    let (path, dir) = (tmp.path().join("partial.txt"), tmp.path().join("swap"));
+    fs::create_dir_all(&dir).unwrap();
+    let swap = dir.join(format!("{}.swp", crate::disk::flat_name(&path)));
+    // the second change is past the end of the text
+    let journal = format!(
+        "evie swap 2\n{}\n{:016x}\n{}\ni 0 3\none\nd 10 20\n",
+        path.display(),
+        0,
+        std::process::id()
+    );
+    fs::write(&swap, journal).unwrap();
+    let engine = Engine::default().with_swap_dir(&dir);
+    let buf = engine.add_buffer(&path, false).unwrap();
+    assert!(engine.recover(&path, false).is_err());
+    assert_eq!(buf.borrow().text, "");
+    assert!(!buf.borrow().modified);
+    assert_eq!(buf.borrow().recovery, Some(swap.clone()));
+    assert!(swap.exists());
+}
+
+#[test]
+fn truncated_record() {
+    let tmp = tempdir().unwrap();
+    let (path, dir) = (tmp.path().join("truncated.txt"), tmp.path().join("swap"));
+    let swap = dir.join("truncated.swp");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        &swap,
+        format!(
+            "evie swap 2\n{}\n{:016x}\n1\ni 0 3\none\ni 3 10\ntw",
+            path.display(),
+            0
+        ),
+    )
+    .unwrap();
+    let recovery = swap::read(&swap).unwrap();
+    assert_eq!(recovery.original, path);
+    assert_eq!(recovery.changes.len(), 1);
+}
+
+#[test]
+fn huge_record() {
+    let tmp = tempdir().unwrap();
+    let (path, dir) = (tmp.path().join("huge.txt"), tmp.path().join("swap"));
+    let swap = dir.join("huge.swp");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        &swap,
+        format!(
+            "evie swap 2\n{}\n{:016x}\n1\ni 0 3\none\ni 3 {}\ntwo\n",
+            path.display(),
+            0,
+            usize::MAX
+        ),
+    )
+    .unwrap();
+    assert_eq!(swap::read(&swap).unwrap().changes.len(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn skip_live_sessions() {
+    let tmp = tempdir().unwrap();
+    let (path, dir) = (tmp.path().join("live.txt"), tmp.path().join("swap"));
+    let swap = dir.join(format!("{}.swp", crate::disk::flat_name(&path)));
+    fs::create_dir_all(&dir).unwrap();
+    let mut other = std::process::Command::new("sleep")
+        .arg("10")
+        .spawn()
+        .unwrap();
+    let header = |pid: u32| format!("evie swap 2\n{}\n{:016x}\n{pid}\n", path.display(), 0);
+    fs::write(&swap, header(other.id())).unwrap();
+    // another evie still has it open
+    assert_eq!(swap::find(&dir, &path), None);
+    other.kill().unwrap();
+    other.wait().unwrap();
+    assert_eq!(swap::find(&dir, &path), Some(swap));
+}
+
+#[test]
+fn named_after_the_full_path() {
+    let dir = tempdir().unwrap();
+    let relative = Path::new("src/main.rs");
+    let full = std::env::current_dir().unwrap().join(relative);
+    assert_eq!(
+        swap::fresh(dir.path(), relative),
+        swap::fresh(dir.path(), &full)
+    );
+    assert_ne!(
+        swap::fresh(dir.path(), relative),
+        swap::fresh(dir.path(), &dir.path().join(relative))
+    );
+}
@@ -0,0 +1,315 @@
+//! Syntax highlighting
+//!
+//! Each language bundled with evie is a lexer that goes over a line at a
+//! time, starting in the [`State`] the line before ended in, like inside a
+//! block comment. A [`Highlighter`] keeps what it found for every line it
+//! has lexed, and after an edit only lexes again from the first changed line
+//! until it reaches one that starts in the same state as before, as
+//! everything after that comes out the same.
+
+use std::ops::Range;
+
+use ropey::Rope;
+
+use crate::motion::line_text;
+
+mod markdown;
+mod rust;
+mod sh;
+#[cfg(test)]
+mod test;
+mod toml;
+
+/// What a piece of text is, named after vim's highlight groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Group {
+    Comment,
+    Constant,
+    String,
+    Character,
+    Number,
+    Boolean,
+    Identifier,
+    Function,
+    Keyword,
+    Type,
+    PreProc,
+    Macro,
+    /// escapes, lifetimes, list markers and the like
+    Special,
+    /// headings and table headers
+    Title,
+    /// links
+    Underlined,
+    Bold,
+    Italic,
+}
+
+impl Group {
+    pub const ALL: [Group; 17] = [
+        Group::Comment,
+        Group::Constant,
+        Group::String,
+        Group::Character,
+        Group::Number,
+        Group::Boolean,
+        Group::Identifier,
+        Group::Function,
+        Group::Keyword,
+        Group::Type,
+        Group::PreProc,
+        Group::Macro,
+        Group::Special,
+        Group::Title,
+        Group::Underlined,
+        Group::Bold,
+        Group::Italic,
+    ];
+
+    /// what vim calls it
+    pub fn name(self) -> &'static str {
+        match self {
+            Group::Comment => "Comment",
+            Group::Constant => "Constant",
+            Group::String => "String",
+            Group::Character => "Character",
+            Group::Number => "Number",
+            Group::Boolean => "Boolean",
+            Group::Identifier => "Identifier",
+            Group::Function => "Function",
+            Group::Keyword => "Keyword",
+            Group::Type => "Type",
+            Group::PreProc => "PreProc",
+            Group::Macro => "Macro",
+            Group::Special => "Special",
+            Group::Title => "Title",
+            Group::Underlined => "Underlined",
+            Group::Bold => "Bold",
+            Group::Italic => "Italic",
+        }
+    }
+}
+
+/// A highlighted piece of a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// where it is in the line, in bytes
+    pub range: Range<usize>,
+    pub group: Group,
+}
+
+/// Where a lexer is at the end of a line, which each language gives its own
+/// meaning
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct State {
+    kind: u8,
+    /// how deep comments are nested, how long a fence is and so on
+    n: u8,
+}
+
+impl State {
+    const fn new(kind: u8, n: u8) -> Self {
+        Self { kind, n }
+    }
+}
+
+/// A language evie knows how to highlight
+#[derive(Debug)]
+pub struct Language {
+    /// the `filetype` it's for
+    pub name: &'static str,
+    lex: fn(&mut Scanner, State) -> State,
+}
+
+static LANGUAGES: [Language; 4] = [
+    Language {
+        name: "rust",
+        lex: rust::lex,
+    },
+    Language {
+        name: "toml",
+        lex: toml::lex,
+    },
+    Language {
+        name: "markdown",
+        lex: markdown::lex,
+    },
+    Language {
+        name: "sh",
+        lex: sh::lex,
+    },
+];
+
+/// the language for a `filetype`, none if there isn't one
+pub fn language(filetype: &str) -> Option<&'static Language> {
+    LANGUAGES.iter().find(|language| language.name == filetype)
+}
+
+impl Language {
+    /// highlights a line, starting in `state`, returning the state it ends in
+    pub fn lex(&self, line: &str, state: State, spans: &mut Vec<Span>) -> State {
+        let mut scanner = Scanner { line, at: 0, spans };
+        (self.lex)(&mut scanner, state)
+    }
+}
+
+/// What a [`Highlighter`] found on a line
+#[derive(Debug)]
+struct Entry {
+    start: State,
+    end: State,
+    spans: Vec<Span>,
+}
+
+/// Highlights a buffer a line at a time, see the module docs
+#[derive(Debug, Default)]
+pub struct Highlighter {
+    language: Option<&'static Language>,
+    /// what was found on each line, none for lines changed since
+    lines: Vec<Option<Entry>>,
+    /// how many lines at the top are known to be right
+    valid: usize,
+}
+
+impl Highlighter {
+    pub fn new(filetype: &str) -> Self {
+        Self {
+            language: language(filetype),
+            ..Self::default()
+        }
+    }
+
+    /// the `filetype` being highlighted, empty when there's no language for
+    /// it
+    pub fn filetype(&self) -> &'static str {
+        self.language.map_or("", |language| language.name)
+    }
+
+    /// notes that lines `first..=last` were replaced with `first..=now`
+    pub fn edit(&mut self, first: usize, last: usize, now: usize) {
+        if first > self.lines.len() {
+            return;
+        }
+        let last = (last + 1).min(self.lines.len());
+        self.lines.splice(first..last, (first..=now).map(|_| None));
+        self.valid = self.valid.min(first);
+    }
+
+    /// the highlighted pieces of a line, lexing whatever lines before it
+    /// need it first
+    pub fn line(&mut self, text: &Rope, line: usize) -> &[Span] {
+        let Some(language) = self.language else {
+            return &[];
+        };
+        if line >= text.len_lines() {
+            return &[];
+        }
+        // edits this wasn't told about, like a reload
+        if self.lines.len() != text.len_lines() {
+            self.lines.clear();
+            self.lines.resize_with(text.len_lines(), || None);
+            self.valid = 0;
+        }
+        while self.valid <= line {
+            let i = self.valid;
+            let start = match i {
+                0 => State::default(),
+                i => self.lines[i - 1]
+                    .as_ref()
+                    .map_or_else(State::default, |e| e.end),
+            };
+            if self.lines[i]
+                .as_ref()
+                .is_some_and(|entry| entry.start == start)
+            {
+                // the line didn't change and starts the same way, so it and
+                // the lines after it until the next change are still right
+                self.valid = self.lines[i..]
+                    .iter()
+                    .position(Option::is_none)
+                    .map_or(self.lines.len(), |n| i + n);
+                continue;
+            }
+            let mut spans = Vec::new();
+            let end = language.lex(&line_text(text, i), start, &mut spans);
+            self.lines[i] = Some(Entry { start, end, spans });
+            self.valid = i + 1;
+        }
+        self.lines[line].as_ref().map_or(&[], |entry| &entry.spans)
+    }
+}
+
+/// Goes through a line for a lexer, collecting what it highlights
+struct Scanner<'a> {
+    line: &'a str,
+    at: usize,
+    spans: &'a mut Vec<Span>,
+}
+
+impl<'a> Scanner<'a> {
+    fn rest(&self) -> &'a str {
+        &self.line[self.at..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// the character after the next one
+    fn peek2(&self) -> Option<char> {
+        self.rest().chars().nth(1)
+    }
+
+    fn done(&self) -> bool {
+        self.at >= self.line.len()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.at += c.len_utf8();
+        Some(c)
+    }
+
+    /// skips `s` if the line carries on with it
+    fn eat(&mut self, s: &str) -> bool {
+        let found = self.rest().starts_with(s);
+        if found {
+            self.at += s.len();
+        }
+        found
+    }
+
+    fn eat_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let start = self.at;
+        while self.peek().is_some_and(&f) {
+            self.bump();
+        }
+        &self.line[start..self.at]
+    }
+
+    /// the character before where the scanner is
+    fn prev(&self) -> Option<char> {
+        self.line[..self.at].chars().next_back()
+    }
+
+    /// highlights from `start` to where the scanner is
+    fn mark(&mut self, start: usize, group: Group) {
+        if start < self.at {
+            self.spans.push(Span {
+                range: start..self.at,
+                group,
+            });
+        }
+    }
+
+    /// highlights the rest of the line
+    fn finish(&mut self, group: Group) {
+        let start = self.at;
+        self.at = self.line.len();
+        self.mark(start, group);
+    }
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
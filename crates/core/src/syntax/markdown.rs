@@ -0,0 +1,155 @@
+//! Markdown
+
+use super::{Group, Scanner, State};
+
+const TEXT: u8 = 0;
+/// in a fenced code block, `n` backticks long, or tildes with the top bit set
+const FENCE: u8 = 1;
+
+/// marks a fence made of tildes in [`FENCE`]'s length
+const TILDES: u8 = 0x80;
+
+pub(super) fn lex(s: &mut Scanner, state: State) -> State {
+    let line = s.line.trim_start();
+    let indent = s.line.len() - line.len();
+    if state.kind == FENCE {
+        let (c, n) = match state.n & TILDES {
+            0 => ('`', state.n),
+            _ => ('~', state.n & !TILDES),
+        };
+        let run = line.len() - line.trim_start_matches(c).len();
+        if run >= n as usize && line[run..].trim().is_empty() {
+            s.finish(Group::Special);
+            return State::new(TEXT, 0);
+        }
+        s.finish(Group::String);
+        return state;
+    }
+    if indent > 3 {
+        return State::new(TEXT, 0);
+    }
+    if let Some(c) = ['`', '~'].into_iter().find(|c| line.starts_with(*c)) {
+        let run = line.len() - line.trim_start_matches(c).len();
+        if run >= 3 {
+            s.finish(Group::Special);
+            let n = run.min(TILDES as usize - 1) as u8;
+            return State::new(FENCE, if c == '~' { n | TILDES } else { n });
+        }
+    }
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    if (1..=6).contains(&hashes)
+        && line[hashes..]
+            .chars()
+            .next()
+            .is_none_or(char::is_whitespace)
+    {
+        s.finish(Group::Title);
+    } else if line.starts_with('>') {
+        s.finish(Group::Comment);
+    } else if is_rule(line) {
+        s.finish(Group::PreProc);
+    } else {
+        s.at = indent;
+        let marker = list_marker(line);
+        if marker > 0 {
+            s.at += marker;
+            s.mark(indent, Group::Special);
+        }
+        inline(s);
+    }
+    State::new(TEXT, 0)
+}
+
+/// `---`, `***` or `___`, maybe with spaces between
+fn is_rule(line: &str) -> bool {
+    ['-', '*', '_'].into_iter().any(|c| {
+        let line = line.trim_end();
+        line.chars().filter(|&d| d == c).count() >= 3 && line.chars().all(|d| d == c || d == ' ')
+    })
+}
+
+/// how long the `-`, `*`, `+` or `1.` starting a list item is, 0 if the
+/// line isn't one
+fn list_marker(line: &str) -> usize {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let marker = match line[digits..].chars().next() {
+        Some('.' | ')') if digits > 0 => digits + 1,
+        Some('-' | '*' | '+') if digits == 0 => 1,
+        _ => return 0,
+    };
+    match line[marker..].chars().next() {
+        Some(' ' | '\t') | None => marker,
+        _ => 0,
+    }
+}
+
+/// code spans, emphasis, links and escapes within a line
+fn inline(s: &mut Scanner) {
+    while let Some(c) = s.peek() {
+        let start = s.at;
+        match c {
+            '\\' => {
+                s.bump();
+                if s.peek().is_some_and(|c| c.is_ascii_punctuation()) {
+                    s.bump();
+                    s.mark(start, Group::Special);
+                }
+            }
+            '`' => {
+                let ticks = s.eat_while(|c| c == '`');
+                match s.rest().find(ticks) {
+                    Some(end) => {
+                        s.at += end + ticks.len();
+                        s.mark(start, Group::String);
+                    }
+                    None => s.at = start + ticks.len(),
+                }
+            }
+            '*' | '_' => {
+                let double = s.peek2() == Some(c);
+                // `_` inside a word, as in snake_case, isn't emphasis
+                let in_word = c == '_' && s.prev().is_some_and(char::is_alphanumeric);
+                let delimiter = &s.line[start..start + if double { 2 } else { 1 }];
+                s.at += delimiter.len();
+                let closed = match s.rest().find(delimiter) {
+                    Some(end) if end > 0 && !in_word => end,
+                    _ => continue,
+                };
+                s.at += closed + delimiter.len();
+                s.mark(start, if double { Group::Bold } else { Group::Italic });
+            }
+            '[' => link(s),
+            '<' if s.rest().starts_with("<http") => {
+                s.eat_while(|c| c != '>');
+                s.bump();
+                s.mark(start, Group::Underlined);
+            }
+            _ => {
+                s.bump();
+            }
+        }
+    }
+}
+
+/// `[text](url)`, with the text underlined
+fn link(s: &mut Scanner) {
+    let start = s.at;
+    let rest = s.rest();
+    let Some(close) = rest.find("](") else {
+        s.bump();
+        return;
+    };
+    let Some(end) = rest[close..].find(')') else {
+        s.bump();
+        return;
+    };
+    s.at += 1;
+    let text = s.at;
+    s.at = start + close;
+    s.mark(text, Group::Underlined);
+    s.at += 2;
+    let url = s.at;
+    s.at = start + close + end;
+    s.mark(url, Group::Special);
+    s.at += 1;
+}
@@ -0,0 +1,241 @@
+//! Rust
+
+use super::{is_ident, Group, Scanner, State};
+
+const CODE: u8 = 0;
+/// in a block comment, `n` deep
+const COMMENT: u8 = 1;
+const STRING: u8 = 2;
+/// in a raw string closed by a quote and `n` hashes
+const RAW: u8 = 3;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "type", "union", "unsafe", "use",
+    "where", "while", "yield",
+];
+
+const TYPES: &[&str] = &[
+    "bool", "char", "str", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64",
+    "u128", "usize", "f32", "f64",
+];
+
+pub(super) fn lex(s: &mut Scanner, state: State) -> State {
+    let mut state = state;
+    loop {
+        state = match state.kind {
+            COMMENT => comment(s, s.at, state.n),
+            STRING => string_from(s, s.at),
+            RAW => raw_from(s, s.at, state.n),
+            _ => {
+                if s.done() {
+                    return state;
+                }
+                code(s)
+            }
+        };
+        if s.done() {
+            return state;
+        }
+    }
+}
+
+/// goes over a token outside of comments and strings, returning the state
+/// after it
+fn code(s: &mut Scanner) -> State {
+    let start = s.at;
+    let rest = s.rest();
+    if rest.starts_with("//") {
+        s.finish(Group::Comment);
+    } else if s.eat("/*") {
+        return comment(s, start, 1);
+    } else if let Some(hashes) = raw_start(rest) {
+        s.at += rest.find('"').unwrap_or(0) + 1;
+        return raw_from(s, start, hashes);
+    } else if s.eat("b\"") || s.eat("\"") {
+        return string_from(s, start);
+    } else if s.eat("b'") || s.peek() == Some('\'') {
+        if start == s.at {
+            s.bump();
+        }
+        quote(s, start);
+    } else if s.eat("#![") || s.eat("#[") {
+        attribute(s, start);
+    } else if s.peek().is_some_and(|c| c.is_ascii_digit()) {
+        number(s);
+    } else if s.peek().is_some_and(is_ident) {
+        ident(s);
+    } else {
+        s.bump();
+    }
+    State::new(CODE, 0)
+}
+
+/// goes through a block comment that started at `start` and is `depth` deep
+fn comment(s: &mut Scanner, start: usize, mut depth: u8) -> State {
+    while !s.done() {
+        if s.eat("/*") {
+            depth = depth.saturating_add(1);
+        } else if s.eat("*/") {
+            depth -= 1;
+            if depth == 0 {
+                s.mark(start, Group::Comment);
+                return State::new(CODE, 0);
+            }
+        } else {
+            s.bump();
+        }
+    }
+    s.mark(start, Group::Comment);
+    State::new(COMMENT, depth)
+}
+
+/// goes through a string that started at `start`, with its escapes
+fn string_from(s: &mut Scanner, mut start: usize) -> State {
+    while let Some(c) = s.peek() {
+        match c {
+            '"' => {
+                s.bump();
+                s.mark(start, Group::String);
+                return State::new(CODE, 0);
+            }
+            '\\' => {
+                s.mark(start, Group::String);
+                let escape = s.at;
+                escape_seq(s);
+                s.mark(escape, Group::Special);
+                start = s.at;
+            }
+            _ => {
+                s.bump();
+            }
+        }
+    }
+    s.mark(start, Group::String);
+    State::new(STRING, 0)
+}
+
+/// `\n`, `\x7f`, `\u{1F600}` and friends
+fn escape_seq(s: &mut Scanner) {
+    s.bump();
+    match s.bump() {
+        Some('x') => {
+            s.bump();
+            s.bump();
+        }
+        Some('u') if s.eat("{") => {
+            s.eat_while(|c| c != '}' && c != '"');
+            s.eat("}");
+        }
+        _ => {}
+    }
+}
+
+/// how many hashes a raw string starting here is closed with
+fn raw_start(rest: &str) -> Option<u8> {
+    let rest = rest.strip_prefix('b').unwrap_or(rest);
+    let rest = rest.strip_prefix('r')?;
+    let hashes = rest.len() - rest.trim_start_matches('#').len();
+    rest[hashes..]
+        .starts_with('"')
+        .then(|| hashes.min(u8::MAX as usize) as u8)
+}
+
+fn raw_from(s: &mut Scanner, start: usize, hashes: u8) -> State {
+    let close = format!("\"{}", "#".repeat(hashes as usize));
+    match s.rest().find(&close) {
+        Some(end) => {
+            s.at += end + close.len();
+            s.mark(start, Group::String);
+            State::new(CODE, 0)
+        }
+        None => {
+            s.finish(Group::String);
+            State::new(RAW, hashes)
+        }
+    }
+}
+
+/// a character like `'a'` or `'\n'`, or a lifetime like `'a`
+fn quote(s: &mut Scanner, start: usize) {
+    if s.peek() == Some('\\') {
+        escape_seq(s);
+        s.eat_while(|c| c != '\'');
+        s.eat("'");
+        s.mark(start, Group::Character);
+    } else if s.peek2() == Some('\'') {
+        s.bump();
+        s.bump();
+        s.mark(start, Group::Character);
+    } else {
+        s.eat_while(is_ident);
+        s.mark(start, Group::Special);
+    }
+}
+
+/// `#[derive(Debug)]`, up to its closing bracket on the same line
+fn attribute(s: &mut Scanner, start: usize) {
+    let mut depth = 1;
+    while let Some(c) = s.bump() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    s.mark(start, Group::PreProc);
+}
+
+fn number(s: &mut Scanner) {
+    let start = s.at;
+    loop {
+        s.eat_while(is_ident);
+        // a fraction, but not a range like `0..1` or a method call
+        if s.peek() == Some('.') && s.peek2().is_some_and(|c| c.is_ascii_digit()) {
+            s.bump();
+            continue;
+        }
+        break;
+    }
+    s.mark(start, Group::Number);
+}
+
+fn ident(s: &mut Scanner) {
+    let start = s.at;
+    let word = s.eat_while(is_ident);
+    let group = if KEYWORDS.contains(&word) {
+        Some(Group::Keyword)
+    } else if word == "true" || word == "false" {
+        Some(Group::Boolean)
+    } else if s.peek() == Some('!') && s.peek2() != Some('=') {
+        s.bump();
+        Some(Group::Macro)
+    } else if TYPES.contains(&word) || word == "Self" {
+        Some(Group::Type)
+    } else if word.len() > 1 && word.chars().all(|c| !c.is_lowercase()) {
+        Some(Group::Constant)
+    } else if word.starts_with(char::is_uppercase) {
+        Some(Group::Type)
+    } else if s.peek() == Some('(') || s.rest().starts_with("::<") || fn_name(s, start) {
+        Some(Group::Function)
+    } else {
+        None
+    };
+    if let Some(group) = group {
+        s.mark(start, group);
+    }
+}
+
+/// whether the word at `start` comes right after `fn`
+fn fn_name(s: &Scanner, start: usize) -> bool {
+    s.line[..start]
+        .trim_end()
+        .strip_suffix("fn")
+        .is_some_and(|before| !before.ends_with(is_ident))
+}
@@ -0,0 +1,144 @@
+//! Shell scripts
+
+use super::{is_ident, Group, Scanner, State};
+
+const CODE: u8 = 0;
+/// in a `"` string
+const DOUBLE: u8 = 1;
+/// in a `'` string
+const SINGLE: u8 = 2;
+
+const KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "case", "esac", "for", "select", "while", "until", "do",
+    "done", "in", "function", "time", "return", "break", "continue", "exit", "local", "export",
+    "readonly", "declare", "typeset", "unset", "source", "eval", "exec", "shift", "trap",
+];
+
+pub(super) fn lex(s: &mut Scanner, state: State) -> State {
+    let mut state = state;
+    loop {
+        state = match state.kind {
+            DOUBLE => double(s, s.at),
+            SINGLE => single(s, s.at),
+            _ if s.done() => return state,
+            _ => code(s),
+        };
+        if s.done() {
+            return state;
+        }
+    }
+}
+
+/// goes over a token outside of strings, returning the state after it
+fn code(s: &mut Scanner) -> State {
+    let start = s.at;
+    let word_start = s
+        .prev()
+        .is_none_or(|c| c.is_whitespace() || ";&|()".contains(c));
+    match s.peek() {
+        Some('#') if word_start => s.finish(Group::Comment),
+        Some('"') => {
+            s.bump();
+            return double(s, start);
+        }
+        Some('\'') => {
+            s.bump();
+            return single(s, start);
+        }
+        Some('$') => variable(s),
+        Some('\\') => {
+            s.bump();
+            s.bump();
+            s.mark(start, Group::Special);
+        }
+        Some(c) if is_ident(c) => {
+            let word = s.eat_while(|c| is_ident(c) || c == '-');
+            let group = if !word_start {
+                None
+            } else if s.peek() == Some('=') {
+                Some(Group::Identifier)
+            } else if KEYWORDS.contains(&word) {
+                Some(Group::Keyword)
+            } else if word.chars().all(|c| c.is_ascii_digit()) {
+                Some(Group::Number)
+            } else if s.rest().trim_start().starts_with("()") {
+                Some(Group::Function)
+            } else {
+                None
+            };
+            if let Some(group) = group {
+                s.mark(start, group);
+            }
+        }
+        _ => {
+            s.bump();
+        }
+    }
+    State::new(CODE, 0)
+}
+
+/// `$name`, `${name}`, `$1` or `$?`, but not the `$(` of a command
+fn variable(s: &mut Scanner) {
+    let start = s.at;
+    s.bump();
+    if s.eat("{") {
+        s.eat_while(|c| c != '}' && c != '"');
+        s.eat("}");
+    } else if s.peek().is_some_and(is_ident) {
+        s.eat_while(is_ident);
+    } else if s.peek().is_some_and(|c| "@*#?$!-".contains(c)) {
+        s.bump();
+    }
+    if s.at > start + 1 {
+        s.mark(start, Group::Identifier);
+    }
+}
+
+/// goes through a `"` string that started at `start`, with its variables and
+/// escapes
+fn double(s: &mut Scanner, mut start: usize) -> State {
+    while let Some(c) = s.peek() {
+        match c {
+            '"' => {
+                s.bump();
+                s.mark(start, Group::String);
+                return State::new(CODE, 0);
+            }
+            '\\' | '$' => {
+                s.mark(start, Group::String);
+                let at = s.at;
+                if c == '\\' {
+                    s.bump();
+                    s.bump();
+                    s.mark(at, Group::Special);
+                } else {
+                    variable(s);
+                    if s.at == at + 1 {
+                        // a lone `$` is part of the string
+                        s.mark(at, Group::String);
+                    }
+                }
+                start = s.at;
+            }
+            _ => {
+                s.bump();
+            }
+        }
+    }
+    s.mark(start, Group::String);
+    State::new(DOUBLE, 0)
+}
+
+fn single(s: &mut Scanner, start: usize) -> State {
+    match s.rest().find('\'') {
+        Some(end) => {
+            s.at += end + 1;
+            s.mark(start, Group::String);
+            State::new(CODE, 0)
+        }
+        None => {
+            s.finish(Group::String);
+            State::new(SINGLE, 0)
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use ropey::Rope;
+
+use crate::syntax::{language, Group, Highlighter, State};
+
+/// the highlighted pieces of each line, as text
+fn lex(filetype: &str, text: &str) -> Vec<Vec<(&'static str, String)>> {
+    let language = language(filetype).unwrap();
+    let mut state = State::default();
+    text.lines()
+        .map(|line| {
+            let mut spans = Vec::new();
+            state = language.lex(line, state, &mut spans);
+            spans
+                .into_iter()
+                .map(|span| (span.group.name(), line[span.range].to_owned()))
+                .collect()
+        })
+        .collect()
+}
+
+fn pieces(list: &[(&'static str, &str)]) -> Vec<(&'static str, String)> {
+    list.iter().map(|(g, s)| (*g, s.to_string())).collect()
+}
+
+#[test]
+fn rust() {
+    let lines = lex(
+        "rust",
+        "#[derive(Debug)]\nfn main<'a>() { let s = \"a\\n\"; /* x\n y */ println!(\"{}\", 1.5) }",
+    );
+    assert_eq!(lines[0], pieces(&[("PreProc", "#[derive(Debug)]")]));
+    assert_eq!(
+        lines[1],
+        pieces(&[
+            ("Keyword", "fn"),
+            ("Function", "main"),
+            ("Special", "'a"),
+            ("Keyword", "let"),
+            ("String", "\"a"),
+            ("Special", "\\n"),
+            ("String", "\""),
+            ("Comment", "/* x"),
+        ])
+    );
+    assert_eq!(
+        lines[2],
+        pieces(&[
+            ("Comment", " y */"),
+            ("Macro", "println!"),
+            ("String", "\"{}\""),
+            ("Number", "1.5"),
+        ])
+    );
+}
+
+#[test]
+fn other_languages() {
+    let toml = lex("toml", "[package]\nname = \"evie\" # c\nyes = true");
+    assert_eq!(toml[0], pieces(&[("Title", "[package]")]));
+    assert_eq!(
+        toml[1],
+        pieces(&[
+            ("Identifier", "name"),
+            ("String", "\"evie\""),
+            ("Comment", "# c")
+        ])
+    );
+    assert_eq!(
+        toml[2],
+        pieces(&[("Identifier", "yes"), ("Boolean", "true")])
+    );
+
+    let sh = lex("sh", "if [ \"$x\" ]; then # hi\n  y=1\nfi");
+    assert_eq!(
+        sh[0],
+        pieces(&[
+            ("Keyword", "if"),
+            ("String", "\""),
+            ("Identifier", "$x"),
+            ("String", "\""),
+            ("Keyword", "then"),
+            ("Comment", "# hi"),
+        ])
+    );
+    assert_eq!(sh[1], pieces(&[("Identifier", "y")]));
+
+    let md = lex(
+        "markdown",
+        "# Title\n- a **b** `c`\n```\n# not a title\n```",
+    );
+    assert_eq!(md[0], pieces(&[("Title", "# Title")]));
+    assert_eq!(
+        md[1],
+        pieces(&[("Special", "-"), ("Bold", "**b**"), ("String", "`c`")])
+    );
+    assert_eq!(md[3], pieces(&[("String", "# not a title")]));
+    assert_eq!(md[4], pieces(&[("Special", "```")]));
+}
+
+#[test]
+fn edits_relex_only_what_changed() {
+    let mut text = Rope::from_str("a\n/* b\nc */\nd\n");
+    let mut syntax = Highlighter::new("rust");
+    let groups = |syntax: &mut Highlighter, text: &Rope, line| -> Vec<Group> {
+        syntax
+            .line(text, line)
+            .iter()
+            .map(|span| span.group)
+            .collect()
+    };
+    assert_eq!(groups(&mut syntax, &text, 2), [Group::Comment]);
+    assert_eq!(syntax.valid, 3);
+    assert_eq!(groups(&mut syntax, &text, 4), []);
+
+    // closing the comment early changes the lines after it
+    text.insert(4, "*/");
+    syntax.edit(1, 1, 1);
+    assert_eq!(groups(&mut syntax, &text, 2), []);
+    assert_eq!(groups(&mut syntax, &text, 4), []);
+
+    // a change that leaves the state alone stops at the changed line
+    text.insert(0, "x\n");
+    syntax.edit(0, 0, 1);
+    assert_eq!(groups(&mut syntax, &text, 2), [Group::Comment]);
+    assert_eq!(syntax.valid, syntax.lines.len());
+}
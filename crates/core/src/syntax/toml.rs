@@ -0,0 +1,117 @@
+//! TOML
+
+use super::{Group, Scanner, State};
+
+const CODE: u8 = 0;
+/// in a `"""` string
+const BASIC: u8 = 1;
+/// in a `'''` string
+const LITERAL: u8 = 2;
+
+pub(super) fn lex(s: &mut Scanner, state: State) -> State {
+    let mut state = match state.kind {
+        BASIC => basic(s, s.at, "\"\"\""),
+        LITERAL => literal(s, s.at, "'''"),
+        _ => State::new(CODE, 0),
+    };
+    // a table header is the whole line, bar a comment after it
+    if state.kind == CODE && s.rest().trim_start().starts_with('[') {
+        s.eat_while(char::is_whitespace);
+        let start = s.at;
+        s.eat_while(|c| c != '#');
+        let header = s.line[start..s.at].trim_end().len();
+        s.at = start + header;
+        s.mark(start, Group::Title);
+    }
+    while state.kind == CODE && !s.done() {
+        state = value(s);
+    }
+    state
+}
+
+/// goes over a token outside of strings, returning the state after it
+fn value(s: &mut Scanner) -> State {
+    let start = s.at;
+    if s.peek() == Some('#') {
+        s.finish(Group::Comment);
+    } else if s.eat("\"\"\"") {
+        return basic(s, start, "\"\"\"");
+    } else if s.eat("'''") {
+        return literal(s, start, "'''");
+    } else if s.eat("\"") {
+        return basic(s, start, "\"");
+    } else if s.eat("'") {
+        return literal(s, start, "'");
+    } else if s.peek().is_some_and(is_bare) {
+        let word = s.eat_while(is_bare);
+        let rest = s.rest().trim_start();
+        let group = if rest.starts_with('=') && !rest.starts_with("==") {
+            Some(Group::Identifier)
+        } else if word == "true" || word == "false" {
+            Some(Group::Boolean)
+        } else if is_number(word) {
+            Some(Group::Number)
+        } else {
+            None
+        };
+        if let Some(group) = group {
+            s.mark(start, group);
+        }
+    } else {
+        s.bump();
+    }
+    State::new(CODE, 0)
+}
+
+/// what keys, numbers, dates and times are made of
+fn is_bare(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+' | ':')
+}
+
+fn is_number(word: &str) -> bool {
+    let digits = word.trim_start_matches(['+', '-']);
+    digits.starts_with(|c: char| c.is_ascii_digit()) || matches!(digits, "inf" | "nan")
+}
+
+/// goes through a string with escapes, closed by `close`
+fn basic(s: &mut Scanner, mut start: usize, close: &str) -> State {
+    while !s.done() {
+        if s.eat(close) {
+            s.mark(start, Group::String);
+            return State::new(CODE, 0);
+        }
+        if s.peek() == Some('\\') {
+            s.mark(start, Group::String);
+            let escape = s.at;
+            s.bump();
+            s.bump();
+            s.mark(escape, Group::Special);
+            start = s.at;
+        } else {
+            s.bump();
+        }
+    }
+    s.mark(start, Group::String);
+    match close.len() {
+        1 => State::new(CODE, 0),
+        _ => State::new(BASIC, 0),
+    }
+}
+
+/// goes through a string without escapes, closed by `close`
+fn literal(s: &mut Scanner, start: usize, close: &str) -> State {
+    match s.rest().find(close) {
+        Some(end) => {
+            s.at += end + close.len();
+            s.mark(start, Group::String);
+            State::new(CODE, 0)
+        }
+        None => {
+            s.finish(Group::String);
+            match close.len() {
+                1 => State::new(CODE, 0),
+                _ => State::new(LITERAL, 0),
+            }
+        }
+    }
+}
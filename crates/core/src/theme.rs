@@ -0,0 +1,342 @@
+//! Colorschemes, what each highlight group looks like
+//!
+//! A theme file has a line for each group, its name and then its colors and
+//! attributes:
+//!
+//! ```text
+//! # a comment
+//! Normal       fg=#f8f8f2 bg=#282a36
+//! Comment      fg=#6272a4 italic
+//!
+//! [light]
+//! Normal       fg=#1f1f1f bg=#fffbeb
+//! ```
+//!
+//! Groups before any section are used on both backgrounds, those in a
+//! `[dark]` or `[light]` section only on that one, over the shared ones. A
+//! group a theme leaves out looks like it does in the `evie` theme.
+//!
+//! Themes are looked for as `colors/{name}.theme` in the config directory,
+//! then among the ones built in.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use thiserror::Error;
+
+use crate::syntax::Group;
+
+#[cfg(test)]
+mod test;
+
+/// the themes built in, by name
+const BUILTIN: &[(&str, &str)] = &[
+    ("evie", include_str!("../colors/evie.theme")),
+    ("solarized", include_str!("../colors/solarized.theme")),
+];
+
+/// what groups a theme leaves out fall back to
+static FALLBACK: LazyLock<Theme> = LazyLock::new(Theme::default);
+
+#[derive(Debug, Error)]
+pub enum ThemeError {
+    #[error("Cannot find color scheme '{0}'")]
+    NotFound(String),
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{name} line {line}: {message}")]
+    Parse {
+        name: String,
+        line: usize,
+        message: String,
+    },
+}
+
+/// A color, parsed from `#rrggbb` or `#rgb`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl FromStr for Rgb {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').ok_or(())?;
+        let digit = |i: usize| u8::from_str_radix(hex.get(i..i + 1).ok_or(())?, 16).map_err(|_| ());
+        let (r, g, b) = match hex.len() {
+            3 => (digit(0)? * 17, digit(1)? * 17, digit(2)? * 17),
+            6 => (
+                digit(0)? << 4 | digit(1)?,
+                digit(2)? << 4 | digit(3)?,
+                digit(4)? << 4 | digit(5)?,
+            ),
+            _ => return Err(()),
+        };
+        Ok(Self { r, g, b })
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// How a group looks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    /// none keeps the color of the text around it
+    pub fg: Option<Rgb>,
+    pub bg: Option<Rgb>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+/// A highlight group for part of the editor rather than of the text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UiGroup {
+    /// the text and the background behind it
+    Normal,
+    LineNr,
+    /// the number of the line the cursor is on
+    CursorLineNr,
+    /// the status line of the window with focus
+    StatusLine,
+    /// the status lines of the other windows
+    StatusLineNC,
+    TabLine,
+    /// the tab page on screen in the tab bar
+    TabLineSel,
+    /// the line between windows side by side
+    VertSplit,
+    /// the selection
+    Visual,
+    /// matches of the last search
+    Search,
+    /// the cursor, with `fg` for the character under it
+    Cursor,
+    /// the line a closed fold is shown as
+    Folded,
+    ErrorMsg,
+    /// diagnostics of each severity, their signs, underlines and messages
+    DiagnosticError,
+    DiagnosticWarn,
+    DiagnosticInfo,
+    DiagnosticHint,
+    /// the completion menu, and its selected candidate
+    Pmenu,
+    PmenuSel,
+}
+
+impl UiGroup {
+    pub const ALL: [Self; 19] = [
+        Self::Normal,
+        Self::LineNr,
+        Self::CursorLineNr,
+        Self::StatusLine,
+        Self::StatusLineNC,
+        Self::TabLine,
+        Self::TabLineSel,
+        Self::VertSplit,
+        Self::Visual,
+        Self::Search,
+        Self::Cursor,
+        Self::Folded,
+        Self::ErrorMsg,
+        Self::DiagnosticError,
+        Self::DiagnosticWarn,
+        Self::DiagnosticInfo,
+        Self::DiagnosticHint,
+        Self::Pmenu,
+        Self::PmenuSel,
+    ];
+
+    /// what the group is called in theme files
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Normal => "Normal",
+            Self::LineNr => "LineNr",
+            Self::CursorLineNr => "CursorLineNr",
+            Self::StatusLine => "StatusLine",
+            Self::StatusLineNC => "StatusLineNC",
+            Self::TabLine => "TabLine",
+            Self::TabLineSel => "TabLineSel",
+            Self::VertSplit => "VertSplit",
+            Self::Visual => "Visual",
+            Self::Search => "Search",
+            Self::Cursor => "Cursor",
+            Self::Folded => "Folded",
+            Self::ErrorMsg => "ErrorMsg",
+            Self::DiagnosticError => "DiagnosticError",
+            Self::DiagnosticWarn => "DiagnosticWarn",
+            Self::DiagnosticInfo => "DiagnosticInfo",
+            Self::DiagnosticHint => "DiagnosticHint",
+            Self::Pmenu => "Pmenu",
+            Self::PmenuSel => "PmenuSel",
+        }
+    }
+}
+
+/// Any highlight group, of the text or of the editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Highlight {
+    Syntax(Group),
+    Ui(UiGroup),
+}
+
+impl From<Group> for Highlight {
+    fn from(group: Group) -> Self {
+        Self::Syntax(group)
+    }
+}
+
+impl From<UiGroup> for Highlight {
+    fn from(group: UiGroup) -> Self {
+        Self::Ui(group)
+    }
+}
+
+impl FromStr for Highlight {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let syntax = Group::ALL.into_iter().find(|group| group.name() == s);
+        let ui = UiGroup::ALL.into_iter().find(|group| group.name() == s);
+        syntax.map(Self::Syntax).or(ui.map(Self::Ui)).ok_or(())
+    }
+}
+
+/// Whether the background is dark or light, see the module docs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Background {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Background {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Dark => "dark",
+            Self::Light => "light",
+        }
+    }
+}
+
+type Groups = HashMap<Highlight, Style>;
+
+/// A colorscheme, see the module docs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    shared: Groups,
+    dark: Groups,
+    light: Groups,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::builtin("evie").expect("the evie theme parses")
+    }
+}
+
+impl Theme {
+    /// reads a theme file's contents
+    pub fn parse(name: &str, text: &str) -> Result<Self, ThemeError> {
+        let mut theme = Self {
+            name: name.to_owned(),
+            shared: Groups::new(),
+            dark: Groups::new(),
+            light: Groups::new(),
+        };
+        let mut section = None;
+        for (i, line) in text.lines().enumerate() {
+            let error = |message: String| ThemeError::Parse {
+                name: name.to_owned(),
+                line: i + 1,
+                message,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                section = Some(match name {
+                    "dark" => Background::Dark,
+                    "light" => Background::Light,
+                    _ => return Err(error(format!("unknown section [{name}]"))),
+                });
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let group = words.next().unwrap_or_default();
+            let highlight = group
+                .parse()
+                .map_err(|_| error(format!("unknown group {group}")))?;
+            let mut style = Style::default();
+            for word in words {
+                let color = |value: &str| {
+                    let color = value
+                        .parse()
+                        .map_err(|_| error(format!("bad color {value}")));
+                    color.map(Some)
+                };
+                match word.split_once('=') {
+                    Some(("fg", value)) => style.fg = color(value)?,
+                    Some(("bg", value)) => style.bg = color(value)?,
+                    None if word == "bold" => style.bold = true,
+                    None if word == "italic" => style.italic = true,
+                    None if word == "underline" => style.underline = true,
+                    _ => return Err(error(format!("unknown attribute {word}"))),
+                }
+            }
+            let groups = match section {
+                None => &mut theme.shared,
+                Some(Background::Dark) => &mut theme.dark,
+                Some(Background::Light) => &mut theme.light,
+            };
+            groups.insert(highlight, style);
+        }
+        Ok(theme)
+    }
+
+    /// one of the themes built in
+    pub fn builtin(name: &str) -> Option<Self> {
+        let (name, text) = BUILTIN.iter().find(|(builtin, _)| *builtin == name)?;
+        Self::parse(name, text).ok()
+    }
+
+    /// finds a theme by name, see the module docs
+    pub fn load(name: &str) -> Result<Self, ThemeError> {
+        let file =
+            crate::disk::config_dir().map(|dir| dir.join("colors").join(format!("{name}.theme")));
+        match file.map(std::fs::read_to_string) {
+            Some(Ok(text)) => Self::parse(name, &text),
+            Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Self::builtin(name).ok_or_else(|| ThemeError::NotFound(name.to_owned())),
+        }
+    }
+
+    /// how `group` looks on `background`
+    pub fn style(&self, group: impl Into<Highlight>, background: Background) -> Style {
+        let group = group.into();
+        self.own(group, background)
+            .or_else(|| FALLBACK.own(group, background))
+            .unwrap_or_default()
+    }
+
+    fn own(&self, group: Highlight, background: Background) -> Option<Style> {
+        let section = match background {
+            Background::Dark => &self.dark,
+            Background::Light => &self.light,
+        };
+        section.get(&group).or(self.shared.get(&group)).copied()
+    }
+}
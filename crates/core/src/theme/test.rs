@@ -0,0 +1,46 @@
+use crate::syntax::Group;
+use crate::theme::{Background, Rgb, Theme, ThemeError, UiGroup};
+
+fn rgb(s: &str) -> Option<Rgb> {
+    Some(s.parse().unwrap())
+}
+
+#[test]
+fn sections_and_fallback() {
+    let theme = Theme::parse(
+        "test",
+        "# shared\nNormal fg=#fff bg=#000000\nComment fg=#808080 italic\n\n[light]\nNormal fg=#000 bg=#ffffff\n",
+    )
+    .unwrap();
+    let normal = |background| theme.style(UiGroup::Normal, background);
+    assert_eq!(normal(Background::Dark).bg, rgb("#000000"));
+    assert_eq!(normal(Background::Light).bg, rgb("#ffffff"));
+    let comment = theme.style(Group::Comment, Background::Light);
+    assert_eq!(comment.fg, rgb("#808080"));
+    assert!(comment.italic);
+    // left out, so as in the evie theme
+    let visual = theme.style(UiGroup::Visual, Background::Dark);
+    assert_eq!(
+        visual,
+        Theme::default().style(UiGroup::Visual, Background::Dark)
+    );
+    assert!(visual.bg.is_some());
+
+    assert_eq!(rgb("#1a2B3c").unwrap().to_string(), "#1a2b3c");
+    for builtin in ["evie", "solarized"] {
+        assert_eq!(Theme::builtin(builtin).unwrap().name, builtin);
+    }
+}
+
+#[test]
+fn errors() {
+    let error = |text| Theme::parse("bad", text).unwrap_err().to_string();
+    assert_eq!(error("Normal fg=red"), "bad line 1: bad color red");
+    assert_eq!(error("\nNope bold"), "bad line 2: unknown group Nope");
+    assert_eq!(error("[dim]"), "bad line 1: unknown section [dim]");
+    assert_eq!(error("Normal blink"), "bad line 1: unknown attribute blink");
+    assert!(matches!(
+        Theme::load("no-such-theme"),
+        Err(ThemeError::NotFound(_))
+    ));
+}
@@ -0,0 +1,287 @@
+//! Syntax trees from the tree-sitter grammars compiled in
+//!
+//! Each grammar sits behind a cargo feature named after its `filetype`. A
+//! buffer written in one of them keeps a [`SyntaxTree`], which hears about
+//! every edit as it's made and parses again, reusing the parts of the old tree
+//! that didn't change, the next time it's asked about.
+
+use std::fmt;
+use std::ops::Range;
+
+use ropey::Rope;
+use tree_sitter::{InputEdit, Language, Node, Parser, Tree};
+
+use crate::motion::TextObject;
+
+#[cfg(all(test, feature = "rust"))]
+mod test;
+
+/// A grammar and what its nodes mean
+struct Grammar {
+    filetype: &'static str,
+    language: fn() -> Language,
+    function: &'static [&'static str],
+    class: &'static [&'static str],
+    parameter: &'static [&'static str],
+    comment: &'static [&'static str],
+    /// nodes that fold when they span several lines
+    fold: &'static [&'static str],
+}
+
+static GRAMMARS: &[Grammar] = &[
+    #[cfg(feature = "rust")]
+    Grammar {
+        filetype: "rust",
+        language: || tree_sitter_rust::LANGUAGE.into(),
+        function: &["function_item", "closure_expression"],
+        class: &[
+            "struct_item",
+            "enum_item",
+            "union_item",
+            "impl_item",
+            "trait_item",
+            "mod_item",
+        ],
+        parameter: &["parameter", "self_parameter"],
+        comment: &["line_comment", "block_comment"],
+        fold: &[
+            "function_item",
+            "struct_item",
+            "enum_item",
+            "union_item",
+            "impl_item",
+            "trait_item",
+            "mod_item",
+            "block",
+            "match_block",
+            "block_comment",
+            "use_declaration",
+            "macro_invocation",
+        ],
+    },
+    #[cfg(feature = "toml")]
+    Grammar {
+        filetype: "toml",
+        language: || tree_sitter_toml_ng::LANGUAGE.into(),
+        function: &[],
+        class: &["table", "table_array_element"],
+        parameter: &["pair"],
+        comment: &["comment"],
+        fold: &["table", "table_array_element", "array", "inline_table"],
+    },
+    #[cfg(feature = "markdown")]
+    Grammar {
+        filetype: "markdown",
+        language: || tree_sitter_md::LANGUAGE.into(),
+        function: &["fenced_code_block"],
+        class: &["section"],
+        parameter: &["list_item"],
+        comment: &["html_block"],
+        fold: &["section", "fenced_code_block", "list", "block_quote"],
+    },
+    #[cfg(feature = "sh")]
+    Grammar {
+        filetype: "sh",
+        language: || tree_sitter_bash::LANGUAGE.into(),
+        function: &["function_definition"],
+        class: &[],
+        parameter: &[],
+        comment: &["comment"],
+        fold: &[
+            "function_definition",
+            "if_statement",
+            "for_statement",
+            "c_style_for_statement",
+            "while_statement",
+            "case_statement",
+            "compound_statement",
+            "heredoc_body",
+        ],
+    },
+];
+
+/// A buffer's syntax tree, see the module docs
+pub struct SyntaxTree {
+    grammar: &'static Grammar,
+    parser: Parser,
+    tree: Option<Tree>,
+    /// whether there were edits since the last parse
+    stale: bool,
+}
+
+impl fmt::Debug for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyntaxTree")
+            .field("filetype", &self.grammar.filetype)
+            .field("stale", &self.stale)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SyntaxTree {
+    /// none when there's no grammar for the `filetype`
+    pub fn new(filetype: &str) -> Option<Self> {
+        let grammar = GRAMMARS
+            .iter()
+            .find(|grammar| grammar.filetype == filetype)?;
+        let mut parser = Parser::new();
+        parser.set_language(&(grammar.language)()).ok()?;
+        Some(Self {
+            grammar,
+            parser,
+            tree: None,
+            stale: true,
+        })
+    }
+
+    pub fn filetype(&self) -> &'static str {
+        self.grammar.filetype
+    }
+
+    /// moves the tree along with a change to the text, parsing again the
+    /// next time it's asked about
+    pub fn edit(&mut self, edit: &InputEdit) {
+        if let Some(tree) = &mut self.tree {
+            tree.edit(edit);
+        }
+        self.stale = true;
+    }
+
+    /// the tree for `text`, parsing it again if it changed
+    pub fn tree(&mut self, text: &Rope) -> Option<&Tree> {
+        if self.stale || self.tree.is_none() {
+            let tree = self.parser.parse_with(
+                &mut |byte, _| match byte < text.len_bytes() {
+                    true => {
+                        let (chunk, start, ..) = text.chunk_at_byte(byte);
+                        &chunk.as_bytes()[byte - start..]
+                    }
+                    false => &[][..],
+                },
+                self.tree.as_ref(),
+            );
+            self.tree = tree;
+            self.stale = false;
+        }
+        self.tree.as_ref()
+    }
+
+    /// the bytes of the innermost `object` around `at`, or just its inside,
+    /// like the body of a function without its braces
+    pub fn text_object(
+        &mut self,
+        text: &Rope,
+        at: usize,
+        object: TextObject,
+        inner: bool,
+    ) -> Option<Range<usize>> {
+        let kinds = match object {
+            TextObject::Function => self.grammar.function,
+            TextObject::Class => self.grammar.class,
+            TextObject::Parameter => self.grammar.parameter,
+            TextObject::Comment => self.grammar.comment,
+        };
+        let root = self.tree(text)?.root_node();
+        let node = enclosing(root.descendant_for_byte_range(at, at)?, |node| {
+            kinds.contains(&node.kind())
+        })?;
+        Some(match (object, inner) {
+            (TextObject::Function | TextObject::Class, true) => {
+                let body = node.child_by_field_name("body").unwrap_or(node);
+                inside(body)
+            }
+            // like `aw` takes the space after a word, a parameter takes the
+            // comma that separates it from the next
+            (TextObject::Parameter, false) => match node.next_sibling() {
+                Some(comma) if comma.kind() == "," => {
+                    let end = comma
+                        .next_sibling()
+                        .map_or(comma.end_byte(), |next| next.start_byte());
+                    node.start_byte()..end
+                }
+                _ => node.byte_range(),
+            },
+            _ => node.byte_range(),
+        })
+    }
+
+    /// the smallest node around `range` that is bigger than it, for growing a
+    /// selection
+    pub fn expand(&mut self, text: &Rope, range: Range<usize>) -> Option<Range<usize>> {
+        let root = self.tree(text)?.root_node();
+        let node = root.named_descendant_for_byte_range(range.start, range.end)?;
+        let node = enclosing(node, |node| {
+            node.is_named()
+                && node.byte_range() != range
+                && node.start_byte() <= range.start
+                && node.end_byte() >= range.end
+        })?;
+        Some(node.byte_range())
+    }
+
+    /// the lines of every fold, outer ones first
+    pub fn folds(&mut self, text: &Rope) -> Vec<Range<usize>> {
+        let fold = self.grammar.fold;
+        let Some(tree) = self.tree(text) else {
+            return Vec::new();
+        };
+        let mut folds = Vec::new();
+        let mut cursor = tree.walk();
+        loop {
+            let node = cursor.node();
+            // a block often covers the same lines as the item it's the body of
+            let lines = lines(node);
+            if fold.contains(&node.kind()) && lines.len() > 1 && folds.last() != Some(&lines) {
+                folds.push(lines);
+            }
+            if cursor.goto_first_child() || cursor.goto_next_sibling() {
+                continue;
+            }
+            loop {
+                if !cursor.goto_parent() {
+                    return folds;
+                }
+                if cursor.goto_next_sibling() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// the first of `node` and its ancestors that `f` picks
+fn enclosing<'t>(node: Node<'t>, f: impl Fn(&Node) -> bool) -> Option<Node<'t>> {
+    let mut node = Some(node);
+    while let Some(n) = node {
+        if f(&n) {
+            return Some(n);
+        }
+        node = n.parent();
+    }
+    None
+}
+
+/// a block without the brackets around it
+fn inside(node: Node) -> Range<usize> {
+    let count = node.child_count();
+    match (
+        node.child(0),
+        count.checked_sub(1).and_then(|i| node.child(i)),
+    ) {
+        (Some(open), Some(close)) if count > 1 && !open.is_named() && !close.is_named() => {
+            open.end_byte()..close.start_byte()
+        }
+        _ => node.byte_range(),
+    }
+}
+
+/// the lines a node covers, leaving out the last when it only ends at its
+/// start
+fn lines(node: Node) -> Range<usize> {
+    let (start, end) = (node.start_position(), node.end_position());
+    let end = match end.column {
+        0 if end.row > start.row => end.row,
+        _ => end.row + 1,
+    };
+    start.row..end
+}
@@ -0,0 +1,70 @@
+use ropey::Rope;
+
+use crate::buffer::Buffer;
+use crate::motion::TextObject;
+use crate::BufferAction;
+
+const ADD: &str = "fn add(a: u32, b: u32) -> u32 {\n    // sum\n    a + b\n}\n";
+
+fn rust(text: &str) -> Buffer {
+    let mut buf = Buffer {
+        text: Rope::from_str(text),
+        ..Buffer::default()
+    };
+    buf.local.filetype = "rust".into();
+    buf
+}
+
+fn slice(buf: &Buffer, range: Option<std::ops::Range<usize>>) -> String {
+    buf.text.byte_slice(range.unwrap()).to_string()
+}
+
+#[test]
+fn text_objects() {
+    let mut buf = rust(ADD);
+    buf.loc.char = ADD.find("a + b").unwrap();
+    let object = |buf: &mut Buffer, object, inner| {
+        let range = buf.text_object(object, inner);
+        slice(buf, range)
+    };
+    assert_eq!(
+        object(&mut buf, TextObject::Function, false),
+        ADD.trim_end()
+    );
+    assert_eq!(
+        object(&mut buf, TextObject::Function, true),
+        "\n    // sum\n    a + b\n"
+    );
+    assert_eq!(buf.text_object(TextObject::Class, false), None);
+    buf.loc.char = 7;
+    assert_eq!(object(&mut buf, TextObject::Parameter, false), "a: u32, ");
+    assert_eq!(object(&mut buf, TextObject::Parameter, true), "a: u32");
+
+    // the tree follows edits made to the text
+    buf.loc.char = 0;
+    buf.apply(BufferAction::Append("struct S {\n    a: u32,\n}\n".into()))
+        .unwrap();
+    buf.loc.char = 4;
+    assert_eq!(
+        object(&mut buf, TextObject::Class, false),
+        "struct S {\n    a: u32,\n}"
+    );
+    buf.loc.char = buf.text.to_string().find("// sum").unwrap() + 3;
+    assert_eq!(object(&mut buf, TextObject::Comment, false), "// sum");
+}
+
+#[test]
+fn nodes_and_folds() {
+    let mut buf = rust(ADD);
+    let at = ADD.find("a + b").unwrap();
+    let range = buf.expand(at..at);
+    assert_eq!(slice(&buf, range.clone()), "a");
+    let range = buf.expand(range.unwrap());
+    assert_eq!(slice(&buf, range), "a + b");
+    assert_eq!(buf.fold_ranges(), vec![0..4]);
+
+    buf.loc.char = ADD.len();
+    buf.apply(BufferAction::Append("mod m {\n\n}\n".into()))
+        .unwrap();
+    assert_eq!(buf.fold_ranges(), vec![0..4, 4..7]);
+}
@@ -0,0 +1,120 @@
+//! What part of a buffer is on screen
+//!
+//! The frontend lays the lines out, so it tells the [`Viewport`] how many fit.
+//! Motions then scroll the view to keep the cursor on screen, and scrolling
+//! moves the cursor to keep it in the view, each keeping `scrolloff` lines
+//! (and `sidescrolloff` columns) between the cursor and the edge.
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    /// the first line on screen
+    pub top: usize,
+    /// the first column on screen, when lines don't wrap
+    pub left: usize,
+    /// how many lines fit on screen
+    pub height: usize,
+    /// how many columns fit on screen, 0 when lines wrap
+    pub width: usize,
+}
+
+/// A way of scrolling the view, from `Ctrl-e` and friends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scroll {
+    /// `Ctrl-e` and `Ctrl-y`, or the mouse wheel, the cursor stays put
+    Lines(isize),
+    /// `Ctrl-d` and `Ctrl-u`, the cursor moves as far as the view
+    HalfPage(isize),
+    /// `Ctrl-f` and `Ctrl-b`, keeping two lines of context
+    Page(isize),
+    /// `zt`, `zz` and `zb`, puts the cursor line at a part of the screen
+    Cursor(Align),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Top,
+    Center,
+    Bottom,
+}
+
+impl Viewport {
+    /// scrolls the view, returning the line the cursor moves to
+    ///
+    /// `lines` is the number of lines in the buffer and `so` the `scrolloff`.
+    pub fn scroll(&mut self, scroll: Scroll, cursor: usize, lines: usize, so: usize) -> usize {
+        let last = lines.saturating_sub(1);
+        let height = self.height.max(1);
+        let cursor = match scroll {
+            Scroll::Lines(n) => {
+                self.top = offset(self.top, n, last);
+                cursor
+            }
+            Scroll::HalfPage(n) => {
+                let n = n * (height / 2).max(1) as isize;
+                self.top = offset(self.top, n, last);
+                offset(cursor, n, last)
+            }
+            Scroll::Page(n) => {
+                self.top = offset(self.top, n * height.saturating_sub(2).max(1) as isize, last);
+                cursor
+            }
+            Scroll::Cursor(align) => {
+                self.top = match align {
+                    Align::Top => cursor.saturating_sub(so),
+                    Align::Center => cursor.saturating_sub(height / 2),
+                    Align::Bottom => (cursor + so + 1).saturating_sub(height),
+                };
+                cursor
+            }
+        };
+        self.confine(cursor, last, so)
+    }
+
+    /// the line nearest to `line` that's on screen, at least `so` lines in
+    /// from the edges unless the view is at the start or end of the buffer
+    pub fn confine(&self, line: usize, last: usize, so: usize) -> usize {
+        let so = self.scrolloff(so);
+        let bottom = self.top + self.height.max(1) - 1;
+        let low = if self.top == 0 { 0 } else { self.top + so }.min(last);
+        let high = if bottom >= last {
+            last
+        } else {
+            bottom.saturating_sub(so)
+        };
+        line.clamp(low, high.max(low))
+    }
+
+    /// scrolls just enough to keep the cursor `so` lines and `siso` columns
+    /// from the edges
+    pub fn follow(&mut self, line: usize, col: usize, so: usize, siso: usize) {
+        let so = self.scrolloff(so);
+        if line < self.top + so {
+            self.top = line.saturating_sub(so);
+        } else if line + so >= self.top + self.height.max(1) {
+            self.top = line + so + 1 - self.height.max(1);
+        }
+
+        if self.width == 0 {
+            self.left = 0;
+            return;
+        }
+        let siso = siso.min(self.width.saturating_sub(1) / 2);
+        if col < self.left + siso {
+            self.left = col.saturating_sub(siso);
+        } else if col + siso >= self.left + self.width {
+            self.left = col + siso + 1 - self.width;
+        }
+    }
+
+    /// `scrolloff` cut down so the cursor can still move on a short screen
+    fn scrolloff(&self, so: usize) -> usize {
+        so.min(self.height.saturating_sub(1) / 2)
+    }
+}
+
+fn offset(at: usize, by: isize, last: usize) -> usize {
+    at.saturating_add_signed(by).min(last)
+}
@@ -0,0 +1,57 @@
+use crate::view::{Align, Scroll, Viewport};
+
+fn view(top: usize) -> Viewport {
+    Viewport {
+        top,
+        height: 10,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn follow_with_scrolloff() {
+    let mut v = view(0);
+    v.follow(12, 0, 3, 0);
+    assert_eq!(v.top, 6);
+    v.follow(8, 0, 3, 0);
+    assert_eq!(v.top, 5);
+    // a scrolloff bigger than half the screen keeps the cursor near the middle
+    v.follow(20, 0, 50, 0);
+    assert_eq!(v.top, 15);
+
+    let mut v = Viewport {
+        width: 20,
+        ..view(0)
+    };
+    v.follow(0, 30, 0, 5);
+    assert_eq!(v.left, 16);
+    v.follow(0, 18, 0, 5);
+    assert_eq!(v.left, 13);
+}
+
+#[test]
+fn scrolling_moves_cursor() {
+    // ctrl-e drags the cursor along once it reaches the top
+    let mut v = view(0);
+    assert_eq!(v.scroll(Scroll::Lines(3), 1, 100, 2), 5);
+    assert_eq!(v.top, 3);
+    // ctrl-d moves both by half a screen
+    assert_eq!(v.scroll(Scroll::HalfPage(1), 5, 100, 2), 10);
+    assert_eq!(v.top, 8);
+    // ctrl-b keeps two lines of context, and can't go above the start
+    assert_eq!(v.scroll(Scroll::Page(-1), 10, 100, 0), 9);
+    assert_eq!(v.top, 0);
+    assert_eq!(v.scroll(Scroll::Lines(500), 0, 100, 0), 99);
+    assert_eq!(v.top, 99);
+}
+
+#[test]
+fn cursor_alignment() {
+    let mut v = view(0);
+    v.scroll(Scroll::Cursor(Align::Top), 40, 100, 2);
+    assert_eq!(v.top, 38);
+    v.scroll(Scroll::Cursor(Align::Center), 40, 100, 2);
+    assert_eq!(v.top, 35);
+    v.scroll(Scroll::Cursor(Align::Bottom), 40, 100, 2);
+    assert_eq!(v.top, 33);
+}
@@ -0,0 +1,469 @@
+//! Windows, the tree of splits they are laid out in, and the tab pages
+//! holding those trees
+//!
+//! Each window is a view into a buffer with its own cursor and scroll
+//! position. Only the focused window's cursor lives in its [`Buffer`], the
+//! others are kept here and put back when they get focus.
+//!
+//! [`Buffer`]: crate::buffer::Buffer
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::buffer::Location;
+use crate::view::Viewport;
+use crate::Move;
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WindowId(usize);
+
+impl WindowId {
+    /// a new id, unique across every tab page
+    fn next() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Window {
+    /// the buffer shown, empty until something is opened
+    pub buffer: PathBuf,
+    pub view: Viewport,
+    /// the cursor, while another window has focus
+    pub loc: Location,
+}
+
+/// Which way a split divides its space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Split {
+    /// windows stacked on top of each other, like `:split`
+    Horizontal,
+    /// windows side by side, like `:vsplit`
+    Vertical,
+}
+
+/// How the windows are arranged
+///
+/// Splits hold their children with a weight each, their share of the split's
+/// space is their weight over the sum of all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Layout {
+    Window(WindowId),
+    Split(Split, Vec<(Layout, f32)>),
+}
+
+/// A window's place on screen, in a unit square
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Windows {
+    layout: Layout,
+    windows: BTreeMap<WindowId, Window>,
+    focus: WindowId,
+}
+
+impl Default for Windows {
+    fn default() -> Self {
+        Self::new(PathBuf::new())
+    }
+}
+
+impl Layout {
+    /// the windows in the layout, top to bottom and left to right
+    pub fn ids(&self) -> Vec<WindowId> {
+        match self {
+            Self::Window(id) => vec![*id],
+            Self::Split(_, children) => children.iter().flat_map(|(c, _)| c.ids()).collect(),
+        }
+    }
+
+    /// where each window goes when the layout fills `area`
+    pub fn rects(&self, area: Rect) -> Vec<(WindowId, Rect)> {
+        match self {
+            Self::Window(id) => vec![(*id, area)],
+            Self::Split(split, children) => {
+                let total: f32 = children.iter().map(|(_, w)| w).sum();
+                let mut offset = 0.0;
+                children
+                    .iter()
+                    .flat_map(|(child, weight)| {
+                        let share = weight / total;
+                        let rect = match split {
+                            Split::Horizontal => Rect {
+                                y: area.y + offset * area.height,
+                                height: share * area.height,
+                                ..area
+                            },
+                            Split::Vertical => Rect {
+                                x: area.x + offset * area.width,
+                                width: share * area.width,
+                                ..area
+                            },
+                        };
+                        offset += share;
+                        child.rects(rect)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// the indices leading to a window, from the root down
+    fn path(&self, id: WindowId) -> Option<Vec<usize>> {
+        match self {
+            Self::Window(w) => (*w == id).then(Vec::new),
+            Self::Split(_, children) => children.iter().enumerate().find_map(|(i, (c, _))| {
+                let mut path = c.path(id)?;
+                path.insert(0, i);
+                Some(path)
+            }),
+        }
+    }
+
+    fn at(&mut self, path: &[usize]) -> &mut Self {
+        match (path.split_first(), self) {
+            (Some((i, rest)), Self::Split(_, children)) => children[*i].0.at(rest),
+            (_, node) => node,
+        }
+    }
+
+    fn equalize(&mut self) {
+        if let Self::Split(_, children) = self {
+            for (child, weight) in children {
+                *weight = 1.0;
+                child.equalize();
+            }
+        }
+    }
+}
+
+impl Windows {
+    /// a single window showing `buffer`
+    pub fn new(buffer: PathBuf) -> Self {
+        let id = WindowId::next();
+        let window = Window {
+            buffer,
+            ..Default::default()
+        };
+        Self {
+            layout: Layout::Window(id),
+            windows: BTreeMap::from([(id, window)]),
+            focus: id,
+        }
+    }
+
+    pub fn focus(&self) -> WindowId {
+        self.focus
+    }
+
+    pub fn set_focus(&mut self, id: WindowId) {
+        if self.windows.contains_key(&id) {
+            self.focus = id;
+        }
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    pub fn get(&self, id: WindowId) -> Option<&Window> {
+        self.windows.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: WindowId) -> Option<&mut Window> {
+        self.windows.get_mut(&id)
+    }
+
+    pub fn focused(&mut self) -> &mut Window {
+        self.windows.entry(self.focus).or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (WindowId, &Window)> {
+        self.windows.iter().map(|(id, w)| (*id, w))
+    }
+
+    /// splits the focused window in two, the new half shows `buffer` or the
+    /// same buffer and gets focus
+    pub fn split(&mut self, split: Split, buffer: Option<PathBuf>) -> WindowId {
+        let id = WindowId::next();
+        let mut window = self.focused().clone();
+        if let Some(buffer) = buffer {
+            window = Window {
+                buffer,
+                ..Default::default()
+            };
+        }
+        self.windows.insert(id, window);
+
+        let path = self.layout.path(self.focus).unwrap_or_default();
+        let (parent, index) = match path.split_last() {
+            Some((index, parent)) => (parent, *index),
+            None => (&path[..], 0),
+        };
+        let old = self.focus;
+        match self.layout.at(parent) {
+            Layout::Split(s, children) if *s == split => {
+                // the new window takes half the space, above or left of the
+                // old one like vim
+                let weight = children[index].1 / 2.0;
+                children[index].1 = weight;
+                children.insert(index, (Layout::Window(id), weight));
+            }
+            node => {
+                let leaf = node.at(&path[parent.len()..]);
+                *leaf = Layout::Split(
+                    split,
+                    vec![(Layout::Window(id), 1.0), (Layout::Window(old), 1.0)],
+                );
+            }
+        }
+        self.focus = id;
+        id
+    }
+
+    /// closes a window, giving its space to a neighbour which gets focus
+    ///
+    /// returns false for the last window, which can't be closed.
+    pub fn close(&mut self, id: WindowId) -> bool {
+        if self.windows.len() <= 1 || !self.windows.contains_key(&id) {
+            return false;
+        }
+        let Some((index, parent)) = self.layout.path(id).and_then(|path| {
+            let (index, parent) = path.split_last()?;
+            Some((*index, parent.to_vec()))
+        }) else {
+            return false;
+        };
+        let node = self.layout.at(&parent);
+        let Layout::Split(_, children) = node else {
+            return false;
+        };
+        let (_, weight) = children.remove(index);
+        let heir = index.min(children.len() - 1);
+        children[heir].1 += weight;
+        let heir = children[heir].0.ids()[0];
+        if children.len() == 1 {
+            let (only, _) = children.remove(0);
+            *node = only;
+        }
+        self.windows.remove(&id);
+        if self.focus == id {
+            self.focus = heir;
+        }
+        true
+    }
+
+    /// closes every window but the focused one
+    pub fn only(&mut self) {
+        self.layout = Layout::Window(self.focus);
+        let focus = self.focus;
+        self.windows.retain(|id, _| *id == focus);
+    }
+
+    /// the window next to the focused one in a direction, the one closest
+    /// and overlapping it most
+    pub fn neighbor(&self, towards: Move) -> Option<WindowId> {
+        let rects = self.layout.rects(Rect {
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 1.0,
+        });
+        let (_, cur) = *rects.iter().find(|(id, _)| *id == self.focus)?;
+        const EPS: f32 = 1e-4;
+        let overlap =
+            |a: f32, a_len: f32, b: f32, b_len: f32| (a + a_len).min(b + b_len) - a.max(b);
+        rects
+            .iter()
+            .filter(|(id, _)| *id != self.focus)
+            .filter_map(|(id, r)| {
+                let (gap, shared) = match towards {
+                    Move::Left => (
+                        cur.x - (r.x + r.width),
+                        overlap(cur.y, cur.height, r.y, r.height),
+                    ),
+                    Move::Right => (
+                        r.x - (cur.x + cur.width),
+                        overlap(cur.y, cur.height, r.y, r.height),
+                    ),
+                    Move::Up => (
+                        cur.y - (r.y + r.height),
+                        overlap(cur.x, cur.width, r.x, r.width),
+                    ),
+                    Move::Down => (
+                        r.y - (cur.y + cur.height),
+                        overlap(cur.x, cur.width, r.x, r.width),
+                    ),
+                };
+                (gap > -EPS && shared > EPS).then_some((*id, gap, shared))
+            })
+            .min_by(|a, b| {
+                a.1.partial_cmp(&b.1)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then(b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal))
+            })
+            .map(|(id, ..)| id)
+    }
+
+    /// the window `by` places after the focused one, wrapping around
+    pub fn cycle(&self, by: isize) -> WindowId {
+        let ids = self.layout.ids();
+        let at = ids.iter().position(|id| *id == self.focus).unwrap_or(0);
+        ids[(at as isize + by).rem_euclid(ids.len() as isize) as usize]
+    }
+
+    /// grows the focused window by `by` twentieths of the nearest split going
+    /// the right way, or shrinks it when negative
+    pub fn resize(&mut self, split: Split, by: i32) {
+        let Some(path) = self.layout.path(self.focus) else {
+            return;
+        };
+        for depth in (0..path.len()).rev() {
+            if let Layout::Split(s, children) = self.layout.at(&path[..depth]) {
+                if *s != split {
+                    continue;
+                }
+                let total: f32 = children.iter().map(|(_, w)| w).sum();
+                let step = total / 20.0;
+                let weight = &mut children[path[depth]].1;
+                *weight = (*weight + by as f32 * step).max(step);
+                return;
+            }
+        }
+    }
+
+    /// gives every window the same share of its split
+    pub fn equalize(&mut self) {
+        self.layout.equalize();
+    }
+
+    /// moves the windows in the focused window's split along by one, the
+    /// last becoming the first, or the other way around
+    pub fn rotate(&mut self, forward: bool) {
+        let Some(path) = self.layout.path(self.focus) else {
+            return;
+        };
+        let Some((_, parent)) = path.split_last() else {
+            return;
+        };
+        if let Layout::Split(_, children) = self.layout.at(parent) {
+            match forward {
+                true => children.rotate_right(1),
+                false => children.rotate_left(1),
+            }
+        }
+    }
+}
+
+/// What the tab bar shows for a tab page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabPage {
+    /// the file name of the buffer in the focused window
+    pub name: String,
+    pub modified: bool,
+    /// whether it's the tab page on screen
+    pub current: bool,
+}
+
+/// Tab pages, each with its own windows
+#[derive(Debug, Clone)]
+pub struct Tabs {
+    pages: Vec<Windows>,
+    current: usize,
+}
+
+impl Default for Tabs {
+    fn default() -> Self {
+        Self {
+            pages: vec![Windows::default()],
+            current: 0,
+        }
+    }
+}
+
+impl Tabs {
+    /// the windows of the tab page on screen
+    pub fn current(&self) -> &Windows {
+        &self.pages[self.current]
+    }
+
+    pub fn current_mut(&mut self) -> &mut Windows {
+        &mut self.pages[self.current]
+    }
+
+    /// where the tab page on screen is, counting from 0
+    pub fn index(&self) -> usize {
+        self.current
+    }
+
+    pub fn pages(&self) -> &[Windows] {
+        &self.pages
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// adds a tab page after the current one and goes to it
+    pub fn open(&mut self, windows: Windows) {
+        self.current += 1;
+        self.pages.insert(self.current, windows);
+    }
+
+    /// closes the current tab page and goes to the one after it, or before
+    /// it when it was the last
+    ///
+    /// returns false for the last tab page, which can't be closed.
+    pub fn close(&mut self) -> bool {
+        if self.pages.len() <= 1 {
+            return false;
+        }
+        self.pages.remove(self.current);
+        self.current = self.current.min(self.pages.len() - 1);
+        true
+    }
+
+    /// goes to the tab page `by` places along, wrapping around
+    pub fn cycle(&mut self, by: isize) {
+        let len = self.pages.len() as isize;
+        self.current = (self.current as isize + by).rem_euclid(len) as usize;
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.pages.len() {
+            self.current = index;
+        }
+    }
+
+    /// moves the current tab page to `index`, or the end past it
+    pub fn shift(&mut self, index: usize) {
+        let page = self.pages.remove(self.current);
+        self.current = index.min(self.pages.len());
+        self.pages.insert(self.current, page);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::window::{Layout, Split, WindowId, Windows};
+use crate::Move;
+
+/// three windows, one on the left and two stacked on the right
+///
+/// returns them as left, top right and bottom right.
+fn three() -> (Windows, [WindowId; 3]) {
+    let mut windows = Windows::default();
+    let right = windows.focus();
+    let left = windows.split(Split::Vertical, None);
+    windows.set_focus(right);
+    let top = windows.split(Split::Horizontal, None);
+    (windows, [left, top, right])
+}
+
+#[test]
+fn split_and_close() {
+    let mut windows = Windows::default();
+    let first = windows.focus();
+    let second = windows.split(Split::Horizontal, None);
+    assert_eq!(windows.focus(), second);
+    // the new window goes above, and a split the same way joins the parent
+    let third = windows.split(Split::Horizontal, None);
+    assert_eq!(windows.layout().ids(), [third, second, first]);
+    assert!(matches!(windows.layout(), Layout::Split(Split::Horizontal, c) if c.len() == 3));
+
+    assert!(windows.close(third));
+    assert_eq!(windows.focus(), second);
+    assert!(windows.close(second));
+    // a split left with one window is replaced by it
+    assert_eq!(windows.layout(), &Layout::Window(first));
+    assert!(!windows.close(first));
+}
+
+#[test]
+fn neighbors() {
+    let (mut windows, [left, top, bottom]) = three();
+    windows.set_focus(left);
+    // the closest window to the right shares the most of the edge
+    assert_eq!(windows.neighbor(Move::Right), Some(top));
+    assert_eq!(windows.neighbor(Move::Up), None);
+    windows.set_focus(bottom);
+    assert_eq!(windows.neighbor(Move::Up), Some(top));
+    assert_eq!(windows.neighbor(Move::Left), Some(left));
+    assert_eq!(windows.neighbor(Move::Down), None);
+
+    assert_eq!(windows.cycle(1), left);
+    assert_eq!(windows.cycle(-1), top);
+}
+
+#[test]
+fn resize_rotate_and_only() {
+    let (mut windows, [left, top, bottom]) = three();
+    windows.set_focus(top);
+    // growing sideways resizes the column the window is in
+    windows.resize(Split::Vertical, 2);
+    let Layout::Split(Split::Vertical, children) = windows.layout() else {
+        panic!("expected a vertical split");
+    };
+    assert!(children[1].1 > children[0].1);
+
+    windows.rotate(true);
+    assert_eq!(windows.layout().ids(), [left, bottom, top]);
+    windows.rotate(false);
+    assert_eq!(windows.layout().ids(), [left, top, bottom]);
+
+    windows.only();
+    assert_eq!(windows.layout(), &Layout::Window(top));
+    assert_eq!(windows.len(), 1);
+}
@@ -0,0 +1,490 @@
+//! Talking to a language server
+//!
+//! A [`Client`] runs two tasks per server: one writing out what's queued for
+//! it and one reading what comes back, handing responses to the requests
+//! waiting on them, answering the server's own requests and queueing its
+//! notifications for [`Client::notification`].
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use lsp_types::notification;
+use lsp_types::request::{self, Request as _};
+use lsp_types::{
+    ClientCapabilities, ClientInfo, CompletionItem, CompletionParams, CompletionResponse,
+    DocumentFormattingParams, FormattingOptions, GeneralClientCapabilities, GotoDefinitionResponse,
+    HoverClientCapabilities, HoverContents, HoverParams, InitializeParams, InitializedParams,
+    Location, MarkedString, MarkupKind, Position, PositionEncodingKind, ReferenceContext,
+    ReferenceParams, RenameParams, ServerCapabilities, TextDocumentClientCapabilities,
+    TextDocumentIdentifier, TextDocumentPositionParams, TextDocumentSyncCapability,
+    TextDocumentSyncClientCapabilities, TextDocumentSyncKind, TextDocumentSyncSaveOptions,
+    TextEdit, Url, WorkDoneProgressParams, WorkspaceClientCapabilities, WorkspaceEdit,
+    WorkspaceEditClientCapabilities, WorkspaceFolder,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncWrite, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::document::Encoding;
+use crate::servers::ServerConfig;
+use crate::{transport, LspError};
+
+/// the requests waiting on a response by id, none once the server is gone
+type Pending = Mutex<Option<HashMap<i64, oneshot::Sender<Result<Value, LspError>>>>>;
+
+/// Something the server said without being asked, like diagnostics
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub method: String,
+    pub params: Value,
+}
+
+impl Notification {
+    /// the params, when it's an `N`
+    pub fn params<N: notification::Notification>(&self) -> Option<N::Params> {
+        if self.method != N::METHOD {
+            return None;
+        }
+        serde_json::from_value(self.params.clone()).ok()
+    }
+}
+
+/// A running language server, cheap to clone
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    outgoing: mpsc::UnboundedSender<Value>,
+    pending: Arc<Pending>,
+    next_id: AtomicI64,
+    notifications: tokio::sync::Mutex<mpsc::UnboundedReceiver<Notification>>,
+    capabilities: OnceLock<ServerCapabilities>,
+    /// the server's process, killed along with the client
+    _child: Option<Child>,
+}
+
+impl Client {
+    /// starts the server for `config` in `root` and initializes it
+    pub async fn spawn(config: &ServerConfig, root: &Path) -> Result<Self, LspError> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|error| LspError::Spawn {
+                command: config.command.clone(),
+                error,
+            })?;
+        let (Some(stdout), Some(stdin)) = (child.stdout.take(), child.stdin.take()) else {
+            return Err(LspError::Exited);
+        };
+        let client = Self::start(stdout, stdin, Some(child));
+        client.initialize(root).await?;
+        Ok(client)
+    }
+
+    /// talks to a server over `reader` and `writer`, which must be done inside
+    /// a tokio runtime. the server still has to be initialized
+    pub fn connect(
+        reader: impl AsyncRead + Unpin + Send + 'static,
+        writer: impl AsyncWrite + Unpin + Send + 'static,
+    ) -> Self {
+        Self::start(reader, writer, None)
+    }
+
+    fn start(
+        reader: impl AsyncRead + Unpin + Send + 'static,
+        mut writer: impl AsyncWrite + Unpin + Send + 'static,
+        child: Option<Child>,
+    ) -> Self {
+        let (outgoing, mut queue) = mpsc::unbounded_channel::<Value>();
+        let (notify, notifications) = mpsc::unbounded_channel();
+        let pending = Arc::new(Mutex::new(Some(HashMap::new())));
+        tokio::spawn(async move {
+            while let Some(message) = queue.recv().await {
+                if transport::write(&mut writer, &message).await.is_err() {
+                    break;
+                }
+            }
+        });
+        tokio::spawn(read_loop(
+            BufReader::new(reader),
+            pending.clone(),
+            outgoing.clone(),
+            notify,
+        ));
+        Self {
+            inner: Arc::new(Inner {
+                outgoing,
+                pending,
+                next_id: AtomicI64::new(0),
+                notifications: tokio::sync::Mutex::new(notifications),
+                capabilities: OnceLock::new(),
+                _child: child,
+            }),
+        }
+    }
+
+    /// the handshake every server needs before anything else, telling it
+    /// what evie can do and learning what it can
+    pub async fn initialize(&self, root: &Path) -> Result<(), LspError> {
+        let uri = Url::from_directory_path(root)
+            .map_err(|_| LspError::Protocol(format!("bad root {}", root.display())))?;
+        let name = root
+            .file_name()
+            .map_or_else(|| uri.to_string(), |name| name.to_string_lossy().into());
+        #[allow(deprecated)]
+        let params = InitializeParams {
+            process_id: Some(std::process::id()),
+            root_uri: Some(uri.clone()),
+            workspace_folders: Some(vec![WorkspaceFolder { uri, name }]),
+            capabilities: capabilities(),
+            client_info: Some(ClientInfo {
+                name: "evie".into(),
+                version: Some(env!("CARGO_PKG_VERSION").into()),
+            }),
+            ..Default::default()
+        };
+        let result = self.request::<request::Initialize>(params).await?;
+        let _ = self.inner.capabilities.set(result.capabilities);
+        self.notify::<notification::Initialized>(InitializedParams {})
+    }
+
+    /// what the server said it can do, none before it's initialized
+    pub fn capabilities(&self) -> Option<&ServerCapabilities> {
+        self.inner.capabilities.get()
+    }
+
+    /// what the server counts columns in
+    pub fn encoding(&self) -> Encoding {
+        Encoding::from_kind(
+            self.capabilities()
+                .and_then(|c| c.position_encoding.as_ref()),
+        )
+    }
+
+    /// how the server wants to hear about changes to documents
+    pub fn sync(&self) -> TextDocumentSyncKind {
+        match self
+            .capabilities()
+            .and_then(|c| c.text_document_sync.as_ref())
+        {
+            Some(TextDocumentSyncCapability::Kind(kind)) => *kind,
+            Some(TextDocumentSyncCapability::Options(options)) => {
+                options.change.unwrap_or(TextDocumentSyncKind::NONE)
+            }
+            None => TextDocumentSyncKind::NONE,
+        }
+    }
+
+    /// whether the server wants to hear of documents being written, and if
+    /// so whether with their text
+    pub fn save(&self) -> Option<bool> {
+        let Some(TextDocumentSyncCapability::Options(options)) = self
+            .capabilities()
+            .and_then(|c| c.text_document_sync.as_ref())
+        else {
+            return None;
+        };
+        match options.save.as_ref()? {
+            TextDocumentSyncSaveOptions::Supported(save) => save.then_some(false),
+            TextDocumentSyncSaveOptions::SaveOptions(options) => {
+                Some(options.include_text.unwrap_or(false))
+            }
+        }
+    }
+
+    /// sends a request and waits for its response
+    pub async fn request<R>(&self, params: R::Params) -> Result<R::Result, LspError>
+    where
+        R: request::Request,
+        R::Params: Serialize,
+        R::Result: DeserializeOwned,
+    {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let (send, receive) = oneshot::channel();
+        match self
+            .inner
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_mut()
+        {
+            Some(pending) => pending.insert(id, send),
+            None => return Err(LspError::Exited),
+        };
+        self.send(json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": R::METHOD,
+            "params": params,
+        }))?;
+        let result = receive.await.map_err(|_| LspError::Exited)??;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// sends a notification, which has no response
+    pub fn notify<N>(&self, params: N::Params) -> Result<(), LspError>
+    where
+        N: notification::Notification,
+        N::Params: Serialize,
+    {
+        self.send(json!({
+            "jsonrpc": "2.0",
+            "method": N::METHOD,
+            "params": params,
+        }))
+    }
+
+    fn send(&self, message: Value) -> Result<(), LspError> {
+        self.inner
+            .outgoing
+            .send(message)
+            .map_err(|_| LspError::Exited)
+    }
+
+    /// waits for the server's next notification, none once it's gone
+    pub async fn notification(&self) -> Option<Notification> {
+        self.inner.notifications.lock().await.recv().await
+    }
+
+    /// the documentation of what's at `position`, as plain text or markdown
+    pub async fn hover(
+        &self,
+        document: TextDocumentIdentifier,
+        position: Position,
+    ) -> Result<Option<String>, LspError> {
+        let params = HoverParams {
+            text_document_position_params: TextDocumentPositionParams::new(document, position),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        let hover = self.request::<request::HoverRequest>(params).await?;
+        let marked = |marked: MarkedString| match marked {
+            MarkedString::String(text) => text,
+            MarkedString::LanguageString(code) => code.value,
+        };
+        Ok(hover.map(|hover| match hover.contents {
+            HoverContents::Scalar(text) => marked(text),
+            HoverContents::Array(texts) => texts
+                .into_iter()
+                .map(marked)
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+            HoverContents::Markup(markup) => markup.value,
+        }))
+    }
+
+    /// where what's at `position` is defined
+    pub async fn definition(
+        &self,
+        document: TextDocumentIdentifier,
+        position: Position,
+    ) -> Result<Vec<Location>, LspError> {
+        let params = lsp_types::GotoDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams::new(document, position),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: Default::default(),
+        };
+        let response = self.request::<request::GotoDefinition>(params).await?;
+        Ok(match response {
+            None => Vec::new(),
+            Some(GotoDefinitionResponse::Scalar(location)) => vec![location],
+            Some(GotoDefinitionResponse::Array(locations)) => locations,
+            Some(GotoDefinitionResponse::Link(links)) => links
+                .into_iter()
+                .map(|link| Location::new(link.target_uri, link.target_selection_range))
+                .collect(),
+        })
+    }
+
+    /// everywhere what's at `position` is used, with its declaration
+    pub async fn references(
+        &self,
+        document: TextDocumentIdentifier,
+        position: Position,
+    ) -> Result<Vec<Location>, LspError> {
+        let params = ReferenceParams {
+            text_document_position: TextDocumentPositionParams::new(document, position),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: Default::default(),
+            context: ReferenceContext {
+                include_declaration: true,
+            },
+        };
+        let locations = self.request::<request::References>(params).await?;
+        Ok(locations.unwrap_or_default())
+    }
+
+    /// the edits renaming what's at `position` everywhere it's used
+    pub async fn rename(
+        &self,
+        document: TextDocumentIdentifier,
+        position: Position,
+        new_name: String,
+    ) -> Result<Option<WorkspaceEdit>, LspError> {
+        let params = RenameParams {
+            text_document_position: TextDocumentPositionParams::new(document, position),
+            new_name,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        self.request::<request::Rename>(params).await
+    }
+
+    /// what could be typed at `position`
+    pub async fn completion(
+        &self,
+        document: TextDocumentIdentifier,
+        position: Position,
+    ) -> Result<Vec<CompletionItem>, LspError> {
+        let params = CompletionParams {
+            text_document_position: TextDocumentPositionParams::new(document, position),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: Default::default(),
+            context: None,
+        };
+        let response = self.request::<request::Completion>(params).await?;
+        Ok(match response {
+            None => Vec::new(),
+            Some(CompletionResponse::Array(items)) => items,
+            Some(CompletionResponse::List(list)) => list.items,
+        })
+    }
+
+    /// the edits formatting a whole document
+    pub async fn format(
+        &self,
+        document: TextDocumentIdentifier,
+        options: FormattingOptions,
+    ) -> Result<Vec<TextEdit>, LspError> {
+        let params = DocumentFormattingParams {
+            text_document: document,
+            options,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        let edits = self.request::<request::Formatting>(params).await?;
+        Ok(edits.unwrap_or_default())
+    }
+
+    /// asks the server to shut down and exit
+    pub async fn shutdown(&self) -> Result<(), LspError> {
+        self.request::<request::Shutdown>(()).await?;
+        self.notify::<notification::Exit>(())
+    }
+}
+
+/// what evie can do, see [`Client::initialize`]
+fn capabilities() -> ClientCapabilities {
+    ClientCapabilities {
+        general: Some(GeneralClientCapabilities {
+            position_encodings: Some(vec![
+                PositionEncodingKind::UTF8,
+                PositionEncodingKind::UTF16,
+            ]),
+            ..Default::default()
+        }),
+        text_document: Some(TextDocumentClientCapabilities {
+            synchronization: Some(TextDocumentSyncClientCapabilities {
+                did_save: Some(true),
+                ..Default::default()
+            }),
+            hover: Some(HoverClientCapabilities {
+                content_format: Some(vec![MarkupKind::PlainText, MarkupKind::Markdown]),
+                ..Default::default()
+            }),
+            publish_diagnostics: Some(Default::default()),
+            completion: Some(Default::default()),
+            ..Default::default()
+        }),
+        // edits with the version they were worked out on, see
+        // `WorkspaceEdit::document_changes`
+        workspace: Some(WorkspaceClientCapabilities {
+            workspace_edit: Some(WorkspaceEditClientCapabilities {
+                document_changes: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// reads until the server goes away, then fails whatever is still waiting
+async fn read_loop(
+    mut reader: BufReader<impl AsyncRead + Unpin>,
+    pending: Arc<Pending>,
+    outgoing: mpsc::UnboundedSender<Value>,
+    notify: mpsc::UnboundedSender<Notification>,
+) {
+    while let Ok(Some(mut message)) = transport::read(&mut reader).await {
+        let id = message.get("id").cloned();
+        let params = message.get_mut("params").map(Value::take);
+        let method = message.get("method").and_then(Value::as_str);
+        match (method, id) {
+            (Some(method), Some(id)) => {
+                let mut reply = answer(method, params.unwrap_or_default());
+                reply["jsonrpc"] = "2.0".into();
+                reply["id"] = id;
+                let _ = outgoing.send(reply);
+            }
+            (Some(method), None) => {
+                let _ = notify.send(Notification {
+                    method: method.to_owned(),
+                    params: params.unwrap_or_default(),
+                });
+            }
+            (None, Some(id)) => {
+                let waiting = pending
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .as_mut()
+                    .and_then(|pending| pending.remove(&id.as_i64()?));
+                let result = match message.get_mut("error") {
+                    Some(error) => Err(LspError::Server {
+                        code: error["code"].as_i64().unwrap_or_default(),
+                        message: error["message"].as_str().unwrap_or_default().to_owned(),
+                    }),
+                    None => Ok(message
+                        .get_mut("result")
+                        .map(Value::take)
+                        .unwrap_or_default()),
+                };
+                if let Some(waiting) = waiting {
+                    let _ = waiting.send(result);
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    let waiting = pending.lock().unwrap_or_else(|e| e.into_inner()).take();
+    for (_, waiting) in waiting.into_iter().flatten() {
+        let _ = waiting.send(Err(LspError::Exited));
+    }
+}
+
+/// the response to a request from the server, the body without its id
+fn answer(method: &str, params: Value) -> Value {
+    match method {
+        request::WorkspaceConfiguration::METHOD => {
+            let items = params["items"].as_array().map_or(0, Vec::len);
+            json!({ "result": vec![Value::Null; items] })
+        }
+        request::RegisterCapability::METHOD
+        | request::UnregisterCapability::METHOD
+        | request::WorkDoneProgressCreate::METHOD => json!({ "result": null }),
+        _ => json!({
+            "error": { "code": -32601, "message": format!("{method} is not supported") },
+        }),
+    }
+}
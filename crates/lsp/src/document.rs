@@ -0,0 +1,192 @@
+//! Keeping a server's copy of a buffer in step with it
+//!
+//! Edits come in as byte ranges of the text as it was before each of them,
+//! but servers want line and column positions, with columns counted in the
+//! encoding agreed on at startup. A [`Document`] keeps its own copy of the
+//! text, which is cheap as ropes share their nodes, to work those positions
+//! out against.
+
+use std::ops::Range;
+
+use lsp_types::{
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    DidSaveTextDocumentParams, Position, PositionEncodingKind, TextDocumentContentChangeEvent,
+    TextDocumentIdentifier, TextDocumentItem, TextEdit, Url, VersionedTextDocumentIdentifier,
+};
+use ropey::Rope;
+
+/// What columns are counted in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    /// what servers assume unless told otherwise
+    #[default]
+    Utf16,
+}
+
+impl Encoding {
+    /// the encoding a server picked, UTF-16 for any other
+    pub fn from_kind(kind: Option<&PositionEncodingKind>) -> Self {
+        match kind {
+            Some(kind) if *kind == PositionEncodingKind::UTF8 => Self::Utf8,
+            _ => Self::Utf16,
+        }
+    }
+}
+
+/// the position of byte `at` in `text`
+pub fn position(text: &Rope, at: usize, encoding: Encoding) -> Position {
+    let at = at.min(text.len_bytes());
+    let line = text.byte_to_line(at);
+    let start = text.line_to_byte(line);
+    let character = match encoding {
+        Encoding::Utf8 => at - start,
+        Encoding::Utf16 => text
+            .byte_slice(start..at)
+            .chars()
+            .map(char::len_utf16)
+            .sum(),
+    };
+    Position::new(line as u32, character as u32)
+}
+
+/// the byte at `position` in `text`, the end of the line for a column past
+/// it and the end of the text for a line past it
+pub fn offset(text: &Rope, position: Position, encoding: Encoding) -> usize {
+    let line = position.line as usize;
+    if line >= text.len_lines() {
+        return text.len_bytes();
+    }
+    let start = text.line_to_byte(line);
+    let mut column = 0;
+    let mut at = start;
+    for c in text.line(line).chars() {
+        if column >= position.character as usize || c == '\n' || c == '\r' {
+            break;
+        }
+        column += match encoding {
+            Encoding::Utf8 => c.len_utf8(),
+            Encoding::Utf16 => c.len_utf16(),
+        };
+        at += c.len_utf8();
+    }
+    at
+}
+
+/// A buffer as its server knows it
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub uri: Url,
+    /// bumped with every change sent
+    pub version: i32,
+    text: Rope,
+    encoding: Encoding,
+}
+
+impl Document {
+    pub fn new(uri: Url, text: Rope, encoding: Encoding) -> Self {
+        Self {
+            uri,
+            version: 0,
+            text,
+            encoding,
+        }
+    }
+
+    /// the text as the server has it
+    pub fn text(&self) -> &Rope {
+        &self.text
+    }
+
+    pub fn id(&self) -> TextDocumentIdentifier {
+        TextDocumentIdentifier::new(self.uri.clone())
+    }
+
+    /// tells the server about the document
+    pub fn open(&self, language_id: &str) -> DidOpenTextDocumentParams {
+        DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(
+                self.uri.clone(),
+                language_id.to_owned(),
+                self.version,
+                self.text.to_string(),
+            ),
+        }
+    }
+
+    /// tells the server the document was written, with its text when `text`
+    pub fn save(&self, text: bool) -> DidSaveTextDocumentParams {
+        DidSaveTextDocumentParams {
+            text_document: self.id(),
+            text: text.then(|| self.text.to_string()),
+        }
+    }
+
+    /// tells the server the document is no longer open
+    pub fn close(&self) -> DidCloseTextDocumentParams {
+        DidCloseTextDocumentParams {
+            text_document: self.id(),
+        }
+    }
+
+    /// takes in edits, each bytes `range` of the text before it becoming
+    /// `text`, for sending on as they are, or as the whole text when `full`.
+    /// none when there are no edits
+    pub fn change(
+        &mut self,
+        edits: impl IntoIterator<Item = (Range<usize>, String)>,
+        full: bool,
+    ) -> Option<DidChangeTextDocumentParams> {
+        let mut changes = Vec::new();
+        for (range, text) in edits {
+            let start = position(&self.text, range.start, self.encoding);
+            let end = position(&self.text, range.end, self.encoding);
+            let start_char = self
+                .text
+                .byte_to_char(range.start.min(self.text.len_bytes()));
+            let end_char = self.text.byte_to_char(range.end.min(self.text.len_bytes()));
+            self.text.remove(start_char..end_char);
+            self.text.insert(start_char, &text);
+            changes.push(TextDocumentContentChangeEvent {
+                range: Some(lsp_types::Range::new(start, end)),
+                range_length: None,
+                text,
+            });
+        }
+        if changes.is_empty() {
+            return None;
+        }
+        if full {
+            changes = vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: self.text.to_string(),
+            }];
+        }
+        self.version += 1;
+        Some(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier::new(self.uri.clone(), self.version),
+            content_changes: changes,
+        })
+    }
+
+    pub fn position(&self, at: usize) -> Position {
+        position(&self.text, at, self.encoding)
+    }
+
+    pub fn offset(&self, position: Position) -> usize {
+        offset(&self.text, position, self.encoding)
+    }
+
+    /// a server's edits as byte ranges and what replaces them
+    pub fn edits(&self, edits: &[TextEdit]) -> Vec<(Range<usize>, String)> {
+        edits
+            .iter()
+            .map(|edit| {
+                let start = self.offset(edit.range.start);
+                let end = self.offset(edit.range.end).max(start);
+                (start..end, edit.new_text.clone())
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,37 @@
+//! A language server client
+//!
+//! Servers run as child processes speaking JSON-RPC over stdio, one per
+//! filetype and project, see [`servers`]. The editor keeps each buffer a
+//! server follows in a [`document::Document`], which turns its edits into
+//! incremental changes, and asks the [`client::Client`] for hovers,
+//! definitions, references, renames and formatting at positions in it.
+
+use std::io;
+
+use thiserror::Error;
+
+pub mod client;
+pub mod document;
+pub mod servers;
+pub mod transport;
+
+#[cfg(test)]
+mod test;
+
+pub use lsp_types;
+
+#[derive(Debug, Error)]
+pub enum LspError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Cannot start {command}: {error}")]
+    Spawn { command: String, error: io::Error },
+    #[error("Language server error: {0}")]
+    Protocol(String),
+    #[error("{message}")]
+    Server { code: i64, message: String },
+    #[error("Language server exited")]
+    Exited,
+}
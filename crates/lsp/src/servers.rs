@@ -0,0 +1,79 @@
+//! Which server a file is edited with
+//!
+//! Each filetype with a server gets one per project, the project being the
+//! outermost directory above the file with one of the filetype's root
+//! markers in it, like the workspace's `Cargo.toml` for Rust, not looking
+//! past a `.git`.
+
+use std::path::{Path, PathBuf};
+
+/// How to run the server for a filetype
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    pub command: String,
+    pub args: Vec<String>,
+    /// what the server calls the language
+    pub language_id: String,
+    /// files marking the root of a project
+    pub roots: Vec<String>,
+}
+
+impl ServerConfig {
+    pub fn new(command: &str, language_id: &str) -> Self {
+        Self {
+            command: command.to_owned(),
+            args: Vec::new(),
+            language_id: language_id.to_owned(),
+            roots: Vec::new(),
+        }
+    }
+
+    pub fn with_args(self, args: &[&str]) -> Self {
+        Self {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            ..self
+        }
+    }
+
+    pub fn with_roots(self, roots: &[&str]) -> Self {
+        Self {
+            roots: roots.iter().map(|root| root.to_string()).collect(),
+            ..self
+        }
+    }
+}
+
+/// the server for a filetype, none for those without one
+pub fn config(filetype: &str) -> Option<ServerConfig> {
+    let git = &[".git"];
+    let config = match filetype {
+        "rust" => ServerConfig::new("rust-analyzer", "rust").with_roots(&["Cargo.toml", ".git"]),
+        "toml" => ServerConfig::new("taplo", "toml")
+            .with_args(&["lsp", "stdio"])
+            .with_roots(git),
+        "markdown" => ServerConfig::new("marksman", "markdown")
+            .with_args(&["server"])
+            .with_roots(git),
+        "sh" => ServerConfig::new("bash-language-server", "shellscript")
+            .with_args(&["start"])
+            .with_roots(git),
+        _ => return None,
+    };
+    Some(config)
+}
+
+/// the project `file` is in, the outermost directory with a root marker
+/// going up until a `.git`, or the file's own directory without any
+pub fn root(file: &Path, markers: &[String]) -> PathBuf {
+    let dir = file.parent().unwrap_or(file);
+    let mut root = None;
+    for ancestor in dir.ancestors() {
+        if markers.iter().any(|marker| ancestor.join(marker).exists()) {
+            root = Some(ancestor);
+        }
+        if ancestor.join(".git").exists() {
+            break;
+        }
+    }
+    root.unwrap_or(dir).to_owned()
+}
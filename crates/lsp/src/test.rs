@@ -0,0 +1,212 @@
+use std::path::Path;
+
+use lsp_types::notification::{DidChangeTextDocument, DidOpenTextDocument, PublishDiagnostics};
+use lsp_types::{FormattingOptions, Position, TextDocumentSyncKind, Url};
+use ropey::Rope;
+use serde_json::{json, Value};
+use tokio::io::{BufReader, DuplexStream};
+
+use crate::client::Client;
+use crate::document::{self, Document, Encoding};
+use crate::servers::{self, ServerConfig};
+use crate::{transport, LspError};
+
+/// a server over `io` that keeps its own copy of the one document it's
+/// given, answering hovers with the line asked about
+async fn fake_server(io: DuplexStream) {
+    let (reader, mut writer) = tokio::io::split(io);
+    let mut reader = BufReader::new(reader);
+    let mut text = Rope::new();
+    let range = |line, start, end| {
+        json!({
+            "start": { "line": line, "character": start },
+            "end": { "line": line, "character": end },
+        })
+    };
+    while let Ok(Some(message)) = transport::read(&mut reader).await {
+        let params = &message["params"];
+        let uri = &params["textDocument"]["uri"];
+        let Some(method) = message["method"].as_str() else {
+            // the client's answer to asking for configuration
+            assert_eq!(message["result"], json!([null]));
+            continue;
+        };
+        let result = match method {
+            "initialize" => {
+                let ask = json!({
+                    "jsonrpc": "2.0",
+                    "id": "config",
+                    "method": "workspace/configuration",
+                    "params": { "items": [{ "section": "fake" }] },
+                });
+                transport::write(&mut writer, &ask).await.unwrap();
+                json!({ "capabilities": {
+                    "textDocumentSync": { "change": 2, "save": { "includeText": false } },
+                    "hoverProvider": true,
+                } })
+            }
+            "textDocument/didOpen" => {
+                text = Rope::from_str(params["textDocument"]["text"].as_str().unwrap());
+                continue;
+            }
+            "textDocument/didChange" => {
+                for change in params["contentChanges"].as_array().unwrap() {
+                    let range: lsp_types::Range =
+                        serde_json::from_value(change["range"].clone()).unwrap();
+                    let start = document::offset(&text, range.start, Encoding::Utf16);
+                    let end = document::offset(&text, range.end, Encoding::Utf16);
+                    let (start, end) = (text.byte_to_char(start), text.byte_to_char(end));
+                    text.remove(start..end);
+                    text.insert(start, change["text"].as_str().unwrap());
+                }
+                let diagnostics = json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": { "uri": uri, "diagnostics": [] },
+                });
+                transport::write(&mut writer, &diagnostics).await.unwrap();
+                continue;
+            }
+            "textDocument/hover" => {
+                let line = params["position"]["line"].as_u64().unwrap() as usize;
+                let line = text.line(line).to_string();
+                json!({ "contents": { "kind": "plaintext", "value": line.trim_end() } })
+            }
+            "textDocument/definition" => json!({ "uri": uri, "range": range(0, 3, 7) }),
+            "textDocument/references" => json!([
+                { "uri": uri, "range": range(0, 3, 7) },
+                { "uri": uri, "range": range(1, 8, 12) },
+            ]),
+            "textDocument/rename" => json!({
+                "changes": { uri.as_str().unwrap(): [
+                    { "range": range(0, 3, 7), "newText": params["newName"] },
+                ] },
+            }),
+            "textDocument/completion" => json!({
+                "isIncomplete": false,
+                "items": [
+                    { "label": "main", "kind": 3 },
+                    { "label": "max", "kind": 3, "insertText": "max(" },
+                ],
+            }),
+            "textDocument/formatting" => json!([{ "range": range(1, 0, 4), "newText": "\t" }]),
+            "shutdown" => Value::Null,
+            "exit" => break,
+            _ => continue,
+        };
+        let response = json!({ "jsonrpc": "2.0", "id": message["id"], "result": result });
+        transport::write(&mut writer, &response).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn talks_to_a_fake_server() {
+    let (client_io, server_io) = tokio::io::duplex(1 << 16);
+    let server = tokio::spawn(fake_server(server_io));
+    let (reader, writer) = tokio::io::split(client_io);
+    let client = Client::connect(reader, writer);
+    client.initialize(Path::new("/tmp")).await.unwrap();
+    assert_eq!(client.sync(), TextDocumentSyncKind::INCREMENTAL);
+    assert_eq!(client.save(), Some(false));
+    assert_eq!(client.encoding(), Encoding::Utf16);
+
+    let uri = Url::parse("file:///tmp/main.rs").unwrap();
+    let text = Rope::from_str("fn main() {\n    let 😀 = 1;\n}\n");
+    let mut document = Document::new(uri.clone(), text, client.encoding());
+    client
+        .notify::<DidOpenTextDocument>(document.open("rust"))
+        .unwrap();
+
+    // after the emoji, two UTF-16 units but four bytes
+    let at = "fn main() {\n    let 😀".len();
+    let change = document.change([(at..at, "x".into())], false).unwrap();
+    assert_eq!(change.text_document.version, 1);
+    assert_eq!(
+        change.content_changes[0].range.unwrap().start,
+        Position::new(1, 10)
+    );
+    client.notify::<DidChangeTextDocument>(change).unwrap();
+    let notification = client.notification().await.unwrap();
+    let diagnostics = notification.params::<PublishDiagnostics>().unwrap();
+    assert_eq!(diagnostics.uri, uri);
+    assert!(notification.params::<DidOpenTextDocument>().is_none());
+
+    let hover = client.hover(document.id(), document.position(at)).await;
+    assert_eq!(hover.unwrap().as_deref(), Some("    let 😀x = 1;"));
+
+    let definition = client.definition(document.id(), Position::new(1, 8));
+    let definition = definition.await.unwrap();
+    assert_eq!(document.offset(definition[0].range.start), 3);
+    let references = client.references(document.id(), Position::new(0, 4));
+    assert_eq!(references.await.unwrap().len(), 2);
+
+    let rename = client.rename(document.id(), Position::new(0, 4), "start".into());
+    let edits = rename.await.unwrap().unwrap().changes.unwrap();
+    assert_eq!(document.edits(&edits[&uri]), [(3..7, "start".to_owned())]);
+    let completion = client.completion(document.id(), Position::new(0, 4));
+    let labels: Vec<_> = completion
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|item| item.insert_text.unwrap_or(item.label))
+        .collect();
+    assert_eq!(labels, ["main", "max("]);
+    let options = FormattingOptions {
+        tab_size: 4,
+        insert_spaces: false,
+        ..Default::default()
+    };
+    let format = client.format(document.id(), options).await.unwrap();
+    assert_eq!(document.edits(&format), [(12..16, "\t".to_owned())]);
+
+    client.shutdown().await.unwrap();
+    server.await.unwrap();
+    let hover = client.hover(document.id(), Position::new(0, 0)).await;
+    assert!(matches!(hover, Err(LspError::Exited)));
+}
+
+#[test]
+fn positions_and_roots() {
+    let text = Rope::from_str("aé😀b\nline two\n");
+    let end = "aé😀".len();
+    assert_eq!(
+        document::position(&text, end, Encoding::Utf8),
+        Position::new(0, 7)
+    );
+    assert_eq!(
+        document::position(&text, end, Encoding::Utf16),
+        Position::new(0, 4)
+    );
+    assert_eq!(
+        document::offset(&text, Position::new(0, 4), Encoding::Utf16),
+        end
+    );
+    // columns past the end of a line stop at it, lines past the end at the
+    // end of the text
+    assert_eq!(
+        document::offset(&text, Position::new(0, 99), Encoding::Utf8),
+        end + 1
+    );
+    assert_eq!(
+        document::offset(&text, Position::new(9, 0), Encoding::Utf8),
+        text.len_bytes()
+    );
+
+    let tmp = tempfile::tempdir().unwrap();
+    let project = tmp.path();
+    let file = project.join("crates/one/src/lib.rs");
+    std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+    std::fs::create_dir_all(project.join(".git")).unwrap();
+    std::fs::write(project.join("Cargo.toml"), "").unwrap();
+    std::fs::write(project.join("crates/one/Cargo.toml"), "").unwrap();
+    let rust = servers::config("rust").unwrap();
+    assert_eq!(servers::root(&file, &rust.roots), project);
+    assert_eq!(servers::config("text"), None);
+}
+
+#[tokio::test]
+async fn missing_server_fails_to_spawn() {
+    let config = ServerConfig::new("evie-no-such-server", "none");
+    let error = Client::spawn(&config, Path::new("/")).await.unwrap_err();
+    assert!(matches!(error, LspError::Spawn { .. }));
+}
@@ -0,0 +1,46 @@
+//! Messages framed with a `Content-Length` header, as language servers speak
+//! over stdio
+
+use serde_json::Value;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::LspError;
+
+/// reads the next message, none once the stream ends
+pub async fn read(reader: &mut (impl AsyncBufRead + Unpin)) -> Result<Option<Value>, LspError> {
+    let mut length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        // other headers, like `Content-Type`, don't change anything
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| LspError::Protocol("missing Content-Length".into()))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// writes a message and flushes it
+pub async fn write(
+    writer: &mut (impl AsyncWrite + Unpin),
+    message: &Value,
+) -> Result<(), LspError> {
+    let body = serde_json::to_vec(message)?;
+    let header = format!("Content-Length: {}\r\n\r\n", body.len());
+    writer.write_all(header.as_bytes()).await?;
+    writer.write_all(&body).await?;
+    writer.flush().await?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! A client for an evie listening on a socket, to script it from rust
+
+use std::collections::VecDeque;
+use std::path::Path;
+
+use evie_lsp::transport;
+use serde_json::{json, Value};
+use tokio::io::BufReader;
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixStream;
+
+use crate::RpcError;
+
+#[derive(Debug)]
+pub struct Client {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    next_id: u64,
+    /// notifications that came in while waiting for an answer
+    notifications: VecDeque<Value>,
+}
+
+impl Client {
+    pub async fn connect(path: &Path) -> Result<Self, RpcError> {
+        let (reader, writer) = UnixStream::connect(path).await?.into_split();
+        Ok(Self {
+            reader: BufReader::new(reader),
+            writer,
+            next_id: 0,
+            notifications: VecDeque::new(),
+        })
+    }
+
+    /// sends a request and waits for its answer
+    pub async fn call(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        self.next_id += 1;
+        let id = self.next_id;
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        transport::write(&mut self.writer, &request).await?;
+        loop {
+            let message = self.read().await?;
+            if message.get("id").is_none() {
+                self.notifications.push_back(message);
+                continue;
+            }
+            if message["id"] != id {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                return Err(RpcError::Server {
+                    code: error["code"].as_i64().unwrap_or_default(),
+                    message: error["message"].as_str().unwrap_or_default().to_owned(),
+                });
+            }
+            return Ok(message["result"].clone());
+        }
+    }
+
+    /// the next notification, as `method` and `params`
+    pub async fn notification(&mut self) -> Result<(String, Value), RpcError> {
+        let message = match self.notifications.pop_front() {
+            Some(message) => message,
+            None => loop {
+                let message = self.read().await?;
+                if message.get("id").is_none() {
+                    break message;
+                }
+            },
+        };
+        let method = message["method"].as_str().unwrap_or_default().to_owned();
+        Ok((method, message["params"].clone()))
+    }
+
+    async fn read(&mut self) -> Result<Value, RpcError> {
+        transport::read(&mut self.reader)
+            .await?
+            .ok_or_else(|| RpcError::Io(std::io::ErrorKind::UnexpectedEof.into()))
+    }
+}
@@ -0,0 +1,81 @@
+//! Keys written the way vim maps them, like `ihello<Esc>` or `<C-w>v`
+//!
+//! | notation                        | key                   |
+//! |---------------------------------|-----------------------|
+//! | `<CR>` `<Enter>` `<Return>`     | enter                 |
+//! | `<Esc>`                         | escape                |
+//! | `<Tab>` `<S-Tab>`               | tab, and with shift   |
+//! | `<BS>` `<Del>` `<Insert>`       |                       |
+//! | `<Up>` `<Down>` `<Left>` `<Right>` | the arrows     |
+//! | `<Home>` `<End>` `<PageUp>` `<PageDown>` |              |
+//! | `<F1>` to `<F12>`               |                       |
+//! | `<Space>` `<lt>` `<Bar>` `<Bslash>` | space, `<`, bar and backslash |
+//! | `<C-x>`                         | `x` with control      |
+//!
+//! Names are matched ignoring case. Anything else between `<` and `>` is
+//! taken as it's written, like vim does.
+
+pub use evie_tui::{KeyAction, Named};
+
+#[cfg(test)]
+mod test;
+
+/// the keys written in `notation`
+pub fn parse(notation: &str) -> Vec<KeyAction> {
+    let mut keys = Vec::new();
+    let mut rest = notation;
+    while let Some(c) = rest.chars().next() {
+        let special = rest
+            .strip_prefix('<')
+            .and_then(|inner| inner.split_once('>'))
+            .and_then(|(name, after)| Some((special(name)?, after)));
+        match special {
+            Some((key, after)) => {
+                keys.push(key);
+                rest = after;
+            }
+            None => {
+                keys.push(KeyAction::Letter(c));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    keys
+}
+
+fn special(name: &str) -> Option<KeyAction> {
+    let lower = name.to_ascii_lowercase();
+    if let Some(key) = lower.strip_prefix("c-") {
+        let mut chars = key.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(KeyAction::Ctrl(c)),
+            _ => None,
+        };
+    }
+    let named = match lower.as_str() {
+        "cr" | "enter" | "return" => Named::Enter,
+        "esc" => Named::Escape,
+        "tab" => Named::Tab,
+        "s-tab" => return Some(KeyAction::Shift(Named::Tab)),
+        "bs" => Named::Backspace,
+        "del" => Named::Delete,
+        "insert" => Named::Insert,
+        "up" => Named::ArrowUp,
+        "down" => Named::ArrowDown,
+        "left" => Named::ArrowLeft,
+        "right" => Named::ArrowRight,
+        "home" => Named::Home,
+        "end" => Named::End,
+        "pageup" => Named::PageUp,
+        "pagedown" => Named::PageDown,
+        "space" => return Some(KeyAction::Letter(' ')),
+        "lt" => return Some(KeyAction::Letter('<')),
+        "bar" => return Some(KeyAction::Letter('|')),
+        "bslash" => return Some(KeyAction::Letter('\\')),
+        f => match f.strip_prefix('f')?.parse() {
+            Ok(n @ 1..=12) => Named::F(n),
+            _ => return None,
+        },
+    };
+    Some(KeyAction::Named(named))
+}
@@ -0,0 +1,28 @@
+use evie_tui::{KeyAction, Named};
+
+use super::parse;
+
+#[test]
+fn vim_notation() {
+    assert_eq!(
+        parse("ia<Esc><C-w>v"),
+        [
+            KeyAction::Letter('i'),
+            KeyAction::Letter('a'),
+            KeyAction::Named(Named::Escape),
+            KeyAction::Ctrl('w'),
+            KeyAction::Letter('v'),
+        ]
+    );
+    assert_eq!(
+        parse("<cr><S-Tab><lt><F12>"),
+        [
+            KeyAction::Named(Named::Enter),
+            KeyAction::Shift(Named::Tab),
+            KeyAction::Letter('<'),
+            KeyAction::Named(Named::F(12)),
+        ]
+    );
+    // unknown names are typed as written
+    assert_eq!(parse("<x>").len(), 3);
+}
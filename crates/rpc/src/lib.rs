@@ -0,0 +1,232 @@
+//! Drives evie from another process, like an alternative frontend or a
+//! script
+//!
+//! Clients speak JSON-RPC 2.0, each message framed with a `Content-Length`
+//! header the way language servers do, either over stdio ([`embed`]) or a
+//! unix socket ([`listen`]). The requests are
+//!
+//! | method        | params                  | result                          |
+//! |---------------|-------------------------|---------------------------------|
+//! | `open`        | `path`, `tab`           | `path`, made absolute           |
+//! | `input`       | `keys`, see [`keys`]    | `handled`, how many keys did    |
+//! | `command`     | `command`, an ex command| null                            |
+//! | `text`        | `path`, or the current  | `path`, `text`, `revision`, `modified` |
+//! | `state`       |                         | `mode`, `path`, `line`, `col`, `message` |
+//! | `buffers`     |                         | `path`, `revision`, `modified` of each |
+//! | `subscribe`   | `events`                | null                            |
+//! | `unsubscribe` | `events`                | null                            |
+//!
+//! and the events a client can subscribe to are `changed`, with the `path`
+//! and `revision` of a buffer whose text changed, `mode`, with the new
+//! `mode`, `closed`, with the `path` of a buffer no window shows anymore or
+//! whose window from `open` was closed, and `quit`, after which the server
+//! stops.
+
+use std::{
+    cell::RefCell,
+    io,
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
+};
+
+use evie_core::{engine::EngineError, EvieCentral, EvieError};
+use evie_lsp::{transport, LspError};
+use evie_tui::KeyAction;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite, BufReader};
+use tokio::sync::mpsc;
+use tokio::task::LocalSet;
+
+use server::{ClientId, Server};
+
+#[cfg(unix)]
+pub mod client;
+pub mod keys;
+#[cfg(unix)]
+pub mod remote;
+pub mod server;
+
+#[cfg(all(test, unix))]
+mod test;
+
+#[derive(Debug, Error)]
+pub enum RpcError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Transport(#[from] LspError),
+    #[error("Parse error: {0}")]
+    Parse(String),
+    #[error("Invalid request")]
+    Request,
+    #[error("Method not found: {0}")]
+    Method(String),
+    #[error("Invalid params: {0}")]
+    Params(String),
+    #[error("Unknown event: {0}")]
+    Event(String),
+    #[error("No buffer")]
+    NoBuffer,
+    #[error("{0}")]
+    Evie(#[from] EvieError),
+    /// an error the server answered with, as the client sees it
+    #[error("{message}")]
+    Server { code: i64, message: String },
+}
+
+impl From<EngineError> for RpcError {
+    fn from(e: EngineError) -> Self {
+        Self::Evie(e.into())
+    }
+}
+
+impl RpcError {
+    /// the JSON-RPC error code, those of the spec for protocol errors and
+    /// -32000 for evie's own
+    pub fn code(&self) -> i64 {
+        match self {
+            Self::Parse(_) => -32700,
+            Self::Request => -32600,
+            Self::Method(_) => -32601,
+            Self::Params(_) | Self::Event(_) => -32602,
+            Self::Server { code, .. } => *code,
+            _ => -32000,
+        }
+    }
+}
+
+/// Something a client can be told about as it happens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Event {
+    Changed,
+    Mode,
+    Closed,
+    Quit,
+}
+
+impl Event {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Changed => "changed",
+            Self::Mode => "mode",
+            Self::Closed => "closed",
+            Self::Quit => "quit",
+        }
+    }
+}
+
+impl FromStr for Event {
+    type Err = RpcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Self::Changed, Self::Mode, Self::Closed, Self::Quit]
+            .into_iter()
+            .find(|event| event.name() == s)
+            .ok_or_else(|| RpcError::Event(s.to_owned()))
+    }
+}
+
+/// serves a single client over stdin and stdout, like `nvim --embed`, until
+/// it hangs up or evie quits
+pub fn embed(evie: EvieCentral<KeyAction>) -> Result<(), RpcError> {
+    let server = Rc::new(RefCell::new(Server::new(evie, keys::parse)));
+    run(connection(
+        server,
+        0,
+        tokio::io::stdin(),
+        tokio::io::stdout(),
+    ))
+}
+
+/// serves any number of clients on a unix socket at `path` until evie
+/// quits, like `nvim --listen`
+#[cfg(unix)]
+pub fn listen(evie: EvieCentral<KeyAction>, path: &Path) -> Result<(), RpcError> {
+    let server = Rc::new(RefCell::new(Server::new(evie, keys::parse)));
+    let path = path.to_owned();
+    run(async move {
+        let listener = remote::bind(&path)?;
+        // the socket goes away with the server, however it stops
+        let _socket = Socket(path);
+        let mut quit = server.borrow().quit();
+        let mut connections = Vec::new();
+        for client in 1.. {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (reader, writer) = accepted?.0.into_split();
+                    connections.push(tokio::task::spawn_local(connection(
+                        server.clone(),
+                        client,
+                        reader,
+                        writer,
+                    )));
+                }
+                _ = quit.wait_for(|quit| *quit) => break,
+            }
+        }
+        // letting each client have its last answers
+        for connection in connections {
+            let _ = connection.await;
+        }
+        Ok(())
+    })
+}
+
+/// Removes the socket file when dropped
+#[cfg(unix)]
+pub(crate) struct Socket(pub(crate) PathBuf);
+
+#[cfg(unix)]
+impl Drop for Socket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// runs a server on a runtime of its own, evie not being `Send`
+fn run(serve: impl std::future::Future<Output = Result<(), RpcError>>) -> Result<(), RpcError> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(LocalSet::new().run_until(serve))
+}
+
+/// answers a client until it hangs up or evie quits
+async fn connection(
+    server: Rc<RefCell<Server<KeyAction>>>,
+    client: ClientId,
+    reader: impl AsyncRead + Unpin,
+    mut writer: impl AsyncWrite + Unpin,
+) -> Result<(), RpcError> {
+    let (out, mut outgoing) = mpsc::unbounded_channel::<Value>();
+    server.borrow_mut().connect(client, out);
+    let mut quit = server.borrow().quit();
+    let write = async {
+        while let Some(message) = outgoing.recv().await {
+            transport::write(&mut writer, &message).await?;
+        }
+        Ok::<_, RpcError>(())
+    };
+    let read = async {
+        let mut reader = BufReader::new(reader);
+        let read = loop {
+            let message = tokio::select! {
+                message = transport::read(&mut reader) => message,
+                _ = quit.wait_for(|quit| *quit) => break Ok(()),
+            };
+            match message {
+                Ok(Some(message)) => server.borrow_mut().handle(client, message),
+                Ok(None) => break Ok(()),
+                Err(LspError::Json(e)) => server.borrow_mut().parse_error(client, e),
+                Err(e) => break Err(e.into()),
+            }
+        };
+        // which ends the writes once they're all out
+        server.borrow_mut().disconnect(client);
+        read
+    };
+    let (read, write) = tokio::join!(read, write);
+    read.and(write)
+}
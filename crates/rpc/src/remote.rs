@@ -0,0 +1,138 @@
+//! One evie per user that others hand their files to, like vim's
+//! `--remote`
+//!
+//! The instance with a window listens on [`socket`], fed to its [`Server`]
+//! through [`serve`] as it's the one thread that can touch evie. Running
+//! evie again with files then only [`open`]s them there.
+//!
+//! [`Server`]: crate::server::Server
+
+use std::collections::HashSet;
+use std::fs::{self, DirBuilder};
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt};
+use std::path::{Path, PathBuf};
+
+use evie_core::disk;
+use evie_lsp::{transport, LspError};
+use serde_json::{json, Value};
+use tokio::io::BufReader;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+
+use crate::client::Client;
+use crate::server::{ClientId, Incoming};
+use crate::{RpcError, Socket};
+
+/// where the user's evie listens
+pub fn socket() -> Option<PathBuf> {
+    disk::runtime_dir().map(|dir| dir.join("remote.sock"))
+}
+
+/// accepts clients on `path`, sending what they say to `incoming`, until
+/// the receiving end is dropped
+pub async fn serve(
+    path: PathBuf,
+    incoming: mpsc::UnboundedSender<Incoming>,
+) -> Result<(), RpcError> {
+    let listener = bind(&path)?;
+    let _socket = Socket(path);
+    for client in 1.. {
+        tokio::select! {
+            accepted = listener.accept() => {
+                tokio::spawn(pipe(client, accepted?.0, incoming.clone()));
+            }
+            _ = incoming.closed() => break,
+        }
+    }
+    Ok(())
+}
+
+/// binds a socket at `path`, in a directory only the user can read,
+/// replacing one left behind by an evie that's gone but nothing else
+pub(crate) fn bind(path: &Path) -> io::Result<UnixListener> {
+    if let Some(dir) = path.parent() {
+        DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    }
+    match UnixListener::bind(path) {
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+            let socket = fs::symlink_metadata(path)?.file_type().is_socket();
+            if !socket || std::os::unix::net::UnixStream::connect(path).is_ok() {
+                return Err(e);
+            }
+            fs::remove_file(path)?;
+            UnixListener::bind(path)
+        }
+        bound => bound,
+    }
+}
+
+/// passes a client's messages on, and writes back what's sent to it until
+/// the server disconnects it
+async fn pipe(client: ClientId, stream: UnixStream, incoming: mpsc::UnboundedSender<Incoming>) {
+    let (reader, mut writer) = stream.into_split();
+    let (out, mut outgoing) = mpsc::unbounded_channel::<Value>();
+    if incoming.send(Incoming::Connected(client, out)).is_err() {
+        return;
+    }
+    let write = async {
+        while let Some(message) = outgoing.recv().await {
+            if transport::write(&mut writer, &message).await.is_err() {
+                break;
+            }
+        }
+    };
+    let read = async {
+        let mut reader = BufReader::new(reader);
+        loop {
+            let message = match transport::read(&mut reader).await {
+                Ok(Some(message)) => Incoming::Message(client, message),
+                Err(LspError::Json(e)) => Incoming::ParseError(client, e.to_string()),
+                Ok(None) | Err(_) => break,
+            };
+            if incoming.send(message).is_err() {
+                break;
+            }
+        }
+        let _ = incoming.send(Incoming::Disconnected(client));
+    };
+    tokio::join!(read, write);
+}
+
+/// opens `files` in tab pages of the evie on `socket`, then with `wait`
+/// waits until the windows they were opened in close or it quits. False
+/// when no evie is listening there.
+pub fn open(socket: &Path, files: &[PathBuf], wait: bool) -> Result<bool, RpcError> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(async {
+        let mut client = match Client::connect(socket).await {
+            Ok(client) => client,
+            Err(RpcError::Io(_)) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        if wait {
+            let events = json!({ "events": ["closed", "quit"] });
+            client.call("subscribe", events).await?;
+        }
+        let mut open = HashSet::new();
+        for file in files {
+            let opened = client
+                .call("open", json!({ "path": file, "tab": true }))
+                .await?;
+            open.insert(opened["path"].clone());
+        }
+        while wait && !open.is_empty() {
+            match client.notification().await {
+                Ok((method, params)) if method == "closed" => {
+                    open.remove(&params["path"]);
+                }
+                // evie closing counts as closing each file
+                Ok(_) | Err(RpcError::Io(_)) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    })
+}
@@ -0,0 +1,347 @@
+//! Answers requests against an [`Evie`], and tells subscribed clients what
+//! changed after each
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use evie_core::{window::WindowId, Evie, EvieCentral, Key, Mode, Note};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, watch};
+
+use crate::{Event, RpcError};
+
+/// Which connection a message came from or goes to
+pub type ClientId = u64;
+
+/// What a connection passes on to the server, when the server lives on
+/// another thread than the connections, see [`Server::receive`]
+#[derive(Debug, Clone)]
+pub enum Incoming {
+    /// a client with where its messages go
+    Connected(ClientId, mpsc::UnboundedSender<Value>),
+    Message(ClientId, Value),
+    /// a message that isn't JSON
+    ParseError(ClientId, String),
+    Disconnected(ClientId),
+}
+
+#[derive(Debug)]
+pub struct Server<K: Key> {
+    evie: EvieCentral<K>,
+    /// reads the keys of `input`, see [`crate::keys`]
+    keys: fn(&str) -> Vec<K>,
+    /// where each client's messages go
+    clients: HashMap<ClientId, mpsc::UnboundedSender<Value>>,
+    subscriptions: HashMap<ClientId, HashSet<Event>>,
+    /// the revision of each buffer as last told, to find the ones that changed
+    revisions: HashMap<PathBuf, u64>,
+    /// the buffers in a window as last told, to find the ones closed
+    shown: HashSet<PathBuf>,
+    /// the windows `open` showed a file in, closing one closes its file too
+    opened: HashMap<WindowId, PathBuf>,
+    mode: Mode,
+    /// set once evie quits, for every connection to wind down
+    quit: watch::Sender<bool>,
+}
+
+impl<K: Key> Server<K> {
+    pub fn new(evie: EvieCentral<K>, keys: fn(&str) -> Vec<K>) -> Self {
+        let mut server = Self {
+            mode: evie.mode.get(),
+            shown: shown(&evie),
+            evie,
+            keys,
+            clients: HashMap::new(),
+            subscriptions: HashMap::new(),
+            revisions: HashMap::new(),
+            opened: HashMap::new(),
+            quit: watch::Sender::new(false),
+        };
+        server.changes();
+        server
+    }
+
+    pub fn evie(&self) -> &EvieCentral<K> {
+        &self.evie
+    }
+
+    /// starts sending messages for `client` to `out`
+    pub fn connect(&mut self, client: ClientId, out: mpsc::UnboundedSender<Value>) {
+        self.clients.insert(client, out);
+    }
+
+    pub fn disconnect(&mut self, client: ClientId) {
+        self.clients.remove(&client);
+        self.subscriptions.remove(&client);
+    }
+
+    /// changes to true once evie quits
+    pub fn quit(&self) -> watch::Receiver<bool> {
+        self.quit.subscribe()
+    }
+
+    pub fn receive(&mut self, incoming: Incoming) {
+        match incoming {
+            Incoming::Connected(client, out) => self.connect(client, out),
+            Incoming::Message(client, message) => self.handle(client, message),
+            Incoming::ParseError(client, e) => self.parse_error(client, e),
+            Incoming::Disconnected(client) => self.disconnect(client),
+        }
+    }
+
+    /// answers a request, or runs a notification without answering, then
+    /// tells subscribers what changed
+    pub fn handle(&mut self, client: ClientId, message: Value) {
+        let id = message.get("id").cloned();
+        let result = match message.get("method").and_then(Value::as_str) {
+            Some(method) => {
+                let params = message.get("params").cloned().unwrap_or_default();
+                self.call(client, method, &params)
+            }
+            None => Err(RpcError::Request),
+        };
+        if let Some(id) = id {
+            self.send(client, response(id, result));
+        }
+        self.notify();
+    }
+
+    /// tells `client` its message couldn't be read
+    pub fn parse_error(&mut self, client: ClientId, e: impl ToString) {
+        let error = RpcError::Parse(e.to_string());
+        self.send(client, response(Value::Null, Err(error)));
+    }
+
+    fn send(&self, client: ClientId, message: Value) {
+        if let Some(out) = self.clients.get(&client) {
+            let _ = out.send(message);
+        }
+    }
+
+    fn call(&mut self, client: ClientId, method: &str, params: &Value) -> Result<Value, RpcError> {
+        let evie = &self.evie;
+        match method {
+            "open" => {
+                let path = param::<String>(params, "path")?;
+                let path = open(
+                    evie,
+                    Path::new(&path),
+                    params["tab"].as_bool() == Some(true),
+                )?;
+                let window = evie.windows()?.focus();
+                self.opened.insert(window, path.clone());
+                Ok(json!({ "path": path }))
+            }
+            "input" => {
+                let keys = (self.keys)(&param::<String>(params, "keys")?);
+                let mut handled = 0;
+                for key in keys {
+                    // like typing them, a key failing doesn't stop the rest
+                    match evie.focused().and_then(|bv| bv.on_key(key)) {
+                        Ok(true) => handled += 1,
+                        Ok(false) => {}
+                        Err(e) => evie.report(e),
+                    }
+                }
+                Ok(json!({ "handled": handled }))
+            }
+            "command" => {
+                let command = param::<String>(params, "command")?;
+                evie.execute(&command, current(evie).as_deref())?;
+                Ok(Value::Null)
+            }
+            "text" => {
+                let path = match params["path"].as_str() {
+                    Some(path) => evie.engine.norm_path(path, true)?,
+                    None => current(evie).ok_or(RpcError::NoBuffer)?,
+                };
+                let buf = evie.engine.get_buffer(&path, false)?;
+                let buf = buf.try_borrow().map_err(evie_core::EvieError::from)?;
+                Ok(json!({
+                    "path": path,
+                    "text": buf.text.to_string(),
+                    "revision": buf.revision,
+                    "modified": buf.modified,
+                }))
+            }
+            "state" => {
+                let bv = evie.focused()?;
+                let status = bv.status()?;
+                let message = match evie.last_note() {
+                    Some(Note::Info(info)) => json!({ "info": info }),
+                    Some(Note::Error(e)) => json!({ "error": e }),
+                    None => Value::Null,
+                };
+                Ok(json!({
+                    "mode": mode_name(evie.mode.get()),
+                    "path": status.full_path,
+                    "line": status.line,
+                    "col": status.col,
+                    "message": message,
+                }))
+            }
+            "buffers" => {
+                let mut buffers = Vec::new();
+                for buf in evie.engine.buffers() {
+                    let buf = buf.try_borrow().map_err(evie_core::EvieError::from)?;
+                    buffers.push(json!({
+                        "path": buf.path,
+                        "revision": buf.revision,
+                        "modified": buf.modified,
+                    }));
+                }
+                Ok(Value::Array(buffers))
+            }
+            "subscribe" | "unsubscribe" => {
+                let events = param::<Vec<String>>(params, "events")?;
+                let events = events
+                    .iter()
+                    .map(|event| event.parse())
+                    .collect::<Result<Vec<Event>, _>>()?;
+                let subscribed = self.subscriptions.entry(client).or_default();
+                for event in events {
+                    match method {
+                        "subscribe" => subscribed.insert(event),
+                        _ => subscribed.remove(&event),
+                    };
+                }
+                Ok(Value::Null)
+            }
+            method => Err(RpcError::Method(method.to_owned())),
+        }
+    }
+
+    /// sends each subscriber the events since the last call, which
+    /// [`Self::handle`] does after each message, and a frontend after its
+    /// own changes
+    pub fn notify(&mut self) {
+        let mut events = self
+            .changes()
+            .into_iter()
+            .map(|(path, revision)| {
+                (
+                    Event::Changed,
+                    json!({ "path": path, "revision": revision }),
+                )
+            })
+            .collect::<Vec<_>>();
+        let mode = self.evie.mode.get();
+        if mode != self.mode {
+            self.mode = mode;
+            events.push((Event::Mode, json!({ "mode": mode_name(mode) })));
+        }
+        let shown = shown(&self.evie);
+        let mut closed = self.shown.difference(&shown).cloned().collect::<Vec<_>>();
+        self.opened.retain(|window, path| {
+            let open = showing(&self.evie, *window, path);
+            if !open && !closed.contains(path) {
+                closed.push(path.clone());
+            }
+            open
+        });
+        for path in closed {
+            events.push((Event::Closed, json!({ "path": path })));
+        }
+        self.shown = shown;
+        if self.evie.quit.get() && !*self.quit.borrow() {
+            events.push((Event::Quit, json!({})));
+            self.quit.send_replace(true);
+        }
+        for (event, params) in events {
+            let message = json!({
+                "jsonrpc": "2.0",
+                "method": event.name(),
+                "params": params,
+            });
+            for (client, subscribed) in &self.subscriptions {
+                if subscribed.contains(&event) {
+                    self.send(*client, message.clone());
+                }
+            }
+        }
+    }
+
+    /// the buffers whose text changed since the last call, with their new
+    /// revisions
+    fn changes(&mut self) -> Vec<(PathBuf, u64)> {
+        let mut changed = Vec::new();
+        for buf in self.evie.engine.buffers() {
+            let Ok(buf) = buf.try_borrow() else {
+                continue;
+            };
+            if self.revisions.get(&buf.path) != Some(&buf.revision) {
+                self.revisions.insert(buf.path.clone(), buf.revision);
+                changed.push((buf.path.clone(), buf.revision));
+            }
+        }
+        changed
+    }
+}
+
+/// shows a file in the window with focus, or in a new tab page, opening it
+/// if it isn't yet
+fn open<K: Key>(evie: &Evie<K>, path: &Path, tab: bool) -> Result<PathBuf, RpcError> {
+    let path = evie.engine.norm_path(path, true)?;
+    if evie.engine.get_buffer(&path, false).is_err() {
+        // a file that isn't there yet is written on the first `:w`
+        match path.exists() {
+            true => evie.add_buffer(&path, false)?,
+            false => evie.engine.scratch(&path, false)?,
+        };
+    }
+    match tab {
+        true => evie.execute(&format!("tabnew {}", path.display()), None)?,
+        false => evie.show(&path, false)?,
+    }
+    Ok(path)
+}
+
+/// the buffer in the window with focus
+fn current<K: Key>(evie: &Evie<K>) -> Option<PathBuf> {
+    let windows = evie.windows().ok()?;
+    Some(windows.get(windows.focus())?.buffer.clone())
+}
+
+/// the buffers in a window of any tab page
+fn shown<K: Key>(evie: &Evie<K>) -> HashSet<PathBuf> {
+    let Ok(tabs) = evie.tabs.try_borrow() else {
+        return HashSet::new();
+    };
+    tabs.pages()
+        .iter()
+        .flat_map(|windows| windows.iter().map(|(_, window)| window.buffer.clone()))
+        .collect()
+}
+
+/// whether `window` is still around and showing `path`
+fn showing<K: Key>(evie: &Evie<K>, window: WindowId, path: &Path) -> bool {
+    let Ok(tabs) = evie.tabs.try_borrow() else {
+        return true;
+    };
+    tabs.pages()
+        .iter()
+        .any(|windows| windows.get(window).is_some_and(|w| w.buffer == path))
+}
+
+fn mode_name(mode: Mode) -> String {
+    mode.name().to_lowercase()
+}
+
+/// a parameter by name
+fn param<T: DeserializeOwned>(params: &Value, name: &str) -> Result<T, RpcError> {
+    let value = params.get(name).cloned().unwrap_or_default();
+    serde_json::from_value(value).map_err(|_| RpcError::Params(name.to_owned()))
+}
+
+/// the answer to a request, with an error object if it failed
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": e.code(), "message": e.to_string() },
+        }),
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use evie_core::{engine::Engine, Evie};
+use evie_tui::trigger::modes;
+use serde_json::json;
+use tempfile::tempdir;
+
+use crate::client::Client;
+use crate::{listen, remote, RpcError};
+
+/// starts a server on its own thread, for evie isn't `Send`
+fn serve(socket: PathBuf, files: Vec<PathBuf>) -> thread::JoinHandle<Result<(), RpcError>> {
+    thread::spawn(move || {
+        let evie = Evie::new(modes())
+            .with_engine(Engine::default())
+            .into_central();
+        evie_tui::open(&evie, &files);
+        listen(evie, &socket)
+    })
+}
+
+async fn connect(socket: &Path) -> Client {
+    for _ in 0..100 {
+        if let Ok(client) = Client::connect(socket).await {
+            return client;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("no server on {}", socket.display());
+}
+
+#[tokio::test]
+async fn drives_evie_over_a_socket() {
+    let tmp = tempdir().unwrap();
+    let socket = tmp.path().join("evie.sock");
+    let file = tmp.path().join("driven.txt");
+    fs::write(&file, "one\ntwo\n").unwrap();
+    let server = serve(socket.clone(), vec![file.clone()]);
+    let mut client = connect(&socket).await;
+
+    let text = client.call("text", json!({})).await.unwrap();
+    assert_eq!(text["text"], "one\ntwo\n");
+    let revision = text["revision"].as_u64().unwrap();
+
+    client
+        .call(
+            "subscribe",
+            json!({ "events": ["changed", "mode", "quit"] }),
+        )
+        .await
+        .unwrap();
+    let input = client.call("input", json!({ "keys": "ji" })).await;
+    assert_eq!(input.unwrap()["handled"], 2);
+    let (method, params) = client.notification().await.unwrap();
+    assert_eq!(
+        (method.as_str(), &params),
+        ("mode", &json!({ "mode": "insert" }))
+    );
+    let input = client.call("input", json!({ "keys": "and <Esc>" })).await;
+    assert_eq!(input.unwrap()["handled"], 5);
+    let (method, params) = client.notification().await.unwrap();
+    assert_eq!(method, "changed");
+    assert!(params["revision"].as_u64().unwrap() > revision);
+    let (method, params) = client.notification().await.unwrap();
+    assert_eq!(
+        (method.as_str(), &params),
+        ("mode", &json!({ "mode": "normal" }))
+    );
+
+    let state = client.call("state", json!({})).await.unwrap();
+    assert_eq!(
+        (state["line"].as_u64(), state["col"].as_u64()),
+        (Some(1), Some(3))
+    );
+    let text = client.call("text", json!({})).await.unwrap();
+    assert_eq!(text["text"], "one\nand two\n");
+    assert_eq!(text["modified"], true);
+
+    client
+        .call("command", json!({ "command": "w" }))
+        .await
+        .unwrap();
+    assert_eq!(fs::read_to_string(&file).unwrap(), "one\nand two\n");
+
+    let other = tmp.path().join("new.txt");
+    let opened = client
+        .call("open", json!({ "path": other, "tab": true }))
+        .await;
+    assert_eq!(opened.unwrap()["path"], json!(other));
+    let buffers = client.call("buffers", json!({})).await.unwrap();
+    assert_eq!(buffers.as_array().unwrap().len(), 2);
+
+    let e = client.call("paste", json!({})).await.unwrap_err();
+    assert_eq!(e.code(), -32601);
+    let e = client.call("subscribe", json!({ "events": ["x"] })).await;
+    assert_eq!(e.unwrap_err().code(), -32602);
+
+    // the first closes the tab page, the second evie
+    for _ in 0..2 {
+        client
+            .call("command", json!({ "command": "q!" }))
+            .await
+            .unwrap();
+    }
+    loop {
+        if client.notification().await.unwrap().0 == "quit" {
+            break;
+        }
+    }
+    server.join().unwrap().unwrap();
+    assert!(!socket.exists());
+}
+
+#[tokio::test]
+async fn remote_wait_returns_once_closed() {
+    let tmp = tempdir().unwrap();
+    let socket = tmp.path().join("remote.sock");
+    let (file, edited) = (tmp.path().join("shown.txt"), tmp.path().join("edited.txt"));
+    fs::write(&file, "").unwrap();
+    let open = {
+        // one of them already shown, the wait is for the new tab pages
+        let (socket, files) = (socket.clone(), vec![edited.clone(), file.clone()]);
+        move || remote::open(&socket, &files, true)
+    };
+    // with no evie there, the files are left to the caller
+    assert!(!thread::spawn(open.clone()).join().unwrap().unwrap());
+
+    let server = serve(socket.clone(), vec![file.clone()]);
+    let mut client = connect(&socket).await;
+    let waiting = thread::spawn(open);
+    loop {
+        let buffers = client.call("buffers", json!({})).await.unwrap();
+        let state = client.call("state", json!({})).await.unwrap();
+        if buffers.as_array().unwrap().len() == 2 && state["path"] == json!(file) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    // the first tab page still shows it
+    client
+        .call("command", json!({ "command": "q" }))
+        .await
+        .unwrap();
+    let state = client.call("state", json!({})).await.unwrap();
+    assert_eq!(state["path"], json!(edited));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!waiting.is_finished());
+
+    client
+        .call("command", json!({ "command": "q" }))
+        .await
+        .unwrap();
+    assert!(waiting.join().unwrap().unwrap());
+    client
+        .call("command", json!({ "command": "q" }))
+        .await
+        .unwrap();
+    server.join().unwrap().unwrap();
+}
+
+#[test]
+fn listen_leaves_other_files_alone() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("notes.txt");
+    fs::write(&path, "notes").unwrap();
+    let server = serve(path.clone(), vec![]);
+    assert!(server.join().unwrap().is_err());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "notes");
+}
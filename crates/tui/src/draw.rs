@@ -0,0 +1,400 @@
+//! What the terminal shows: the tab line, each window with its status line
+//! under it, and the message line
+//!
+//! A grapheme takes a cell, tabs included, which is how the core counts
+//! columns too.
+
+use evie_core::{
+    completion::Menu,
+    motion, status,
+    theme::{Style, UiGroup},
+    window::{Layout, Rect, WindowId},
+    BufferView, EvieCentral, EvieError, Mode, Note,
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::screen::{over, Cursor, Screen, Shape};
+use crate::KeyAction;
+
+/// draws everything, with the cursor where keys go
+pub fn draw(evie: &EvieCentral<KeyAction>, screen: &mut Screen) {
+    let normal = style(evie, UiGroup::Normal);
+    for y in 0..screen.height {
+        screen.fill(0, y, screen.width, normal);
+    }
+    screen.cursor = None;
+    let top = tab_line(evie, screen) as u16;
+    let bottom = screen.height.saturating_sub(1);
+    let layout = evie
+        .windows()
+        .map(|windows| (windows.layout().clone(), windows.focus()));
+    match layout {
+        Ok((layout, focus)) => panes(evie, screen, &layout, focus, top, bottom),
+        Err(e) => error(evie, screen, 0, top, screen.width, &e),
+    }
+    message_line(evie, screen, bottom);
+}
+
+/// the style of `group` in the theme in use
+fn style(evie: &EvieCentral<KeyAction>, group: UiGroup) -> Style {
+    evie.theme.borrow().style(group, evie.background())
+}
+
+fn error(
+    evie: &EvieCentral<KeyAction>,
+    screen: &mut Screen,
+    x: u16,
+    y: u16,
+    end: u16,
+    e: &EvieError,
+) {
+    let style = over(style(evie, UiGroup::Normal), style(evie, UiGroup::ErrorMsg));
+    screen.put(x, y, end, &e.to_string(), style);
+}
+
+/// the windows between rows `top` and `bottom`, split up like the layout
+fn panes(
+    evie: &EvieCentral<KeyAction>,
+    screen: &mut Screen,
+    layout: &Layout,
+    focus: WindowId,
+    top: u16,
+    bottom: u16,
+) {
+    let area = Rect {
+        x: 0.0,
+        y: top as f32,
+        width: screen.width as f32,
+        height: bottom.saturating_sub(top) as f32,
+    };
+    let mut menu = None;
+    for (id, rect) in layout.rects(area) {
+        let (x, y) = (rect.x.round() as u16, rect.y.round() as u16);
+        let end = (rect.x + rect.width).round() as u16;
+        let height = ((rect.y + rect.height).round() as u16).saturating_sub(y);
+        // a rule between windows side by side, like vim's `|`
+        let end = match end < screen.width {
+            true => {
+                let rule = over(
+                    style(evie, UiGroup::Normal),
+                    style(evie, UiGroup::VertSplit),
+                );
+                for y in y..y + height {
+                    screen.set(end - 1, y, "│", rule);
+                }
+                end - 1
+            }
+            false => end,
+        };
+        let area = Area {
+            x,
+            y,
+            width: end.saturating_sub(x),
+            height,
+        };
+        let drawn = evie
+            .view_window(id)
+            .and_then(|bv| window(&bv, screen, area, id == focus));
+        match drawn {
+            Ok(Some(open)) => menu = Some(open),
+            Ok(None) => {}
+            Err(e) => error(evie, screen, x, y, end, &e),
+        }
+    }
+    // over the windows below it too
+    if let Some((menu, typed)) = menu {
+        completion(evie, screen, &menu, typed);
+    }
+}
+
+/// A part of the screen
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+}
+
+/// a window and its status line, returning the completion open in it with
+/// how many columns of the word have been typed
+fn window(
+    bv: &BufferView<KeyAction>,
+    screen: &mut Screen,
+    area: Area,
+    focused: bool,
+) -> Result<Option<(Menu, u16)>, EvieError> {
+    let evie = bv.evie();
+    let (wrap, number, relative, numberwidth, statusline) = {
+        let options = evie.engine.options.try_borrow()?;
+        (
+            options.wrap,
+            options.number,
+            options.relativenumber,
+            options.numberwidth,
+            options.statusline.clone(),
+        )
+    };
+    let theme = evie.theme.try_borrow()?;
+    let background = evie.background();
+    let group = |group: UiGroup| {
+        over(
+            theme.style(UiGroup::Normal, background),
+            theme.style(group, background),
+        )
+    };
+    let normal = group(UiGroup::Normal);
+
+    let rope = bv.rope()?;
+    // a trailing newline ends the last line rather than starting another
+    let trailing = rope.len_chars() > 0 && rope.char(rope.len_chars() - 1) == '\n';
+    let lines = (rope.len_lines() - trailing as usize).max(1);
+    let gutter = match number || relative {
+        true => {
+            let digits = lines.ilog10() as usize + 1;
+            (digits.max(numberwidth.saturating_sub(1)) + 1) as u16
+        }
+        false => 0,
+    };
+    let text_x = area.x + gutter.min(area.width);
+    let end = area.x + area.width;
+    let height = area.height.saturating_sub(1);
+    bv.resize(
+        height as usize,
+        if wrap {
+            0
+        } else {
+            end.saturating_sub(text_x) as usize
+        },
+    )?;
+    let view = bv.viewport()?;
+    let at = bv.cursor()?;
+    let cursor_line = rope.byte_to_line(at.min(rope.len_bytes()));
+    let marked = bv.selection()?.or(bv.placeholder()?);
+    let folds = bv.folds()?;
+    let mut cursor = None;
+
+    let (mut line, mut row) = (view.top, 0);
+    while row < height {
+        let y = area.y + row;
+        if line >= lines {
+            screen.put(area.x, y, end, "~", group(UiGroup::LineNr));
+            row += 1;
+            continue;
+        }
+        if gutter > 0 {
+            let width = gutter as usize - 1;
+            // the cursor line's own number goes left with both on, like vim
+            let label = match (number, relative) {
+                (true, true) if line == cursor_line => format!("{:<width$}", line + 1),
+                (true, false) => format!("{:>width$}", line + 1),
+                _ => format!("{:>width$}", line.abs_diff(cursor_line)),
+            };
+            let look = group(match line == cursor_line {
+                true => UiGroup::CursorLineNr,
+                false => UiGroup::LineNr,
+            });
+            screen.put(area.x, y, text_x, &label, look);
+        }
+        let content = motion::line_text(&rope, line);
+        let start = rope.line_to_byte(line);
+        if let Some(fold) = folds.iter().find(|fold| fold.start == line) {
+            let folded = group(UiGroup::Folded);
+            screen.fill(text_x, y, end, folded);
+            let text = format!("+--{:>3} lines: {}", fold.len(), content.trim());
+            screen.put(text_x, y, end, &text, folded);
+            if fold.contains(&cursor_line) {
+                cursor = Some((text_x, y));
+            }
+            line = fold.end;
+            row += 1;
+            continue;
+        }
+        let spans = bv.highlight(line)?;
+        let (mut x, mut y) = (text_x, y);
+        for (column, (i, grapheme)) in content.grapheme_indices(true).enumerate() {
+            if !wrap && column < view.left {
+                continue;
+            }
+            if x >= end {
+                if !wrap || row + 1 >= height {
+                    break;
+                }
+                row += 1;
+                (x, y) = (text_x, area.y + row);
+            }
+            let mut look = normal;
+            if let Some(span) = spans.iter().find(|span| span.range.contains(&i)) {
+                look = over(look, theme.style(span.group, background));
+            }
+            if marked
+                .as_ref()
+                .is_some_and(|marked| marked.contains(&(start + i)))
+            {
+                look = over(look, theme.style(UiGroup::Visual, background));
+            }
+            screen.set(x, y, grapheme, look);
+            if start + i == at {
+                cursor = Some((x, y));
+            }
+            x += 1;
+        }
+        // past the end of the line, as in insert mode
+        if line == cursor_line && at >= start + content.len() {
+            cursor = Some((x.min(end.saturating_sub(1)), y));
+        }
+        line += 1;
+        row += 1;
+    }
+
+    let (left, right) = status::format(&statusline, &bv.status()?);
+    let status = group(match focused {
+        true => UiGroup::StatusLine,
+        false => UiGroup::StatusLineNC,
+    });
+    let y = area.y + height;
+    screen.fill(area.x, y, end, status);
+    let right_x = end.saturating_sub(right.graphemes(true).count() as u16);
+    let left_end = screen.put(area.x, y, end, &left, status);
+    screen.put(right_x.max(left_end + 1), y, end, &right, status);
+
+    if !focused {
+        return Ok(None);
+    }
+    let mode = evie.mode.get();
+    if let (Some((x, y)), false) = (cursor, mode == Mode::Command) {
+        screen.cursor = Some(Cursor {
+            x,
+            y,
+            shape: match mode {
+                Mode::Insert => Shape::Bar,
+                Mode::Replace => Shape::Underline,
+                _ => Shape::Block,
+            },
+        });
+    }
+    let Some(menu) = bv.completion()?.filter(|menu| !menu.items.is_empty()) else {
+        return Ok(None);
+    };
+    let typed = rope
+        .get_byte_slice(menu.start.min(at)..at)
+        .map_or(0, |typed| typed.to_string().graphemes(true).count());
+    Ok(Some((menu, typed as u16)))
+}
+
+/// the completion menu, under the cursor where there's room and otherwise
+/// over it, lined up with the start of the word
+fn completion(evie: &EvieCentral<KeyAction>, screen: &mut Screen, menu: &Menu, typed: u16) {
+    let Some(cursor) = screen.cursor else {
+        return;
+    };
+    let pumheight = evie.engine.options.borrow().pumheight.max(1);
+    let below = screen.height.saturating_sub(cursor.y + 2) as usize;
+    let above = cursor.y as usize;
+    let height = menu.items.len().min(pumheight);
+    let (y, height) = match height <= below || below >= above {
+        true => (cursor.y + 1, height.min(below)),
+        false => (
+            (cursor.y as usize - height.min(above)) as u16,
+            height.min(above),
+        ),
+    };
+    let entries: Vec<_> = menu
+        .items
+        .iter()
+        .map(|item| match item.menu.is_empty() {
+            true => format!(" {} ", item.word),
+            false => format!(" {}  {} ", item.word, item.menu),
+        })
+        .collect();
+    let width = entries
+        .iter()
+        .map(|entry| entry.graphemes(true).count())
+        .max()
+        .unwrap_or(0) as u16;
+    let x = cursor
+        .x
+        .saturating_sub(typed + 1)
+        .min(screen.width.saturating_sub(width));
+    // scrolled to keep the selection in sight
+    let first = menu
+        .selected
+        .map_or(0, |selected| (selected + 1).saturating_sub(height));
+    let (item, selected) = (style(evie, UiGroup::Pmenu), style(evie, UiGroup::PmenuSel));
+    for (row, (i, entry)) in entries
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(height)
+        .enumerate()
+    {
+        let look = match menu.selected == Some(i) {
+            true => selected,
+            false => item,
+        };
+        let y = y + row as u16;
+        screen.fill(x, y, x + width, look);
+        screen.put(x, y, x + width, entry, look);
+    }
+}
+
+/// a label for each tab page, none when `showtabline` hides them, returns
+/// whether it took a row
+fn tab_line(evie: &EvieCentral<KeyAction>, screen: &mut Screen) -> bool {
+    let pages = match evie.tab_pages() {
+        Ok(pages) => pages,
+        Err(e) => {
+            error(evie, screen, 0, 0, screen.width, &e);
+            return true;
+        }
+    };
+    let show = match evie.engine.options.borrow().showtabline {
+        0 => false,
+        1 => pages.len() > 1,
+        _ => true,
+    };
+    if !show {
+        return false;
+    }
+    let normal = style(evie, UiGroup::Normal);
+    screen.fill(
+        0,
+        0,
+        screen.width,
+        over(normal, style(evie, UiGroup::TabLine)),
+    );
+    let mut x = 0;
+    for page in pages {
+        let modified = if page.modified { " [+]" } else { "" };
+        let group = match page.current {
+            true => UiGroup::TabLineSel,
+            false => UiGroup::TabLine,
+        };
+        let label = format!(" {}{modified} ", page.name);
+        x = screen.put(x, 0, screen.width, &label, over(normal, style(evie, group))) + 1;
+    }
+    true
+}
+
+/// the command line while it's being typed on, otherwise the last note
+fn message_line(evie: &EvieCentral<KeyAction>, screen: &mut Screen, y: u16) {
+    let normal = style(evie, UiGroup::Normal);
+    let width = screen.width;
+    if evie.mode.get() == Mode::Command {
+        let x = screen.put(0, y, width, &format!(":{}", evie.cmdline.borrow()), normal);
+        screen.cursor = Some(Cursor {
+            x: x.min(width.saturating_sub(1)),
+            y,
+            shape: Shape::Bar,
+        });
+        return;
+    }
+    match evie.last_note() {
+        Some(Note::Info(info)) => screen.put(0, y, width, &info, normal),
+        Some(Note::Error(e)) => {
+            let look = over(normal, style(evie, UiGroup::ErrorMsg));
+            screen.put(0, y, width, &e, look)
+        }
+        None => 0,
+    };
+}
@@ -0,0 +1,334 @@
+//! A terminal frontend, and a headless one for scripts
+//!
+//! [`launch`] draws the editor in the terminal through crossterm, for when
+//! there's no display, like over SSH. [`headless`] draws nothing at all, it
+//! runs ex commands against files and exits:
+//!
+//! ```sh
+//! evie --headless -c ':%s/a/b/g' -c ':wq' file
+//! ```
+
+use std::{
+    io::{self, Stdout, Write},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor::{SetCursorStyle, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use evie_core::{
+    disk::{self, DiskEvent},
+    engine::{Engine, EngineError},
+    swap, Evie, EvieCentral, EvieError, Note,
+};
+use thiserror::Error;
+
+use draw::draw;
+use screen::Screen;
+use trigger::modes;
+
+pub mod draw;
+pub mod screen;
+pub mod trigger;
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Error)]
+pub enum TuiError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Evie(#[from] EvieError),
+    #[error("{0}")]
+    Engine(#[from] EngineError),
+    /// a `-c` command that failed, which ends a headless run
+    #[error("{command}: {error}")]
+    Command { command: String, error: EvieError },
+    /// a file that's there but couldn't be read, which a headless run
+    /// doesn't start with
+    #[error("{}: {error}", path.display())]
+    Open { path: PathBuf, error: EngineError },
+}
+
+/// the buffer shown when no file is given
+const NO_NAME: &str = "[No Name]";
+
+/// edits `files` in the terminal, the first one shown, after running
+/// `commands` on it
+pub fn launch(files: &[PathBuf], commands: &[String]) -> Result<(), TuiError> {
+    let mut engine = Engine::default();
+    if let Some(dir) = swap::default_dir() {
+        engine = engine.with_swap_dir(dir);
+    }
+    let evie = Evie::new(modes()).with_engine(engine).into_central();
+    if let Some(rc) = disk::config_dir().map(|dir| dir.join("evierc")) {
+        if rc.exists() {
+            if let Err(e) = evie.source(&rc) {
+                evie.report(e);
+            }
+        }
+    }
+    open(&evie, files);
+    for command in commands {
+        if let Err(e) = evie.execute(command, current(&evie).as_deref()) {
+            evie.report(e);
+        }
+    }
+    let result = Terminal::enter().and_then(|mut terminal| run(&evie, &mut terminal));
+    if let Err(e) = evie.engine.close() {
+        eprintln!("failed to clean up swap files: {e}");
+    }
+    result
+}
+
+/// runs `commands` on `files` without drawing anything, stopping at the
+/// first that fails
+///
+/// The evierc isn't read and no swap files are written, so a script does
+/// the same wherever it runs. Changes that aren't written are lost. Errors
+/// that don't stop the run go to stderr.
+pub fn headless(files: &[PathBuf], commands: &[String]) -> Result<(), TuiError> {
+    let evie = Evie::new(modes()).into_central();
+    let result = script(&evie, files, commands);
+    for note in evie.notes() {
+        if let Note::Error(e) = note {
+            eprintln!("{e}");
+        }
+    }
+    result
+}
+
+fn script(
+    evie: &EvieCentral<KeyAction>,
+    files: &[PathBuf],
+    commands: &[String],
+) -> Result<(), TuiError> {
+    // an empty buffer in place of a file that can't be read would have the
+    // commands, and a `:w`, write over it
+    for path in files {
+        evie.add_buffer(path, true)
+            .map_err(|error| TuiError::Open {
+                path: path.clone(),
+                error,
+            })?;
+    }
+    match files.first() {
+        Some(first) => evie.show(first, true)?,
+        None => open(evie, files),
+    }
+    for command in commands {
+        evie.execute(command, current(evie).as_deref())
+            .map_err(|error| TuiError::Command {
+                command: command.clone(),
+                error,
+            })?;
+        if evie.quit.get() {
+            break;
+        }
+    }
+    Ok(evie.engine.close()?)
+}
+
+/// loads `files`, showing the first, or an empty buffer without any
+pub fn open(evie: &EvieCentral<KeyAction>, files: &[PathBuf]) {
+    let Some((first, rest)) = files.split_first() else {
+        if let Err(e) = evie.engine.scratch(NO_NAME, true) {
+            evie.report(e);
+        }
+        if let Err(e) = evie.show(NO_NAME, true) {
+            evie.report(e);
+        }
+        return;
+    };
+    if let Err(e) = evie.add_buffer(first, true) {
+        evie.report(e);
+        if let Err(e) = evie.engine.scratch(first, true) {
+            evie.report(e);
+        }
+    }
+    if let Ok(buf) = evie.engine.get_buffer(first, true) {
+        if let Ok(buf) = buf.try_borrow() {
+            if buf.recovery.is_some() {
+                evie.inform(format!(
+                    "\"{}\" has a swap file from an earlier session",
+                    first.display()
+                ));
+            }
+        }
+    }
+    if let Err(e) = evie.show(first, true) {
+        evie.report(e);
+    }
+    for file in rest {
+        if let Err(e) = evie.add_buffer(file, true) {
+            evie.report(e);
+        }
+    }
+}
+
+/// the buffer in the window with focus
+fn current(evie: &EvieCentral<KeyAction>) -> Option<PathBuf> {
+    let windows = evie.windows().ok()?;
+    Some(windows.get(windows.focus())?.buffer.clone())
+}
+
+/// how often open files are checked for changes on disk
+const CHECK_DISK: Duration = Duration::from_secs(1);
+/// how often pending changes go to the swap files
+const SYNC_SWAPS: Duration = Duration::from_secs(4);
+
+fn run(evie: &EvieCentral<KeyAction>, terminal: &mut Terminal) -> Result<(), TuiError> {
+    let mut last: Option<Screen> = None;
+    let (mut checked, mut synced) = (Instant::now(), Instant::now());
+    while !evie.quit.take() {
+        let (width, height) = terminal::size()?;
+        let mut screen = Screen::new(width, height);
+        draw(evie, &mut screen);
+        screen.flush(last.as_ref(), &mut terminal.out)?;
+        last = Some(screen);
+
+        if event::poll(CHECK_DISK)? {
+            match event::read()? {
+                Event::Key(event) if event.kind != KeyEventKind::Release => {
+                    if let Some(key) = KeyAction::from_event(event) {
+                        on_key(evie, key);
+                    }
+                }
+                // the terminal may have been cleared
+                Event::Resize(..) => last = None,
+                _ => {}
+            }
+        }
+        if checked.elapsed() >= CHECK_DISK {
+            check_disk(evie);
+            checked = Instant::now();
+        }
+        if synced.elapsed() >= SYNC_SWAPS {
+            if let Err(e) = evie.sync_swaps() {
+                evie.report(e);
+            }
+            synced = Instant::now();
+        }
+    }
+    Ok(())
+}
+
+fn on_key(evie: &EvieCentral<KeyAction>, key: KeyAction) {
+    if let Err(e) = evie.focused().and_then(|bv| bv.on_key(key)) {
+        evie.report(e);
+    }
+}
+
+/// tells about files changed outside of evie, there's no dialog to pick a
+/// side so conflicts are left to `:e!` or `:w`
+fn check_disk(evie: &Evie<KeyAction>) {
+    let events = match evie.check_disk() {
+        Ok(events) => events,
+        Err(e) => return evie.report(e),
+    };
+    for event in events {
+        match event {
+            DiskEvent::Reloaded(path) => evie.inform(format!("\"{}\" reloaded", path.display())),
+            DiskEvent::Deleted(path) => evie.inform(format!("\"{}\" was deleted", path.display())),
+            DiskEvent::Conflict(path) => evie.inform(format!(
+                "\"{}\" changed on disk, :e! to reload it",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// The terminal in raw mode on the alternate screen, put back when dropped
+struct Terminal {
+    out: Stdout,
+}
+
+impl Terminal {
+    fn enter() -> Result<Self, TuiError> {
+        terminal::enable_raw_mode()?;
+        let mut out = io::stdout();
+        execute!(out, EnterAlternateScreen)?;
+        Ok(Self { out })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(
+            self.out,
+            SetCursorStyle::DefaultUserShape,
+            Show,
+            LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+        let _ = self.out.flush();
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub enum KeyAction {
+    Letter(char),
+    /// a letter pressed with control held
+    Ctrl(char),
+    Named(Named),
+    /// a named key pressed with shift held, for the few told apart from the
+    /// key alone, like `Tab`
+    Shift(Named),
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub enum Named {
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Insert,
+    Escape,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    ArrowUp,
+    End,
+    Home,
+    PageDown,
+    PageUp,
+    F(u8),
+}
+
+impl KeyAction {
+    /// the key for a terminal key event, none for those evie doesn't use
+    pub fn from_event(event: KeyEvent) -> Option<Self> {
+        let named = match event.code {
+            KeyCode::Char(c) if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Some(Self::Ctrl(c.to_ascii_lowercase()))
+            }
+            _ if event.modifiers.contains(KeyModifiers::ALT) => return None,
+            KeyCode::Char(c) => return Some(Self::Letter(c)),
+            KeyCode::BackTab => return Some(Self::Shift(Named::Tab)),
+            KeyCode::Enter => Named::Enter,
+            KeyCode::Tab => Named::Tab,
+            KeyCode::Backspace => Named::Backspace,
+            KeyCode::Delete => Named::Delete,
+            KeyCode::Insert => Named::Insert,
+            KeyCode::Esc => Named::Escape,
+            KeyCode::Down => Named::ArrowDown,
+            KeyCode::Left => Named::ArrowLeft,
+            KeyCode::Right => Named::ArrowRight,
+            KeyCode::Up => Named::ArrowUp,
+            KeyCode::End => Named::End,
+            KeyCode::Home => Named::Home,
+            KeyCode::PageDown => Named::PageDown,
+            KeyCode::PageUp => Named::PageUp,
+            KeyCode::F(n) => Named::F(n),
+            _ => return None,
+        };
+        Some(Self::Named(named))
+    }
+}
+
+impl evie_core::Key for KeyAction {}
@@ -0,0 +1,196 @@
+//! A grid of cells drawn into before going to the terminal
+//!
+//! Only the rows that changed since the last frame are written out, which
+//! keeps redrawing cheap over a slow connection.
+
+use std::io::{self, Write};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, SetCursorStyle, Show},
+    queue,
+    style::{
+        Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+    },
+};
+use evie_core::theme::{Rgb, Style};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// One character cell, a grapheme and how it looks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub symbol: String,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            symbol: " ".into(),
+            style: Style::default(),
+        }
+    }
+}
+
+/// The cursor, where it is and its shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub x: u16,
+    pub y: u16,
+    pub shape: Shape,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Block,
+    Bar,
+    Underline,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screen {
+    pub width: u16,
+    pub height: u16,
+    cells: Vec<Cell>,
+    /// none hides it
+    pub cursor: Option<Cursor>,
+}
+
+impl Screen {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell::default(); width as usize * height as usize],
+            cursor: None,
+        }
+    }
+
+    pub fn get(&self, x: u16, y: u16) -> Option<&Cell> {
+        (x < self.width && y < self.height)
+            .then(|| &self.cells[y as usize * self.width as usize + x as usize])
+    }
+
+    fn get_mut(&mut self, x: u16, y: u16) -> Option<&mut Cell> {
+        (x < self.width && y < self.height)
+            .then(|| &mut self.cells[y as usize * self.width as usize + x as usize])
+    }
+
+    /// writes `text` from column `x` of row `y` on, a grapheme a cell, cut
+    /// off at `end`, returning the column after it
+    pub fn put(&mut self, x: u16, y: u16, end: u16, text: &str, style: Style) -> u16 {
+        let mut x = x;
+        for grapheme in text.graphemes(true) {
+            if x >= end.min(self.width) {
+                break;
+            }
+            self.set(x, y, grapheme, style);
+            x += 1;
+        }
+        x
+    }
+
+    pub fn set(&mut self, x: u16, y: u16, symbol: &str, style: Style) {
+        if let Some(cell) = self.get_mut(x, y) {
+            // control characters would move the terminal's cursor
+            cell.symbol = match symbol.starts_with(char::is_control) {
+                true => " ".into(),
+                false => symbol.into(),
+            };
+            cell.style = style;
+        }
+    }
+
+    /// gives columns `x..end` of row `y` a style, keeping what's in them
+    pub fn paint(&mut self, x: u16, y: u16, end: u16, style: impl Fn(Style) -> Style) {
+        for x in x..end {
+            if let Some(cell) = self.get_mut(x, y) {
+                cell.style = style(cell.style);
+            }
+        }
+    }
+
+    /// blanks columns `x..end` of row `y`
+    pub fn fill(&mut self, x: u16, y: u16, end: u16, style: Style) {
+        for x in x..end {
+            self.set(x, y, " ", style);
+        }
+    }
+
+    /// the text of a row, for tests
+    pub fn row(&self, y: u16) -> String {
+        (0..self.width)
+            .filter_map(|x| self.get(x, y))
+            .map(|cell| cell.symbol.as_str())
+            .collect()
+    }
+
+    /// writes out the rows that differ from `last`, everything without it
+    pub fn flush(&self, last: Option<&Screen>, out: &mut impl Write) -> io::Result<()> {
+        let last = last.filter(|last| (last.width, last.height) == (self.width, self.height));
+        queue!(out, Hide)?;
+        for y in 0..self.height {
+            let start = y as usize * self.width as usize;
+            let row = &self.cells[start..start + self.width as usize];
+            if last.is_some_and(|last| &last.cells[start..start + self.width as usize] == row) {
+                continue;
+            }
+            queue!(out, MoveTo(0, y))?;
+            let mut current = None;
+            for cell in row {
+                if current != Some(cell.style) {
+                    style(out, cell.style)?;
+                    current = Some(cell.style);
+                }
+                queue!(out, Print(&cell.symbol))?;
+            }
+        }
+        queue!(out, ResetColor, SetAttribute(Attribute::Reset))?;
+        if let Some(cursor) = self.cursor {
+            let shape = match cursor.shape {
+                Shape::Block => SetCursorStyle::SteadyBlock,
+                Shape::Bar => SetCursorStyle::SteadyBar,
+                Shape::Underline => SetCursorStyle::SteadyUnderScore,
+            };
+            queue!(out, MoveTo(cursor.x, cursor.y), shape, Show)?;
+        }
+        out.flush()
+    }
+}
+
+fn style(out: &mut impl Write, style: Style) -> io::Result<()> {
+    queue!(
+        out,
+        SetAttribute(Attribute::Reset),
+        SetForegroundColor(style.fg.map_or(Color::Reset, color)),
+        SetBackgroundColor(style.bg.map_or(Color::Reset, color)),
+    )?;
+    if style.bold {
+        queue!(out, SetAttribute(Attribute::Bold))?;
+    }
+    if style.italic {
+        queue!(out, SetAttribute(Attribute::Italic))?;
+    }
+    if style.underline {
+        queue!(out, SetAttribute(Attribute::Underlined))?;
+    }
+    Ok(())
+}
+
+fn color(rgb: Rgb) -> Color {
+    Color::Rgb {
+        r: rgb.r,
+        g: rgb.g,
+        b: rgb.b,
+    }
+}
+
+/// `top` drawn over `base`, keeping the colors it doesn't have
+pub fn over(base: Style, top: Style) -> Style {
+    Style {
+        fg: top.fg.or(base.fg),
+        bg: top.bg.or(base.bg),
+        bold: base.bold || top.bold,
+        italic: base.italic || top.italic,
+        underline: base.underline || top.underline,
+    }
+}
@@ -0,0 +1,113 @@
+use std::{fs, path::PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use evie_core::{engine::Engine, Evie, EvieCentral};
+use tempfile::tempdir;
+
+use crate::draw::draw;
+use crate::screen::{Screen, Shape};
+use crate::trigger::modes;
+use crate::{headless, on_key, open, KeyAction, Named, TuiError};
+
+fn start(files: &[PathBuf]) -> EvieCentral<KeyAction> {
+    let evie = Evie::new(modes())
+        .with_engine(Engine::default())
+        .into_central();
+    open(&evie, files);
+    evie
+}
+
+fn type_keys(evie: &EvieCentral<KeyAction>, keys: &str) {
+    for c in keys.chars() {
+        let key = match c {
+            '\n' => KeyAction::Named(Named::Enter),
+            '\x1b' => KeyAction::Named(Named::Escape),
+            c => KeyAction::Letter(c),
+        };
+        on_key(evie, key);
+    }
+}
+
+#[test]
+fn headless_edits_and_writes() {
+    let tmp = tempdir().unwrap();
+    let files = [tmp.path().join("headless.txt")];
+    let path = &files[0];
+    fs::write(path, "a cat\nand a\n").unwrap();
+    let commands = [":%s/a/b/g", ":2s/b$/c/", ":wq"].map(String::from);
+    headless(&files, &commands).unwrap();
+    assert_eq!(fs::read_to_string(path).unwrap(), "b cbt\nbnd c\n");
+
+    // a failing command stops the script before the write
+    let commands = [":%s/x/y/", ":wq"].map(String::from);
+    let e = headless(&files, &commands).unwrap_err();
+    assert!(matches!(e, TuiError::Command { ref command, .. } if command == ":%s/x/y/"));
+    assert_eq!(e.to_string(), ":%s/x/y/: Pattern not found: x");
+    assert_eq!(fs::read_to_string(path).unwrap(), "b cbt\nbnd c\n");
+
+    // a file that can't be read stops it before any command
+    let files = [tmp.path().join("unreadable")];
+    fs::create_dir_all(&files[0]).unwrap();
+    let commands = [":wq".to_owned()];
+    let e = headless(&files, &commands).unwrap_err();
+    assert!(matches!(e, TuiError::Open { ref path, .. } if *path == files[0]));
+    assert!(files[0].is_dir());
+}
+
+#[test]
+fn terminal_keys() {
+    let key = |code, modifiers| KeyAction::from_event(KeyEvent::new(code, modifiers));
+    assert_eq!(
+        key(KeyCode::Char('x'), KeyModifiers::NONE),
+        Some(KeyAction::Letter('x'))
+    );
+    assert_eq!(
+        key(
+            KeyCode::Char('W'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT
+        ),
+        Some(KeyAction::Ctrl('w'))
+    );
+    assert_eq!(
+        key(KeyCode::BackTab, KeyModifiers::SHIFT),
+        Some(KeyAction::Shift(Named::Tab))
+    );
+    assert_eq!(
+        key(KeyCode::Esc, KeyModifiers::NONE),
+        Some(KeyAction::Named(Named::Escape))
+    );
+    assert_eq!(key(KeyCode::Char('x'), KeyModifiers::ALT), None);
+}
+
+#[test]
+fn draws_windows_and_the_command_line() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("draw.txt");
+    fs::write(&path, "first line\nsecond\n").unwrap();
+    let evie = start(&[path]);
+    evie.execute("set statusline=%t%=%l", None).unwrap();
+    let mut screen = Screen::new(16, 6);
+    draw(&evie, &mut screen);
+    assert_eq!(screen.row(0), "first line      ");
+    assert_eq!(screen.row(1), "second          ");
+    assert_eq!(screen.row(2), "~               ");
+    assert_eq!(screen.row(4), "draw.txt       1");
+    let cursor = screen.cursor.unwrap();
+    assert_eq!((cursor.x, cursor.y, cursor.shape), (0, 0, Shape::Block));
+
+    // lines too long for the window wrap onto the next row
+    type_keys(&evie, "jixxxxxxxxxxxx");
+    draw(&evie, &mut screen);
+    assert_eq!(screen.row(1), "xxxxxxxxxxxxseco");
+    assert_eq!(screen.row(2), "nd              ");
+    let cursor = screen.cursor.unwrap();
+    assert_eq!((cursor.x, cursor.y, cursor.shape), (12, 1, Shape::Bar));
+
+    type_keys(&evie, "\x1b:set nu");
+    draw(&evie, &mut screen);
+    assert_eq!(screen.row(5), ":set nu         ");
+    assert_eq!(screen.cursor.unwrap().y, 5);
+    type_keys(&evie, "\n");
+    draw(&evie, &mut screen);
+    assert_eq!(screen.row(0), "  1 first line  ");
+}
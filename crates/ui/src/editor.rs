@@ -1,19 +1,30 @@
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 
-use evie_core::BufferView;
-use iced::advanced::text::Paragraph as _;
-use iced::advanced::text::Renderer as _;
+use evie_core::diagnostic::Diagnostic;
+use evie_core::motion::DisplayLines;
+use evie_core::options::Options;
+use evie_core::theme::UiGroup;
+use evie_core::view::Scroll;
+use evie_core::window::WindowId;
+use evie_core::{motion, BufferView, EvieError, Mode, Pointer};
+use iced::advanced::clipboard::{self, Clipboard};
+use iced::advanced::mouse;
+use iced::advanced::text::{self, Renderer as _};
 use iced::advanced::widget::tree;
-use iced::advanced::{layout, text, Widget};
+use iced::advanced::{layout, Widget};
 use iced::advanced::{renderer, Renderer as _};
 use iced::keyboard::key;
-use iced::widget;
 use iced::widget::text::{LineHeight, Shaping, Wrapping};
-use iced::Size;
 use iced::{alignment, Background, Border, Color, Element, Length, Padding, Pixels, Theme};
-use iced_renderer::graphics::text::Paragraph;
+use iced::{Point, Rectangle, Size, Vector};
+use ropey::Rope;
 
-use crate::{KeyAction, Message, Named, DEFAULT_FONT};
+use crate::font::Fonts;
+use crate::gutter::{Context, Gutter};
+use crate::highlight::{self, Syntax};
+use crate::lines::{Cursor, LineCache, Shape, Source};
+use crate::{KeyAction, Message, Named};
 
 pub fn evie_editor(bf: BufferView<KeyAction>) -> Editor {
     Editor::new(bf)
@@ -24,6 +35,53 @@ pub struct Editor {
     bv: BufferView<KeyAction>,
     // ed: iced::widget::TextEditor<>,
     styling: Styling,
+    gutter: Gutter,
+    /// whether the cursor is shown, false while it blinks off
+    cursor: bool,
+    /// whether keys go to this editor's window
+    focus: bool,
+}
+
+/// How the cursor is drawn, which depends on the mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorShape {
+    /// covers the character, in normal mode
+    Block,
+    /// a line before the character, in insert mode
+    Bar,
+    /// a line under the character, in replace mode or partway through a key
+    /// sequence
+    Underline,
+}
+
+impl CursorShape {
+    /// none when the cursor isn't in the buffer, like on the command line
+    pub fn of(mode: Mode, pending: bool) -> Option<Self> {
+        match mode {
+            Mode::Command => None,
+            Mode::Replace => Some(Self::Underline),
+            _ if pending => Some(Self::Underline),
+            Mode::Insert | Mode::Terminal => Some(Self::Bar),
+            Mode::Normal | Mode::Visual => Some(Self::Block),
+        }
+    }
+
+    /// the part of the cell the cursor covers
+    fn bounds(self, cell: Rectangle) -> Rectangle {
+        const THICKNESS: f32 = 2.0;
+        match self {
+            Self::Block => cell,
+            Self::Bar => Rectangle {
+                width: THICKNESS,
+                ..cell
+            },
+            Self::Underline => Rectangle {
+                y: cell.y + cell.height - THICKNESS,
+                height: THICKNESS,
+                ..cell
+            },
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -34,7 +92,6 @@ pub struct Styling {
     text_size: Option<Pixels>,
     line_height: Option<LineHeight>,
     padding: Padding,
-    wrapping: Wrapping,
 }
 
 impl Styling {
@@ -46,7 +103,6 @@ impl Styling {
             text_size: None,
             line_height: None,
             padding: Padding::new(5.0),
-            wrapping: Wrapping::default(),
         }
     }
 }
@@ -56,36 +112,531 @@ impl Editor {
         Self {
             bv,
             styling: Styling::new(),
+            gutter: Gutter::default(),
+            cursor: true,
+            focus: true,
+        }
+    }
+
+    pub fn with_gutter(self, gutter: Gutter) -> Self {
+        Self { gutter, ..self }
+    }
+
+    pub fn with_cursor(self, cursor: bool) -> Self {
+        Self { cursor, ..self }
+    }
+
+    pub fn with_focus(self, focus: bool) -> Self {
+        Self { focus, ..self }
+    }
+
+    /// where the cursor is drawn, none if it isn't on screen
+    fn cursor(&self, state: &State) -> Option<Cursor> {
+        let rope = self.bv.rope().ok()?;
+        let (line, offset) = motion::line_col(&rope, self.bv.cursor().ok()?);
+        state.lines.cursor(line, offset)
+    }
+
+    fn draw_cursor(
+        &self,
+        renderer: &mut iced::Renderer,
+        state: &State,
+        style: &Style,
+        text_bounds: Rectangle,
+    ) {
+        let evie = self.bv.evie();
+        let Some(shape) = CursorShape::of(evie.mode.get(), evie.pending()) else {
+            return;
+        };
+        let (Some(cursor), Some(text)) = (self.cursor(state), state.lines.shape()) else {
+            return;
+        };
+        if cursor.bounds.y >= text_bounds.height {
+            return;
+        }
+        let cell = cursor.bounds + Vector::new(text_bounds.x, text_bounds.y) + self.shift(state);
+        renderer.fill_quad(
+            renderer::Quad {
+                bounds: shape.bounds(cell),
+                ..renderer::Quad::default()
+            },
+            style.cursor,
+        );
+        // the character under a block cursor is drawn again on top of it
+        if shape == CursorShape::Block && !cursor.grapheme.is_empty() {
+            renderer.fill_text(
+                text::Text {
+                    content: cursor.grapheme,
+                    bounds: cell.size(),
+                    size: text.size,
+                    line_height: text.line_height,
+                    font: text.font,
+                    horizontal_alignment: alignment::Horizontal::Left,
+                    vertical_alignment: alignment::Vertical::Top,
+                    shaping: Shaping::Advanced,
+                    wrapping: Wrapping::None,
+                },
+                cell.position(),
+                style.cursor_text,
+                text_bounds,
+            );
         }
     }
 
+    /// lays out the gutter and text inside `bounds`, the editor less its
+    /// padding
     fn update_state(
         &self,
-        renderer: &iced::Renderer,
         state: &mut State,
         styling: &Styling,
-        text_bounds: iced::Rectangle,
-    ) {
-        state.pg = Paragraph::with_text(text::Text {
-            content: &self.bv.rope().unwrap().chunks().collect::<String>(),
-            bounds: text_bounds.size(),
-            size: styling.text_size.unwrap_or_else(|| renderer.default_size()),
-            line_height: styling.line_height.unwrap_or_default(),
-            font: styling.font.unwrap_or(DEFAULT_FONT),
-            horizontal_alignment: alignment::Horizontal::Left,
-            vertical_alignment: alignment::Vertical::Top,
-            shaping: Shaping::Advanced,
-            wrapping: styling.wrapping,
+        bounds: iced::Rectangle,
+    ) -> Result<(), EvieError> {
+        // the widget tree can hand this editor another window's state when
+        // the layout changes
+        let window = Some(self.bv.window());
+        if state.window != window {
+            *state = State {
+                window,
+                syntax: std::mem::take(&mut state.syntax),
+                ..State::default()
+            };
+        }
+        let fonts = Fonts::from_evie(self.bv.evie())?;
+        let size = styling.text_size.unwrap_or(fonts.size);
+        let font = styling.font.unwrap_or(fonts.font);
+        let cell = state.lines.measure(size, font);
+        let rope = self.bv.rope()?;
+        let revision = self.bv.revision()?;
+
+        let gutter = self.with_context(&rope, |cx| self.gutter.width(cx)) as f32 * cell;
+        state.gutter = Rectangle {
+            width: gutter,
+            ..bounds
+        };
+        state.text = Rectangle {
+            x: bounds.x + gutter,
+            width: (bounds.width - gutter).max(0.0),
+            ..bounds
+        };
+        let text_bounds = state.text;
+
+        let shape = {
+            let options = self.bv.evie().engine.options.try_borrow()?;
+            let wrapping = wrapping(&options);
+            let wraps = wrapping != Wrapping::None;
+            Shape {
+                width: text_bounds.width,
+                size,
+                line_height: styling.line_height.unwrap_or(fonts.line_height),
+                font,
+                wide: fonts.wide,
+                ligatures: fonts.ligatures,
+                wrapping,
+                breakindent: wraps && options.breakindent,
+                showbreak: match wraps {
+                    true => state.lines.showbreak(&options.showbreak, size, font),
+                    false => 0.0,
+                },
+            }
+        };
+        let top = self.bv.viewport()?.top;
+        let syntax = &state.syntax;
+        let highlight = |line| {
+            self.bv
+                .highlight(line)
+                .map(|spans| syntax.highlights(&spans, font))
+                .unwrap_or_default()
+        };
+        let folds = self.bv.folds()?;
+        let source = Source {
+            rope: &rope,
+            revision,
+            folds: &folds,
+            highlight: &highlight,
+        };
+        state.lines.layout(&source, top, shape, text_bounds.height);
+
+        // now that the lines are shaped the view knows what fits, which can
+        // scroll it to keep the cursor on screen
+        let width = match shape.wrapping {
+            Wrapping::None => (text_bounds.width / state.lines.cell().max(1.0)) as usize,
+            _ => 0,
+        };
+        self.bv
+            .resize(state.lines.fits(text_bounds.height), width)?;
+        let scrolled = self.bv.viewport()?.top;
+        if scrolled != top {
+            state
+                .lines
+                .layout(&source, scrolled, shape, text_bounds.height);
+        }
+        Ok(())
+    }
+
+    /// runs a key, reporting what goes wrong instead of failing, returns
+    /// whether the key did anything
+    ///
+    /// motions over wrapped rows go by `display`, the lines as last laid out.
+    pub(crate) fn press(&self, ka: KeyAction, display: &dyn DisplayLines) -> bool {
+        match self.bv.on_key_with(ka, display) {
+            Ok(used) => used,
+            Err(e) => {
+                self.bv.evie().report(e);
+                true
+            }
+        }
+    }
+
+    /// runs `f` with what the gutter needs to know about the buffer
+    fn with_context<T>(&self, rope: &Rope, f: impl FnOnce(&Context) -> T) -> T {
+        let options = self.bv.evie().engine.options.borrow();
+        let cursor = self.bv.cursor().unwrap_or_default();
+        let signs = self
+            .bv
+            .diagnostics()
+            .map(|diagnostics| diagnostics.signs(rope))
+            .unwrap_or_default();
+        f(&Context {
+            cursor: motion::line_col(rope, cursor).0,
+            lines: rope.len_lines(),
+            options: &options,
+            signs: &signs,
+        })
+    }
+
+    /// the foreground of `group` in the theme in use
+    fn color(&self, group: UiGroup) -> Color {
+        let evie = self.bv.evie();
+        highlight::colors(&evie.theme.borrow(), evie.background(), group).0
+    }
+
+    fn draw_gutter(&self, renderer: &mut iced::Renderer, state: &State, style: &Style) {
+        let (Some(shape), Ok(rope)) = (state.lines.shape(), self.bv.rope()) else {
+            return;
+        };
+        let cell = state.lines.cell();
+        self.with_context(&rope, |cx| {
+            for line in &state.lines.visible {
+                if line.y > state.text.height {
+                    break;
+                }
+                for (at, width, content) in self.gutter.cells(line.index, cx) {
+                    let x = state.gutter.x
+                        + match content.align {
+                            alignment::Horizontal::Right => (at + width) as f32 * cell,
+                            alignment::Horizontal::Center => {
+                                (at as f32 + width as f32 / 2.0) * cell
+                            }
+                            alignment::Horizontal::Left => at as f32 * cell,
+                        };
+                    renderer.fill_text(
+                        text::Text {
+                            content: content.text,
+                            bounds: Size::new(width as f32 * cell, line.height),
+                            size: shape.size,
+                            line_height: shape.line_height,
+                            font: shape.font,
+                            horizontal_alignment: content.align,
+                            vertical_alignment: alignment::Vertical::Top,
+                            shaping: Shaping::Basic,
+                            wrapping: Wrapping::None,
+                        },
+                        Point::new(x, state.text.y + line.y),
+                        match (content.group, content.highlight) {
+                            (Some(group), _) => self.color(group),
+                            (None, true) => style.cursor_line_number,
+                            (None, false) => style.line_number,
+                        },
+                        state.gutter,
+                    );
+                }
+            }
         });
-        // println!("{:#?}", state.pg.buffer());
+    }
+
+    /// wavy lines under what diagnostics are about, and after each line the
+    /// message of its worst one with `virtualtext`
+    fn draw_diagnostics(&self, renderer: &mut iced::Renderer, state: &State) {
+        let (Ok(diagnostics), Ok(rope)) = (self.bv.diagnostics(), self.bv.rope()) else {
+            return;
+        };
+        let Some(shape) = state.lines.shape().filter(|_| !diagnostics.is_empty()) else {
+            return;
+        };
+        let text_bounds = state.text;
+        let origin = Vector::new(text_bounds.x, text_bounds.y) + self.shift(state);
+        let mut worst = BTreeMap::new();
+        for diagnostic in diagnostics.all() {
+            let mut range = diagnostic.range.clone();
+            // one about no text at all is drawn under the character after it
+            if range.is_empty() {
+                range.end = motion::next_char(&rope, range.start);
+            }
+            let color = self.color(diagnostic.severity.group());
+            for area in state.lines.highlight(&rope, range) {
+                squiggle(renderer, area + origin, color, text_bounds);
+            }
+            let line = motion::line_col(&rope, diagnostic.range.start).0;
+            worst
+                .entry(line)
+                .and_modify(|worst: &mut &Diagnostic| {
+                    if diagnostic.severity < worst.severity {
+                        *worst = diagnostic;
+                    }
+                })
+                .or_insert(diagnostic);
+        }
+        if !self.bv.evie().engine.options.borrow().virtualtext {
+            return;
+        }
+        for (line, diagnostic) in worst {
+            // closed folds show their own text instead
+            let shown = state
+                .lines
+                .visible
+                .iter()
+                .any(|shown| shown.index == line && shown.hidden == 0);
+            let Some(end) = state.lines.cursor(line, usize::MAX).filter(|_| shown) else {
+                continue;
+            };
+            if end.bounds.y >= text_bounds.height {
+                continue;
+            }
+            let message = diagnostic.message.lines().next().unwrap_or_default();
+            let gap = Vector::new(2.0 * state.lines.cell(), 0.0);
+            renderer.fill_text(
+                text::Text {
+                    content: format!("■ {message}"),
+                    bounds: Size::new(f32::INFINITY, end.bounds.height),
+                    size: shape.size,
+                    line_height: shape.line_height,
+                    font: shape.font,
+                    horizontal_alignment: alignment::Horizontal::Left,
+                    vertical_alignment: alignment::Vertical::Top,
+                    shaping: Shaping::Advanced,
+                    wrapping: Wrapping::None,
+                },
+                end.bounds.position() + origin + gap,
+                self.color(diagnostic.severity.group()),
+                text_bounds,
+            );
+        }
+    }
+
+    /// the completion menu, under the word being completed or over it when
+    /// there's no room below, on top of everything else in `bounds`
+    fn draw_completion(&self, renderer: &mut iced::Renderer, state: &State, bounds: Rectangle) {
+        let (Ok(Some(menu)), Ok(rope)) = (self.bv.completion(), self.bv.rope()) else {
+            return;
+        };
+        let Some(shape) = state.lines.shape().filter(|_| !menu.items.is_empty()) else {
+            return;
+        };
+        let (line, col) = motion::line_col(&rope, menu.start);
+        let Some(anchor) = state.lines.cursor(line, col) else {
+            return;
+        };
+        let evie = self.bv.evie();
+        let height = evie.engine.options.borrow().pumheight.max(1);
+        // scrolled along to keep the selection in sight
+        let first = menu
+            .selected
+            .map_or(0, |selected| (selected + 1).saturating_sub(height));
+        let shown = &menu.items[first..menu.items.len().min(first + height)];
+        let cell = state.lines.cell();
+        let row = anchor.bounds.height;
+        let columns = shown
+            .iter()
+            .map(|item| item.word.chars().count() + item.menu.chars().count() + 3)
+            .max()
+            .unwrap_or_default();
+        let size = Size::new(columns as f32 * cell, shown.len() as f32 * row);
+        let at =
+            anchor.bounds.position() + Vector::new(state.text.x, state.text.y) + self.shift(state);
+        let y = match at.y + row + size.height > bounds.y + bounds.height {
+            true if at.y - size.height >= bounds.y => at.y - size.height,
+            _ => at.y + row,
+        };
+        let x = at.x.min(bounds.x + bounds.width - size.width).max(bounds.x);
+        let colors = |group| highlight::colors(&evie.theme.borrow(), evie.background(), group);
+        renderer.with_layer(bounds, |renderer| {
+            for (i, item) in shown.iter().enumerate() {
+                let group = match menu.selected == Some(first + i) {
+                    true => UiGroup::PmenuSel,
+                    false => UiGroup::Pmenu,
+                };
+                let (fg, bg) = colors(group);
+                let area = Rectangle::new(
+                    Point::new(x, y + i as f32 * row),
+                    Size::new(size.width, row),
+                );
+                renderer.fill_quad(
+                    renderer::Quad {
+                        bounds: area,
+                        ..renderer::Quad::default()
+                    },
+                    bg,
+                );
+                let parts = [
+                    (&item.word, alignment::Horizontal::Left, area.x + cell / 2.0),
+                    (
+                        &item.menu,
+                        alignment::Horizontal::Right,
+                        area.x + area.width - cell / 2.0,
+                    ),
+                ];
+                for (content, align, x) in parts {
+                    renderer.fill_text(
+                        text::Text {
+                            content: content.clone(),
+                            bounds: Size::new(area.width, row),
+                            size: shape.size,
+                            line_height: shape.line_height,
+                            font: shape.font,
+                            horizontal_alignment: align,
+                            vertical_alignment: alignment::Vertical::Top,
+                            shaping: Shaping::Advanced,
+                            wrapping: Wrapping::None,
+                        },
+                        Point::new(x, area.y),
+                        fg,
+                        bounds,
+                    );
+                }
+            }
+        });
+    }
+
+    /// how far lines are moved left, when they don't wrap
+    fn shift(&self, state: &State) -> Vector {
+        let left = self.bv.viewport().map_or(0, |view| view.left);
+        Vector::new(-(left as f32) * state.lines.cell(), 0.0)
+    }
+
+    /// the character under the mouse, when it's over the editor
+    fn hit(&self, state: &State, cursor: mouse::Cursor, bounds: Rectangle) -> Option<usize> {
+        let position = cursor.position_over(bounds)?;
+        let point = position - (Vector::new(state.text.x, state.text.y) + self.shift(state));
+        state.lines.hit(&self.bv.rope().ok()?, point)
+    }
+
+    /// handles a mouse button or movement, none if the editor has no use for
+    /// it
+    fn mouse(
+        &self,
+        state: &mut State,
+        event: mouse::Event,
+        cursor: mouse::Cursor,
+        bounds: Rectangle,
+        clipboard: &mut dyn Clipboard,
+    ) -> Option<Result<bool, EvieError>> {
+        let pointer = match event {
+            mouse::Event::ButtonPressed(mouse::Button::Left) => {
+                let at = self.hit(state, cursor, bounds)?;
+                let click = mouse::Click::new(cursor.position()?, mouse::Button::Left, state.click);
+                state.click = Some(click);
+                state.drag = true;
+                match click.kind() {
+                    mouse::click::Kind::Single => Pointer::Place(at),
+                    mouse::click::Kind::Double => Pointer::Word(at),
+                    mouse::click::Kind::Triple => Pointer::Line(at),
+                }
+            }
+            mouse::Event::CursorMoved { .. } if state.drag => {
+                // past the edges the nearest character is selected
+                let bounds = Rectangle::new(Point::ORIGIN, Size::INFINITY);
+                Pointer::Drag(self.hit(state, cursor, bounds)?)
+            }
+            mouse::Event::ButtonReleased(mouse::Button::Left) if state.drag => {
+                state.drag = false;
+                // like other X11 and wayland programs, what's selected can be
+                // pasted elsewhere with a middle click
+                let selection = match self.bv.selection() {
+                    Ok(selection) => selection?,
+                    Err(e) => return Some(Err(e)),
+                };
+                let text = self.bv.rope().ok()?.byte_slice(selection).to_string();
+                clipboard.write(clipboard::Kind::Primary, text);
+                return None;
+            }
+            mouse::Event::ButtonPressed(mouse::Button::Middle) => {
+                let at = self.hit(state, cursor, bounds)?;
+                Pointer::Paste(at, clipboard.read(clipboard::Kind::Primary)?)
+            }
+            _ => return None,
+        };
+        Some(self.bv.pointer(pointer))
+    }
+
+    /// scrolls by a mouse wheel movement, keeping what's left of a line for
+    /// the next one so touchpads scroll smoothly
+    fn wheel(&self, state: &mut State, delta: mouse::ScrollDelta) -> Result<bool, EvieError> {
+        let lines = match delta {
+            mouse::ScrollDelta::Lines { y, .. } => -y * WHEEL_LINES,
+            mouse::ScrollDelta::Pixels { y, .. } => {
+                let row = state.lines.shape().map_or(1.0, |shape| shape.row_height());
+                -y / row.max(1.0)
+            }
+        };
+        state.wheel += lines;
+        let whole = state.wheel.trunc();
+        state.wheel -= whole;
+        if whole == 0.0 {
+            return Ok(false);
+        }
+        self.bv.scroll(Scroll::Lines(whole as isize))?;
+        Ok(true)
     }
 }
 
+/// draws a wavy line along the bottom of `area`, as far as it's inside
+/// `clip`
+fn squiggle(renderer: &mut iced::Renderer, area: Rectangle, color: Color, clip: Rectangle) {
+    const STEP: f32 = 2.0;
+    const WAVE: [f32; 4] = [0.0, 1.0, 2.0, 1.0];
+    let top = area.y + area.height - 3.0;
+    let steps = (area.width / STEP).ceil() as usize;
+    for (i, height) in WAVE.iter().cycle().take(steps).enumerate() {
+        let x = area.x + i as f32 * STEP;
+        let bounds = Rectangle {
+            x,
+            y: top + height,
+            width: STEP.min(area.x + area.width - x),
+            height: 1.0,
+        };
+        if let Some(bounds) = bounds.intersection(&clip) {
+            renderer.fill_quad(
+                renderer::Quad {
+                    bounds,
+                    ..renderer::Quad::default()
+                },
+                color,
+            );
+        }
+    }
+}
+
+/// lines scrolled by a notch of the mouse wheel
+const WHEEL_LINES: f32 = 3.0;
+
 type EditorState = RefCell<State>;
 
 #[derive(Debug, Default)]
 struct State {
-    pg: Paragraph,
+    /// the window laid out, see [`Editor::update_state`]
+    window: Option<WindowId>,
+    lines: LineCache,
+    /// where the gutter and the text were last laid out
+    gutter: Rectangle,
+    text: Rectangle,
+    /// how highlighted text is drawn, from the theme
+    syntax: Syntax,
+    /// mouse wheel movement too small to scroll a line yet
+    wheel: f32,
+    /// the last press of the left button, to tell double and triple clicks
+    click: Option<mouse::Click>,
+    /// whether the left button is held after pressing it in the text
+    drag: bool,
 }
 
 impl Widget<Message, Theme, iced::Renderer> for Editor {
@@ -111,10 +662,9 @@ impl Widget<Message, Theme, iced::Renderer> for Editor {
         let limits = limits.width(self.styling.width).height(self.styling.height);
         if let Length::Shrink = self.styling.height {
             let state = tree.state.downcast_ref::<EditorState>().borrow();
-            let min_bounds = state.pg.min_bounds();
             layout::Node::new(
                 limits
-                    .height(min_bounds.height)
+                    .height(state.lines.height())
                     .max()
                     .expand(Size::new(0.0, self.styling.padding.vertical())),
             )
@@ -127,7 +677,7 @@ impl Widget<Message, Theme, iced::Renderer> for Editor {
         &self,
         tree: &iced::advanced::widget::Tree,
         renderer: &mut iced::Renderer,
-        theme: &Theme,
+        _theme: &Theme,
         _style: &iced::advanced::renderer::Style,
         layout: iced::advanced::Layout<'_>,
         _cursor: iced::advanced::mouse::Cursor,
@@ -137,7 +687,14 @@ impl Widget<Message, Theme, iced::Renderer> for Editor {
 
         let bounds = layout.bounds();
         let styling = &self.styling;
-        let style = default(&theme);
+        let evie = self.bv.evie();
+        let background = evie.background();
+        let style = default(&evie.theme.borrow(), background);
+        let syntax = Syntax::from_theme(&evie.theme.borrow(), background);
+        if state.syntax != syntax {
+            state.syntax = syntax;
+            state.lines.refresh();
+        }
 
         renderer.fill_quad(
             renderer::Quad {
@@ -148,9 +705,66 @@ impl Widget<Message, Theme, iced::Renderer> for Editor {
             style.background,
         );
 
-        let text_bounds = bounds.shrink(styling.padding);
-        self.update_state(renderer, &mut state, styling, text_bounds);
-        renderer.fill_paragraph(&state.pg, text_bounds.position(), style.value, text_bounds);
+        // drawing can't report errors, the status line shows them instead and
+        // the last layout is drawn
+        let _ = self.update_state(&mut state, styling, bounds.shrink(styling.padding));
+        let text_bounds = state.text;
+        self.draw_gutter(renderer, &state, &style);
+        let shift = self.shift(&state);
+        let selection = self.bv.selection().ok().flatten();
+        let placeholder = self.bv.placeholder().ok().flatten();
+        if let (Some(selection), Ok(rope)) = (selection.or(placeholder), self.bv.rope()) {
+            for area in state.lines.highlight(&rope, selection) {
+                renderer.fill_quad(
+                    renderer::Quad {
+                        bounds: area + Vector::new(text_bounds.x, text_bounds.y) + shift,
+                        ..renderer::Quad::default()
+                    },
+                    style.selection,
+                );
+            }
+        }
+        for line in &state.lines.visible {
+            if line.y > text_bounds.height {
+                break;
+            }
+            let position = text_bounds.position() + Vector::new(0.0, line.y) + shift;
+            let color = match line.hidden {
+                0 => style.value,
+                _ => {
+                    renderer.fill_quad(
+                        renderer::Quad {
+                            bounds: Rectangle {
+                                x: text_bounds.x,
+                                y: text_bounds.y + line.y,
+                                width: text_bounds.width,
+                                height: line.height,
+                            },
+                            ..renderer::Quad::default()
+                        },
+                        style.fold,
+                    );
+                    style.fold_text
+                }
+            };
+            for part in state.lines.parts(line) {
+                renderer.fill_paragraph(
+                    &part.paragraph,
+                    position + part.offset,
+                    color,
+                    text_bounds,
+                );
+            }
+            for (at, showbreak) in state.lines.breaks(line) {
+                let position = text_bounds.position() + Vector::new(at.x, at.y) + shift;
+                renderer.fill_paragraph(showbreak, position, style.line_number, text_bounds);
+            }
+        }
+        self.draw_diagnostics(renderer, &state);
+        if self.cursor {
+            self.draw_cursor(renderer, &state, &style, text_bounds);
+        }
+        self.draw_completion(renderer, &state, bounds);
     }
 
     fn on_event(
@@ -158,20 +772,55 @@ impl Widget<Message, Theme, iced::Renderer> for Editor {
         tree: &mut tree::Tree,
         event: iced::Event,
         layout: layout::Layout<'_>,
-        _cursor: iced::advanced::mouse::Cursor,
-        renderer: &iced::Renderer,
-        _clipboard: &mut dyn iced::advanced::Clipboard,
-        _shell: &mut iced::advanced::Shell<'_, Message>,
+        cursor: iced::advanced::mouse::Cursor,
+        _renderer: &iced::Renderer,
+        clipboard: &mut dyn iced::advanced::Clipboard,
+        shell: &mut iced::advanced::Shell<'_, Message>,
         _viewport: &iced::Rectangle,
     ) -> iced_renderer::core::event::Status {
-        if let Some(ka) = decode_event(event) {
-            if self.bv.on_key(ka).unwrap() {
-                self.update_state(
-                    renderer,
-                    &mut tree.state.downcast_ref::<EditorState>().borrow_mut(),
-                    &self.styling,
-                    layout.bounds().shrink(self.styling.padding),
-                );
+        if let iced::Event::Mouse(mouse::Event::WheelScrolled { delta }) = event {
+            if !cursor.is_over(layout.bounds()) {
+                return iced_renderer::core::event::Status::Ignored;
+            }
+            let mut state = tree.state.downcast_ref::<EditorState>().borrow_mut();
+            match self.wheel(&mut state, delta) {
+                Ok(true) => shell.publish(Message::Idle),
+                Ok(false) => {}
+                Err(e) => {
+                    self.bv.evie().report(e);
+                    shell.publish(Message::Idle);
+                }
+            }
+            return iced_renderer::core::event::Status::Captured;
+        }
+        if let iced::Event::Mouse(event) = event {
+            let mut state = tree.state.downcast_ref::<EditorState>().borrow_mut();
+            let bounds = layout.bounds();
+            let used = match self.mouse(&mut state, event, cursor, bounds, clipboard) {
+                Some(Ok(used)) => used,
+                Some(Err(e)) => {
+                    self.bv.evie().report(e);
+                    true
+                }
+                None => false,
+            };
+            if used {
+                let bounds = bounds.shrink(self.styling.padding);
+                if let Err(e) = self.update_state(&mut state, &self.styling, bounds) {
+                    self.bv.evie().report(e);
+                }
+                shell.publish(Message::Idle);
+                return iced_renderer::core::event::Status::Captured;
+            }
+        }
+        if let Some(ka) = decode_event(event).filter(|_| self.focus) {
+            let mut state = tree.state.downcast_ref::<EditorState>().borrow_mut();
+            if self.press(ka, &state.lines) {
+                let bounds = layout.bounds().shrink(self.styling.padding);
+                if let Err(e) = self.update_state(&mut state, &self.styling, bounds) {
+                    self.bv.evie().report(e);
+                }
+                shell.publish(Message::Idle);
                 return iced_renderer::core::event::Status::Captured;
             }
         }
@@ -179,9 +828,18 @@ impl Widget<Message, Theme, iced::Renderer> for Editor {
     }
 }
 
-impl<'a> Into<Element<'a, Message>> for Editor {
-    fn into(self) -> Element<'a, Message> {
-        Element::new(self)
+/// how lines wrap, from the `wrap` and `linebreak` options
+fn wrapping(options: &Options) -> Wrapping {
+    match (options.wrap, options.linebreak) {
+        (false, _) => Wrapping::None,
+        (true, true) => Wrapping::Word,
+        (true, false) => Wrapping::Glyph,
+    }
+}
+
+impl<'a> From<Editor> for Element<'a, Message> {
+    fn from(editor: Editor) -> Self {
+        Element::new(editor)
     }
 }
 
@@ -191,10 +849,20 @@ fn decode_event(event: iced::Event) -> Option<KeyAction> {
         return None;
     };
     match event {
+        KeyPressed {
+            key: key::Key::Named(key::Named::Tab),
+            modifiers,
+            ..
+        } if modifiers.shift() => Some(KeyAction::Shift(Named::Tab)),
         KeyPressed {
             key: key::Key::Named(named),
             ..
         } => Some(KeyAction::Named(Named::from_iced(named)?)),
+        KeyPressed {
+            key: key::Key::Character(c),
+            modifiers,
+            ..
+        } if modifiers.control() => Some(KeyAction::Ctrl(c.chars().next()?.to_ascii_lowercase())),
         KeyPressed {
             text: Some(text), ..
         } => Some(KeyAction::Letter(text.chars().find(|c| !c.is_control())?)),
@@ -210,23 +878,40 @@ pub struct Style {
     pub placeholder: Color,
     pub value: Color,
     pub selection: Color,
+    pub cursor: Color,
+    /// the character under a block cursor
+    pub cursor_text: Color,
+    pub line_number: Color,
+    /// the number of the line the cursor is on
+    pub cursor_line_number: Color,
+    /// behind the line a closed fold is shown as
+    pub fold: Color,
+    pub fold_text: Color,
 }
 
-/// The default style of a [`TextEditor`].
-pub fn default(theme: &Theme) -> Style {
-    let palette = theme.extended_palette();
-
+/// The style of a [`TextEditor`], from the evie theme.
+pub fn default(theme: &evie_core::theme::Theme, background: evie_core::theme::Background) -> Style {
+    let colors = |group| highlight::colors(theme, background, group);
+    let (value, base) = colors(UiGroup::Normal);
+    let (fold_text, fold) = colors(UiGroup::Folded);
+    let (cursor_text, cursor) = colors(UiGroup::Cursor);
     Style {
-        background: Background::Color(palette.background.base.color),
+        background: Background::Color(base),
         border: Border {
             radius: 2.0.into(),
             width: 1.0,
-            color: palette.background.strong.color,
+            color: colors(UiGroup::VertSplit).0,
         },
-        icon: palette.background.weak.text,
-        placeholder: palette.background.strong.color,
-        value: palette.background.base.text,
-        selection: palette.primary.weak.color,
+        icon: value,
+        placeholder: colors(UiGroup::LineNr).0,
+        value,
+        selection: colors(UiGroup::Visual).1,
+        cursor,
+        cursor_text,
+        line_number: colors(UiGroup::LineNr).0,
+        cursor_line_number: colors(UiGroup::CursorLineNr).0,
+        fold,
+        fold_text,
     }
 
     // match status {
@@ -0,0 +1,106 @@
+//! The fonts text is drawn in, from the `guifont` options
+//!
+//! Text is drawn in `guifont`, with wide characters in `guifontwide` when it's
+//! set. Anything a font has no glyph for falls back to the system's fonts.
+//!
+//! Ligatures can't be turned off in the shaper, so without `ligatures` a line
+//! is shaped in pieces split between the characters that could join.
+
+use std::collections::HashSet;
+use std::ops::Range;
+use std::sync::{LazyLock, Mutex};
+
+use evie_core::{Evie, EvieError};
+use iced::font::Family;
+use iced::widget::text::LineHeight;
+use iced::{Font, Pixels};
+
+use crate::KeyAction;
+
+/// What the editor's text is drawn with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fonts {
+    pub font: Font,
+    /// for wide characters, none leaves them to the fallback fonts
+    pub wide: Option<Font>,
+    pub size: Pixels,
+    pub line_height: LineHeight,
+    pub ligatures: bool,
+}
+
+impl Fonts {
+    /// the fonts the options ask for, zoomed in or out
+    pub fn from_evie(evie: &Evie<KeyAction>) -> Result<Self, EvieError> {
+        let options = evie.engine.options.try_borrow()?;
+        let font = Font {
+            family: family(&options.guifont),
+            ..Font::MONOSPACE
+        };
+        let wide = (!options.guifontwide.is_empty()).then(|| Font {
+            family: family(&options.guifontwide),
+            ..font
+        });
+        Ok(Self {
+            font,
+            wide,
+            size: (options.font_size(evie.zoom.get()) as f32).into(),
+            line_height: LineHeight::Relative(options.lineheight.max(1) as f32 / 100.0),
+            ligatures: options.ligatures,
+        })
+    }
+}
+
+/// the family called `name`, whose name is kept for good as iced only takes
+/// static ones
+fn family(name: &str) -> Family {
+    static NAMES: LazyLock<Mutex<HashSet<&'static str>>> = LazyLock::new(Default::default);
+    if name.is_empty() || name == "monospace" {
+        return Family::Monospace;
+    }
+    let mut names = NAMES.lock().unwrap_or_else(|e| e.into_inner());
+    let name = match names.get(name) {
+        Some(name) => *name,
+        None => {
+            let name: &'static str = Box::leak(name.into());
+            names.insert(name);
+            name
+        }
+    };
+    Family::Name(name)
+}
+
+/// the pieces of `text` shaped apart, with whether they're wide characters
+/// when `wide` asks for them
+pub fn runs(text: &str, ligatures: bool, wide: bool) -> Vec<(Range<usize>, bool)> {
+    let mut runs: Vec<(Range<usize>, bool)> = Vec::new();
+    let mut last = None;
+    for (at, c) in text.char_indices() {
+        let is_wide = wide && is_wide(c);
+        let joins = !ligatures
+            && last.is_some_and(|last: char| last.is_ascii_punctuation())
+            && c.is_ascii_punctuation();
+        match runs.last_mut() {
+            Some((range, w)) if *w == is_wide && !joins => range.end = at + c.len_utf8(),
+            _ => runs.push((at..at + c.len_utf8(), is_wide)),
+        }
+        last = Some(c);
+    }
+    runs
+}
+
+/// whether `c` takes two cells, for the CJK and emoji blocks
+fn is_wide(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x115f
+            | 0x2e80..=0xa4cf
+            | 0xac00..=0xd7a3
+            | 0xf900..=0xfaff
+            | 0xfe30..=0xfe4f
+            | 0xff00..=0xff60
+            | 0xffe0..=0xffe6
+            | 0x1f300..=0x1f64f
+            | 0x1f900..=0x1f9ff
+            | 0x20000..=0x3fffd
+    )
+}
@@ -0,0 +1,145 @@
+//! The columns left of the text, like line numbers
+//!
+//! Each [`Column`] says how wide it is and what goes next to each line, the
+//! [`Gutter`] lines them up. Signs and fold markers are just more columns.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use evie_core::diagnostic::Severity;
+use evie_core::options::Options;
+use evie_core::theme::UiGroup;
+use iced::alignment::Horizontal;
+
+#[cfg(test)]
+mod test;
+
+/// What the gutter knows about the buffer it's next to
+#[derive(Debug, Clone, Copy)]
+pub struct Context<'a> {
+    /// the line the cursor is on
+    pub cursor: usize,
+    /// how many lines the buffer has
+    pub lines: usize,
+    pub options: &'a Options,
+    /// the worst diagnostic on each line that has one
+    pub signs: &'a BTreeMap<usize, Severity>,
+}
+
+/// What a column shows next to a line, on the line's first row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub text: String,
+    pub align: Horizontal,
+    /// drawn brighter, like the number of the cursor line
+    pub highlight: bool,
+    /// drawn in this group's color instead
+    pub group: Option<UiGroup>,
+}
+
+pub trait Column: Debug {
+    /// how many character cells wide the column is, 0 hides it
+    fn width(&self, cx: &Context) -> usize;
+
+    /// what to show next to `line`, none to leave it blank
+    fn cell(&self, line: usize, cx: &Context) -> Option<Cell>;
+}
+
+/// Line numbers, absolute with `number`, relative to the cursor with
+/// `relativenumber`, and both at once showing the cursor line's number
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LineNumbers;
+
+impl Column for LineNumbers {
+    fn width(&self, cx: &Context) -> usize {
+        if !cx.options.number && !cx.options.relativenumber {
+            return 0;
+        }
+        let digits = cx.lines.max(1).ilog10() as usize + 1;
+        // a space keeps the numbers off the text
+        digits.max(cx.options.numberwidth.saturating_sub(1)) + 1
+    }
+
+    fn cell(&self, line: usize, cx: &Context) -> Option<Cell> {
+        let (text, align) = match (cx.options.number, cx.options.relativenumber) {
+            (false, false) => return None,
+            (true, true) if line == cx.cursor => (line + 1, Horizontal::Left),
+            (true, false) => (line + 1, Horizontal::Right),
+            (_, true) => (line.abs_diff(cx.cursor), Horizontal::Right),
+        };
+        Some(Cell {
+            text: format!("{text} "),
+            align,
+            highlight: line == cx.cursor,
+            group: None,
+        })
+    }
+}
+
+/// A sign for the worst diagnostic on each line, only there while the buffer
+/// has diagnostics
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Signs;
+
+impl Column for Signs {
+    fn width(&self, cx: &Context) -> usize {
+        match cx.signs.is_empty() {
+            true => 0,
+            false => 2,
+        }
+    }
+
+    fn cell(&self, line: usize, cx: &Context) -> Option<Cell> {
+        let severity = cx.signs.get(&line)?;
+        Some(Cell {
+            text: severity.sign().to_owned(),
+            align: Horizontal::Left,
+            highlight: false,
+            group: Some(severity.group()),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Gutter {
+    columns: Vec<Box<dyn Column>>,
+}
+
+impl Default for Gutter {
+    fn default() -> Self {
+        Self {
+            columns: vec![Box::new(Signs), Box::new(LineNumbers)],
+        }
+    }
+}
+
+impl Gutter {
+    pub fn with_column(mut self, column: impl Column + 'static) -> Self {
+        self.columns.push(Box::new(column));
+        self
+    }
+
+    /// the width of every column together, in cells
+    pub fn width(&self, cx: &Context) -> usize {
+        self.columns.iter().map(|column| column.width(cx)).sum()
+    }
+
+    /// the cells next to `line`, with the column each starts at and how wide
+    /// it is
+    pub fn cells<'a>(
+        &'a self,
+        line: usize,
+        cx: &'a Context,
+    ) -> impl Iterator<Item = (usize, usize, Cell)> + 'a {
+        self.columns
+            .iter()
+            .scan(0, |start, column| {
+                let width = column.width(cx);
+                let at = *start;
+                *start += width;
+                Some((at, width, column))
+            })
+            .filter(|&(_, width, _)| width > 0)
+            .filter_map(move |(at, width, column)| Some((at, width, column.cell(line, cx)?)))
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+
+use evie_core::diagnostic::Severity;
+use evie_core::options::{Options, Set};
+use evie_core::theme::UiGroup;
+use iced::alignment::Horizontal;
+
+use crate::gutter::{Cell, Column, Context, Gutter, LineNumbers, Signs};
+
+fn numbers(set: &[&str], lines: usize) -> (usize, Vec<(String, Horizontal)>) {
+    let mut options = Options::default();
+    set.iter().for_each(|arg| options.set(arg).unwrap());
+    let cx = Context {
+        cursor: 2,
+        lines,
+        options: &options,
+        signs: &BTreeMap::new(),
+    };
+    let cells = (0..5)
+        .filter_map(|line| LineNumbers.cell(line, &cx))
+        .map(|Cell { text, align, .. }| (text.trim_end().to_owned(), align))
+        .collect();
+    (LineNumbers.width(&cx), cells)
+}
+
+#[test]
+fn number_modes() {
+    let right = |n: &str| (n.to_owned(), Horizontal::Right);
+    assert_eq!(numbers(&[], 5), (0, vec![]));
+    assert_eq!(
+        numbers(&["nu"], 5),
+        (4, ["1", "2", "3", "4", "5"].map(right).to_vec())
+    );
+    assert_eq!(
+        numbers(&["rnu"], 5),
+        (4, ["2", "1", "0", "1", "2"].map(right).to_vec())
+    );
+    let (_, hybrid) = numbers(&["nu", "rnu"], 5);
+    assert_eq!(hybrid[2], ("3".to_owned(), Horizontal::Left));
+    assert_eq!(hybrid[0], right("2"));
+}
+
+#[test]
+fn width_follows_line_count() {
+    assert_eq!(numbers(&["nu"], 99_999).0, 6);
+    assert_eq!(numbers(&["nu", "nuw=1"], 7).0, 2);
+
+    let mut options = Options::default();
+    options.set("nu").unwrap();
+    let cx = Context {
+        cursor: 0,
+        lines: 10,
+        options: &options,
+        signs: &BTreeMap::new(),
+    };
+    let gutter = Gutter::default().with_column(LineNumbers);
+    assert_eq!(gutter.width(&cx), 8);
+    let starts: Vec<_> = gutter.cells(0, &cx).map(|(at, ..)| at).collect();
+    assert_eq!(starts, [0, 4]);
+}
+
+#[test]
+fn signs_only_with_diagnostics() {
+    let options = Options::default();
+    let mut cx = Context {
+        cursor: 0,
+        lines: 3,
+        options: &options,
+        signs: &BTreeMap::new(),
+    };
+    assert_eq!(Gutter::default().width(&cx), 0);
+
+    let signs = BTreeMap::from([(1, Severity::Warning)]);
+    cx.signs = &signs;
+    assert_eq!(Gutter::default().width(&cx), 2);
+    assert_eq!(Signs.cell(0, &cx), None);
+    let cell = Signs.cell(1, &cx).unwrap();
+    assert_eq!(
+        (cell.text.as_str(), cell.group),
+        ("W", Some(UiGroup::DiagnosticWarn))
+    );
+}
@@ -0,0 +1,100 @@
+//! How highlighted text is drawn, and the rest of the editor, with colors
+//! from the evie theme
+
+use std::collections::HashMap;
+
+use evie_core::syntax::{Group, Span};
+use evie_core::theme::{Background, Rgb, Theme, UiGroup};
+use iced::font::{Style, Weight};
+use iced::theme::Palette;
+use iced::{Color, Font};
+
+use crate::lines::Highlight;
+
+/// How text of a group looks
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GroupStyle {
+    /// none keeps the text's color
+    pub color: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+/// How each highlight group is drawn
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Syntax {
+    groups: HashMap<Group, GroupStyle>,
+}
+
+impl Syntax {
+    /// the styles of the syntax groups in `theme`
+    pub fn from_theme(theme: &Theme, background: Background) -> Self {
+        let groups = Group::ALL.into_iter().map(|group| {
+            let style = theme.style(group, background);
+            let style = GroupStyle {
+                color: style.fg.map(color),
+                bold: style.bold,
+                italic: style.italic,
+                underline: style.underline,
+            };
+            (group, style)
+        });
+        Self {
+            groups: groups.collect(),
+        }
+    }
+
+    /// how the highlighted pieces of a line are drawn in `font`
+    pub fn highlights(&self, spans: &[Span], font: Font) -> Vec<Highlight> {
+        spans
+            .iter()
+            .filter_map(|span| {
+                let style = self.groups.get(&span.group)?;
+                let mut font = font;
+                if style.bold {
+                    font.weight = Weight::Bold;
+                }
+                if style.italic {
+                    font.style = Style::Italic;
+                }
+                Some(Highlight {
+                    range: span.range.clone(),
+                    color: style.color,
+                    font,
+                    underline: style.underline,
+                })
+            })
+            .collect()
+    }
+}
+
+/// the color iced draws for an evie theme's color
+pub fn color(rgb: Rgb) -> Color {
+    Color::from_rgb8(rgb.r, rgb.g, rgb.b)
+}
+
+/// what a group's colors are drawn as, falling back to `Normal`'s for those
+/// it leaves out
+pub fn colors(theme: &Theme, background: Background, group: UiGroup) -> (Color, Color) {
+    let normal = theme.style(UiGroup::Normal, background);
+    let style = theme.style(group, background);
+    let fg = style.fg.or(normal.fg).map_or(Color::WHITE, color);
+    let bg = style.bg.or(normal.bg).map_or(Color::BLACK, color);
+    (fg, bg)
+}
+
+/// the iced theme for the widgets around the editors, so they match the
+/// evie theme
+pub fn iced_theme(theme: &Theme, background: Background) -> iced::Theme {
+    let colors = |group| colors(theme, background, group);
+    let (text, background) = colors(UiGroup::Normal);
+    let palette = Palette {
+        background,
+        text,
+        primary: colors(UiGroup::TabLineSel).1,
+        success: colors(UiGroup::Search).1,
+        danger: colors(UiGroup::ErrorMsg).0,
+    };
+    iced::Theme::custom(theme.name.clone(), palette)
+}
@@ -1,12 +1,36 @@
-use evie_core::{Evie, EvieCentral};
-use iced::{Element, Task};
+use std::{path::PathBuf, time::Duration};
+
+use evie_core::{
+    disk::{self, DiskEvent},
+    engine::Engine,
+    swap,
+    theme::{Background, UiGroup},
+    window::{Layout, Split, WindowId},
+    Evie, EvieCentral,
+};
+use evie_rpc::server::{Incoming, Server};
+use iced::widget::{column, container, rule, text, vertical_rule, Column, Row};
+use iced::{window, Element, Length, Subscription, Task};
 
 use editor::evie_editor;
+use lsp::{Lsp, Reply};
+use prompt::{Recover, Resolve, Unsaved};
+use status::{diagnostics_list, message_area, status_line, tab_bar};
 use trigger::modes;
 
 pub mod editor;
+pub mod font;
+pub mod gutter;
+pub mod highlight;
+pub mod lines;
+pub mod lsp;
+pub mod prompt;
+pub mod status;
 pub mod trigger;
 
+#[cfg(test)]
+mod test;
+
 pub type IceResult = iced::Result;
 
 pub const DEFAULT_FONT: iced::Font = iced::Font {
@@ -14,54 +38,424 @@ pub const DEFAULT_FONT: iced::Font = iced::Font {
     ..iced::Font::MONOSPACE
 };
 
-pub fn launch() -> IceResult {
+/// opens a window on `files`, the first one shown
+pub fn launch(files: Vec<PathBuf>) -> IceResult {
     iced::application("Editor - Iced", EvieMain::update, EvieMain::view)
         .font(include_bytes!("../font/ttf/FiraCode-Regular.ttf"))
         .default_font(DEFAULT_FONT)
-        .theme(|_| iced::Theme::Dracula)
-        .run_with(EvieMain::new)
+        .theme(EvieMain::theme)
+        .subscription(EvieMain::subscription)
+        .exit_on_close_request(false)
+        .run_with(move || EvieMain::new(files))
 }
 
 #[derive(Debug)]
 struct EvieMain {
     inner: EvieCentral<KeyAction>, // content: Content,
+    /// whether the cursor is shown, it blinks off every `cursorblink`
+    cursor: bool,
+    lsp: Lsp,
+    /// answers other processes, like `evie file` run again, see
+    /// [`evie_rpc::remote`]
+    remote: Server<KeyAction>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     Idle,
+    /// toggle the cursor, see the `cursorblink` option
+    Blink,
+    /// look for files changed outside of evie
+    CheckDisk,
+    Resolve(PathBuf, Resolve),
+    /// write pending changes to the swap files
+    SyncSwaps,
+    Recover(PathBuf, Recover),
+    /// go to a tab page, from the tab bar
+    Tab(usize),
+    /// ask the system whether it prefers dark or light
+    DetectSystem,
+    /// the system's preference, for the `background` option
+    System(Background),
+    Lsp(Reply),
+    /// go to byte `at` of a file, from the diagnostics list
+    Goto(PathBuf, usize),
+    /// the window is asked to close, which asks first when there are
+    /// unsaved changes
+    Close(window::Id),
+    Quit(window::Id, Unsaved),
+    /// a message from another process
+    Remote(Incoming),
 }
 
 impl EvieMain {
-    fn new() -> (Self, Task<Message>) {
+    fn new(files: Vec<PathBuf>) -> (Self, Task<Message>) {
+        // buffers are known by their full path, which language servers need
+        let mut engine = std::env::current_dir()
+            .ok()
+            .and_then(|dir| Engine::new(dir).ok())
+            .unwrap_or_default();
+        if let Some(dir) = swap::default_dir() {
+            engine = engine.with_swap_dir(dir);
+        }
+        let evie = Evie::new(modes()).with_engine(engine).into_central();
+        if let Some(rc) = disk::config_dir().map(|dir| dir.join("evierc")) {
+            if rc.exists() {
+                if let Err(e) = evie.source(&rc) {
+                    evie.report(e);
+                }
+            }
+        }
+        let first = files.first().cloned().unwrap_or_else(|| "yeah.txt".into());
+        for file in files.iter().skip(1) {
+            if let Err(e) = evie.add_buffer(file, true) {
+                evie.report(e);
+            }
+        }
+        let (evie_main, recover) = Self::open(evie, first);
+        let task = iced::window::get_latest().and_then(|id| iced::window::maximize(id, true));
+        let system = Task::done(Message::DetectSystem);
+        (evie_main, Task::batch([task, recover, system]))
+    }
+
+    /// starts editing `buffer` in the focused window, with an empty buffer if
+    /// it can't be read
+    fn open(evie: EvieCentral<KeyAction>, buffer: PathBuf) -> (Self, Task<Message>) {
+        let buffer = std::path::absolute(&buffer).unwrap_or(buffer);
+        let buf = match evie.add_buffer(&buffer, true) {
+            Ok(buf) => Some(buf),
+            Err(e) => {
+                evie.report(e);
+                evie.engine
+                    .scratch(&buffer, true)
+                    .map_err(|e| evie.report(e))
+                    .ok()
+            }
+        };
+        let recover = buf
+            .as_ref()
+            .and_then(|buf| {
+                let buf = buf.try_borrow().ok()?;
+                Some(Task::perform(
+                    prompt::recover(buf.path.clone(), buf.recovery.clone()?),
+                    |(path, recover)| Message::Recover(path, recover),
+                ))
+            })
+            .unwrap_or_else(Task::none);
+        if let Err(e) = evie.show(&buffer, true) {
+            evie.report(e);
+        }
         let evie_main = Self {
-            inner: Evie::central(modes()),
+            remote: Server::new(evie.clone(), remote_keys),
+            inner: evie,
+            cursor: true,
+            lsp: Lsp::default(),
         };
-        let task = iced::window::get_latest().and_then(|id| iced::window::maximize(id, true));
-        (evie_main, task)
+        (evie_main, recover)
+    }
+
+    /// handles a message, then brings the language servers and remote
+    /// clients up to date with what it changed
+    fn update(&mut self, message: Message) -> Task<Message> {
+        let task = self.handle(message);
+        self.remote.notify();
+        Task::batch([task, self.lsp.sync(&self.inner)])
+    }
+
+    fn handle(&mut self, message: Message) -> Task<Message> {
+        match message {
+            // typing shows the cursor straight away
+            Message::Idle => self.cursor = true,
+            Message::Blink => self.cursor = !self.cursor,
+            Message::CheckDisk => match self.inner.check_disk() {
+                Ok(events) => {
+                    return Task::batch(events.into_iter().map(|event| self.disk_event(event)))
+                }
+                Err(e) => self.inner.report(e),
+            },
+            Message::Resolve(path, Resolve::Reload) => {
+                if let Err(e) = self.inner.execute("e!", Some(&path)) {
+                    self.inner.report(e);
+                }
+            }
+            Message::Resolve(_, Resolve::Keep) => {}
+            Message::Resolve(path, Resolve::Diff) => match self.inner.disk_diff(&path, false) {
+                Ok(diff) => {
+                    return Task::perform(prompt::diff(path, diff), |path| {
+                        Message::Resolve(path, Resolve::Keep)
+                    })
+                    .then(|message| {
+                        let Message::Resolve(path, _) = message else {
+                            return Task::none();
+                        };
+                        Task::perform(prompt::conflict(path), |(path, resolve)| {
+                            Message::Resolve(path, resolve)
+                        })
+                    })
+                }
+                Err(e) => self.inner.report(e),
+            },
+            Message::SyncSwaps => {
+                if let Err(e) = self.inner.sync_swaps() {
+                    self.inner.report(e);
+                }
+            }
+            Message::Recover(path, Recover::Recover) => match self.inner.recover(&path, false) {
+                Ok(n) => self.inner.inform(format!("recovered {n} changes")),
+                Err(e) => self.inner.report(e),
+            },
+            Message::Recover(path, Recover::Delete) => {
+                if let Err(e) = self.inner.engine.discard_swap(&path, false) {
+                    self.inner.report(e);
+                }
+            }
+            Message::Recover(_, Recover::Keep) => {}
+            Message::Tab(index) => {
+                if let Err(e) = self.inner.select_tab(index) {
+                    self.inner.report(e);
+                }
+            }
+            Message::DetectSystem => return Task::perform(detect_system(), Message::System),
+            Message::System(background) => self.inner.system.set(background),
+            Message::Lsp(reply) => match self.lsp.reply(&self.inner, reply) {
+                Ok(task) => return task,
+                Err(e) => self.inner.report(e),
+            },
+            Message::Goto(path, at) => {
+                if let Err(e) = self.inner.goto(&path, at) {
+                    self.inner.report(e);
+                }
+            }
+            Message::Close(id) => {
+                let modified = self.inner.engine.modified();
+                if modified.is_empty() {
+                    return self.quit(id);
+                }
+                return Task::perform(prompt::unsaved(modified), move |unsaved| {
+                    Message::Quit(id, unsaved)
+                });
+            }
+            Message::Quit(id, Unsaved::Discard) => return self.quit(id),
+            Message::Quit(_, Unsaved::Cancel) => {}
+            Message::Remote(incoming) => self.remote.receive(incoming),
+        }
+        // `:quit` in the last window, which already refused unsaved changes
+        // unless forced
+        if self.inner.quit.take() {
+            return window::get_latest()
+                .and_then(|id| Task::done(Message::Quit(id, Unsaved::Discard)));
+        }
+        Task::none()
     }
 
-    fn update(&mut self, _message: Message) -> Task<Message> {
-        // if let Message::Action(_action) = message {}
+    /// removes the swap files, the changes in them being written or
+    /// discarded, and exits
+    fn quit(&mut self, id: window::Id) -> Task<Message> {
+        if let Err(e) = self.inner.engine.close() {
+            self.inner.report(e);
+        }
+        window::close(id).chain(iced::exit())
+    }
+
+    fn disk_event(&mut self, event: DiskEvent) -> Task<Message> {
+        match event {
+            DiskEvent::Reloaded(path) => {
+                self.inner
+                    .inform(format!("\"{}\" reloaded", path.display()));
+            }
+            DiskEvent::Deleted(path) => {
+                self.inner
+                    .inform(format!("\"{}\" was deleted", path.display()));
+            }
+            DiskEvent::Conflict(path) => {
+                return Task::perform(prompt::conflict(path), |(path, resolve)| {
+                    Message::Resolve(path, resolve)
+                });
+            }
+        }
         Task::none()
     }
-    fn view(&self) -> Element<Message> {
-        self.inner.add_buffer("yeah.txt", true).unwrap();
-        evie_editor(self.inner.view_buffer("yeah.txt", true).unwrap()).into()
+
+    fn subscription(&self) -> Subscription<Message> {
+        let blink = match self.inner.engine.options.borrow().cursorblink {
+            0 => Subscription::none(),
+            ms => iced::time::every(Duration::from_millis(ms as u64)).map(|_| Message::Blink),
+        };
+        Subscription::batch([
+            iced::time::every(Duration::from_secs(1)).map(|_| Message::CheckDisk),
+            iced::time::every(Duration::from_secs(4)).map(|_| Message::SyncSwaps),
+            iced::time::every(Duration::from_secs(5)).map(|_| Message::DetectSystem),
+            window::close_requests().map(Message::Close),
+            #[cfg(unix)]
+            Subscription::run(remote),
+            blink,
+        ])
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        // drawing a window borrows the windows, so the layout is copied out
+        let pane = match self.inner.windows() {
+            Ok(windows) => {
+                let (layout, focus) = (windows.layout().clone(), windows.focus());
+                drop(windows);
+                self.pane(&layout, focus)
+            }
+            Err(e) => text(e.to_string()).height(Length::Fill).into(),
+        };
+        column![]
+            .push_maybe(tab_bar(&self.inner))
+            .push(pane)
+            .push_maybe(diagnostics_list(&self.inner))
+            .push(message_area(&self.inner))
+            .into()
+    }
+
+    /// a window with its status line, or a split of them
+    fn pane(&self, layout: &Layout, focus: WindowId) -> Element<'_, Message> {
+        let children = match layout {
+            Layout::Window(id) => {
+                let focused = *id == focus;
+                return match self.inner.view_window(*id) {
+                    Ok(bv) => {
+                        let status = status_line(&bv, focused);
+                        let blink = self.inner.engine.options.borrow().cursorblink;
+                        let cursor = focused && (self.cursor || blink == 0);
+                        column![
+                            evie_editor(bv).with_focus(focused).with_cursor(cursor),
+                            status
+                        ]
+                        .into()
+                    }
+                    Err(e) => text(e.to_string()).height(Length::Fill).into(),
+                };
+            }
+            Layout::Split(_, children) => children,
+        };
+        let total: f32 = children.iter().map(|(_, weight)| weight).sum();
+        let panes = children.iter().map(|(child, weight)| {
+            let share = Length::FillPortion((weight / total * 1000.0).max(1.0) as u16);
+            (container(self.pane(child, focus)), share)
+        });
+        match layout {
+            Layout::Split(Split::Vertical, _) => {
+                let mut row = Row::new();
+                for (i, (pane, share)) in panes.enumerate() {
+                    // a rule between windows side by side, like vim's `|`
+                    if i > 0 {
+                        let (color, _) = highlight::colors(
+                            &self.inner.theme.borrow(),
+                            self.inner.background(),
+                            UiGroup::VertSplit,
+                        );
+                        row = row.push(vertical_rule(1).style(move |theme| rule::Style {
+                            color,
+                            ..rule::default(theme)
+                        }));
+                    }
+                    row = row.push(pane.width(share).height(Length::Fill));
+                }
+                row.into()
+            }
+            _ => Column::with_children(
+                panes.map(|(pane, share)| pane.width(Length::Fill).height(share).into()),
+            )
+            .into(),
+        }
+    }
+
+    /// the iced theme matching the evie theme in use
+    fn theme(&self) -> iced::Theme {
+        highlight::iced_theme(&self.inner.theme.borrow(), self.inner.background())
     }
-    // fn theme(&self) -> Theme {
-    //     if self.theme.is_dark() {
-    //         Theme::Dark
-    //     } else {
-    //         Theme::Light
-    //     }
-    // }
+}
+
+/// whether the system prefers dark or light, dark when it can't tell
+async fn detect_system() -> Background {
+    match tokio::task::spawn_blocking(dark_light::detect).await {
+        Ok(dark_light::Mode::Light) => Background::Light,
+        _ => Background::Dark,
+    }
+}
+
+/// the messages of other processes on the user's socket, see
+/// [`evie_rpc::remote`]
+#[cfg(unix)]
+fn remote() -> impl iced::futures::Stream<Item = Message> {
+    use iced::futures::SinkExt;
+    iced::stream::channel(16, |mut output| async move {
+        let Some(socket) = evie_rpc::remote::socket() else {
+            return;
+        };
+        let (incoming, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let forward = async move {
+            while let Some(incoming) = received.recv().await {
+                if output.send(Message::Remote(incoming)).await.is_err() {
+                    break;
+                }
+            }
+        };
+        // another evie already listening is fine, this one just won't
+        let (served, ()) = tokio::join!(evie_rpc::remote::serve(socket, incoming), forward);
+        if let Err(e) = served {
+            eprintln!("not taking files from other processes: {e}");
+        }
+    })
+}
+
+/// the keys of a remote `input`, written in vim's notation
+fn remote_keys(notation: &str) -> Vec<KeyAction> {
+    use evie_rpc::keys::{self, KeyAction as Key, Named as Name};
+    let named = |name: Name| match name {
+        Name::Enter => Named::Enter,
+        Name::Tab => Named::Tab,
+        Name::Backspace => Named::Backspace,
+        Name::Delete => Named::Delete,
+        Name::Insert => Named::Insert,
+        Name::Escape => Named::Escape,
+        Name::ArrowDown => Named::ArrowDown,
+        Name::ArrowLeft => Named::ArrowLeft,
+        Name::ArrowRight => Named::ArrowRight,
+        Name::ArrowUp => Named::ArrowUp,
+        Name::End => Named::End,
+        Name::Home => Named::Home,
+        Name::PageDown => Named::PageDown,
+        Name::PageUp => Named::PageUp,
+        Name::F(1) => Named::F1,
+        Name::F(2) => Named::F2,
+        Name::F(3) => Named::F3,
+        Name::F(4) => Named::F4,
+        Name::F(5) => Named::F5,
+        Name::F(6) => Named::F6,
+        Name::F(7) => Named::F7,
+        Name::F(8) => Named::F8,
+        Name::F(9) => Named::F9,
+        Name::F(10) => Named::F10,
+        Name::F(11) => Named::F11,
+        // the notation has no keys past F12
+        Name::F(_) => Named::F12,
+    };
+    keys::parse(notation)
+        .into_iter()
+        .map(|key| match key {
+            // iced tells the space bar apart from other letters
+            Key::Letter(' ') => KeyAction::Named(Named::Space),
+            Key::Letter(c) => KeyAction::Letter(c),
+            Key::Ctrl(c) => KeyAction::Ctrl(c),
+            Key::Named(name) => KeyAction::Named(named(name)),
+            Key::Shift(name) => KeyAction::Shift(named(name)),
+        })
+        .collect()
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum KeyAction {
     Letter(char),
+    /// a letter pressed with control held
+    Ctrl(char),
     Named(Named),
+    /// a named key pressed with shift held, for the few told apart from the
+    /// key alone, like `Tab`
+    Shift(Named),
 }
 
 macro_rules! gen_named {
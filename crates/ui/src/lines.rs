@@ -0,0 +1,672 @@
+//! Shaping only the lines on screen
+//!
+//! Shaping a whole buffer every frame is O(file), so the editor lays out the
+//! visible lines plus a margin and keeps their paragraphs around, keyed by
+//! their contents, for as long as they stay near the screen.
+//!
+//! A line that wraps with `breakindent` or `showbreak` is shaped in two
+//! parts, its first row and the rest narrower and moved right, as paragraphs
+//! can't indent their own rows.
+//!
+//! Syntax highlighting comes in as a [`Highlight`] for each colored piece of a
+//! line, which becomes a span of rich text.
+//!
+//! A closed fold takes a single line, showing how many lines it hides and the
+//! first of them. Its row has no characters, so the cursor and clicks land on
+//! the start of the fold.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Range;
+
+use evie_core::disk::Fnv;
+use evie_core::motion::{line_col, line_text, DisplayLines, DisplayMove};
+use iced::advanced::text::{self, Paragraph as _};
+use iced::widget::text::{LineHeight, Shaping, Wrapping};
+use iced::{alignment, Color, Font, Pixels, Point, Rectangle, Size, Vector};
+use iced_renderer::graphics::text::Paragraph;
+use ropey::Rope;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::font;
+
+/// lines shaped past the bottom of the screen, so scrolling doesn't stall
+const MARGIN: usize = 16;
+
+/// Everything a paragraph's shape depends on besides its text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shape {
+    pub width: f32,
+    pub size: Pixels,
+    pub line_height: LineHeight,
+    pub font: Font,
+    /// for wide characters, see [`crate::font`]
+    pub wide: Option<Font>,
+    pub ligatures: bool,
+    pub wrapping: Wrapping,
+    /// whether wrapped rows are indented as far as their line
+    pub breakindent: bool,
+    /// the width of `showbreak`, drawn before wrapped rows
+    pub showbreak: f32,
+}
+
+impl Shape {
+    /// the height of a single row of text
+    pub fn row_height(&self) -> f32 {
+        self.line_height.to_absolute(self.size).0
+    }
+}
+
+/// How a piece of a line is drawn, see [`crate::highlight`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    /// where it is in the line, in bytes
+    pub range: Range<usize>,
+    /// none keeps the text's color
+    pub color: Option<Color>,
+    pub font: Font,
+    pub underline: bool,
+}
+
+/// What lines are laid out from
+pub struct Source<'a> {
+    pub rope: &'a Rope,
+    /// bumped whenever the text changes
+    pub revision: u64,
+    /// the closed folds, each shown as a single line
+    pub folds: &'a [Range<usize>],
+    /// the highlights of each line
+    pub highlight: &'a dyn Fn(usize) -> Vec<Highlight>,
+}
+
+/// A line laid out on screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Line {
+    /// the line's index in the buffer
+    pub index: usize,
+    /// how many lines after it are folded away behind it
+    pub hidden: usize,
+    hash: u64,
+    /// the offset from the top of the text area
+    pub y: f32,
+    pub height: f32,
+}
+
+/// Part of a line shaped on its own, see the module docs
+#[derive(Debug)]
+pub struct Part {
+    pub paragraph: Paragraph,
+    /// where the part starts in the line, in bytes
+    pub start: usize,
+    /// where it's drawn, relative to the line
+    pub offset: Vector,
+}
+
+/// A row of text on screen, one of several when its line wraps
+#[derive(Debug)]
+struct Row {
+    /// the line's index in the buffer
+    index: usize,
+    /// where the row starts in the line, in bytes
+    start: usize,
+    /// where each cluster starts in the line, with its left edge
+    clusters: Vec<(usize, f32)>,
+    /// the right edge of the text
+    right: f32,
+    /// the top of the row and its height, relative to the text area
+    top: f32,
+    height: f32,
+    /// whether the line carries on in the next row
+    wraps: bool,
+}
+
+impl Row {
+    /// the cluster drawn at `x`, or `end` past the end of the line
+    fn at(&self, x: f32, end: usize) -> usize {
+        if x >= self.right && !self.wraps {
+            return end;
+        }
+        self.clusters
+            .iter()
+            .rev()
+            .find(|(_, left)| *left <= x)
+            .or(self.clusters.first())
+            .map_or(self.start, |(at, _)| *at)
+    }
+}
+
+/// Where the cursor is drawn
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    /// the cell under the cursor, relative to the text area
+    pub bounds: Rectangle,
+    /// the grapheme under the cursor, empty past the end of the line
+    pub grapheme: String,
+}
+
+#[derive(Debug, Default)]
+pub struct LineCache {
+    shape: Option<Shape>,
+    /// the width of a `0` and what it was measured with, for a cursor with
+    /// no character under it and for turning columns into pixels
+    cell: Option<(Pixels, Font, f32)>,
+    shaped: HashMap<u64, Vec<Part>>,
+    /// the `showbreak` text and what it was shaped with
+    showbreak: Option<(String, Pixels, Font, Paragraph)>,
+    /// the lines on screen, top to bottom, followed by the margin
+    pub visible: Vec<Line>,
+    /// what `visible` was built from, to skip rebuilding it
+    built: Option<(u64, usize, f32, Vec<Range<usize>>)>,
+}
+
+impl LineCache {
+    /// lays out the lines starting at `top` that fit in `height`
+    pub fn layout(&mut self, source: &Source, top: usize, shape: Shape, height: f32) {
+        if self.shape != Some(shape) {
+            self.shaped.clear();
+            self.built = None;
+            self.shape = Some(shape);
+        }
+        if let Some((revision, t, h, folds)) = &self.built {
+            if (*revision, *t, *h) == (source.revision, top, height) && folds == source.folds {
+                return;
+            }
+        }
+
+        let rope = source.rope;
+        let row = shape.row_height();
+        let mut visible = Vec::new();
+        let mut y = 0.0;
+        let mut margin = 0;
+        let mut index = top;
+        while index < rope.len_lines() {
+            if y >= height {
+                margin += 1;
+                if margin > MARGIN {
+                    break;
+                }
+            }
+            let fold = source.folds.iter().find(|fold| fold.contains(&index));
+            let (content, highlights, hidden) = match fold {
+                Some(fold) => {
+                    let first = line_text(rope, fold.start);
+                    let content = format!("+--{:>3} lines: {}", fold.len(), first.trim());
+                    (content, Vec::new(), fold.end - index - 1)
+                }
+                None => (line_text(rope, index), (source.highlight)(index), 0),
+            };
+            let hash = hash(&content, &highlights, fold.is_some());
+            let parts = self
+                .shaped
+                .entry(hash)
+                .or_insert_with(|| shape_line(&content, &highlights, shape));
+            let line_height = parts
+                .iter()
+                .map(|part| part.offset.y + part.paragraph.min_bounds().height)
+                .fold(row, f32::max);
+            visible.push(Line {
+                index,
+                hidden,
+                hash,
+                y,
+                height: line_height,
+            });
+            y += line_height;
+            index += hidden + 1;
+        }
+        self.shaped
+            .retain(|hash, _| visible.iter().any(|line| line.hash == *hash));
+        self.visible = visible;
+        self.built = Some((source.revision, top, height, source.folds.to_vec()));
+    }
+
+    /// lays the lines out again next time, as when their highlights change
+    pub fn refresh(&mut self) {
+        self.built = None;
+    }
+
+    /// what the lines were last laid out with
+    pub fn shape(&self) -> Option<Shape> {
+        self.shape
+    }
+
+    pub fn parts(&self, line: &Line) -> &[Part] {
+        self.shaped.get(&line.hash).map_or(&[], Vec::as_slice)
+    }
+
+    /// shapes `showbreak`, returning its width
+    pub fn showbreak(&mut self, content: &str, size: Pixels, font: Font) -> f32 {
+        if content.is_empty() {
+            self.showbreak = None;
+            return 0.0;
+        }
+        match &self.showbreak {
+            Some((c, s, f, paragraph)) if (c.as_str(), *s, *f) == (content, size, font) => {
+                paragraph.min_width()
+            }
+            _ => {
+                let paragraph = paragraph(
+                    content,
+                    size,
+                    LineHeight::default(),
+                    font,
+                    f32::INFINITY,
+                    Wrapping::None,
+                );
+                let width = paragraph.min_width();
+                self.showbreak = Some((content.to_owned(), size, font, paragraph));
+                width
+            }
+        }
+    }
+
+    /// where `showbreak` goes before each wrapped row of a line, relative to
+    /// the text area
+    pub fn breaks(&self, line: &Line) -> Vec<(Point, &Paragraph)> {
+        let Some((.., showbreak)) = &self.showbreak else {
+            return Vec::new();
+        };
+        let width = showbreak.min_width();
+        self.parts(line)
+            .iter()
+            .skip(1)
+            .flat_map(|part| {
+                part.paragraph.buffer().layout_runs().map(move |run| {
+                    let at =
+                        Point::new(part.offset.x - width, line.y + part.offset.y + run.line_top);
+                    (at, showbreak)
+                })
+            })
+            .collect()
+    }
+
+    /// every row on screen, top to bottom
+    fn rows(&self) -> Vec<Row> {
+        let mut rows: Vec<Row> = Vec::new();
+        for line in &self.visible {
+            if line.hidden > 0 {
+                rows.push(Row {
+                    index: line.index,
+                    start: 0,
+                    clusters: Vec::new(),
+                    right: f32::INFINITY,
+                    top: line.y,
+                    height: line.height,
+                    wraps: false,
+                });
+                continue;
+            }
+            for part in self.parts(line) {
+                for run in part.paragraph.buffer().layout_runs() {
+                    let mut clusters: Vec<_> = run
+                        .glyphs
+                        .iter()
+                        .map(|glyph| (part.start + glyph.start, part.offset.x + glyph.x))
+                        .collect();
+                    clusters.dedup_by_key(|(start, _)| *start);
+                    rows.push(Row {
+                        index: line.index,
+                        start: clusters.first().map_or(part.start, |(start, _)| *start),
+                        clusters,
+                        right: part.offset.x + run.line_w,
+                        top: line.y + part.offset.y + run.line_top,
+                        height: run.line_height,
+                        wraps: true,
+                    });
+                }
+            }
+            if let Some(last) = rows.last_mut().filter(|row| row.index == line.index) {
+                last.wraps = false;
+            }
+        }
+        rows
+    }
+
+    /// the character drawn at `point`, relative to the text area, as a byte
+    /// offset into the text. points past the end of a row or below the last
+    /// one land on the nearest character
+    pub fn hit(&self, text: &Rope, point: Point) -> Option<usize> {
+        let rows = self.rows();
+        let row = rows
+            .iter()
+            .find(|row| point.y < row.top + row.height)
+            .or(rows.last())?;
+        let end = line_text(text, row.index).len();
+        Some(text.line_to_byte(row.index) + row.at(point.x, end))
+    }
+
+    /// the areas that cover `range` of the text, one for each row it's on,
+    /// relative to the text area
+    pub fn highlight(&self, text: &Rope, range: Range<usize>) -> Vec<Rectangle> {
+        self.rows()
+            .iter()
+            .filter_map(|row| {
+                let start = text.line_to_byte(row.index);
+                let inside = |at: usize| range.contains(&(start + at));
+                let first = row.clusters.iter().position(|(at, _)| inside(*at))?;
+                let last = row.clusters.iter().rposition(|(at, _)| inside(*at))?;
+                let right = row
+                    .clusters
+                    .get(last + 1)
+                    .map_or(row.right, |(_, left)| *left);
+                let left = row.clusters[first].1;
+                Some(Rectangle {
+                    x: left,
+                    y: row.top,
+                    width: right - left,
+                    height: row.height,
+                })
+            })
+            .collect()
+    }
+
+    /// finds the cell `offset` bytes into line `index`, none if the line isn't
+    /// on screen
+    ///
+    /// a glyph can cover several graphemes, as with ligatures, in which case
+    /// it is split evenly between them. a grapheme can also be drawn with
+    /// several glyphs, like a letter with combining marks, in which case the
+    /// cell covers all of them.
+    pub fn cursor(&self, index: usize, offset: usize) -> Option<Cursor> {
+        let line = self.visible.iter().find(|line| line.index == index)?;
+        if line.hidden > 0 {
+            return Some(Cursor {
+                bounds: Rectangle {
+                    x: 0.0,
+                    y: line.y,
+                    width: self.cell(),
+                    height: line.height,
+                },
+                grapheme: String::new(),
+            });
+        }
+        let part = self
+            .parts(line)
+            .iter()
+            .rev()
+            .find(|part| part.start <= offset)?;
+        let offset = offset - part.start;
+        let (x, y) = (part.offset.x, line.y + part.offset.y);
+        let mut last = None;
+        for run in part.paragraph.buffer().layout_runs() {
+            last = Some((run.line_w, run.line_top, run.line_height));
+            let cluster = run
+                .glyphs
+                .iter()
+                .filter(|glyph| glyph.start <= offset && offset < glyph.end);
+            let Some((start, end, left, right)) = cluster.fold(None, |acc, glyph| {
+                let (start, end, left, right) =
+                    acc.unwrap_or((glyph.start, glyph.end, glyph.x, glyph.x + glyph.w));
+                Some((
+                    start.min(glyph.start),
+                    end.max(glyph.end),
+                    left.min(glyph.x),
+                    right.max(glyph.x + glyph.w),
+                ))
+            }) else {
+                continue;
+            };
+            let graphemes: Vec<_> = run.text[start..end].grapheme_indices(true).collect();
+            let nth = graphemes
+                .iter()
+                .rposition(|(i, _)| start + i <= offset)
+                .unwrap_or(0);
+            let width = (right - left) / graphemes.len().max(1) as f32;
+            return Some(Cursor {
+                bounds: Rectangle {
+                    x: x + left + width * nth as f32,
+                    y: y + run.line_top,
+                    width,
+                    height: run.line_height,
+                },
+                grapheme: graphemes.get(nth).map_or("", |(_, g)| g).to_owned(),
+            });
+        }
+        // past the end of the line
+        let (right, top, height) = last.unwrap_or((0.0, 0.0, line.height));
+        Some(Cursor {
+            bounds: Rectangle {
+                x: x + right,
+                y: y + top,
+                width: self.cell(),
+                height,
+            },
+            grapheme: String::new(),
+        })
+    }
+
+    /// the width of a character cell, see [`LineCache::measure`]
+    pub fn cell(&self) -> f32 {
+        self.cell.map_or(0.0, |(.., width)| width)
+    }
+
+    /// measures the width of a character cell in the given text size and font
+    pub fn measure(&mut self, size: Pixels, font: Font) -> f32 {
+        match self.cell {
+            Some((s, f, width)) if (s, f) == (size, font) => width,
+            _ => {
+                let width = paragraph(
+                    "0",
+                    size,
+                    LineHeight::default(),
+                    font,
+                    f32::INFINITY,
+                    Wrapping::None,
+                )
+                .min_width();
+                self.cell = Some((size, font, width));
+                width
+            }
+        }
+    }
+
+    /// how many lines fit whole in `height`, at least one, counting those
+    /// folded away
+    pub fn fits(&self, height: f32) -> usize {
+        self.visible
+            .iter()
+            .take_while(|line| line.y + line.height <= height)
+            .map(|line| line.hidden + 1)
+            .sum::<usize>()
+            .max(1)
+    }
+
+    /// the height of the lines on screen, for shrinking to fit
+    pub fn height(&self) -> f32 {
+        self.visible.last().map_or(0.0, |line| line.y + line.height)
+    }
+}
+
+fn hash(content: &str, highlights: &[Highlight], fold: bool) -> u64 {
+    let mut hasher = Fnv::default();
+    hasher.write(content.as_bytes());
+    fold.hash(&mut hasher);
+    for highlight in highlights {
+        let Highlight {
+            range,
+            color,
+            font,
+            underline,
+        } = highlight;
+        range.hash(&mut hasher);
+        color.map(Color::into_rgba8).hash(&mut hasher);
+        font.hash(&mut hasher);
+        underline.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+impl DisplayLines for LineCache {
+    fn resolve(
+        &self,
+        text: &Rope,
+        at: usize,
+        want: Option<usize>,
+        mv: DisplayMove,
+    ) -> Option<(usize, Option<usize>)> {
+        let (line, offset) = line_col(text, at);
+        let rows = self.rows();
+        let i = rows
+            .iter()
+            .rposition(|row| row.index == line && row.start <= offset)?;
+        let start = |row: &Row| text.line_to_byte(row.index);
+        let end = |row: &Row| line_text(text, row.index).len();
+        let row = &rows[i];
+        match mv {
+            DisplayMove::Start => Some((start(row) + row.start, None)),
+            DisplayMove::End if row.wraps => {
+                let last = row.clusters.last().map_or(row.start, |(at, _)| *at);
+                Some((start(row) + last, None))
+            }
+            DisplayMove::End => Some((start(row) + end(row), None)),
+            DisplayMove::Rows(by) => {
+                // like vim the column aimed for is in cells, kept while
+                // passing through short rows
+                let cell = self.cell().max(1.0);
+                let x = match want {
+                    Some(want) => want as f32 * cell,
+                    None => self.cursor(line, offset)?.bounds.x,
+                };
+                let target = rows.get(i.checked_add_signed(by)?)?;
+                let at = target.at(x + cell / 2.0, end(target));
+                let want = want.unwrap_or((x / cell).round() as usize);
+                Some((start(target) + at, Some(want)))
+            }
+        }
+    }
+}
+
+/// shapes a line, in two parts when its wrapped rows are indented
+fn shape_line(content: &str, highlights: &[Highlight], shape: Shape) -> Vec<Part> {
+    let shaped = |range: Range<usize>, width, wrapping| {
+        let spans = spans(content, range.clone(), highlights, &shape);
+        Paragraph::with_spans(text::Text {
+            content: spans.as_slice(),
+            bounds: Size::new(width, f32::INFINITY),
+            size: shape.size,
+            line_height: shape.line_height,
+            font: shape.font,
+            horizontal_alignment: alignment::Horizontal::Left,
+            vertical_alignment: alignment::Vertical::Top,
+            shaping: Shaping::Advanced,
+            wrapping,
+        })
+    };
+    let whole = shaped(0..content.len(), shape.width, shape.wrapping);
+    let hang = shape.breakindent || shape.showbreak > 0.0;
+    let split = {
+        let mut runs = whole.buffer().layout_runs();
+        match (runs.next(), runs.next()) {
+            (Some(first), Some(_)) if hang => {
+                let split = first.glyphs.iter().map(|glyph| glyph.end).max();
+                // the first glyph that isn't blank is as far as the line is
+                // indented
+                let indent = first
+                    .glyphs
+                    .iter()
+                    .find(|glyph| !content[glyph.start..glyph.end].trim().is_empty())
+                    .map_or(0.0, |glyph| glyph.x);
+                split.map(|split| (split, indent, first.line_top + first.line_height))
+            }
+            _ => None,
+        }
+    };
+    let Some((split, indent, top)) = split.filter(|(split, ..)| *split < content.len()) else {
+        return vec![Part {
+            paragraph: whole,
+            start: 0,
+            offset: Vector::ZERO,
+        }];
+    };
+    let indent = if shape.breakindent { indent } else { 0.0 };
+    // wrapped rows keep at least half the width for their text
+    let hanging = (indent + shape.showbreak).min(shape.width / 2.0);
+    vec![
+        Part {
+            paragraph: shaped(0..split, f32::INFINITY, Wrapping::None),
+            start: 0,
+            offset: Vector::ZERO,
+        },
+        Part {
+            paragraph: shaped(split..content.len(), shape.width - hanging, shape.wrapping),
+            start: split,
+            offset: Vector::new(hanging, top),
+        },
+    ]
+}
+
+/// the rich text for `range` of a line, highlighted or not, in pieces for
+/// the fonts
+fn spans<'a>(
+    content: &'a str,
+    range: Range<usize>,
+    highlights: &[Highlight],
+    shape: &Shape,
+) -> Vec<text::Span<'a, (), Font>> {
+    let mut pieces = Vec::new();
+    let mut at = range.start;
+    for highlight in highlights {
+        let start = highlight.range.start.clamp(at, range.end);
+        let end = highlight.range.end.clamp(start, range.end);
+        if start == end {
+            continue;
+        }
+        if at < start {
+            pieces.push((at..start, None));
+        }
+        pieces.push((start..end, Some(highlight)));
+        at = end;
+    }
+    if at < range.end || pieces.is_empty() {
+        pieces.push((at..range.end, None));
+    }
+    let mut spans = Vec::new();
+    for (range, highlight) in pieces {
+        let font = highlight.map_or(shape.font, |highlight| highlight.font);
+        let runs = font::runs(
+            &content[range.clone()],
+            shape.ligatures,
+            shape.wide.is_some(),
+        );
+        if runs.is_empty() {
+            spans.push(text::Span::new(&content[range.clone()]));
+        }
+        for (run, wide) in runs {
+            let font = match shape.wide {
+                Some(wide_font) if wide => Font {
+                    family: wide_font.family,
+                    ..font
+                },
+                _ => font,
+            };
+            spans.push(text::Span {
+                color: highlight.and_then(|highlight| highlight.color),
+                font: Some(font),
+                underline: highlight.is_some_and(|highlight| highlight.underline),
+                ..text::Span::new(&content[range.start + run.start..range.start + run.end])
+            });
+        }
+    }
+    spans
+}
+
+fn paragraph(
+    content: &str,
+    size: Pixels,
+    line_height: LineHeight,
+    font: Font,
+    width: f32,
+    wrapping: Wrapping,
+) -> Paragraph {
+    Paragraph::with_text(text::Text {
+        content,
+        bounds: Size::new(width, f32::INFINITY),
+        size,
+        line_height,
+        font,
+        horizontal_alignment: alignment::Horizontal::Left,
+        vertical_alignment: alignment::Vertical::Top,
+        shaping: Shaping::Advanced,
+        wrapping,
+    })
+}
@@ -0,0 +1,436 @@
+//! Language servers for the buffers being edited, see [`evie_lsp`]
+//!
+//! After every update the buffers are looked over. One with a server for its
+//! filetype starts that server if it isn't running yet, is opened on it once
+//! it is, and has its edits sent on from then. Requests the core queued go
+//! out as tasks, their answers coming back as [`Message::Lsp`], as do the
+//! diagnostics servers publish.
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
+
+use evie_core::completion::Item;
+use evie_core::diagnostic::{Diagnostic, Severity};
+use evie_core::engine::{BufferPointer, Replace};
+use evie_core::{Evie, EvieError, LspAction, LspRequest};
+use evie_lsp::client::{Client, Notification};
+use evie_lsp::document::{self, Document, Encoding};
+use evie_lsp::lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    PublishDiagnostics,
+};
+use evie_lsp::lsp_types::{
+    CompletionItem, DiagnosticSeverity, DocumentChanges, FormattingOptions, InsertTextFormat,
+    Location, OneOf, PublishDiagnosticsParams, TextDocumentSyncKind, TextEdit, Url, WorkspaceEdit,
+};
+use evie_lsp::{servers, LspError};
+use iced::futures::stream;
+use iced::Task;
+
+use crate::{KeyAction, Message};
+
+/// a server is kept for each filetype and project root
+type Key = (String, PathBuf);
+
+/// what the diagnostics of language servers are set as, see
+/// [`Diagnostics::set`](evie_core::diagnostic::Diagnostics::set), and the
+/// source their completions come from
+const PROVIDER: &str = "lsp";
+
+#[derive(Debug)]
+enum Server {
+    Starting,
+    Running(Client),
+    /// it couldn't be started, which was reported once
+    Failed,
+}
+
+/// A buffer a server follows
+#[derive(Debug)]
+struct Tracked {
+    key: Key,
+    document: Document,
+}
+
+/// What came back from a server
+#[derive(Debug, Clone)]
+pub enum Reply {
+    Started(Key, Result<Client, String>),
+    Hover(Option<String>),
+    Definition(Vec<Location>, Encoding),
+    References(Vec<Location>, Encoding),
+    /// a rename or formatting, worked out on `version` of the document at
+    /// `path`
+    Edit {
+        edit: WorkspaceEdit,
+        encoding: Encoding,
+        path: PathBuf,
+        version: i32,
+    },
+    /// candidates for the completion open in a buffer
+    Completion(PathBuf, Vec<CompletionItem>),
+    /// something a server said without being asked
+    Notification(Notification),
+    Error(String),
+}
+
+#[derive(Debug, Default)]
+pub struct Lsp {
+    servers: HashMap<Key, Server>,
+    documents: HashMap<PathBuf, Tracked>,
+}
+
+impl Lsp {
+    /// the filetypes and project roots with a server, running or not
+    pub fn projects(&self) -> impl Iterator<Item = &Key> {
+        self.servers.keys()
+    }
+
+    /// starts servers and opens buffers on them, sends on edits and writes,
+    /// closes the buffers that are gone and sends off requests
+    pub fn sync(&mut self, evie: &Evie<KeyAction>) -> Task<Message> {
+        if !evie.engine.options.borrow().lsp {
+            evie.requests.borrow_mut().clear();
+            return Task::none();
+        }
+        let buffers = evie.engine.buffers();
+        self.close(&buffers);
+        let mut tasks = Vec::new();
+        for buf in buffers {
+            let Ok(mut buf) = buf.try_borrow_mut() else {
+                continue;
+            };
+            if let Some(tracked) = self.documents.get_mut(&buf.path) {
+                let Some(Server::Running(client)) = self.servers.get(&tracked.key) else {
+                    continue;
+                };
+                let edits = buf.edits.replace(Vec::new()).unwrap_or_default();
+                let edits = edits.into_iter().map(|edit| (edit.range, edit.text));
+                let full = match client.sync() {
+                    TextDocumentSyncKind::NONE => continue,
+                    kind => kind == TextDocumentSyncKind::FULL,
+                };
+                if let Some(change) = tracked.document.change(edits, full) {
+                    let _ = client.notify::<DidChangeTextDocument>(change);
+                }
+                if let (true, Some(text)) = (std::mem::take(&mut buf.written), client.save()) {
+                    let _ = client.notify::<DidSaveTextDocument>(tracked.document.save(text));
+                }
+                continue;
+            }
+            let Some(config) = servers::config(&buf.local.filetype) else {
+                continue;
+            };
+            let Ok(uri) = Url::from_file_path(&buf.path) else {
+                continue;
+            };
+            let key = (
+                buf.local.filetype.clone(),
+                servers::root(&buf.path, &config.roots),
+            );
+            match self.servers.get(&key) {
+                None => {
+                    self.servers.insert(key.clone(), Server::Starting);
+                    let root = key.1.clone();
+                    tasks.push(Task::perform(
+                        async move { Client::spawn(&config, &root).await },
+                        move |client| {
+                            let client = client.map_err(|e| e.to_string());
+                            Message::Lsp(Reply::Started(key.clone(), client))
+                        },
+                    ));
+                }
+                Some(Server::Running(client)) => {
+                    let document = Document::new(uri, buf.text.clone(), client.encoding());
+                    let _ =
+                        client.notify::<DidOpenTextDocument>(document.open(&config.language_id));
+                    buf.edits = Some(Vec::new());
+                    buf.written = false;
+                    let tracked = Tracked { key, document };
+                    self.documents.insert(buf.path.clone(), tracked);
+                }
+                Some(Server::Starting | Server::Failed) => {}
+            }
+        }
+        let requests = std::mem::take(&mut *evie.requests.borrow_mut());
+        tasks.extend(requests.into_iter().map(|request| self.request(request)));
+        Task::batch(tasks)
+    }
+
+    /// stops following the documents whose buffers are gone
+    fn close(&mut self, buffers: &[BufferPointer]) {
+        // a buffer in use may well be one of them, that's for next time
+        let Ok(open) = buffers
+            .iter()
+            .map(|buf| Ok(buf.try_borrow()?.path.clone()))
+            .collect::<Result<HashSet<PathBuf>, std::cell::BorrowError>>()
+        else {
+            return;
+        };
+        self.documents.retain(|path, tracked| {
+            if open.contains(path) {
+                return true;
+            }
+            if let Some(Server::Running(client)) = self.servers.get(&tracked.key) {
+                let _ = client.notify::<DidCloseTextDocument>(tracked.document.close());
+            }
+            false
+        });
+    }
+
+    /// sends a request off to the server of its buffer
+    fn request(&self, request: LspRequest) -> Task<Message> {
+        let server = self.documents.get(&request.path).and_then(|tracked| {
+            match self.servers.get(&tracked.key) {
+                Some(Server::Running(client)) => Some((client.clone(), &tracked.document)),
+                _ => None,
+            }
+        });
+        let Some((client, document)) = server else {
+            // completing carries on with the other sources
+            if request.action == LspAction::Complete {
+                return Task::none();
+            }
+            let error = "No language server for this buffer".to_owned();
+            return Task::done(Message::Lsp(Reply::Error(error)));
+        };
+        let (id, position) = (document.id(), document.position(request.at));
+        let (encoding, version, path) = (client.encoding(), document.version, request.path);
+        match request.action {
+            LspAction::Hover => {
+                perform(async move { client.hover(id, position).await.map(Reply::Hover) })
+            }
+            LspAction::Definition => perform(async move {
+                let locations = client.definition(id, position).await?;
+                Ok(Reply::Definition(locations, encoding))
+            }),
+            LspAction::References => perform(async move {
+                let locations = client.references(id, position).await?;
+                Ok(Reply::References(locations, encoding))
+            }),
+            LspAction::Rename(name) => perform(async move {
+                let edit = client.rename(id, position, name).await?;
+                Ok(Reply::Edit {
+                    edit: edit.unwrap_or_default(),
+                    encoding,
+                    path,
+                    version,
+                })
+            }),
+            LspAction::Complete => perform(async move {
+                let items = client.completion(id, position).await?;
+                Ok(Reply::Completion(path, items))
+            }),
+            LspAction::Format => {
+                let options = FormattingOptions {
+                    tab_size: 4,
+                    insert_spaces: true,
+                    ..Default::default()
+                };
+                perform(async move {
+                    let uri = id.uri.clone();
+                    let edits = client.format(id, options).await?;
+                    let edit = WorkspaceEdit::new([(uri, edits)].into());
+                    Ok(Reply::Edit {
+                        edit,
+                        encoding,
+                        path,
+                        version,
+                    })
+                })
+            }
+        }
+    }
+
+    /// acts on what came back from a server, a server that started is
+    /// listened to from then on
+    pub fn reply(
+        &mut self,
+        evie: &Evie<KeyAction>,
+        reply: Reply,
+    ) -> Result<Task<Message>, EvieError> {
+        match reply {
+            Reply::Started(key, Ok(client)) => {
+                self.servers.insert(key, Server::Running(client.clone()));
+                return Ok(listen(client));
+            }
+            Reply::Started(key, Err(e)) => {
+                self.servers.insert(key, Server::Failed);
+                return Err(EvieError::Lsp(e));
+            }
+            Reply::Hover(Some(text)) => evie.inform(text.trim()),
+            Reply::Hover(None) => evie.inform("No information available"),
+            Reply::Definition(locations, encoding) => match locations.first() {
+                Some(location) => goto(evie, location, encoding)?,
+                None => evie.inform("No definition found"),
+            },
+            Reply::References(locations, encoding) => match locations.first() {
+                Some(location) => {
+                    goto(evie, location, encoding)?;
+                    evie.inform(format!("{} references", locations.len()));
+                }
+                None => evie.inform("No references found"),
+            },
+            Reply::Edit {
+                edit,
+                encoding,
+                path: asked,
+                version: asked_version,
+            } => {
+                let mut files = Vec::new();
+                for (uri, version, edits) in workspace_edits(edit) {
+                    let path = file_path(&uri)?;
+                    // what the server worked on, as it says or as it was
+                    // asked about
+                    let version = version.or((path == asked).then_some(asked_version));
+                    let current = self.documents.get(&path).map(|t| t.document.version);
+                    if let (Some(version), Some(current)) = (version, current) {
+                        if version != current {
+                            return Err(EvieError::Lsp(format!(
+                                "\"{}\" changed since, not applying the edit",
+                                path.display()
+                            )));
+                        }
+                    }
+                    files.push((path, edits));
+                }
+                for (path, edits) in files {
+                    let buf = match evie.engine.get_buffer(&path, false) {
+                        Ok(buf) => buf,
+                        Err(_) => evie.add_buffer(&path, false)?,
+                    };
+                    let mut buf = buf.try_borrow_mut()?;
+                    let edits: Vec<_> = edits
+                        .iter()
+                        .map(|edit| {
+                            let start = document::offset(&buf.text, edit.range.start, encoding);
+                            let end = document::offset(&buf.text, edit.range.end, encoding);
+                            Replace {
+                                range: start..end.max(start),
+                                new_text: &edit.new_text,
+                            }
+                        })
+                        .collect();
+                    buf.replace(&edits)?;
+                }
+            }
+            Reply::Completion(path, items) => {
+                let items = items.into_iter().map(|item| {
+                    let menu = item.kind.map(|kind| format!("{kind:?}").to_lowercase());
+                    let snippet = item.insert_text_format == Some(InsertTextFormat::SNIPPET);
+                    let completed = match (snippet, item.insert_text) {
+                        // shown as their label, and expanded when accepted
+                        (true, Some(body)) => Item::new(item.label, PROVIDER).with_snippet(body),
+                        (_, text) => Item::new(text.unwrap_or(item.label), PROVIDER),
+                    };
+                    match menu {
+                        Some(menu) => completed.with_menu(menu),
+                        None => completed,
+                    }
+                });
+                evie.complete_with(&path, items.collect())?;
+            }
+            Reply::Notification(notification) => {
+                if let Some(params) = notification.params::<PublishDiagnostics>() {
+                    self.diagnostics(evie, params)?;
+                }
+            }
+            Reply::Error(e) => return Err(EvieError::Lsp(e)),
+        }
+        Ok(Task::none())
+    }
+
+    /// takes in what a server found wrong with one of the buffers it follows
+    fn diagnostics(
+        &self,
+        evie: &Evie<KeyAction>,
+        params: PublishDiagnosticsParams,
+    ) -> Result<(), EvieError> {
+        let path = file_path(&params.uri)?;
+        let (Some(tracked), Ok(buf)) = (
+            self.documents.get(&path),
+            evie.engine.get_buffer(&path, false),
+        ) else {
+            return Ok(());
+        };
+        let diagnostics = params.diagnostics.into_iter().map(|found| {
+            let start = tracked.document.offset(found.range.start);
+            let end = tracked.document.offset(found.range.end).max(start);
+            let severity = match found.severity {
+                Some(DiagnosticSeverity::WARNING) => Severity::Warning,
+                Some(DiagnosticSeverity::INFORMATION) => Severity::Info,
+                Some(DiagnosticSeverity::HINT) => Severity::Hint,
+                _ => Severity::Error,
+            };
+            let diagnostic = Diagnostic::new(start..end, severity, found.message);
+            match found.source {
+                Some(source) => diagnostic.with_source(source),
+                None => diagnostic,
+            }
+        });
+        buf.try_borrow_mut()?
+            .diagnostics
+            .set(PROVIDER, diagnostics.collect());
+        Ok(())
+    }
+}
+
+/// the notifications of a server, for as long as it runs
+fn listen(client: Client) -> Task<Message> {
+    let notifications = stream::unfold(client, |client| async move {
+        let notification = client.notification().await?;
+        Some((notification, client))
+    });
+    Task::run(notifications, |notification| {
+        Message::Lsp(Reply::Notification(notification))
+    })
+}
+
+fn perform(
+    future: impl Future<Output = Result<Reply, LspError>> + Send + 'static,
+) -> Task<Message> {
+    Task::perform(future, |reply| {
+        Message::Lsp(reply.unwrap_or_else(|e| Reply::Error(e.to_string())))
+    })
+}
+
+fn file_path(uri: &Url) -> Result<PathBuf, EvieError> {
+    uri.to_file_path()
+        .map_err(|_| EvieError::Lsp(format!("Not a file: {uri}")))
+}
+
+/// puts the cursor on a location a server pointed at
+fn goto(evie: &Evie<KeyAction>, location: &Location, encoding: Encoding) -> Result<(), EvieError> {
+    let path = file_path(&location.uri)?;
+    let buf = match evie.engine.get_buffer(&path, false) {
+        Ok(buf) => buf,
+        Err(_) => evie.add_buffer(&path, false)?,
+    };
+    let at = document::offset(&buf.try_borrow()?.text, location.range.start, encoding);
+    evie.goto(&path, at)
+}
+
+/// the edits to each file, from either of the forms servers send them in,
+/// with the version they're for when the server gave it
+fn workspace_edits(edit: WorkspaceEdit) -> Vec<(Url, Option<i32>, Vec<TextEdit>)> {
+    let mut files: Vec<_> = edit
+        .changes
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(uri, edits)| (uri, None, edits))
+        .collect();
+    let documents = match edit.document_changes {
+        Some(DocumentChanges::Edits(documents)) => documents,
+        Some(DocumentChanges::Operations(_)) | None => Vec::new(),
+    };
+    for document in documents {
+        let edits = document.edits.into_iter().map(|edit| match edit {
+            OneOf::Left(edit) => edit,
+            OneOf::Right(annotated) => annotated.text_edit,
+        });
+        let id = document.text_document;
+        files.push((id.uri, id.version, edits.collect()));
+    }
+    files
+}
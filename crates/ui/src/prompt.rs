@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+
+use rfd::{AsyncMessageDialog, MessageButtons, MessageDialogResult, MessageLevel};
+
+/// What to do with a buffer whose file was changed by something else
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolve {
+    /// throw away local changes, like `:e!`
+    Reload,
+    /// keep the buffer as it is
+    Keep,
+    /// show what differs, then ask again
+    Diff,
+}
+
+/// What to do with a swap file left behind by an earlier session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recover {
+    /// replay the unsaved changes
+    Recover,
+    /// throw the swap file away
+    Delete,
+    /// leave the swap file for later
+    Keep,
+}
+
+/// What to do with unsaved changes when the window is closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unsaved {
+    /// close anyway, throwing the changes and their swap files away
+    Discard,
+    /// leave the window open
+    Cancel,
+}
+
+pub async fn recover(path: PathBuf, swap: PathBuf) -> (PathBuf, Recover) {
+    let result = AsyncMessageDialog::new()
+        .set_level(MessageLevel::Warning)
+        .set_title("Swap file found")
+        .set_description(format!(
+            "\"{}\" has unsaved changes from a session that didn't exit cleanly.\n\nSwap file: {}",
+            path.display(),
+            swap.display(),
+        ))
+        .set_buttons(MessageButtons::YesNoCancelCustom(
+            "Recover".into(),
+            "Delete".into(),
+            "Keep".into(),
+        ))
+        .show()
+        .await;
+    let recover = match result {
+        MessageDialogResult::Custom(s) if s == "Recover" => Recover::Recover,
+        MessageDialogResult::Custom(s) if s == "Delete" => Recover::Delete,
+        MessageDialogResult::Yes => Recover::Recover,
+        _ => Recover::Keep,
+    };
+    (path, recover)
+}
+
+pub async fn conflict(path: PathBuf) -> (PathBuf, Resolve) {
+    let result = AsyncMessageDialog::new()
+        .set_level(MessageLevel::Warning)
+        .set_title("File changed on disk")
+        .set_description(format!(
+            "\"{}\" was changed outside of evie, but the buffer has unsaved changes.",
+            path.display()
+        ))
+        .set_buttons(MessageButtons::YesNoCancelCustom(
+            "Reload".into(),
+            "Keep".into(),
+            "Diff".into(),
+        ))
+        .show()
+        .await;
+    let resolve = match result {
+        MessageDialogResult::Custom(s) if s == "Reload" => Resolve::Reload,
+        MessageDialogResult::Custom(s) if s == "Diff" => Resolve::Diff,
+        MessageDialogResult::Yes => Resolve::Reload,
+        _ => Resolve::Keep,
+    };
+    (path, resolve)
+}
+
+pub async fn unsaved(paths: Vec<PathBuf>) -> Unsaved {
+    let files = paths
+        .iter()
+        .map(|path| format!("\"{}\"", path.display()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let result = AsyncMessageDialog::new()
+        .set_level(MessageLevel::Warning)
+        .set_title("Unsaved changes")
+        .set_description(format!(
+            "These buffers have changes that haven't been written:\n\n{files}"
+        ))
+        .set_buttons(MessageButtons::OkCancelCustom(
+            "Discard".into(),
+            "Cancel".into(),
+        ))
+        .show()
+        .await;
+    match result {
+        MessageDialogResult::Custom(s) if s == "Discard" => Unsaved::Discard,
+        MessageDialogResult::Ok => Unsaved::Discard,
+        _ => Unsaved::Cancel,
+    }
+}
+
+pub async fn diff(path: PathBuf, diff: String) -> PathBuf {
+    AsyncMessageDialog::new()
+        .set_title(format!("{} (disk -> buffer)", path.display()))
+        .set_description(diff)
+        .set_buttons(MessageButtons::Ok)
+        .show()
+        .await;
+    path
+}
@@ -0,0 +1,147 @@
+//! The tab bar over everything, the status line under each window, and the
+//! diagnostics list and message area under everything
+
+use evie_core::theme::UiGroup;
+use evie_core::{status, BufferView, Evie, Mode, Note};
+use iced::widget::{button, container, horizontal_space, row, scrollable, text, Column, Row};
+use iced::{Color, Element, Length};
+
+use crate::{highlight, KeyAction, Message};
+
+/// the colors of `group` in the theme in use
+fn colors(evie: &Evie<KeyAction>, group: UiGroup) -> (Color, Color) {
+    highlight::colors(&evie.theme.borrow(), evie.background(), group)
+}
+
+/// the buffer's mode, name, position and so on, laid out by `statusline`,
+/// dimmed for windows without focus
+pub fn status_line<'a>(bv: &BufferView<KeyAction>, focus: bool) -> Element<'a, Message> {
+    let (left, right) = match bv.status() {
+        Ok(status) => {
+            let options = bv.evie().engine.options.borrow();
+            status::format(&options.statusline, &status)
+        }
+        Err(e) => (e.to_string(), String::new()),
+    };
+    let (fg, bg) = colors(
+        bv.evie(),
+        match focus {
+            true => UiGroup::StatusLine,
+            false => UiGroup::StatusLineNC,
+        },
+    );
+    container(row![text(left), horizontal_space(), text(right)])
+        .width(Length::Fill)
+        .style(move |_| container::Style::default().background(bg).color(fg))
+        .into()
+}
+
+/// the command line while it's being typed on, otherwise the last note
+pub fn message_area<'a>(evie: &Evie<KeyAction>) -> Element<'a, Message> {
+    if evie.mode.get() == Mode::Command {
+        return text(format!(":{}", evie.cmdline.borrow())).into();
+    }
+    match evie.last_note() {
+        Some(Note::Info(info)) => text(info).into(),
+        Some(Note::Error(e)) => text(e).color(colors(evie, UiGroup::ErrorMsg).0).into(),
+        None => text("").into(),
+    }
+}
+
+/// every buffer's diagnostics, each a button going to it, none unless
+/// `:diagnostics` shows them
+pub fn diagnostics_list<'a>(evie: &Evie<KeyAction>) -> Option<Element<'a, Message>> {
+    if !evie.diagnostics_list.get() {
+        return None;
+    }
+    let listed = match evie.diagnostics() {
+        Ok(listed) => listed,
+        Err(e) => {
+            let error = colors(evie, UiGroup::ErrorMsg).0;
+            return Some(text(e.to_string()).color(error).into());
+        }
+    };
+    if listed.is_empty() {
+        return Some(text("No diagnostics").into());
+    }
+    let (fg, bg) = colors(evie, UiGroup::Normal);
+    let entries = listed.into_iter().map(|listed| {
+        let diagnostic = listed.diagnostic;
+        let path = listed
+            .path
+            .strip_prefix(evie.engine.base())
+            .unwrap_or(&listed.path);
+        let severity = diagnostic.severity;
+        let source = diagnostic
+            .source
+            .map_or(String::new(), |source| format!(" [{source}]"));
+        let entry = row![
+            text(format!(
+                "{}:{}:{} ",
+                path.display(),
+                listed.line + 1,
+                listed.col + 1
+            )),
+            text(severity.name()).color(colors(evie, severity.group()).0),
+            text(format!(": {}{source}", diagnostic.message)),
+        ];
+        button(entry)
+            .padding([0, 4])
+            .width(Length::Fill)
+            .style(move |_, _| button::Style {
+                background: Some(bg.into()),
+                text_color: fg,
+                ..button::Style::default()
+            })
+            .on_press(Message::Goto(listed.path, diagnostic.range.start))
+            .into()
+    });
+    Some(
+        container(scrollable(Column::with_children(entries)))
+            .max_height(LIST_HEIGHT)
+            .into(),
+    )
+}
+
+/// how tall the diagnostics list grows before it scrolls, in pixels
+const LIST_HEIGHT: f32 = 200.0;
+
+/// a button for each tab page, naming the buffer it shows, none when
+/// `showtabline` hides it
+pub fn tab_bar<'a>(evie: &Evie<KeyAction>) -> Option<Element<'a, Message>> {
+    let pages = match evie.tab_pages() {
+        Ok(pages) => pages,
+        Err(e) => {
+            let error = colors(evie, UiGroup::ErrorMsg).0;
+            return Some(text(e.to_string()).color(error).into());
+        }
+    };
+    let show = match evie.engine.options.borrow().showtabline {
+        0 => false,
+        1 => pages.len() > 1,
+        _ => true,
+    };
+    if !show {
+        return None;
+    }
+    let tabs = pages.into_iter().enumerate().map(|(i, page)| {
+        let modified = if page.modified { " [+]" } else { "" };
+        let (fg, bg) = colors(
+            evie,
+            match page.current {
+                true => UiGroup::TabLineSel,
+                false => UiGroup::TabLine,
+            },
+        );
+        button(text(format!("{}{modified}", page.name)))
+            .padding([0, 8])
+            .style(move |_, _| button::Style {
+                background: Some(bg.into()),
+                text_color: fg,
+                ..button::Style::default()
+            })
+            .on_press(Message::Tab(i))
+            .into()
+    });
+    Some(Row::with_children(tabs).spacing(1).into())
+}
@@ -0,0 +1,633 @@
+use std::{fs, path::PathBuf};
+
+use evie_core::theme::Background;
+use evie_core::{engine::Engine, motion::Logical, Evie, Mode, Note, Pointer};
+use tempfile::tempdir;
+
+use crate::editor::evie_editor;
+use crate::trigger::modes;
+use crate::{EvieMain, KeyAction, Message, Named};
+
+fn open(path: PathBuf) -> EvieMain {
+    let evie = Evie::new(modes())
+        .with_engine(Engine::default())
+        .into_central();
+    EvieMain::open(evie, path).0
+}
+
+/// types `keys` into the focused window, with `\n` as enter and `\x1b` as
+/// escape
+fn type_keys(app: &EvieMain, keys: &str) {
+    for c in keys.chars() {
+        press(
+            app,
+            match c {
+                '\n' => KeyAction::Named(Named::Enter),
+                '\x1b' => KeyAction::Named(Named::Escape),
+                c => KeyAction::Letter(c),
+            },
+        );
+    }
+}
+
+/// presses a key in the focused window, which the key may change
+fn press(app: &EvieMain, ka: KeyAction) {
+    evie_editor(app.inner.focused().unwrap()).press(ka, &Logical);
+}
+
+fn last_error(app: &EvieMain) -> String {
+    match app.inner.last_note() {
+        Some(Note::Error(e)) => e,
+        note => panic!("expected an error, got {note:?}"),
+    }
+}
+
+#[test]
+fn failing_write_keeps_changes() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("write.txt");
+    fs::write(&path, "one\n").unwrap();
+    let app = open(path.clone());
+    type_keys(&app, "ix\x1b");
+
+    // a directory where the file was makes every write fail
+    fs::remove_file(&path).unwrap();
+    fs::create_dir(&path).unwrap();
+    type_keys(&app, ":w\n");
+    assert!(!last_error(&app).is_empty());
+
+    let bv = app.inner.focused().unwrap();
+    assert_eq!(bv.rope().unwrap(), "xone\n");
+    assert!(bv.status().unwrap().modified);
+    let _ = app.view();
+}
+
+#[test]
+fn unreadable_file_opens_empty() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("unreadable");
+    fs::create_dir_all(&path).unwrap();
+    let app = open(path);
+    assert!(!last_error(&app).is_empty());
+
+    type_keys(&app, "ihi");
+    let bv = app.inner.focused().unwrap();
+    assert_eq!(bv.rope().unwrap(), "hi");
+    let _ = app.view();
+}
+
+#[test]
+fn failing_reload_is_reported() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("reload.txt");
+    fs::write(&path, "one\n").unwrap();
+    let mut app = open(path.clone());
+    fs::remove_file(&path).unwrap();
+    fs::create_dir(&path).unwrap();
+
+    let _ = app.update(Message::CheckDisk);
+    let _ = app.update(Message::Resolve(path, crate::prompt::Resolve::Reload));
+    assert!(!last_error(&app).is_empty());
+    let bv = app.inner.focused().unwrap();
+    assert_eq!(bv.rope().unwrap(), "one\n");
+}
+
+#[test]
+fn closing_with_changes_keeps_swap_files() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("close.txt");
+    fs::write(&path, "one\n").unwrap();
+    let engine = Engine::default().with_swap_dir(tmp.path().join("swap"));
+    let evie = Evie::new(modes()).with_engine(engine).into_central();
+    let mut app = EvieMain::open(evie, path.clone()).0;
+    type_keys(&app, "ix\x1b");
+    let _ = app.update(Message::SyncSwaps);
+    let buf = app.inner.engine.get_buffer(&path, false).unwrap();
+    let swap = buf.borrow().swap.as_ref().unwrap().path().to_owned();
+    assert!(swap.exists());
+
+    // asks first, and keeps the journal until the changes are discarded
+    let id = iced::window::Id::unique();
+    let _ = app.update(Message::Close(id));
+    assert!(swap.exists());
+    let _ = app.update(Message::Quit(id, crate::prompt::Unsaved::Cancel));
+    assert!(swap.exists());
+    let _ = app.update(Message::Quit(id, crate::prompt::Unsaved::Discard));
+    assert!(!swap.exists());
+}
+
+#[test]
+fn windows_keep_their_own_cursor() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("windows.txt");
+    fs::write(&path, "one\ntwo\nthree\n").unwrap();
+    let app = open(path);
+    type_keys(&app, ":sp\n");
+    assert_eq!(app.inner.windows().unwrap().len(), 2);
+    type_keys(&app, "jj");
+
+    press(&app, KeyAction::Ctrl('w'));
+    type_keys(&app, "j");
+    assert_eq!(app.inner.focused().unwrap().status().unwrap().line, 0);
+    press(&app, KeyAction::Ctrl('w'));
+    type_keys(&app, "k");
+    assert_eq!(app.inner.focused().unwrap().status().unwrap().line, 2);
+    let _ = app.view();
+
+    type_keys(&app, ":q\n");
+    assert_eq!(app.inner.windows().unwrap().len(), 1);
+    // the last window leaves evie
+    type_keys(&app, ":q\n");
+    assert!(app.inner.quit.get());
+}
+
+#[test]
+fn tab_pages_have_their_own_windows() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("tabs.txt");
+    fs::write(&path, "one\ntwo\n").unwrap();
+    let app = open(path);
+    type_keys(&app, ":vsplit\n:tabnew other.txt\n");
+    assert_eq!(app.inner.windows().unwrap().len(), 1);
+    type_keys(&app, "ix\x1b");
+
+    let pages = app.inner.tab_pages().unwrap();
+    let names: Vec<_> = pages.iter().map(|page| page.name.as_str()).collect();
+    assert_eq!(names, ["tabs.txt", "other.txt"]);
+    assert!(pages[1].modified && pages[1].current);
+
+    type_keys(&app, "gt");
+    assert_eq!(app.inner.windows().unwrap().len(), 2);
+    type_keys(&app, ":tabmove\n");
+    assert_eq!(app.inner.tab_pages().unwrap()[1].name, "tabs.txt");
+    let _ = app.view();
+
+    type_keys(&app, "gT:tabclose\n");
+    assert_eq!(app.inner.tabs.borrow().len(), 1);
+    assert_eq!(app.inner.focused().unwrap().status().unwrap().line, 0);
+}
+
+#[test]
+fn display_rows_follow_wrapping() {
+    use crate::lines::{LineCache, Shape, Source};
+    use evie_core::motion::{DisplayLines, DisplayMove};
+    use iced::widget::text::{LineHeight, Wrapping};
+    use ropey::Rope;
+
+    // the bundled font, so every character is a cell wide
+    iced_renderer::graphics::text::font_system()
+        .write()
+        .unwrap()
+        .load_font(
+            include_bytes!("../font/ttf/FiraCode-Regular.ttf")
+                .as_slice()
+                .into(),
+        );
+    let mut lines = LineCache::default();
+    let cell = lines.measure(16.into(), crate::DEFAULT_FONT);
+    let shape = Shape {
+        width: cell * 10.5,
+        size: 16.into(),
+        line_height: LineHeight::default(),
+        font: crate::DEFAULT_FONT,
+        wide: None,
+        ligatures: true,
+        wrapping: Wrapping::Glyph,
+        breakindent: false,
+        showbreak: 0.0,
+    };
+    // ten characters to a row
+    let text = Rope::from_str("aaaa bbbb cccc dddd\nx\n");
+    // highlighted text is shaped in pieces, which mustn't move anything
+    let highlight = |line| match line {
+        0 => vec![crate::lines::Highlight {
+            range: 5..12,
+            color: Some(iced::Color::WHITE),
+            font: crate::DEFAULT_FONT,
+            underline: true,
+        }],
+        _ => Vec::new(),
+    };
+    let source = Source {
+        rope: &text,
+        revision: 0,
+        folds: &[],
+        highlight: &highlight,
+    };
+    lines.layout(&source, 0, shape, 1000.0);
+    let resolve = |lines: &LineCache, at, mv| lines.resolve(&text, at, None, mv).unwrap().0;
+    assert_eq!(resolve(&lines, 2, DisplayMove::Rows(1)), 12);
+    // past the end of a shorter line, normal mode steps back onto it
+    assert_eq!(resolve(&lines, 12, DisplayMove::Rows(1)), 21);
+    assert_eq!(resolve(&lines, 12, DisplayMove::Start), 10);
+    // the space a row breaks at isn't drawn on either
+    assert_eq!(resolve(&lines, 2, DisplayMove::End), 8);
+    assert_eq!(resolve(&lines, 12, DisplayMove::End), 19);
+    // clicks land on the character under them, or the nearest one
+    let row = shape.row_height();
+    let hit = |x, y| lines.hit(&text, iced::Point::new(x, y));
+    assert_eq!(hit(cell * 2.9, row * 1.5), Some(12));
+    assert_eq!(hit(cell * 9.0, row * 2.5), Some(21));
+    assert_eq!(hit(cell * 20.0, row * 9.0), Some(22));
+    let highlight = lines.highlight(&text, 7..12);
+    assert_eq!(highlight.len(), 2);
+    assert_eq!(highlight[1].x, 0.0);
+
+    // wrapped rows line up under the indent, so moving down stays in it
+    let text = Rope::from_str("  aaaa bbbb cccc\n");
+    let shape = Shape {
+        width: cell * 8.5,
+        breakindent: true,
+        ..shape
+    };
+    let source = Source {
+        rope: &text,
+        revision: 1,
+        folds: &[],
+        highlight: &|_| Vec::new(),
+    };
+    lines.layout(&source, 0, shape, 1000.0);
+    assert_eq!(lines.parts(&lines.visible[0]).len(), 2);
+    let resolve = |lines: &LineCache, at, mv| lines.resolve(&text, at, None, mv).unwrap().0;
+    // "  aaaa" then " bbbb" drawn from under the first a
+    assert_eq!(resolve(&lines, 2, DisplayMove::Rows(1)), 6);
+    assert_eq!(resolve(&lines, 3, DisplayMove::Rows(1)), 7);
+    assert_eq!(resolve(&lines, 8, DisplayMove::Start), 6);
+}
+
+#[test]
+fn mouse_selects_and_pastes() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("mouse.txt");
+    fs::write(&path, "one two\nthree\n").unwrap();
+    let app = open(path);
+    let bv = app.inner.focused().unwrap();
+
+    assert!(bv.pointer(Pointer::Word(5)).unwrap());
+    assert_eq!(app.inner.mode.get(), Mode::Visual);
+    assert_eq!(bv.selection().unwrap(), Some(4..7));
+    bv.pointer(Pointer::Drag(9)).unwrap();
+    assert_eq!(bv.selection().unwrap(), Some(4..10));
+    bv.pointer(Pointer::Line(9)).unwrap();
+    assert_eq!(bv.selection().unwrap(), Some(8..13));
+
+    bv.pointer(Pointer::Place(1)).unwrap();
+    assert_eq!(app.inner.mode.get(), Mode::Normal);
+    assert_eq!(bv.selection().unwrap(), None);
+    assert_eq!(bv.cursor().unwrap(), 1);
+    // a press that stays put doesn't start a selection
+    assert!(!bv.pointer(Pointer::Drag(1)).unwrap());
+
+    bv.pointer(Pointer::Paste(3, "!".into())).unwrap();
+    assert_eq!(bv.rope().unwrap(), "one! two\nthree\n");
+    type_keys(&app, "u");
+    assert_eq!(bv.rope().unwrap(), "one two\nthree\n");
+
+    type_keys(&app, ":set mouse=\n");
+    let cursor = bv.cursor().unwrap();
+    assert!(!bv.pointer(Pointer::Place(5)).unwrap());
+    assert_eq!(bv.cursor().unwrap(), cursor);
+    let _ = app.view();
+}
+
+#[test]
+fn syntax_tree_folds_and_selects() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("tree.rs");
+    fs::write(&path, "fn one() {\n    1\n}\n\nfn two(a: u8, b: u8) {}\n").unwrap();
+    let app = open(path);
+    let bv = app.inner.focused().unwrap();
+
+    type_keys(&app, "jzc");
+    assert_eq!(bv.folds().unwrap(), vec![0..3]);
+    assert_eq!(bv.cursor().unwrap(), 0);
+    // the fold is passed over as one line
+    type_keys(&app, "j");
+    assert_eq!(bv.status().unwrap().line, 3);
+    type_keys(&app, "kzozM");
+    assert_eq!(bv.folds().unwrap(), vec![0..3]);
+    type_keys(&app, "zR");
+    assert!(bv.folds().unwrap().is_empty());
+
+    type_keys(&app, "jjjjlllllllvia");
+    assert_eq!(app.inner.mode.get(), Mode::Visual);
+    assert_eq!(bv.selection().unwrap(), Some(27..32));
+    type_keys(&app, "af");
+    assert_eq!(bv.selection().unwrap(), Some(20..43));
+    type_keys(&app, "v");
+
+    bv.pointer(Pointer::Place(27)).unwrap();
+    type_keys(&app, "gnn");
+    assert_eq!(bv.selection().unwrap(), Some(27..28));
+    type_keys(&app, "grngrn");
+    assert_eq!(bv.selection().unwrap(), Some(26..40));
+    type_keys(&app, "grm");
+    assert_eq!(bv.selection().unwrap(), Some(27..32));
+    let _ = app.view();
+}
+
+#[test]
+fn colorscheme_follows_background() {
+    let tmp = tempdir().unwrap();
+    let mut app = open(tmp.path().join("colors.txt"));
+    let normal = |app: &EvieMain| app.theme().palette().background;
+    let dark = normal(&app);
+
+    type_keys(&app, ":colo solarized\n");
+    assert_eq!(app.inner.theme.borrow().name, "solarized");
+    let solarized = normal(&app);
+    assert_ne!(solarized, dark);
+
+    type_keys(&app, ":colo nope\n");
+    assert_eq!(last_error(&app), "Cannot find color scheme 'nope'");
+    assert_eq!(app.inner.theme.borrow().name, "solarized");
+
+    // the system's preference counts until the option says otherwise
+    let _ = app.update(Message::System(Background::Light));
+    assert_ne!(normal(&app), solarized);
+    type_keys(&app, ":set bg=dark\n");
+    assert_eq!(normal(&app), solarized);
+    let _ = app.view();
+}
+
+#[test]
+fn zoom_and_font_pieces() {
+    let tmp = tempdir().unwrap();
+    use crate::font::{self, Fonts};
+
+    let app = open(tmp.path().join("zoom.txt"));
+    let size = |app: &EvieMain| Fonts::from_evie(&app.inner).unwrap().size.0;
+    press(&app, KeyAction::Ctrl('='));
+    press(&app, KeyAction::Ctrl('='));
+    press(&app, KeyAction::Ctrl('-'));
+    assert_eq!(size(&app), 17.0);
+
+    // zooming out stops at the smallest size, and zooming back in works
+    // straight away
+    type_keys(&app, ":set fsz=6\n");
+    assert_eq!(size(&app), 7.0);
+    for _ in 0..5 {
+        press(&app, KeyAction::Ctrl('-'));
+    }
+    press(&app, KeyAction::Ctrl('='));
+    assert_eq!(size(&app), 5.0);
+    press(&app, KeyAction::Ctrl('0'));
+    assert_eq!(size(&app), 6.0);
+    let _ = app.view();
+
+    // without ligatures symbols are shaped apart, and wide characters get
+    // their own font
+    assert_eq!(font::runs("a->b", true, false), [(0..4, false)]);
+    assert_eq!(
+        font::runs("a->b", false, false),
+        [(0..2, false), (2..4, false)]
+    );
+    assert_eq!(
+        font::runs("x世界", true, true),
+        [(0..1, false), (1..7, true)]
+    );
+}
+
+#[test]
+fn language_server_replies() {
+    let tmp = tempdir().unwrap();
+    use evie_core::{LspAction, LspRequest};
+    use evie_lsp::document::Encoding;
+    use evie_lsp::lsp_types::{Location, Position, Range, TextEdit, Url, WorkspaceEdit};
+
+    use crate::lsp::Reply;
+
+    let path = tmp.path().join("lsp.txt");
+    fs::write(&path, "fn main() {\n    main();\n}\n").unwrap();
+    let mut app = open(path.clone());
+    type_keys(&app, "jllllK");
+    let request = LspRequest {
+        path: path.clone(),
+        at: 16,
+        action: LspAction::Hover,
+    };
+    assert_eq!(*app.inner.requests.borrow(), [request]);
+    type_keys(&app, ":rename\n");
+    assert!(!last_error(&app).is_empty());
+    // without a server for the buffer the request is answered straight away
+    let _ = app.update(Message::Idle);
+    assert!(app.inner.requests.borrow().is_empty());
+
+    let uri = Url::from_file_path(&path).unwrap();
+    let range = |line, start, end| Range::new(Position::new(line, start), Position::new(line, end));
+    let location = Location::new(uri.clone(), range(0, 3, 7));
+    let _ = app.update(Message::Lsp(Reply::Definition(
+        vec![location],
+        Encoding::Utf16,
+    )));
+    let bv = app.inner.focused().unwrap();
+    assert_eq!(bv.status().unwrap().col, 3);
+
+    // a rename is undone all at once
+    let edits = vec![
+        TextEdit::new(range(0, 3, 7), "start".into()),
+        TextEdit::new(range(1, 4, 8), "start".into()),
+    ];
+    let edit = WorkspaceEdit::new([(uri, edits)].into());
+    let _ = app.update(Message::Lsp(Reply::Edit {
+        edit,
+        encoding: Encoding::Utf16,
+        path: path.clone(),
+        version: 0,
+    }));
+    assert_eq!(bv.rope().unwrap(), "fn start() {\n    start();\n}\n");
+    type_keys(&app, "u");
+    assert_eq!(bv.rope().unwrap(), "fn main() {\n    main();\n}\n");
+
+    let _ = app.update(Message::Lsp(Reply::Error("gone".into())));
+    assert_eq!(last_error(&app), "gone");
+    let _ = app.view();
+}
+
+#[test]
+fn relative_paths_get_a_server() {
+    let mut app = open(PathBuf::from("src/lib.rs"));
+    let _ = app.update(Message::Idle);
+    let path = std::env::current_dir().unwrap().join("src/lib.rs");
+    assert_eq!(
+        app.inner.focused().unwrap().status().unwrap().full_path,
+        path
+    );
+    assert!(app.lsp.projects().any(|(filetype, _)| filetype == "rust"));
+}
+
+#[test]
+fn diagnostics_navigation_and_list() {
+    let tmp = tempdir().unwrap();
+    use evie_core::diagnostic::{Diagnostic, Severity};
+
+    let path = tmp.path().join("diagnostics.txt");
+    fs::write(&path, "let a = 1;\nlet b = a +;\n").unwrap();
+    let app = open(path.clone());
+    let buf = app.inner.engine.get_buffer(&path, false).unwrap();
+    buf.borrow_mut().diagnostics.set(
+        "linter",
+        vec![
+            Diagnostic::new(4..5, Severity::Warning, "unused"),
+            Diagnostic::new(22..23, Severity::Error, "expected expression"),
+        ],
+    );
+    let bv = app.inner.focused().unwrap();
+    type_keys(&app, "]d");
+    assert_eq!(bv.cursor().unwrap(), 4);
+    assert_eq!(
+        app.inner.last_note(),
+        Some(Note::Info("warning: unused".into()))
+    );
+    type_keys(&app, "]d]d");
+    assert_eq!(bv.cursor().unwrap(), 4);
+    type_keys(&app, "[d");
+    assert_eq!(bv.cursor().unwrap(), 22);
+
+    // typing where the second starts moves it along
+    type_keys(&app, "i  \x1b");
+    let listed = app.inner.diagnostics().unwrap();
+    let places: Vec<_> = listed.iter().map(|l| (l.line, l.col)).collect();
+    assert_eq!(places, [(0, 4), (1, 13)]);
+
+    type_keys(&app, ":diag\n");
+    assert!(app.inner.diagnostics_list.get());
+    let _ = app.view();
+    type_keys(&app, ":diagnostics\n");
+    assert!(!app.inner.diagnostics_list.get());
+}
+
+#[test]
+fn completion_skips_large_buffers() {
+    let tmp = tempdir().unwrap();
+    let (path, log) = (tmp.path().join("small.txt"), tmp.path().join("big.log"));
+    fs::write(&path, "zebra\n\n").unwrap();
+    fs::write(&log, "zealous\n".repeat(1 << 18)).unwrap();
+    let app = open(path);
+    app.inner.engine.options.borrow_mut().largefile = 1;
+    app.inner.engine.add_buffer(&log, false).unwrap();
+    type_keys(&app, "jize");
+    press(&app, KeyAction::Ctrl('n'));
+    let menu = app.inner.focused().unwrap().completion().unwrap().unwrap();
+    let words: Vec<_> = menu.items.into_iter().map(|item| item.word).collect();
+    assert_eq!(words, ["zebra"]);
+}
+
+#[test]
+fn insert_mode_completion() {
+    let tmp = tempdir().unwrap();
+    use evie_core::{LspAction, LspRequest};
+    use evie_lsp::lsp_types::{CompletionItem, CompletionItemKind};
+
+    use crate::lsp::Reply;
+
+    let path = tmp.path().join("completion.txt");
+    fs::write(&path, "let buffer = 1;\nlet bar = 2;\n").unwrap();
+    let mut app = open(path.clone());
+    let bv = app.inner.focused().unwrap();
+    type_keys(&app, "jjibu");
+    press(&app, KeyAction::Ctrl('n'));
+    let words = |bv: &evie_core::BufferView<KeyAction>| -> Vec<_> {
+        let menu = bv.completion().unwrap().unwrap();
+        menu.items.into_iter().map(|item| item.word).collect()
+    };
+    assert_eq!(words(&bv), ["buffer"]);
+    let request = LspRequest {
+        path: path.clone(),
+        at: 31,
+        action: LspAction::Complete,
+    };
+    assert_eq!(*app.inner.requests.borrow(), [request]);
+    // without a server typing just carries on
+    let _ = app.update(Message::Idle);
+    assert!(app.inner.requests.borrow().is_empty());
+
+    // what a server sends later is ranked in without moving the selection
+    let item = CompletionItem {
+        label: "build".into(),
+        kind: Some(CompletionItemKind::FUNCTION),
+        ..Default::default()
+    };
+    let _ = app.update(Message::Lsp(Reply::Completion(path.clone(), vec![item])));
+    let menu = bv.completion().unwrap().unwrap();
+    assert_eq!(words(&bv), ["build", "buffer"]);
+    assert_eq!(menu.selected, Some(1));
+    assert_eq!(menu.items[0].menu, "function");
+    let _ = app.view();
+
+    type_keys(&app, "f");
+    assert_eq!(words(&bv), ["buffer"]);
+    press(&app, KeyAction::Ctrl('y'));
+    assert!(bv.completion().unwrap().is_none());
+    assert_eq!(bv.rope().unwrap(), "let buffer = 1;\nlet bar = 2;\nbuffer");
+    assert_eq!(bv.cursor().unwrap(), 35);
+
+    // typing past the word closes it, and the completed word is undone with
+    // the rest of what was typed
+    press(&app, KeyAction::Ctrl('p'));
+    assert!(bv.completion().unwrap().is_some());
+    press(&app, KeyAction::Ctrl('e'));
+    assert!(bv.completion().unwrap().is_none());
+    press(&app, KeyAction::Ctrl('n'));
+    type_keys(&app, " ");
+    assert!(bv.completion().unwrap().is_none());
+    type_keys(&app, "\x1bu");
+    assert_eq!(bv.rope().unwrap(), "let buffer = 1;\nlet bar = 2;\n");
+}
+
+#[test]
+fn snippets_expand_and_jump() {
+    use evie_core::snippet::Library;
+
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("snippets");
+    fs::create_dir_all(&dir).unwrap();
+    let body = "\tfn ${1:name}(${2|&self,self|}) {\n\t\t$1();$0\n\t}\n";
+    fs::write(dir.join("rust.snippets"), format!("snippet fn\n{body}")).unwrap();
+    let path = tmp.path().join("snippets.rs");
+    fs::write(&path, "impl A {\n\n}\n").unwrap();
+    let evie = Evie::new(modes())
+        .with_engine(Engine::default())
+        .with_snippets(Library::new([dir]))
+        .into_central();
+    let app = EvieMain::open(evie, path).0;
+    let bv = app.inner.focused().unwrap();
+    let text = |bv: &evie_core::BufferView<KeyAction>| bv.rope().unwrap().to_string();
+
+    type_keys(&app, "ji    fn");
+    press(&app, KeyAction::Named(Named::Tab));
+    assert_eq!(
+        text(&bv),
+        "impl A {\n    fn name(&self) {\n    \tname();\n    }\n}\n"
+    );
+    assert_eq!(bv.placeholder().unwrap(), Some(16..20));
+    // typing replaces the placeholder, and its mirror follows
+    type_keys(&app, "run");
+    assert_eq!(
+        text(&bv),
+        "impl A {\n    fn run(&self) {\n    \trun();\n    }\n}\n"
+    );
+    assert_eq!(bv.placeholder().unwrap(), None);
+
+    // the choices are offered in the order given
+    press(&app, KeyAction::Named(Named::Tab));
+    press(&app, KeyAction::Ctrl('n'));
+    press(&app, KeyAction::Ctrl('n'));
+    let menu = bv.completion().unwrap().unwrap();
+    let choices: Vec<_> = menu.items.iter().map(|item| item.word.as_str()).collect();
+    assert_eq!((choices, menu.selected), (vec!["&self", "self"], Some(1)));
+    press(&app, KeyAction::Ctrl('y'));
+    press(&app, KeyAction::Shift(Named::Tab));
+    assert_eq!(bv.placeholder().unwrap(), Some(16..19));
+    press(&app, KeyAction::Named(Named::Tab));
+    press(&app, KeyAction::Named(Named::Tab));
+    let line = "impl A {\n    fn run(self) {\n    \trun();";
+    assert_eq!(bv.cursor().unwrap(), line.len());
+
+    // past the end it's a tab again, and the whole lot is one undo step
+    press(&app, KeyAction::Named(Named::Tab));
+    assert!(text(&bv).starts_with(&format!("{line}\t\n")));
+    type_keys(&app, "\x1bu");
+    assert_eq!(text(&bv), "impl A {\n\n}\n");
+    let _ = app.view();
+}
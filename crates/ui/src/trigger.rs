@@ -1,43 +1,252 @@
 use std::sync::Arc;
 
 use evie_core::{
-    trigger::{Modes, Trigger, TriggerMap},
-    Mode,
+    motion::{DisplayMove, TextObject},
+    trigger::{Modes, Trigger, TriggerFallback, TriggerMap},
+    view::{Align, Scroll::*},
+    window::Split,
+    CompleteAction, FoldAction, LspAction, Mode, Select, WindowAction,
 };
 
 use crate::KeyAction;
 use crate::Named;
 
-use evie_core::{BufferAction::*, CoreAction::*};
+use evie_core::{BufferAction::*, CoreAction::*, Move::*};
 use Trigger::*;
 
+#[allow(clippy::arc_with_non_send_sync)]
 pub fn modes() -> Modes<KeyAction> {
     Modes::new(
-        TriggerMap::from([(KeyAction::Letter('i'), End(SetMode(Mode::Insert).into()))]),
+        TriggerMap::from([
+            (KeyAction::Letter('i'), End(SetMode(Mode::Insert).into())),
+            (KeyAction::Letter(':'), End(SetMode(Mode::Command).into())),
+            (KeyAction::Letter('v'), End(SetMode(Mode::Visual).into())),
+            (KeyAction::Letter('u'), End(Undo.into())),
+            (KeyAction::Letter('K'), End(Lsp(LspAction::Hover).into())),
+            (KeyAction::Ctrl('r'), End(Redo.into())),
+            (KeyAction::Letter('h'), End(Move(Left).into())),
+            (KeyAction::Letter('j'), End(Move(Down).into())),
+            (KeyAction::Letter('k'), End(Move(Up).into())),
+            (KeyAction::Letter('l'), End(Move(Right).into())),
+            (KeyAction::Ctrl('e'), End(Scroll(Lines(1)).into())),
+            (KeyAction::Ctrl('y'), End(Scroll(Lines(-1)).into())),
+            (KeyAction::Ctrl('d'), End(Scroll(HalfPage(1)).into())),
+            (KeyAction::Ctrl('u'), End(Scroll(HalfPage(-1)).into())),
+            (KeyAction::Ctrl('f'), End(Scroll(Page(1)).into())),
+            (KeyAction::Ctrl('b'), End(Scroll(Page(-1)).into())),
+            (KeyAction::Ctrl('w'), Map(Arc::new(windows()))),
+            (
+                KeyAction::Letter(']'),
+                Map(Arc::new(TriggerMap::from([(
+                    KeyAction::Letter('d'),
+                    End(NextDiagnostic(true).into()),
+                )]))),
+            ),
+            (
+                KeyAction::Letter('['),
+                Map(Arc::new(TriggerMap::from([(
+                    KeyAction::Letter('d'),
+                    End(NextDiagnostic(false).into()),
+                )]))),
+            ),
+            (
+                KeyAction::Letter('g'),
+                Map(Arc::new(TriggerMap::from([
+                    (KeyAction::Letter('t'), End(NextTab(1).into())),
+                    (KeyAction::Letter('T'), End(NextTab(-1).into())),
+                    (KeyAction::Letter('j'), End(DisplayMove::Rows(1).into())),
+                    (KeyAction::Letter('k'), End(DisplayMove::Rows(-1).into())),
+                    (KeyAction::Letter('0'), End(DisplayMove::Start.into())),
+                    (KeyAction::Letter('$'), End(DisplayMove::End.into())),
+                    (
+                        KeyAction::Letter('d'),
+                        End(Lsp(LspAction::Definition).into()),
+                    ),
+                    (
+                        KeyAction::Letter('r'),
+                        Map(Arc::new(TriggerMap::from([(
+                            KeyAction::Letter('r'),
+                            End(Lsp(LspAction::References).into()),
+                        )]))),
+                    ),
+                    (
+                        KeyAction::Letter('n'),
+                        Map(Arc::new(TriggerMap::from([(
+                            KeyAction::Letter('n'),
+                            End(Select(Select::Node).into()),
+                        )]))),
+                    ),
+                ]))),
+            ),
+            (
+                KeyAction::Letter('z'),
+                Map(Arc::new(TriggerMap::from([
+                    (
+                        KeyAction::Letter('t'),
+                        End(Scroll(Cursor(Align::Top)).into()),
+                    ),
+                    (
+                        KeyAction::Letter('z'),
+                        End(Scroll(Cursor(Align::Center)).into()),
+                    ),
+                    (
+                        KeyAction::Letter('b'),
+                        End(Scroll(Cursor(Align::Bottom)).into()),
+                    ),
+                    (KeyAction::Letter('c'), End(Fold(FoldAction::Close).into())),
+                    (KeyAction::Letter('o'), End(Fold(FoldAction::Open).into())),
+                    (KeyAction::Letter('a'), End(Fold(FoldAction::Toggle).into())),
+                    (
+                        KeyAction::Letter('M'),
+                        End(Fold(FoldAction::CloseAll).into()),
+                    ),
+                    (
+                        KeyAction::Letter('R'),
+                        End(Fold(FoldAction::OpenAll).into()),
+                    ),
+                ]))),
+            ),
+        ])
+        .with_fallback(call(arrows)),
         TriggerMap::from((
             [],
             call(|ka| match ka {
                 &KeyAction::Letter(s) => Some(Trigger::End(Append(s.into()).into())),
                 KeyAction::Named(Named::Enter) => Some(Trigger::End(Append('\n'.into()).into())),
-                _ => None,
+                KeyAction::Ctrl('n') => Some(End(Complete(CompleteAction::Next).into())),
+                KeyAction::Ctrl('p') => Some(End(Complete(CompleteAction::Prev).into())),
+                KeyAction::Ctrl('y') => Some(End(Complete(CompleteAction::Accept).into())),
+                KeyAction::Ctrl('e') => Some(End(Complete(CompleteAction::Cancel).into())),
+                KeyAction::Named(Named::Tab) => Some(End(Snippet(true).into())),
+                KeyAction::Shift(Named::Tab) => Some(End(Snippet(false).into())),
+                ka => arrows(ka),
             }),
         )),
-        TriggerMap::from([]),
-        TriggerMap::from([]),
-        TriggerMap::from([]),
-        TriggerMap::from([]),
+        TriggerMap::from([
+            (KeyAction::Letter('v'), End(SetMode(Mode::Normal).into())),
+            (KeyAction::Letter(':'), End(SetMode(Mode::Command).into())),
+            (KeyAction::Letter('h'), End(Move(Left).into())),
+            (KeyAction::Letter('j'), End(Move(Down).into())),
+            (KeyAction::Letter('k'), End(Move(Up).into())),
+            (KeyAction::Letter('l'), End(Move(Right).into())),
+            (KeyAction::Letter('a'), Map(Arc::new(objects(false)))),
+            (KeyAction::Letter('i'), Map(Arc::new(objects(true)))),
+            (
+                KeyAction::Letter('g'),
+                Map(Arc::new(TriggerMap::from([(
+                    KeyAction::Letter('r'),
+                    Map(Arc::new(TriggerMap::from([
+                        (KeyAction::Letter('n'), End(Select(Select::Expand).into())),
+                        (KeyAction::Letter('m'), End(Select(Select::Shrink).into())),
+                    ]))),
+                )]))),
+            ),
+        ])
+        .with_fallback(call(arrows)),
         TriggerMap::from((
             [],
             call(|ka| match ka {
-                KeyAction::Named(Named::Escape) => Some(End(SetMode(Mode::Normal).into())),
+                &KeyAction::Letter(c) => Some(End(CmdlinePush(c).into())),
+                KeyAction::Named(Named::Space) => Some(End(CmdlinePush(' ').into())),
+                KeyAction::Named(Named::Backspace) => Some(End(CmdlinePop.into())),
+                KeyAction::Named(Named::Enter) => Some(End(CmdlineExecute.into())),
                 _ => None,
             }),
         )),
+        TriggerMap::from([]),
+        TriggerMap::from([]),
+        TriggerMap::from([
+            (KeyAction::Ctrl('='), End(Zoom(1).into())),
+            (KeyAction::Ctrl('+'), End(Zoom(1).into())),
+            (KeyAction::Ctrl('-'), End(Zoom(-1).into())),
+            (KeyAction::Ctrl('0'), End(Zoom(0).into())),
+        ])
+        .with_fallback(call(|ka| match ka {
+            KeyAction::Named(Named::Escape) => Some(End(SetMode(Mode::Normal).into())),
+            _ => None,
+        })),
     )
 }
 
+/// what follows `Ctrl-w`
+fn windows() -> TriggerMap<KeyAction> {
+    use WindowAction::{Close, Cycle, Equalize, Focus, Only, Quit, Resize, Rotate};
+    let window = |action| End(Window(action).into());
+    TriggerMap::from([
+        (
+            KeyAction::Letter('s'),
+            window(WindowAction::Split(Split::Horizontal)),
+        ),
+        (
+            KeyAction::Letter('S'),
+            window(WindowAction::Split(Split::Horizontal)),
+        ),
+        (
+            KeyAction::Letter('v'),
+            window(WindowAction::Split(Split::Vertical)),
+        ),
+        (KeyAction::Letter('c'), window(Close)),
+        (KeyAction::Letter('q'), window(Quit)),
+        (KeyAction::Letter('o'), window(Only)),
+        (KeyAction::Letter('h'), window(Focus(Left))),
+        (KeyAction::Letter('j'), window(Focus(Down))),
+        (KeyAction::Letter('k'), window(Focus(Up))),
+        (KeyAction::Letter('l'), window(Focus(Right))),
+        (KeyAction::Ctrl('h'), window(Focus(Left))),
+        (KeyAction::Ctrl('j'), window(Focus(Down))),
+        (KeyAction::Ctrl('k'), window(Focus(Up))),
+        (KeyAction::Ctrl('l'), window(Focus(Right))),
+        (KeyAction::Letter('w'), window(Cycle(1))),
+        (KeyAction::Ctrl('w'), window(Cycle(1))),
+        (KeyAction::Letter('W'), window(Cycle(-1))),
+        (KeyAction::Letter('+'), window(Resize(Split::Horizontal, 1))),
+        (
+            KeyAction::Letter('-'),
+            window(Resize(Split::Horizontal, -1)),
+        ),
+        (KeyAction::Letter('>'), window(Resize(Split::Vertical, 1))),
+        (KeyAction::Letter('<'), window(Resize(Split::Vertical, -1))),
+        (KeyAction::Letter('='), window(Equalize)),
+        (KeyAction::Letter('r'), window(Rotate(true))),
+        (KeyAction::Letter('R'), window(Rotate(false))),
+    ])
+    .with_fallback(call(|ka| {
+        let towards = match ka {
+            KeyAction::Named(Named::ArrowLeft) => Left,
+            KeyAction::Named(Named::ArrowDown) => Down,
+            KeyAction::Named(Named::ArrowUp) => Up,
+            KeyAction::Named(Named::ArrowRight) => Right,
+            _ => return None,
+        };
+        Some(End(Window(Focus(towards)).into()))
+    }))
+}
+
+/// what follows `a` or `i` in visual mode, the text objects from the syntax
+/// tree
+fn objects(inner: bool) -> TriggerMap<KeyAction> {
+    let object = |object| End(Select(Select::Object(object, inner)).into());
+    TriggerMap::from([
+        (KeyAction::Letter('f'), object(TextObject::Function)),
+        (KeyAction::Letter('c'), object(TextObject::Class)),
+        (KeyAction::Letter('a'), object(TextObject::Parameter)),
+        (KeyAction::Letter('/'), object(TextObject::Comment)),
+    ])
+}
+
+fn arrows(ka: &KeyAction) -> Option<Trigger<KeyAction>> {
+    let mv = match ka {
+        KeyAction::Named(Named::ArrowLeft) => Left,
+        KeyAction::Named(Named::ArrowDown) => Down,
+        KeyAction::Named(Named::ArrowUp) => Up,
+        KeyAction::Named(Named::ArrowRight) => Right,
+        _ => return None,
+    };
+    Some(End(Move(mv).into()))
+}
+
 fn call(
     call: impl Fn(&KeyAction) -> Option<Trigger<KeyAction>> + 'static,
-) -> Arc<dyn Fn(&KeyAction) -> Option<Trigger<KeyAction>>> {
+) -> TriggerFallback<KeyAction> {
     Arc::new(move |ka| call(ka))
 }
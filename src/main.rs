@@ -1,5 +1,113 @@
-fn main() -> evie_ui::IceResult {
-    evie_ui::launch()
+use std::{path::PathBuf, process::ExitCode};
+
+use evie_core::{engine::Engine, Evie};
+
+const USAGE: &str = "usage: evie [--tui | --headless | --embed | --listen path | --remote-wait] \
+                     [-c command]... [file]...";
+
+/// How evie was asked to run
+#[derive(Debug, Default)]
+struct Args {
+    tui: bool,
+    headless: bool,
+    /// to be driven over rpc on stdio, with no ui
+    embed: bool,
+    /// a socket to be driven over rpc on, with no ui
+    listen: Option<PathBuf>,
+    /// when the files go to an evie already running, to return once it
+    /// closes them, as `$GIT_EDITOR` needs
+    remote_wait: bool,
+    /// ex commands to run once the first file is open, from `-c`
+    commands: Vec<String>,
+    files: Vec<PathBuf>,
+}
+
+fn args() -> Result<Args, String> {
+    let mut args = Args::default();
+    let mut given = std::env::args().skip(1);
+    while let Some(arg) = given.next() {
+        match arg.as_str() {
+            "--tui" => args.tui = true,
+            "--headless" => args.headless = true,
+            "--embed" => args.embed = true,
+            "--listen" => args.listen = Some(given.next().ok_or("--listen needs a path")?.into()),
+            "--remote-wait" => args.remote_wait = true,
+            "-c" => args
+                .commands
+                .push(given.next().ok_or("-c needs a command")?),
+            "--" => args.files.extend(given.by_ref().map(PathBuf::from)),
+            "-h" | "--help" => return Err(USAGE.into()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}\n{USAGE}")),
+            _ => args.files.push(arg.into()),
+        }
+    }
+    Ok(args)
 }
 
-// one two three four
+/// serves rpc clients until one quits evie, see [`evie_rpc`]
+fn serve(args: &Args) -> Result<(), evie_rpc::RpcError> {
+    let evie = Evie::new(evie_tui::trigger::modes())
+        .with_engine(Engine::default())
+        .into_central();
+    evie_tui::open(&evie, &args.files);
+    for command in &args.commands {
+        if let Err(e) = evie.execute(command, None) {
+            evie.report(e);
+        }
+    }
+    match &args.listen {
+        #[cfg(unix)]
+        Some(path) => evie_rpc::listen(evie, path),
+        #[cfg(not(unix))]
+        Some(_) => Err(std::io::Error::from(std::io::ErrorKind::Unsupported).into()),
+        None => evie_rpc::embed(evie),
+    }
+}
+
+/// hands the files to the user's evie if one is running, or opens a window
+/// on them
+fn gui(args: &Args) -> Result<(), String> {
+    #[cfg(unix)]
+    if let Some(socket) = evie_rpc::remote::socket().filter(|_| !args.files.is_empty()) {
+        // the running evie may have started in another directory
+        let files = args
+            .files
+            .iter()
+            .map(std::path::absolute)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        if evie_rpc::remote::open(&socket, &files, args.remote_wait).map_err(|e| e.to_string())? {
+            return Ok(());
+        }
+    }
+    evie_ui::launch(args.files.clone()).map_err(|e| e.to_string())
+}
+
+fn main() -> ExitCode {
+    let args = match args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = if args.embed || args.listen.is_some() {
+        serve(&args).map_err(|e| e.to_string())
+    } else if args.headless {
+        evie_tui::headless(&args.files, &args.commands).map_err(|e| e.to_string())
+    } else if args.tui {
+        evie_tui::launch(&args.files, &args.commands).map_err(|e| e.to_string())
+    } else {
+        if !args.commands.is_empty() {
+            eprintln!("evie: -c is only taken with --tui, --headless, --embed or --listen");
+        }
+        gui(&args)
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("evie: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}